@@ -0,0 +1,4 @@
+-- Add up migration script here
+-- The version is increased on every update, so the concurrent updates can be detected
+ALTER TABLE content
+    ADD COLUMN version integer NOT NULL DEFAULT 1;
@@ -0,0 +1,16 @@
+-- Add up migration script here
+-- Every login attempt, the failures lock the usernames and the IPs out for a while
+CREATE TABLE login_attempts
+(
+    id           uuid        NOT NULL DEFAULT gen_random_uuid(),
+    username     text        NOT NULL,
+    ip           inet        NOT NULL,
+    user_agent   text,
+    -- succeeded, failed, locked (refused during the lockout) or unlocked (by an admin)
+    outcome      varchar(16) NOT NULL,
+    attempted_at timestamptz NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (id)
+);
+
+CREATE INDEX login_attempts_username_idx ON login_attempts (username, attempted_at DESC);
+CREATE INDEX login_attempts_ip_idx ON login_attempts (ip, attempted_at DESC);
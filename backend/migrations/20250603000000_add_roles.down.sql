@@ -0,0 +1,5 @@
+-- Add down migration script here
+ALTER TABLE resource
+    DROP COLUMN created_by;
+ALTER TABLE users
+    DROP COLUMN role;
@@ -0,0 +1,10 @@
+-- Add up migration script here
+-- The role tells what a user can do, the existing users keep doing everything as the owners
+ALTER TABLE users
+    ADD COLUMN role varchar(16) NOT NULL DEFAULT 'owner';
+ALTER TABLE users
+    ALTER COLUMN role SET DEFAULT 'viewer';
+
+-- The authors can only change the resources they have created, it's unknown for the older ones
+ALTER TABLE resource
+    ADD COLUMN created_by uuid;
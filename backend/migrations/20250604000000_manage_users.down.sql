@@ -0,0 +1,6 @@
+-- Add down migration script here
+DROP TABLE audit_log;
+ALTER TABLE users
+    DROP COLUMN created_at,
+    DROP COLUMN must_reset_password,
+    DROP COLUMN disabled;
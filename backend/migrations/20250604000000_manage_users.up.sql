@@ -0,0 +1,24 @@
+-- Add up migration script here
+-- The disabled users can't log in, the flagged ones have to change their passwords first
+ALTER TABLE users
+    ADD COLUMN disabled            boolean     NOT NULL DEFAULT false,
+    ADD COLUMN must_reset_password boolean     NOT NULL DEFAULT false,
+    ADD COLUMN created_at          timestamptz NOT NULL DEFAULT NOW();
+
+-- Who did what, the user is missing if it's done on the server
+CREATE TABLE audit_log
+(
+    id          uuid        NOT NULL DEFAULT gen_random_uuid(),
+    user_id     uuid,
+    ip          inet,
+    -- create, update, delete, disable, enable or force_password_reset
+    action      varchar(32) NOT NULL,
+    -- e.g. user
+    target_type varchar(32) NOT NULL,
+    target_id   text        NOT NULL,
+    detail      jsonb,
+    created_at  timestamptz NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (id)
+);
+
+CREATE INDEX audit_log_created_at_idx ON audit_log (created_at DESC);
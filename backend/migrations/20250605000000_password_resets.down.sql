@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE password_resets;
+ALTER TABLE users
+    DROP COLUMN email;
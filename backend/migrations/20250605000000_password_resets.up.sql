@@ -0,0 +1,16 @@
+-- Add up migration script here
+-- Where the links to reset the passwords are sent, the users without emails can't reset them
+ALTER TABLE users
+    ADD COLUMN email TEXT;
+
+-- Only the hashes of the tokens are kept, a token is deleted once it's used
+CREATE TABLE password_resets
+(
+    token_hash TEXT        NOT NULL,
+    user_id    uuid        NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    expires_at timestamptz NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (token_hash)
+);
+
+CREATE INDEX password_resets_user_id_idx ON password_resets (user_id);
@@ -0,0 +1,7 @@
+-- Add down migration script here
+DROP TABLE security_settings;
+DROP TABLE recovery_codes;
+ALTER TABLE users
+    DROP COLUMN totp_last_step,
+    DROP COLUMN totp_enabled,
+    DROP COLUMN totp_secret;
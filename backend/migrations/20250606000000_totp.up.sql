@@ -0,0 +1,25 @@
+-- Add up migration script here
+-- The secret is kept once the enrolment starts, the second factor is on once it's confirmed. The
+-- last step is the time step of the last used code, so the codes can't be replayed
+ALTER TABLE users
+    ADD COLUMN totp_secret    TEXT,
+    ADD COLUMN totp_enabled   boolean NOT NULL DEFAULT false,
+    ADD COLUMN totp_last_step bigint;
+
+-- Only the hashes of the recovery codes are kept, a code is deleted once it's used
+CREATE TABLE recovery_codes
+(
+    user_id   uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    PRIMARY KEY (user_id, code_hash)
+);
+
+-- A single row of the settings which apply to all the users
+CREATE TABLE security_settings
+(
+    id           boolean NOT NULL DEFAULT true CHECK (id),
+    require_totp boolean NOT NULL DEFAULT false,
+    PRIMARY KEY (id)
+);
+
+INSERT INTO security_settings DEFAULT VALUES;
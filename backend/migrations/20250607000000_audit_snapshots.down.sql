@@ -0,0 +1,14 @@
+-- Add down migration script here
+DROP INDEX audit_log_target_idx;
+DROP INDEX audit_log_user_id_idx;
+
+ALTER TABLE audit_log
+    ADD COLUMN detail jsonb;
+
+UPDATE audit_log
+SET detail = after_snapshot;
+
+ALTER TABLE audit_log
+    DROP COLUMN after_snapshot,
+    DROP COLUMN before_snapshot,
+    DROP COLUMN language;
@@ -0,0 +1,16 @@
+-- Add up migration script here
+-- The entries keep the target before and after the change, the detail is what it's after
+ALTER TABLE audit_log
+    ADD COLUMN language        varchar(8),
+    ADD COLUMN before_snapshot jsonb,
+    ADD COLUMN after_snapshot  jsonb;
+
+UPDATE audit_log
+SET after_snapshot = detail;
+
+ALTER TABLE audit_log
+    DROP COLUMN detail;
+
+-- the entries are browsed by who changed what
+CREATE INDEX audit_log_user_id_idx ON audit_log (user_id, created_at DESC);
+CREATE INDEX audit_log_target_idx ON audit_log (target_type, target_id, created_at DESC);
@@ -1,8 +1,16 @@
+use crate::api::caching::version_tag;
+use crate::api::request_id::current_request_id;
+use crate::domain::entities::Language;
+use crate::domain::validation::FieldError;
 use axum::extract::rejection::JsonRejection;
-use axum::http::StatusCode;
+use axum::http::{header, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use serde_json::json;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+/// The media type of the error responses
+pub const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
 
 #[derive(thiserror::Error, Debug)]
 pub enum ApiError {
@@ -12,35 +20,242 @@ pub enum ApiError {
     InternalServerError(String),
     #[error("Bad Request")]
     BadRequest,
+    #[error("The request has invalid fields")]
+    InvalidFields(Vec<FieldErrorPayload>),
     #[error("Not found")]
     NotFound,
+    #[error("`{0}` refers to a resource which doesn't exist")]
+    InvalidReference(&'static str),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("The resource has been changed by someone else, the current version is {0}")]
+    VersionMismatch(i32),
+    #[error("Too many requests, retry after {0} seconds")]
+    TooManyRequests(u64),
+    #[error("Too many failed logins, retry after {0} seconds")]
+    AccountLocked(u64),
     #[error("invalid credentials")]
     InvalidCredentials,
     #[error("missing bearer token")]
     MissingBearer,
     #[error("credentials is expired")]
     ExpiredCredentials,
+    #[error("The user isn't allowed to do it")]
+    Forbidden,
+    #[error("The password has to be changed first")]
+    PasswordResetRequired,
+    #[error("The second factor has to be enrolled first")]
+    TotpEnrolmentRequired,
+}
+
+impl ApiError {
+    /// Describes the failing fields in the language
+    pub fn invalid_fields(fields: Vec<FieldError>, language: &Language) -> Self {
+        ApiError::InvalidFields(
+            fields
+                .into_iter()
+                .map(|field| FieldErrorPayload {
+                    message: field.message(language),
+                    field: field.path,
+                    rule: field.rule,
+                })
+                .collect(),
+        )
+    }
+}
+
+/** The payload of the error responses, an RFC 7807 problem details object.
+*
+* The `code` is stable so the clients can tell the failures apart without parsing the messages.
+*/
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct Problem {
+    /// Always `about:blank`, the failure is told by the code
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// The reason phrase of the status
+    title: String,
+    status: u16,
+    /// The description of the failure, it's generic if it's a server error
+    detail: String,
+    /// The stable code of the failure, e.g. `resource.not_found`
+    code: &'static str,
+    /// The ID of the request which can be found in the logs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    /// The fields which fail the validation
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldErrorPayload>,
+    /// The current version of the resource when the precondition fails
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current_version: Option<i32>,
+}
+
+/// A field which fails the validation, so the client can highlight its input
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct FieldErrorPayload {
+    /// The path of the field, e.g. `tags[0]`
+    field: String,
+    /// The violated rule, e.g. `length`
+    rule: String,
+    /// The localized description of the violation
+    message: String,
+}
+
+impl ApiError {
+    /// The stable code which tells the failure
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::JsonExtractorRejection(_) => "request.malformed",
+            ApiError::InternalServerError(_) => "server.internal",
+            ApiError::BadRequest => "request.invalid",
+            ApiError::InvalidFields(_) => "request.invalid_fields",
+            ApiError::NotFound => "resource.not_found",
+            ApiError::InvalidReference(_) => "resource.invalid_reference",
+            ApiError::Conflict(_) => "resource.conflict",
+            ApiError::VersionMismatch(_) => "resource.version_mismatch",
+            ApiError::TooManyRequests(_) => "rate_limit.exceeded",
+            ApiError::AccountLocked(_) => "auth.locked",
+            ApiError::InvalidCredentials => "auth.invalid_credentials",
+            ApiError::MissingBearer => "auth.missing_bearer",
+            ApiError::ExpiredCredentials => "auth.expired",
+            ApiError::Forbidden => "auth.forbidden",
+            ApiError::PasswordResetRequired => "auth.password_reset_required",
+            ApiError::TotpEnrolmentRequired => "auth.totp_enrolment_required",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::JsonExtractorRejection(json_rejection) => json_rejection.status(),
+            ApiError::InternalServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::BadRequest => StatusCode::BAD_REQUEST,
+            ApiError::InvalidFields(_) => StatusCode::BAD_REQUEST,
+            ApiError::NotFound => StatusCode::NOT_FOUND,
+            ApiError::InvalidReference(_) => StatusCode::BAD_REQUEST,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::VersionMismatch(_) => StatusCode::PRECONDITION_FAILED,
+            ApiError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::AccountLocked(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::InvalidCredentials => StatusCode::FORBIDDEN,
+            ApiError::MissingBearer => StatusCode::UNAUTHORIZED,
+            ApiError::ExpiredCredentials => StatusCode::FORBIDDEN,
+            ApiError::Forbidden => StatusCode::FORBIDDEN,
+            ApiError::PasswordResetRequired => StatusCode::FORBIDDEN,
+            ApiError::TotpEnrolmentRequired => StatusCode::FORBIDDEN,
+        }
+    }
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        let (status, message) = match self {
-            ApiError::JsonExtractorRejection(json_rejection) => (
-                json_rejection.status(),
-                format!("Json parsing error: {}", json_rejection.body_text()),
-            ),
-            ApiError::InternalServerError(reason) => (StatusCode::INTERNAL_SERVER_ERROR, reason),
-            ApiError::BadRequest => (StatusCode::BAD_REQUEST, self.to_string()),
-            ApiError::NotFound => (StatusCode::NOT_FOUND, self.to_string()),
-            ApiError::InvalidCredentials => (StatusCode::FORBIDDEN, self.to_string()),
-            ApiError::MissingBearer => (StatusCode::FORBIDDEN, self.to_string()),
-            ApiError::ExpiredCredentials => (StatusCode::FORBIDDEN, self.to_string()),
+        let status = self.status();
+        let code = self.code();
+        let request_id = current_request_id();
+
+        // the cause of a server error is only logged, it might reveal the internals
+        let detail = if status.is_server_error() {
+            let cause = match &self {
+                ApiError::InternalServerError(reason) => reason.to_string(),
+                _ => self.to_string(),
+            };
+            tracing::error!(code, request_id, cause, "Failed to handle the request");
+
+            "An unexpected error occurred, please try again later".to_string()
+        } else {
+            match &self {
+                ApiError::JsonExtractorRejection(json_rejection) => {
+                    format!("Json parsing error: {}", json_rejection.body_text())
+                }
+                _ => self.to_string(),
+            }
+        };
+
+        let retry_after = match self {
+            ApiError::TooManyRequests(seconds) | ApiError::AccountLocked(seconds) => Some(seconds),
+            _ => None,
+        };
+        let current_version = match self {
+            ApiError::VersionMismatch(current) => Some(current),
+            _ => None,
+        };
+        let errors = match self {
+            ApiError::InvalidFields(errors) => errors,
+            _ => vec![],
+        };
+
+        let problem = Problem {
+            kind: "about:blank",
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            detail,
+            code,
+            request_id,
+            errors,
+            current_version,
         };
 
-        let payload = json!({
-            "message": message,
-        });
+        let mut response = (
+            status,
+            [(header::CONTENT_TYPE, PROBLEM_CONTENT_TYPE)],
+            Json(problem),
+        )
+            .into_response();
+        // the current version is the tag which the next `If-Match` has to send
+        if let Some(version) = current_version {
+            if let Ok(etag) = HeaderValue::from_str(&version_tag(version)) {
+                response.headers_mut().insert(header::ETAG, etag);
+            }
+        }
+        if let Some(seconds) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+    use serde_json::Value;
+
+    async fn problem(error: ApiError) -> (Response, Value) {
+        let response = error.into_response();
+        let (parts, body) = response.into_parts();
+        let body = to_bytes(body, usize::MAX).await.unwrap();
+
+        (
+            Response::from_parts(parts, axum::body::Body::empty()),
+            serde_json::from_slice(&body).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn it_should_describe_the_failure_as_a_problem() {
+        let (response, body) = problem(ApiError::NotFound).await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            PROBLEM_CONTENT_TYPE
+        );
+        assert_eq!(body["type"], "about:blank");
+        assert_eq!(body["title"], "Not Found");
+        assert_eq!(body["status"], 404);
+        assert_eq!(body["code"], "resource.not_found");
+    }
+
+    #[tokio::test]
+    async fn it_should_hide_the_cause_of_a_server_error() {
+        let cause = "error returned from database: relation \"users\" does not exist";
+        let (response, body) = problem(ApiError::InternalServerError(cause.to_string())).await;
 
-        (status, Json(payload)).into_response()
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["code"], "server.internal");
+        assert!(!body.to_string().contains("relation"));
     }
 }
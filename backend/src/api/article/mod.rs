@@ -1,11 +1 @@
-pub mod create;
-
-pub mod retrieve;
-
-pub mod list;
-
-pub mod delete;
-
-pub mod update;
-
 pub mod view;
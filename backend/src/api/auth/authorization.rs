@@ -0,0 +1,48 @@
+use crate::api::api_error::ApiError;
+use crate::api::auth::Claims;
+use crate::domain::users::permissions::Permission;
+use crate::startup::AppState;
+use axum::extract::{FromRequestParts, Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+/// The permissions which are required by the route, the claims of the request must have them all
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RequiredPermissions(pub Vec<Permission>);
+
+/** Requires the permission on the routes which are layered, e.g.
+*
+* `axum::middleware::from_fn_with_state(Permission::new(Subject::Users, Action::Read), require)`
+*
+* The bearer token is verified here along with the permissions, so the requests without one are
+* refused even if the handler doesn't extract the claims. The verified claims are kept in the
+* extensions, the handlers extract them without verifying the token again.
+*/
+pub async fn require(
+    State(permission): State<Permission>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (mut parts, body) = request.into_parts();
+    match parts.extensions.get_mut::<RequiredPermissions>() {
+        Some(RequiredPermissions(permissions)) => permissions.push(permission),
+        None => {
+            parts
+                .extensions
+                .insert(RequiredPermissions(vec![permission]));
+        }
+    }
+
+    let Some(state) = parts.extensions.get::<AppState>().cloned() else {
+        return ApiError::InternalServerError("can't get app state from extensions".to_string())
+            .into_response();
+    };
+    match Claims::from_request_parts(&mut parts, &state).await {
+        Ok(claims) => {
+            parts.extensions.insert(claims);
+        }
+        Err(e) => return e.into_response(),
+    }
+
+    next.run(Request::from_parts(parts, body)).await
+}
@@ -1,79 +1,227 @@
 use crate::api::api_error::ApiError;
-use crate::api::auth::Claims;
-use crate::domain::users::authentication::{validate_credentials, Credentials, Error};
-use crate::repositories::{Connection, SqlxUserRepository};
+use crate::api::auth::{access_token, must_enroll_totp};
+use crate::api::client_ip::ClientIp;
+use crate::domain::entities::{Role, UserID};
+use crate::domain::users::authentication::{
+    validate_code, validate_credentials, Attempt, Credentials, Error,
+};
+use crate::repositories::{
+    Connection, RedisSessionRepository, SqlxLoginAttemptRepository, SqlxRecoveryCodeRepository,
+    SqlxUserRepository,
+};
 use crate::startup::AppState;
-use anyhow::Context;
 use axum::extract::State;
 use axum::{Extension, Json};
 use axum_extra::extract::WithRejection;
-use chrono::{Duration, Utc};
-use redis::Commands;
+use axum_extra::headers::UserAgent;
+use axum_extra::TypedHeader;
+use chrono::Utc;
+use schemars::JsonSchema;
 use secrecy::SecretBox;
 use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
-#[derive(Debug, Deserialize)]
+/// The audience of the challenge tokens, so they can't be used as access tokens
+const CHALLENGE_AUDIENCE: &str = "totp";
+
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct LoginRequest {
     username: String,
     password: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct LoginResponse {
     user_id: String,
     username: String,
+    /// The access token
     token: String,
+    /// Exchanges for a new access token, it can only be used once
+    refresh_token: String,
+    /// How many seconds the access token lasts
+    expires_in: i64,
+    role: Role,
+    /// The password has to be changed before anything else can be done
+    must_reset_password: bool,
+    /// The second factor has to be enrolled before anything else can be done
+    must_enroll_totp: bool,
+}
+
+/// The password is right, the code of the second factor has to be given with the challenge
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TotpChallengeResponse {
+    /// The token which is sent along with the code
+    challenge: String,
+    /// How many seconds the code can be given
+    expires_in: i64,
+}
+
+/// Either the tokens, or a challenge if the user has a second factor
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(untagged)]
+pub enum LoginResult {
+    Tokens(LoginResponse),
+    TotpChallenge(TotpChallengeResponse),
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TotpLoginRequest {
+    /// The challenge of the login
+    challenge: String,
+    /// The code of the authenticator app, or a recovery code
+    code: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChallengeClaims {
+    sub: String,
+    exp: usize,
+    aud: String,
 }
 
 pub async fn login(
     State(state): State<AppState>,
     Extension(redis_client): Extension<Arc<redis::Client>>,
+    ClientIp(ip): ClientIp,
+    user_agent: Option<TypedHeader<UserAgent>>,
     WithRejection(Json(req), _): WithRejection<Json<LoginRequest>, ApiError>,
-) -> Result<Json<LoginResponse>, ApiError> {
-    let user_repo = SqlxUserRepository::new(Connection::Pool(state.pool));
+) -> Result<Json<LoginResult>, ApiError> {
+    let user_repo = SqlxUserRepository::new(Connection::Pool(state.pool.clone()));
+    let attempt_repo = SqlxLoginAttemptRepository::new(Connection::Pool(state.pool.clone()));
 
     let credentials = Credentials {
-        username: req.username.clone(),
+        username: req.username,
         password: SecretBox::new(Box::new(req.password)),
     };
+    let attempt = attempt(ip, user_agent);
+
+    let user = validate_credentials(
+        credentials,
+        attempt.clone(),
+        &state.lockout,
+        &state.password_hashing,
+        Mutex::new(user_repo),
+        Mutex::new(attempt_repo),
+    )
+    .await
+    .map_err(login_error)?;
+
+    if !user.totp_enabled {
+        let tokens = start_session(&state, redis_client, user.id, attempt).await?;
+        return Ok(Json(LoginResult::Tokens(tokens)));
+    }
+
+    let ttl = state.totp.challenge_ttl;
+    let claims = ChallengeClaims {
+        sub: user.id.to_string(),
+        exp: (Utc::now() + ttl).timestamp() as usize,
+        aud: CHALLENGE_AUDIENCE.to_string(),
+    };
+    let challenge = jsonwebtoken::encode(
+        &jsonwebtoken::Header::default(),
+        &claims,
+        &state.jwt_encoding_key,
+    )
+    .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+
+    Ok(Json(LoginResult::TotpChallenge(TotpChallengeResponse {
+        challenge,
+        expires_in: ttl.num_seconds(),
+    })))
+}
+
+/// Completes the login of a user with a second factor
+pub async fn login_totp(
+    State(state): State<AppState>,
+    Extension(redis_client): Extension<Arc<redis::Client>>,
+    ClientIp(ip): ClientIp,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    WithRejection(Json(req), _): WithRejection<Json<TotpLoginRequest>, ApiError>,
+) -> Result<Json<LoginResponse>, ApiError> {
+    let mut validation = jsonwebtoken::Validation::default();
+    validation.set_audience(&[CHALLENGE_AUDIENCE]);
+    let token_data = jsonwebtoken::decode::<ChallengeClaims>(
+        &req.challenge,
+        &state.jwt_decoding_key,
+        &validation,
+    )
+    .map_err(|_| ApiError::InvalidCredentials)?;
+    let user_id =
+        UserID::try_from(token_data.claims.sub).map_err(|_| ApiError::InvalidCredentials)?;
+
+    let user_repo = SqlxUserRepository::new(Connection::Pool(state.pool.clone()));
+    let recovery_repo = SqlxRecoveryCodeRepository::new(Connection::Pool(state.pool.clone()));
+    let attempt_repo = SqlxLoginAttemptRepository::new(Connection::Pool(state.pool.clone()));
+    let attempt = attempt(ip, user_agent);
+
+    let user = validate_code(
+        &user_id,
+        &req.code,
+        attempt.clone(),
+        &state.lockout,
+        Mutex::new(user_repo),
+        Mutex::new(recovery_repo),
+        Mutex::new(attempt_repo),
+    )
+    .await
+    .map_err(login_error)?;
+
+    let tokens = start_session(&state, redis_client, user.id, attempt).await?;
+    Ok(Json(tokens))
+}
+
+fn attempt(ip: Option<IpAddr>, user_agent: Option<TypedHeader<UserAgent>>) -> Attempt {
+    Attempt {
+        ip: ip.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+        user_agent: user_agent.map(|TypedHeader(user_agent)| user_agent.to_string()),
+    }
+}
 
-    let res = validate_credentials(credentials, Mutex::new(user_repo)).await;
-
-    match res {
-        Ok(id) => {
-            let user_id = id.to_string();
-            let exp = Utc::now() + Duration::days(7);
-
-            let mut redis_connection = redis_client
-                .get_connection()
-                .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
-
-            let () = redis_connection
-                .set(&user_id, exp.timestamp() as usize)
-                .context("Failed to set a user to session storage.")
-                .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
-
-            let claims = Claims {
-                sub: user_id.clone(),
-                exp: exp.timestamp() as usize,
-            };
-
-            let token = jsonwebtoken::encode(
-                &jsonwebtoken::Header::default(),
-                &claims,
-                &state.jwt_encoding_key,
-            )
-            .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
-
-            Ok(Json(LoginResponse {
-                user_id,
-                username: req.username,
-                token,
-            }))
+fn login_error(e: Error) -> ApiError {
+    match e {
+        Error::InvalidCredentials => ApiError::InvalidCredentials,
+        Error::Locked { until } => {
+            let seconds = (until - Utc::now()).num_seconds().max(1) as u64;
+            ApiError::AccountLocked(seconds)
         }
-        Err(Error::InvalidCredentials) => Err(ApiError::InvalidCredentials),
-        Err(Error::Unknown(e)) => Err(ApiError::InternalServerError(e.to_string())),
+        Error::Unknown(e) => ApiError::InternalServerError(e.to_string()),
     }
 }
+
+/// Starts a session of the user who has logged in
+async fn start_session(
+    state: &AppState,
+    redis_client: Arc<redis::Client>,
+    user_id: UserID,
+    attempt: Attempt,
+) -> Result<LoginResponse, ApiError> {
+    let session_repo = RedisSessionRepository::new(redis_client);
+    let new_session = crate::domain::sessions::start::Request { user_id, attempt };
+
+    let user_repo = SqlxUserRepository::new(Connection::Pool(state.pool.clone()));
+    let issued = crate::domain::sessions::start::execute(
+        new_session,
+        &state.sessions,
+        Mutex::new(user_repo),
+        Mutex::new(session_repo),
+    )
+    .await
+    .map_err(|crate::domain::sessions::start::Error::Unknown(e)| {
+        ApiError::InternalServerError(e)
+    })?;
+    let must_enroll_totp = must_enroll_totp(state, &issued.user).await?;
+    let (token, expires_in) = access_token(state, &issued.session, &issued.user, must_enroll_totp)?;
+
+    Ok(LoginResponse {
+        user_id: issued.session.user_id.to_string(),
+        username: issued.user.username,
+        token,
+        refresh_token: issued.refresh_token,
+        expires_in,
+        role: issued.user.role,
+        must_reset_password: issued.user.must_reset_password,
+        must_enroll_totp,
+    })
+}
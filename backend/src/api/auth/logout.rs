@@ -1,23 +1,28 @@
 use crate::api::api_error::ApiError;
 use crate::api::auth::Claims;
+use crate::repositories::RedisSessionRepository;
 use axum::http::StatusCode;
 use axum::Extension;
-use redis::Commands;
 use std::sync::Arc;
+use tokio::sync::Mutex;
 
+/// Ends the session of the token, the other sessions of the user are kept
 pub async fn logout(
     claims: Claims,
     Extension(redis_client): Extension<Arc<redis::Client>>,
 ) -> Result<StatusCode, ApiError> {
-    let user_id = claims.sub;
+    let session_repo = RedisSessionRepository::new(redis_client);
+    let req = crate::domain::sessions::revoke::Request {
+        user_id: claims.user_id()?,
+        session_id: claims.jti,
+    };
 
-    let mut conn = redis_client
-        .get_connection()
-        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
-
-    let () = conn
-        .del(&user_id)
-        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
-
-    Ok(StatusCode::OK)
+    match crate::domain::sessions::revoke::execute(req, Mutex::new(session_repo)).await {
+        Ok(()) => Ok(StatusCode::OK),
+        Err(crate::domain::sessions::revoke::Error::BadRequest) => Err(ApiError::BadRequest),
+        Err(crate::domain::sessions::revoke::Error::NotFound) => Err(ApiError::ExpiredCredentials),
+        Err(crate::domain::sessions::revoke::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
 }
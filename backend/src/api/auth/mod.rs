@@ -1,23 +1,122 @@
 use crate::api::api_error::ApiError;
+use crate::api::auth::authorization::RequiredPermissions;
+use crate::domain::entities::{Role, Session, SessionID, User, UserID};
+use crate::domain::users::permissions::{Permission, Scope};
+use crate::repositories::{
+    Connection, ISecuritySettingsRepository, ISessionRepository, RedisSessionRepository,
+    SqlxSecuritySettingsRepository,
+};
 use crate::startup::AppState;
 use anyhow::Context;
 use axum::extract::{FromRef, FromRequestParts};
 use axum::http::request::Parts;
 use chrono::Utc;
-use redis::Commands;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+pub mod authorization;
+
 pub mod login;
 
 pub mod logout;
 
-#[derive(Serialize, Deserialize)]
+pub mod refresh;
+
+pub mod sessions;
+
+/// How often the time a session is last seen is updated
+const LAST_SEEN_PRECISION: chrono::Duration = chrono::Duration::minutes(1);
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
+    /// The session of the token
+    pub jti: String,
+    /// The role of the user when the token is issued
+    pub role: Role,
+    /// Only the own account can be managed until the password is changed
+    #[serde(default)]
+    pub must_reset_password: bool,
+    /// Only the own account can be managed until the second factor is enrolled
+    #[serde(default)]
+    pub must_enroll_totp: bool,
+}
+
+impl Claims {
+    pub fn user_id(&self) -> Result<UserID, ApiError> {
+        UserID::try_from(self.sub.clone()).map_err(|_| ApiError::InvalidCredentials)
+    }
+
+    pub fn session_id(&self) -> Result<SessionID, ApiError> {
+        SessionID::try_from(self.jti.clone()).map_err(|_| ApiError::InvalidCredentials)
+    }
+
+    /// The routes without the required permissions are of the own account
+    fn authorize(&self, parts: &Parts) -> Result<(), ApiError> {
+        if let Some(RequiredPermissions(permissions)) = parts.extensions.get() {
+            if self.must_reset_password {
+                return Err(ApiError::PasswordResetRequired);
+            }
+            if self.must_enroll_totp {
+                return Err(ApiError::TotpEnrolmentRequired);
+            }
+            if !permissions.iter().all(|p| self.role.can(p)) {
+                return Err(ApiError::Forbidden);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The user whose own ones are only covered by the permission, it's none if all are covered
+    pub fn owner(&self, permission: &Permission) -> Result<Option<UserID>, ApiError> {
+        match self.role.scope(permission) {
+            Scope::Own => Ok(Some(self.user_id()?)),
+            _ => Ok(None),
+        }
+    }
 }
 
+/// Whether the user has to enrol the second factor before anything else can be done
+async fn must_enroll_totp(state: &AppState, user: &User) -> Result<bool, ApiError> {
+    let settings = SqlxSecuritySettingsRepository::new(Connection::Pool(state.pool.clone()))
+        .get()
+        .await
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+
+    Ok(settings.require_totp && !user.totp_enabled)
+}
+
+/// Issues an access token of the session, it returns the token along with its lifetime in seconds
+fn access_token(
+    state: &AppState,
+    session: &Session,
+    user: &User,
+    must_enroll_totp: bool,
+) -> Result<(String, i64), ApiError> {
+    let ttl = state.sessions.access_token_ttl;
+    let claims = Claims {
+        sub: session.user_id.to_string(),
+        exp: (Utc::now() + ttl).timestamp() as usize,
+        jti: session.id.to_string(),
+        role: user.role,
+        must_reset_password: user.must_reset_password,
+        must_enroll_totp,
+    };
+
+    let token = jsonwebtoken::encode(
+        &jsonwebtoken::Header::default(),
+        &claims,
+        &state.jwt_encoding_key,
+    )
+    .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+
+    Ok((token, ttl.num_seconds()))
+}
+
+/// Verifies the bearer token, its session must not be revoked and its role must have the
+/// permissions which are required by the route
 impl<S> FromRequestParts<S> for Claims
 where
     AppState: FromRef<S>,
@@ -26,21 +125,13 @@ where
     type Rejection = ApiError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let now = Utc::now().timestamp() as usize;
-        let state = AppState::from_ref(state);
-        let redis_client = parts.extensions.get::<Arc<redis::Client>>();
-
-        if redis_client.is_none() {
-            return Err(ApiError::InternalServerError(
-                "can't get redis client from extensions".to_string(),
-            ));
+        // the token is verified once by the `require` layer
+        if let Some(claims) = parts.extensions.get::<Claims>().cloned() {
+            claims.authorize(parts)?;
+            return Ok(claims);
         }
 
-        let mut redis_connection = redis_client
-            .unwrap()
-            .get_connection()
-            .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
-
+        let state = AppState::from_ref(state);
         let bearer = parts
             .headers
             .get("Authorization")
@@ -49,22 +140,44 @@ where
             .context("Missing Authorization header")
             .map_err(|_| ApiError::MissingBearer)?
             .1;
+        let redis_client = parts
+            .extensions
+            .get::<Arc<redis::Client>>()
+            .cloned()
+            .ok_or_else(|| {
+                ApiError::InternalServerError("can't get redis client from extensions".to_string())
+            })?;
 
         let token_data = jsonwebtoken::decode::<Claims>(
             bearer,
             &state.jwt_decoding_key,
             &jsonwebtoken::Validation::default(),
         )
-        .context("Failed to decode jwt")
-        .map_err(|_| ApiError::InvalidCredentials)?;
+        // the clients refresh the expired tokens
+        .map_err(|e| match e.kind() {
+            jsonwebtoken::errors::ErrorKind::ExpiredSignature => ApiError::ExpiredCredentials,
+            _ => ApiError::InvalidCredentials,
+        })?;
+
+        token_data.claims.authorize(parts)?;
+
+        let session_repo = RedisSessionRepository::new(redis_client);
+        let session = session_repo
+            .get(&token_data.claims.session_id()?)
+            .await
+            .context("Failed to get the session of the token")
+            .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
 
-        let exp_from_session: Option<usize> = redis_connection
-            .get(&token_data.claims.sub)
-            .context("Failed to get expired date by token")
-            .map_err(|_| ApiError::InvalidCredentials)?;
+        let session = match session {
+            Some(session) if session.user_id == token_data.claims.user_id()? => session,
+            _ => return Err(ApiError::ExpiredCredentials),
+        };
 
-        if exp_from_session.is_none() || exp_from_session.unwrap() < now {
-            return Err(ApiError::ExpiredCredentials)?;
+        let now = Utc::now();
+        if now - session.last_seen_at > LAST_SEEN_PRECISION {
+            if let Err(e) = session_repo.touch(&session.id, now).await {
+                tracing::warn!(error = %e, "Failed to mark the session as seen");
+            }
         }
 
         Ok(token_data.claims)
@@ -0,0 +1,66 @@
+use crate::api::api_error::ApiError;
+use crate::api::auth::{access_token, must_enroll_totp};
+use crate::repositories::{Connection, RedisSessionRepository, SqlxUserRepository};
+use crate::startup::AppState;
+use axum::extract::State;
+use axum::{Extension, Json};
+use axum_extra::extract::WithRejection;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RefreshRequest {
+    refresh_token: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RefreshResponse {
+    /// The access token
+    token: String,
+    /// Replaces the refresh token which is used
+    refresh_token: String,
+    /// How many seconds the access token lasts
+    expires_in: i64,
+}
+
+/// Exchanges a refresh token for a new access token and a new refresh token
+pub async fn refresh(
+    State(state): State<AppState>,
+    Extension(redis_client): Extension<Arc<redis::Client>>,
+    WithRejection(Json(req), _): WithRejection<Json<RefreshRequest>, ApiError>,
+) -> Result<Json<RefreshResponse>, ApiError> {
+    let user_repo = SqlxUserRepository::new(Connection::Pool(state.pool.clone()));
+    let session_repo = RedisSessionRepository::new(redis_client);
+    let req = crate::domain::sessions::refresh::Request {
+        refresh_token: req.refresh_token,
+    };
+
+    match crate::domain::sessions::refresh::execute(
+        req,
+        &state.sessions,
+        Mutex::new(user_repo),
+        Mutex::new(session_repo),
+    )
+    .await
+    {
+        Ok(issued) => {
+            let must_enroll_totp = must_enroll_totp(&state, &issued.user).await?;
+            let (token, expires_in) =
+                access_token(&state, &issued.session, &issued.user, must_enroll_totp)?;
+
+            Ok(Json(RefreshResponse {
+                token,
+                refresh_token: issued.refresh_token,
+                expires_in,
+            }))
+        }
+        Err(crate::domain::sessions::refresh::Error::InvalidToken) => {
+            Err(ApiError::InvalidCredentials)
+        }
+        Err(crate::domain::sessions::refresh::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use crate::api::api_error::ApiError;
+use crate::api::auth::Claims;
+use crate::api::client_ip::ClientIp;
+use crate::domain::audit::Actor;
+use crate::domain::entities::Session;
+use crate::domain::users::permissions::{Action, Permission, Subject};
+use crate::repositories::RedisSessionRepository;
+use crate::startup::AppState;
+use crate::uow::user::InDatabase;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::{Extension, Json};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// A device where the user is logged in
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct SessionResponse {
+    id: String,
+    ip: IpAddr,
+    user_agent: Option<String>,
+    #[schemars(with = "String")]
+    created_at: DateTime<Utc>,
+    /// It's updated once a minute at most
+    #[schemars(with = "String")]
+    last_seen_at: DateTime<Utc>,
+    /// The session ends then if its refresh token isn't used
+    #[schemars(with = "String")]
+    expires_at: DateTime<Utc>,
+    /// Whether it's the session of the request
+    current: bool,
+}
+
+impl SessionResponse {
+    fn new(session: Session, claims: &Claims) -> Self {
+        Self {
+            current: session.id.to_string() == claims.jti,
+            id: session.id.to_string(),
+            ip: session.ip,
+            user_agent: session.user_agent,
+            created_at: session.created_at,
+            last_seen_at: session.last_seen_at,
+            expires_at: session.expires_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct SessionsResponse {
+    sessions: Vec<SessionResponse>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct RevokedSessionsResponse {
+    /// How many sessions are ended
+    revoked: usize,
+}
+
+/// Lists where the user is logged in
+pub async fn list_sessions(
+    claims: Claims,
+    Extension(redis_client): Extension<Arc<redis::Client>>,
+) -> Result<Json<SessionsResponse>, ApiError> {
+    let session_repo = RedisSessionRepository::new(redis_client);
+    let req = crate::domain::sessions::list::Request {
+        user_id: claims.user_id()?,
+    };
+
+    match crate::domain::sessions::list::execute(req, Mutex::new(session_repo)).await {
+        Ok(sessions) => Ok(Json(SessionsResponse {
+            sessions: sessions
+                .into_iter()
+                .map(|session| SessionResponse::new(session, &claims))
+                .collect(),
+        })),
+        Err(crate::domain::sessions::list::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
+
+/// Ends a session of the user, e.g. of a lost device
+pub async fn revoke_session(
+    claims: Claims,
+    Extension(redis_client): Extension<Arc<redis::Client>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let session_repo = RedisSessionRepository::new(redis_client);
+    let req = crate::domain::sessions::revoke::Request {
+        session_id: id,
+        user_id: claims.user_id()?,
+    };
+
+    match crate::domain::sessions::revoke::execute(req, Mutex::new(session_repo)).await {
+        Ok(()) => Ok(StatusCode::OK),
+        Err(crate::domain::sessions::revoke::Error::BadRequest) => Err(ApiError::BadRequest),
+        Err(crate::domain::sessions::revoke::Error::NotFound) => Err(ApiError::NotFound),
+        Err(crate::domain::sessions::revoke::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
+
+/// Ends the sessions of the user except the current one
+pub async fn revoke_other_sessions(
+    claims: Claims,
+    State(state): State<AppState>,
+    Extension(redis_client): Extension<Arc<redis::Client>>,
+    ClientIp(ip): ClientIp,
+) -> Result<Json<RevokedSessionsResponse>, ApiError> {
+    let user_id = claims.user_id()?;
+    let req = crate::domain::sessions::revoke_all::Request {
+        user_id: user_id.to_string(),
+        except: Some(claims.session_id()?),
+        by: Actor { user_id, ip },
+        privileged: false,
+    };
+
+    revoke_all(req, state, redis_client).await
+}
+
+/// Ends all the sessions of another user, only the users who manage the users can do it
+pub async fn revoke_user_sessions(
+    claims: Claims,
+    State(state): State<AppState>,
+    Extension(redis_client): Extension<Arc<redis::Client>>,
+    Path(id): Path<String>,
+    ClientIp(ip): ClientIp,
+) -> Result<Json<RevokedSessionsResponse>, ApiError> {
+    let req = crate::domain::sessions::revoke_all::Request {
+        user_id: id,
+        except: None,
+        by: Actor {
+            user_id: claims.user_id()?,
+            ip,
+        },
+        privileged: claims
+            .role
+            .can(&Permission::new(Subject::Users, Action::Delete)),
+    };
+
+    revoke_all(req, state, redis_client).await
+}
+
+async fn revoke_all(
+    req: crate::domain::sessions::revoke_all::Request,
+    state: AppState,
+    redis_client: Arc<redis::Client>,
+) -> Result<Json<RevokedSessionsResponse>, ApiError> {
+    let session_repo = RedisSessionRepository::new(redis_client);
+    let uow = InDatabase::new(&state.pool)
+        .await
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+
+    match crate::domain::sessions::revoke_all::execute(
+        req,
+        Mutex::new(session_repo),
+        Mutex::new(uow),
+    )
+    .await
+    {
+        Ok(revoked) => Ok(Json(RevokedSessionsResponse { revoked })),
+        Err(crate::domain::sessions::revoke_all::Error::BadRequest) => Err(ApiError::BadRequest),
+        Err(crate::domain::sessions::revoke_all::Error::Forbidden) => Err(ApiError::Forbidden),
+        Err(crate::domain::sessions::revoke_all::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
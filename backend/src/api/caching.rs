@@ -0,0 +1,236 @@
+use crate::configuration::CacheControlSettings;
+use axum::body::{to_bytes, Body};
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+/** Supports the conditional requests of the public read routes.
+*
+* A resource is tagged with its version by the handler, which is the same tag that `If-Match`
+* sends, so the handler answers `If-None-Match` itself before the body is serialized. Moving a
+* resource bumps its version as well, since the sequence is a part of the body. The responses
+* which are built from many resources, e.g. the lists, don't have a single version, so their
+* strong ETags are the digests of their bodies, which are the same on every instance.
+* A request whose `If-None-Match` matches the ETag gets `304 Not Modified` without the body.
+* The `Cache-Control` header is configured per route group.
+*/
+pub async fn conditional_get(
+    State(settings): State<Arc<CacheControlSettings>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::GET && request.method() != Method::HEAD {
+        return next.run(request).await;
+    }
+
+    // the path is relative to the version because the routes are nested
+    let group = request
+        .uri()
+        .path()
+        .trim_start_matches('/')
+        .split('/')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let if_none_match = request.headers().get(header::IF_NONE_MATCH).cloned();
+
+    let mut response = next.run(request).await;
+    let cache_control = settings.for_group(&group);
+
+    // the handler has tagged the resource with its version
+    if response.status() == StatusCode::NOT_MODIFIED
+        || (response.status() == StatusCode::OK && response.headers().contains_key(header::ETAG))
+    {
+        cache_headers(response.headers_mut(), cache_control);
+        return response;
+    }
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!("Failed to read the response body: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let etag = format!("\"{}\"", hex::encode(Sha256::digest(&body)));
+    if let Ok(etag) = HeaderValue::from_str(&etag) {
+        parts.headers.insert(header::ETAG, etag);
+    }
+    cache_headers(&mut parts.headers, cache_control);
+
+    if if_none_match.is_some_and(|value| matches(&value, &etag)) {
+        let mut not_modified = StatusCode::NOT_MODIFIED.into_response();
+        for name in [header::ETAG, header::CACHE_CONTROL, header::VARY] {
+            if let Some(value) = parts.headers.get(&name) {
+                not_modified.headers_mut().insert(name, value.clone());
+            }
+        }
+
+        return not_modified;
+    }
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+fn cache_headers(headers: &mut HeaderMap, cache_control: &str) {
+    if let Ok(cache_control) = HeaderValue::from_str(cache_control) {
+        headers.insert(header::CACHE_CONTROL, cache_control);
+    }
+    // the content is localized, so the caches have to keep a response per language
+    headers.insert(header::VARY, HeaderValue::from_static("Accept-Language"));
+}
+
+/// The ETag of a version of a resource, e.g. `"3"`, which is sent back as `If-Match`
+pub(crate) fn version_tag(version: i32) -> String {
+    format!("\"{}\"", version)
+}
+
+/// Whether the `If-None-Match` of the request matches the ETag
+pub(crate) fn not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|value| matches(value, etag))
+}
+
+/// `If-None-Match` uses the weak comparison, so `W/"x"` matches `"x"` (RFC 9110 13.1.2)
+fn matches(if_none_match: &HeaderValue, etag: &str) -> bool {
+    let Ok(value) = if_none_match.to_str() else {
+        return false;
+    };
+
+    value.trim() == "*"
+        || value
+            .split(',')
+            .map(|tag| tag.trim())
+            .any(|tag| tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use axum::Router;
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        let settings = CacheControlSettings {
+            default: "public, no-cache".to_string(),
+            groups: HashMap::from([("articles".to_string(), "public, max-age=60".to_string())]),
+        };
+
+        Router::new()
+            .route("/articles", get(|| async { "articles" }))
+            .route("/members", get(|| async { "members" }))
+            .route(
+                "/services/1",
+                get(|headers: HeaderMap| async move {
+                    let etag = version_tag(3);
+                    if not_modified(&headers, &etag) {
+                        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+                    }
+
+                    ([(header::ETAG, etag)], "service").into_response()
+                }),
+            )
+            .route("/missing", get(|| async { StatusCode::NOT_FOUND }))
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(settings),
+                conditional_get,
+            ))
+    }
+
+    async fn send(uri: &str, if_none_match: Option<&str>) -> Response {
+        let mut request = Request::builder().uri(uri);
+        if let Some(etag) = if_none_match {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+
+        app()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn it_should_tag_the_response_and_apply_the_cache_control_of_the_group() {
+        let articles = send("/articles", None).await;
+        assert_eq!(articles.status(), StatusCode::OK);
+        assert!(articles.headers().contains_key(header::ETAG));
+        assert_eq!(
+            articles.headers()[header::CACHE_CONTROL],
+            "public, max-age=60"
+        );
+
+        let members = send("/members", None).await;
+        assert_eq!(members.headers()[header::CACHE_CONTROL], "public, no-cache");
+        assert_ne!(
+            members.headers()[header::ETAG],
+            articles.headers()[header::ETAG]
+        );
+    }
+
+    #[tokio::test]
+    async fn it_should_return_not_modified_when_the_etag_matches() {
+        let response = send("/articles", None).await;
+        let etag = response.headers()[header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        for if_none_match in [
+            etag.clone(),
+            format!("W/{}", etag),
+            format!("\"x\", {}", etag),
+        ] {
+            let response = send("/articles", Some(&if_none_match)).await;
+
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+            assert_eq!(response.headers()[header::ETAG], etag.as_str());
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert!(body.is_empty());
+        }
+
+        let response = send("/articles", Some("\"stale\"")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn it_should_keep_the_version_which_the_resource_is_tagged_with() {
+        let response = send("/services/1", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ETAG], "\"3\"");
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            "public, no-cache"
+        );
+        assert_eq!(response.headers()[header::VARY], "Accept-Language");
+
+        let response = send("/services/1", Some("W/\"3\"")).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], "\"3\"");
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            "public, no-cache"
+        );
+
+        let response = send("/services/1", Some("\"2\"")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn it_should_leave_the_failures_alone() {
+        let response = send("/missing", None).await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(!response.headers().contains_key(header::ETAG));
+    }
+}
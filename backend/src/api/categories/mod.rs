@@ -1,6 +1 @@
-pub mod create;
-pub mod update;
-
-pub mod delete;
-pub mod list;
-pub mod retrieve;
+pub mod tree;
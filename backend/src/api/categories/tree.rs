@@ -0,0 +1,44 @@
+use crate::api::api_error::ApiError;
+use crate::api::resources::accept_language;
+use crate::domain::categories::tree::CategoryNode;
+use crate::domain::entities::Language;
+use crate::startup::AppState;
+use crate::uow::InDatabase;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::Json;
+use schemars::JsonSchema;
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CategoryTreeResponse {
+    pub categories: Vec<CategoryNode>,
+}
+
+/** Lists the categories as a tree along with how many articles are in them.
+*
+* The articles don't have a published flag, so every article in the requested language is counted.
+*/
+pub async fn retrieve_category_tree(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<CategoryTreeResponse>, ApiError> {
+    let uow = InDatabase::new(&state.pool)
+        .await
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+    let uow = Mutex::new(uow);
+
+    let req = crate::domain::categories::tree::Request {
+        language: accept_language(&headers),
+        default_language: Language::ZH,
+    };
+
+    match crate::domain::categories::tree::execute(uow, req).await {
+        Ok(categories) => Ok(Json(CategoryTreeResponse { categories })),
+        Err(crate::domain::categories::tree::Error::BadRequest) => Err(ApiError::BadRequest),
+        Err(crate::domain::categories::tree::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::request::Parts;
+use axum::http::{Extensions, HeaderMap, HeaderName};
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+/// The header which holds the IP of the client, it's only trusted if it's configured
+#[derive(Debug, Clone, Default)]
+pub struct ClientIpHeader(pub Option<HeaderName>);
+
+/// The IP of the client, the header of the proxy is preferred to the address of the connection
+pub fn client_ip(headers: &HeaderMap, extensions: &Extensions) -> Option<IpAddr> {
+    let forwarded = extensions
+        .get::<ClientIpHeader>()
+        .and_then(|ClientIpHeader(name)| name.as_ref())
+        .and_then(|name| headers.get(name))
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .and_then(|value| value.trim().parse::<IpAddr>().ok());
+
+    forwarded.or_else(|| {
+        extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+    })
+}
+
+/// Extracts the IP of the client, it's missing if the server isn't served with the addresses
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        Ok(ClientIp(client_ip(&parts.headers, &parts.extensions)))
+    }
+}
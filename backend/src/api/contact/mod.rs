@@ -1,5 +0,0 @@
-pub mod create;
-pub mod list;
-pub mod retrieve;
-
-pub mod update;
@@ -0,0 +1 @@
+pub mod structured_data;
@@ -0,0 +1,150 @@
+use crate::api::api_error::ApiError;
+use crate::domain::entities::{FaqEntity, Language, Pagination};
+use crate::domain::registry::{filters_from_params, FaqKind};
+use crate::startup::AppState;
+use crate::uow::InDatabase;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+
+/// Converts the FAQs into a schema.org `FAQPage` JSON-LD document
+fn to_faq_page(language: &str, faqs: Vec<FaqEntity>) -> serde_json::Value {
+    let questions = faqs
+        .into_iter()
+        .map(|faq| {
+            json!({
+                "@type": "Question",
+                "name": faq.data.question,
+                "acceptedAnswer": {
+                    "@type": "Answer",
+                    "text": faq.data.answer,
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "@context": "https://schema.org",
+        "@type": "FAQPage",
+        "inLanguage": language,
+        "mainEntity": questions,
+    })
+}
+
+/// Emits the FAQs of a service as schema.org `FAQPage` JSON-LD
+pub async fn retrieve_service_faq_page(
+    State(state): State<AppState>,
+    Path(params): Path<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let uow = InDatabase::new(&state.pool)
+        .await
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+    let uow = Mutex::new(uow);
+
+    let service_id = params.get("id").ok_or(ApiError::BadRequest)?;
+    let filters = filters_from_params::<FaqKind>(&HashMap::from([(
+        "service_id".to_string(),
+        service_id.to_string(),
+    )]));
+    let lang = headers
+        .get("Accept-Language")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("zh");
+
+    let req = crate::domain::resources::list::Request {
+        filters,
+        language: lang.to_string(),
+        default_language: Language::ZH,
+        pagination: Pagination::All,
+    };
+
+    match crate::domain::resources::list::execute::<_, FaqKind>(uow, req).await {
+        Ok((faqs, _)) => {
+            // the contents might fall back to the default language
+            let language = faqs
+                .first()
+                .map(|faq| faq.language.clone())
+                .unwrap_or(lang.to_string());
+            let mut response = Json(to_faq_page(&language, faqs)).into_response();
+            response.headers_mut().insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/ld+json"),
+            );
+
+            Ok(response)
+        }
+        Err(crate::domain::resources::list::Error::BadRequest) => Err(ApiError::BadRequest),
+        Err(crate::domain::resources::list::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::FaqData;
+    use crate::domain::registry::ServiceKind;
+    use crate::domain::resources::test_helpers::tests::{
+        create_some_fake_data_and_return_uow, insert_fake_resource,
+    };
+
+    #[tokio::test]
+    async fn it_should_describe_the_faqs_of_the_service_as_a_faq_page() {
+        let (mut uow, service) = create_some_fake_data_and_return_uow::<ServiceKind>().await;
+        insert_fake_resource::<FaqKind>(
+            &mut uow,
+            &FaqData::new(
+                "question".to_string(),
+                "answer".to_string(),
+                vec![service.to_string()],
+            ),
+        )
+        .await;
+        // the FAQs of other services are left out
+        insert_fake_resource::<FaqKind>(
+            &mut uow,
+            &FaqData::new(
+                "another question".to_string(),
+                "another answer".to_string(),
+                vec![],
+            ),
+        )
+        .await;
+
+        let req = crate::domain::resources::list::Request {
+            filters: filters_from_params::<FaqKind>(&HashMap::from([(
+                "service_id".to_string(),
+                service.to_string(),
+            )])),
+            language: "en".to_string(),
+            default_language: Language::ZH,
+            pagination: Pagination::All,
+        };
+        let (faqs, _) = crate::domain::resources::list::execute::<_, FaqKind>(Mutex::new(uow), req)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            to_faq_page("zh", faqs),
+            json!({
+                "@context": "https://schema.org",
+                "@type": "FAQPage",
+                "inLanguage": "zh",
+                "mainEntity": [{
+                    "@type": "Question",
+                    "name": "question",
+                    "acceptedAnswer": {
+                        "@type": "Answer",
+                        "text": "answer",
+                    },
+                }],
+            })
+        );
+    }
+}
@@ -1,11 +1 @@
-pub mod create;
-
-pub mod delete;
-
-pub mod list;
-
-pub mod retrieve;
-
-pub mod update;
-
 pub mod upload_avatar;
@@ -1,7 +1,9 @@
 use crate::api::api_error::ApiError;
 use crate::api::auth::Claims;
-use crate::domain::entities::ResourceType;
+use crate::api::client_ip::ClientIp;
+use crate::domain::audit::Actor;
 use crate::domain::member::upload_avatar::{execute, Error, Request};
+use crate::domain::registry::{MemberKind, ResourceKind};
 use crate::startup::AppState;
 use crate::uow::InDatabase;
 use crate::utils::image::ImageUtil;
@@ -12,8 +14,9 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 
 pub async fn upload_member_avatar(
-    _: Claims,
+    claims: Claims,
     State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
     Extension(image_util): Extension<Arc<ImageUtil>>,
     Path(params): Path<HashMap<String, String>>,
     mut multipart: Multipart,
@@ -36,11 +39,23 @@ pub async fn upload_member_avatar(
             let data = data.to_vec();
             let req = Request {
                 id: member_id.to_string(),
-                resource_type: ResourceType::Member,
+                resource_type: MemberKind::TYPE,
                 data,
+                actor: Some(Actor {
+                    user_id: claims.user_id()?,
+                    ip,
+                }),
             };
 
-            match execute(uow, state.upload_folder.clone(), image_util, req).await {
+            match execute(
+                uow,
+                &*state.cache,
+                state.upload_folder.clone(),
+                image_util,
+                req,
+            )
+            .await
+            {
                 Ok(_) => {}
                 Err(Error::NotFound) => return Err(ApiError::NotFound),
                 Err(Error::BadRequest) => return Err(ApiError::BadRequest),
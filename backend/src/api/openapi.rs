@@ -0,0 +1,826 @@
+use crate::api::api_error::{Problem, PROBLEM_CONTENT_TYPE};
+use crate::api::auth::login::{LoginRequest, LoginResponse, LoginResult, TotpLoginRequest};
+use crate::api::auth::refresh::{RefreshRequest, RefreshResponse};
+use crate::api::auth::sessions::{RevokedSessionsResponse, SessionsResponse};
+use crate::api::categories::tree::CategoryTreeResponse;
+use crate::api::users::audit_log::AuditEntriesResponse;
+use crate::api::users::change_password::ChangePasswordRequest;
+use crate::api::users::login_attempts::LoginAttemptsResponse;
+use crate::api::users::manage::{CreateUserRequest, CreateUserResponse, UsersResponse};
+use crate::api::users::reset_password::{ForgotPasswordRequest, ResetPasswordRequest};
+use crate::api::users::totp::{
+    RecoveryCodesResponse, TotpCodeRequest, TotpEnrolmentResponse, TotpStatusResponse,
+};
+use crate::api::versions::ApiVersion;
+use crate::configuration::ApiSettings;
+use crate::domain::entities::SecuritySettings;
+use axum::http::{Method, StatusCode};
+use schemars::generate::SchemaSettings;
+use schemars::{JsonSchema, SchemaGenerator};
+use serde_json::{json, Map, Value};
+
+/** An OpenAPI 3.0 document, the schemas are generated from the request and response types.
+*
+* The operations are added along with the path of their routes, the path parameters are read
+* from the path.
+*/
+pub struct ApiDoc {
+    generator: SchemaGenerator,
+    paths: Map<String, Value>,
+    error: Value,
+    prefix: String,
+    deprecated: bool,
+}
+
+impl Default for ApiDoc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ApiDoc {
+    pub fn new() -> Self {
+        let mut generator = SchemaSettings::openapi3().into_generator();
+        let error = generator.subschema_for::<Problem>().to_value();
+
+        Self {
+            generator,
+            paths: Map::new(),
+            error,
+            prefix: String::new(),
+            deprecated: false,
+        }
+    }
+
+    /// The operations which are added by the function are prefixed like [`ApiRouter::nest`]
+    ///
+    /// [`ApiRouter::nest`]: crate::api::ApiRouter::nest
+    pub fn nest(&mut self, prefix: &str, deprecated: bool, f: impl FnOnce(&mut ApiDoc)) {
+        let nested = format!("{}{}", self.prefix, prefix);
+        let outer = std::mem::replace(&mut self.prefix, nested);
+        let outer_deprecated = std::mem::replace(&mut self.deprecated, deprecated);
+
+        f(self);
+
+        self.prefix = outer;
+        self.deprecated = outer_deprecated;
+    }
+
+    /// Returns the schema of a type, the named ones are referenced from the components
+    pub fn schema<T: JsonSchema>(&mut self) -> Value {
+        self.generator.subschema_for::<T>().to_value()
+    }
+
+    pub fn add(&mut self, path: &str, operation: Operation) {
+        let path = format!("{}{}", self.prefix, path);
+        let parameters = path_params(&path)
+            .map(|name| {
+                json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" },
+                })
+            })
+            .chain(operation.parameters.iter().cloned())
+            .collect::<Vec<_>>();
+
+        let mut responses = Map::new();
+        for (status, description, schema) in &operation.responses {
+            let mut response = json!({ "description": description });
+            if let Some((content_type, schema)) = schema {
+                response["content"] = json!({ *content_type: { "schema": schema } });
+            }
+            responses.insert(status.as_u16().to_string(), response);
+        }
+        for (status, description) in &operation.errors {
+            responses.insert(
+                status.as_u16().to_string(),
+                json!({
+                    "description": description,
+                    "content": { PROBLEM_CONTENT_TYPE: { "schema": self.error } },
+                }),
+            );
+        }
+
+        let mut value = json!({
+            "tags": [operation.tag],
+            "summary": operation.summary,
+            "parameters": parameters,
+            "responses": responses,
+        });
+        if let Some((content_type, schema)) = operation.body {
+            value["requestBody"] = json!({
+                "required": true,
+                "content": { content_type: { "schema": schema } },
+            });
+        }
+        if operation.secured {
+            value["security"] = json!([{ "bearerAuth": [] }]);
+        }
+        if self.deprecated {
+            value["deprecated"] = json!(true);
+        }
+
+        let method = operation.method.as_str().to_lowercase();
+        match self.paths.get_mut(&path) {
+            Some(item) => {
+                item[method] = value;
+            }
+            None => {
+                self.paths
+                    .insert(path.to_string(), json!({ method: value }));
+            }
+        }
+    }
+
+    pub fn into_json(mut self) -> Value {
+        json!({
+            "openapi": "3.0.3",
+            "info": {
+                "title": "Attorneys Website API",
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "paths": self.paths,
+            "components": {
+                "schemas": self.generator.take_definitions(true),
+                "securitySchemes": {
+                    "bearerAuth": {
+                        "type": "http",
+                        "scheme": "bearer",
+                        "bearerFormat": "JWT",
+                    },
+                },
+            },
+        })
+    }
+}
+
+/// The names of the parameters in a path, e.g. `id` in `/members/{id}`
+fn path_params(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+}
+
+/// The content type and the schema of a body
+type Content = (&'static str, Value);
+
+/// An operation of a route
+pub struct Operation {
+    method: Method,
+    tag: &'static str,
+    summary: String,
+    secured: bool,
+    parameters: Vec<Value>,
+    body: Option<Content>,
+    responses: Vec<(StatusCode, &'static str, Option<Content>)>,
+    errors: Vec<(StatusCode, &'static str)>,
+}
+
+impl Operation {
+    pub fn new(method: Method, tag: &'static str, summary: impl Into<String>) -> Self {
+        Self {
+            method,
+            tag,
+            summary: summary.into(),
+            secured: false,
+            parameters: vec![],
+            body: None,
+            responses: vec![],
+            errors: vec![],
+        }
+    }
+
+    /// The operation requires a bearer token which is issued by the login
+    pub fn secured(mut self) -> Self {
+        self.secured = true;
+        self.error(StatusCode::UNAUTHORIZED, "The bearer token is missing")
+            .error(StatusCode::FORBIDDEN, "The bearer token is invalid")
+    }
+
+    /// The operation requires a bearer token whose role has the permission of the operation
+    pub fn restricted(self) -> Self {
+        self.secured().error(
+            StatusCode::FORBIDDEN,
+            "The bearer token is invalid, or its role isn't permitted",
+        )
+    }
+
+    pub fn query(mut self, name: &str, description: &str) -> Self {
+        self.parameters.push(json!({
+            "name": name,
+            "in": "query",
+            "required": false,
+            "description": description,
+            "schema": { "type": "string" },
+        }));
+        self
+    }
+
+    pub fn header(mut self, name: &str, description: &str) -> Self {
+        self.parameters.push(json!({
+            "name": name,
+            "in": "header",
+            "required": false,
+            "description": description,
+            "schema": { "type": "string" },
+        }));
+        self
+    }
+
+    /// The resources are returned in the requested language if they are translated
+    pub fn localized(self) -> Self {
+        self.header(
+            "Accept-Language",
+            "The language of the content, the default language is used when it's missing",
+        )
+    }
+
+    /// The response is tagged, it can be revalidated with `If-None-Match`
+    pub fn conditional(self) -> Self {
+        self.header(
+            "If-None-Match",
+            "The ETag of the cached response, it isn't sent again if it's not modified",
+        )
+        .response(
+            StatusCode::NOT_MODIFIED,
+            "The cached response is still fresh",
+        )
+    }
+
+    pub fn body(mut self, content_type: &'static str, schema: Value) -> Self {
+        self.body = Some((content_type, schema));
+        self.error(StatusCode::BAD_REQUEST, "The request is invalid")
+    }
+
+    pub fn json_body(self, schema: Value) -> Self {
+        self.body("application/json", schema)
+    }
+
+    pub fn response(mut self, status: StatusCode, description: &'static str) -> Self {
+        self.responses.push((status, description, None));
+        self
+    }
+
+    pub fn content(
+        mut self,
+        status: StatusCode,
+        description: &'static str,
+        content_type: &'static str,
+        schema: Value,
+    ) -> Self {
+        self.responses
+            .push((status, description, Some((content_type, schema))));
+        self
+    }
+
+    pub fn json(self, status: StatusCode, description: &'static str, schema: Value) -> Self {
+        self.content(status, description, "application/json", schema)
+    }
+
+    /// The operation may fail with the status, the error payload is returned
+    pub fn error(mut self, status: StatusCode, description: &'static str) -> Self {
+        self.errors.push((status, description));
+        self
+    }
+}
+
+/// Generates the OpenAPI document of the API
+pub fn document(versions: &[ApiVersion], settings: &ApiSettings) -> Value {
+    let mut doc = ApiDoc::new();
+
+    doc.add(
+        "/health",
+        Operation::new(Method::GET, "health", "Check whether the server is running")
+            .response(StatusCode::OK, "The server is running"),
+    );
+
+    for version in versions {
+        let deprecated = settings.deprecation(version.name).is_some();
+        doc.nest(&version.prefix(), deprecated, |doc| {
+            document_shared_routes(doc);
+            version.resources.document(doc);
+        });
+    }
+
+    doc.into_json()
+}
+
+/// Documents the routes which are served by every version
+fn document_shared_routes(doc: &mut ApiDoc) {
+    let login = Operation::new(
+        Method::POST,
+        "users",
+        "Log in with the username and the password",
+    )
+    .json_body(doc.schema::<LoginRequest>())
+    .json(
+        StatusCode::OK,
+        "The access token along with the refresh token of the new session, or a challenge if the user has a second factor",
+        doc.schema::<LoginResult>(),
+    )
+    .error(StatusCode::FORBIDDEN, "The credentials are invalid")
+    .error(
+        StatusCode::TOO_MANY_REQUESTS,
+        "Too many attempts, or the username is locked out, retry after the seconds of `Retry-After`",
+    );
+    doc.add("/admin/login", login);
+
+    let login_totp = Operation::new(
+        Method::POST,
+        "users",
+        "Complete the login with the code of the second factor",
+    )
+    .json_body(doc.schema::<TotpLoginRequest>())
+    .json(
+        StatusCode::OK,
+        "The access token along with the refresh token of the new session",
+        doc.schema::<LoginResponse>(),
+    )
+    .error(
+        StatusCode::FORBIDDEN,
+        "The challenge is invalid or expired, or the code is wrong",
+    )
+    .error(
+        StatusCode::TOO_MANY_REQUESTS,
+        "Too many attempts, or the username is locked out, retry after the seconds of `Retry-After`",
+    );
+    doc.add("/admin/login/totp", login_totp);
+
+    let refresh = Operation::new(
+        Method::POST,
+        "users",
+        "Exchange the refresh token for new tokens",
+    )
+    .json_body(doc.schema::<RefreshRequest>())
+    .json(
+        StatusCode::OK,
+        "The new access token and refresh token, the used refresh token is void",
+        doc.schema::<RefreshResponse>(),
+    )
+    .error(
+        StatusCode::FORBIDDEN,
+        "The refresh token is invalid, expired or used, a used one revokes its session",
+    )
+    .error(
+        StatusCode::TOO_MANY_REQUESTS,
+        "Too many attempts, retry after the seconds of `Retry-After`",
+    );
+    doc.add("/admin/refresh", refresh);
+
+    let forgot_password = Operation::new(
+        Method::POST,
+        "users",
+        "Email a link to reset the forgotten password",
+    )
+    .json_body(doc.schema::<ForgotPasswordRequest>())
+    .response(
+        StatusCode::OK,
+        "The link is emailed if the user exists and has an email, the response is the same either way",
+    )
+    .error(
+        StatusCode::TOO_MANY_REQUESTS,
+        "Too many attempts, retry after the seconds of `Retry-After`",
+    );
+    doc.add("/admin/password/forgot", forgot_password);
+
+    let reset_password = Operation::new(
+        Method::POST,
+        "users",
+        "Reset the password with the emailed token",
+    )
+    .json_body(doc.schema::<ResetPasswordRequest>())
+    .header(
+        "Accept-Language",
+        "The language of the messages of the invalid fields",
+    )
+    .response(
+        StatusCode::OK,
+        "The password is reset, the token is void and all the sessions of the user are ended",
+    )
+    .error(
+        StatusCode::BAD_REQUEST,
+        "The new password is too short, too common or the same as the username",
+    )
+    .error(
+        StatusCode::FORBIDDEN,
+        "The token is invalid, expired or used",
+    )
+    .error(
+        StatusCode::TOO_MANY_REQUESTS,
+        "Too many attempts, retry after the seconds of `Retry-After`",
+    );
+    doc.add("/admin/password/reset", reset_password);
+
+    doc.add(
+        "/admin/logout",
+        Operation::new(Method::POST, "users", "Log out of the current session")
+            .secured()
+            .response(
+                StatusCode::OK,
+                "The session is ended, the other sessions of the user are kept",
+            ),
+    );
+
+    let change_password = Operation::new(Method::PUT, "users", "Change the password")
+        .secured()
+        .json_body(doc.schema::<ChangePasswordRequest>())
+        .header(
+            "Accept-Language",
+            "The language of the messages of the invalid fields",
+        )
+        .response(
+            StatusCode::OK,
+            "The password is changed, the other sessions of the user are ended",
+        )
+        .error(
+            StatusCode::BAD_REQUEST,
+            "The new password is too short, too common or the same as the username",
+        )
+        .error(StatusCode::FORBIDDEN, "The current password is wrong")
+        .error(
+            StatusCode::TOO_MANY_REQUESTS,
+            "The username is locked out by the wrong passwords, retry after the seconds of `Retry-After`",
+        );
+    doc.add("/admin/password", change_password);
+
+    let totp_status = Operation::new(
+        Method::GET,
+        "users",
+        "Check whether the user logs in with a second factor",
+    )
+    .secured()
+    .json(
+        StatusCode::OK,
+        "The status of the second factor",
+        doc.schema::<TotpStatusResponse>(),
+    );
+    doc.add("/admin/totp", totp_status);
+
+    let enroll_totp = Operation::new(Method::POST, "users", "Start to enrol a second factor")
+        .secured()
+        .json(
+            StatusCode::OK,
+            "The secret which is added to the authenticator app, it's on once a code is confirmed",
+            doc.schema::<TotpEnrolmentResponse>(),
+        )
+        .error(StatusCode::CONFLICT, "The second factor is already enabled");
+    doc.add("/admin/totp", enroll_totp);
+
+    let confirm_totp = Operation::new(
+        Method::POST,
+        "users",
+        "Turn the second factor on with a code of the authenticator app",
+    )
+    .secured()
+    .json_body(doc.schema::<TotpCodeRequest>())
+    .json(
+        StatusCode::OK,
+        "The recovery codes, they're only shown once. The access token has to be refreshed if the enrolment is required",
+        doc.schema::<RecoveryCodesResponse>(),
+    )
+    .error(StatusCode::FORBIDDEN, "The code is wrong")
+    .error(
+        StatusCode::CONFLICT,
+        "The second factor isn't enrolled or it's already enabled",
+    );
+    doc.add("/admin/totp/confirm", confirm_totp);
+
+    let disable_totp = Operation::new(Method::POST, "users", "Turn the second factor off")
+        .secured()
+        .json_body(doc.schema::<TotpCodeRequest>())
+        .response(
+            StatusCode::OK,
+            "The second factor is off along with the recovery codes",
+        )
+        .error(StatusCode::FORBIDDEN, "The code is wrong")
+        .error(
+            StatusCode::CONFLICT,
+            "The second factor isn't enabled or it's required",
+        );
+    doc.add("/admin/totp/disable", disable_totp);
+
+    let recovery_codes = Operation::new(Method::POST, "users", "Replace the recovery codes")
+        .secured()
+        .json_body(doc.schema::<TotpCodeRequest>())
+        .json(
+            StatusCode::OK,
+            "The new recovery codes, the old ones are void",
+            doc.schema::<RecoveryCodesResponse>(),
+        )
+        .error(StatusCode::FORBIDDEN, "The code is wrong")
+        .error(StatusCode::CONFLICT, "The second factor isn't enabled");
+    doc.add("/admin/totp/recovery-codes", recovery_codes);
+
+    let security_settings = Operation::new(Method::GET, "users", "Retrieve the security settings")
+        .restricted()
+        .json(
+            StatusCode::OK,
+            "The security settings",
+            doc.schema::<SecuritySettings>(),
+        );
+    doc.add("/admin/security", security_settings);
+
+    let update_security_settings = Operation::new(
+        Method::PUT,
+        "users",
+        "Update the security settings",
+    )
+    .restricted()
+    .json_body(doc.schema::<SecuritySettings>())
+    .json(
+        StatusCode::OK,
+        "The updated settings, the users without a second factor have to enrol once it's required",
+        doc.schema::<SecuritySettings>(),
+    );
+    doc.add("/admin/security", update_security_settings);
+
+    let login_attempts = Operation::new(Method::GET, "users", "List the latest login attempts")
+        .restricted()
+        .query("username", "Only the attempts of the username")
+        .query("ip", "Only the attempts from the IP")
+        .query(
+            "limit",
+            "How many attempts are listed, 100 by default and 500 at most",
+        )
+        .json(
+            StatusCode::OK,
+            "The attempts, the latest one first",
+            doc.schema::<LoginAttemptsResponse>(),
+        )
+        .error(StatusCode::BAD_REQUEST, "The IP is invalid");
+    doc.add("/admin/login-attempts", login_attempts);
+
+    let audit_log = Operation::new(Method::GET, "users", "Browse the audit log")
+        .restricted()
+        .query("user_id", "Only the changes done by the user")
+        .query("action", "Only the changes of the action, e.g. `update`")
+        .query("target_type", "Only the changes of the type, e.g. `user` or `article`")
+        .query("target_id", "Only the changes of the target")
+        .query("language", "Only the changes of the content in the language")
+        .query("since", "Only the changes from the time, in RFC 3339")
+        .query(
+            "until",
+            "Only the changes before the time, the older pages are browsed by the time of the last entry",
+        )
+        .query(
+            "limit",
+            "How many entries are listed, 100 by default and 500 at most",
+        )
+        .json(
+            StatusCode::OK,
+            "The entries, the latest one first",
+            doc.schema::<AuditEntriesResponse>(),
+        )
+        .error(StatusCode::BAD_REQUEST, "A filter is invalid");
+    doc.add("/admin/audit-log", audit_log);
+
+    doc.add(
+        "/admin/lockouts/{username}",
+        Operation::new(
+            Method::DELETE,
+            "users",
+            "Unlock a username which has failed to log in too many times",
+        )
+        .restricted()
+        .response(StatusCode::OK, "The failures of the username are forgiven"),
+    );
+
+    let sessions = Operation::new(Method::GET, "users", "List where the user is logged in")
+        .secured()
+        .json(
+            StatusCode::OK,
+            "The sessions of the user, the latest used one first",
+            doc.schema::<SessionsResponse>(),
+        );
+    doc.add("/admin/sessions", sessions);
+
+    let revoke_others = Operation::new(
+        Method::DELETE,
+        "users",
+        "End the sessions of the user except the current one",
+    )
+    .secured()
+    .json(
+        StatusCode::OK,
+        "How many sessions are ended",
+        doc.schema::<RevokedSessionsResponse>(),
+    );
+    doc.add("/admin/sessions/others", revoke_others);
+
+    let revoke_session = Operation::new(Method::DELETE, "users", "End a session of the user")
+        .secured()
+        .response(StatusCode::OK, "The session is ended")
+        .error(StatusCode::BAD_REQUEST, "The ID is invalid")
+        .error(
+            StatusCode::NOT_FOUND,
+            "The session has ended or it's not of the user",
+        );
+    doc.add("/admin/sessions/{id}", revoke_session);
+
+    let revoke_user_sessions = Operation::new(
+        Method::DELETE,
+        "users",
+        "End all the sessions of a user, e.g. to force them to log out",
+    )
+    .restricted()
+    .json(
+        StatusCode::OK,
+        "How many sessions are ended",
+        doc.schema::<RevokedSessionsResponse>(),
+    )
+    .error(StatusCode::BAD_REQUEST, "The ID is invalid");
+    doc.add("/admin/users/{id}/sessions", revoke_user_sessions);
+
+    let users = Operation::new(Method::GET, "users", "List the users")
+        .restricted()
+        .json(
+            StatusCode::OK,
+            "The users ordered by their usernames",
+            doc.schema::<UsersResponse>(),
+        );
+    doc.add("/admin/users", users);
+
+    let create_user = Operation::new(Method::POST, "users", "Create a user")
+        .restricted()
+        .json_body(doc.schema::<CreateUserRequest>())
+        .json(
+            StatusCode::OK,
+            "The user is created",
+            doc.schema::<CreateUserResponse>(),
+        )
+        .error(
+            StatusCode::BAD_REQUEST,
+            "The password is too short, too common or the same as the username",
+        )
+        .error(StatusCode::CONFLICT, "The username is taken");
+    doc.add("/admin/users", create_user);
+
+    let manage_user = |method: Method, summary: &str, done: &'static str| {
+        Operation::new(method, "users", summary)
+            .restricted()
+            .response(StatusCode::OK, done)
+            .error(StatusCode::BAD_REQUEST, "The ID is invalid")
+            .error(StatusCode::NOT_FOUND, "The user doesn't exist")
+    };
+    doc.add(
+        "/admin/users/{id}/disable",
+        manage_user(
+            Method::POST,
+            "Disable a user, the user is logged out as well",
+            "The user is disabled",
+        )
+        .error(StatusCode::CONFLICT, "The user is the one who disables"),
+    );
+    doc.add(
+        "/admin/users/{id}/enable",
+        manage_user(Method::POST, "Enable a user", "The user is enabled"),
+    );
+    doc.add(
+        "/admin/users/{id}/password-reset",
+        manage_user(
+            Method::POST,
+            "Log a user out and have them change the password on the next login",
+            "The user has to reset the password",
+        ),
+    );
+    doc.add(
+        "/admin/users/{id}/totp-reset",
+        manage_user(
+            Method::POST,
+            "Remove the second factor of a user who lost it, the user is logged out as well",
+            "The second factor is removed, the user can enrol again",
+        ),
+    );
+    doc.add(
+        "/admin/users/{id}",
+        manage_user(
+            Method::DELETE,
+            "Delete a user along with the sessions",
+            "The user is deleted",
+        )
+        .error(StatusCode::CONFLICT, "The user is the one who deletes"),
+    );
+
+    doc.add(
+        "/admin/members/{id}/avatar",
+        Operation::new(Method::POST, "members", "Upload the avatar of a member")
+            .restricted()
+            .body(
+                "multipart/form-data",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "avatar": { "type": "string", "format": "binary" },
+                    },
+                }),
+            )
+            .response(StatusCode::OK, "The avatar is resized and saved")
+            .error(StatusCode::NOT_FOUND, "The member doesn't exist")
+            .error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "The image can't be processed",
+            ),
+    );
+
+    doc.add(
+        "/articles/{id}/view",
+        Operation::new(Method::POST, "articles", "Count a view of an article")
+            .response(StatusCode::OK, "The view is counted")
+            .error(
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many views, retry after the seconds of `Retry-After`",
+            ),
+    );
+
+    doc.add(
+        "/services/{id}/faqs/json-ld",
+        Operation::new(
+            Method::GET,
+            "faqs",
+            "Retrieve the FAQs of a service as schema.org FAQPage JSON-LD",
+        )
+        .localized()
+        .conditional()
+        .content(
+            StatusCode::OK,
+            "The FAQPage document",
+            "application/ld+json",
+            json!({ "type": "object" }),
+        ),
+    );
+
+    let category_tree = Operation::new(
+        Method::GET,
+        "categories",
+        "List the categories as a tree with the article counts",
+    )
+    .localized()
+    .conditional()
+    .json(
+        StatusCode::OK,
+        "The top level categories",
+        doc.schema::<CategoryTreeResponse>(),
+    )
+    .error(StatusCode::BAD_REQUEST, "The language is invalid");
+    doc.add("/categories/tree", category_tree);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::ApiSettings;
+    use crate::startup::api;
+
+    #[test]
+    fn it_should_document_every_route() {
+        let (routes, spec) = api(&ApiSettings::default());
+
+        for (method, path) in routes.routes() {
+            let method = method.as_str().to_lowercase();
+            assert!(
+                spec["paths"][path][&method].is_object(),
+                "{} {} is missing from the OpenAPI document",
+                method,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn it_should_only_document_the_existing_routes() {
+        let (routes, spec) = api(&ApiSettings::default());
+
+        for (path, item) in spec["paths"].as_object().unwrap() {
+            for method in item.as_object().unwrap().keys() {
+                assert!(
+                    routes
+                        .routes()
+                        .iter()
+                        .any(|(m, p)| p == path && m.as_str().eq_ignore_ascii_case(method)),
+                    "{} {} is documented but not routed",
+                    method,
+                    path
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn it_should_secure_the_admin_routes() {
+        let (routes, spec) = api(&ApiSettings::default());
+
+        for (method, path) in routes.routes() {
+            let operation = &spec["paths"][path][method.as_str().to_lowercase()];
+            let secured = operation["security"].is_array();
+            // the tokens are issued by the public routes, the forgotten passwords are reset by them
+            let public = path.ends_with("/login")
+                || path.ends_with("/login/totp")
+                || path.ends_with("/refresh")
+                || path.ends_with("/password/forgot")
+                || path.ends_with("/password/reset");
+            let admin = path.contains("/admin/") && !public;
+
+            assert_eq!(secured, admin, "{} {} has wrong security", method, path);
+        }
+    }
+}
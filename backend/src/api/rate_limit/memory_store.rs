@@ -0,0 +1,43 @@
+use crate::api::rate_limit::{Bucket, Decision, IRateLimitStore};
+use crate::configuration::RateLimitPolicy;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// The buckets are pruned when there are more than this
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Keeps the buckets in the process
+pub struct InMemoryRateLimitStore {
+    started_at: Instant,
+    /// The buckets along with their policies, the groups have different policies
+    buckets: Mutex<HashMap<String, (Bucket, RateLimitPolicy)>>,
+}
+
+impl Default for InMemoryRateLimitStore {
+    fn default() -> Self {
+        Self {
+            started_at: Instant::now(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl IRateLimitStore for InMemoryRateLimitStore {
+    async fn acquire(&self, key: &str, policy: &RateLimitPolicy) -> anyhow::Result<Decision> {
+        let now = self.started_at.elapsed().as_secs_f64();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        // the refilled buckets are the same as the missing ones
+        if buckets.len() > PRUNE_THRESHOLD {
+            buckets.retain(|_, (bucket, policy)| !bucket.is_full(policy, now));
+        }
+
+        let (bucket, _) = buckets
+            .entry(key.to_string())
+            .or_insert_with(|| (Bucket::full(policy, now), *policy));
+
+        Ok(bucket.take(policy, now))
+    }
+}
@@ -0,0 +1,257 @@
+pub use memory_store::InMemoryRateLimitStore;
+pub use redis_store::RedisRateLimitStore;
+
+use crate::api::api_error::ApiError;
+use crate::api::auth::Claims;
+use crate::api::client_ip::client_ip;
+use crate::configuration::{RateLimitKey, RateLimitPolicy, RateLimitSettings};
+use axum::extract::{Request, State};
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use jsonwebtoken::DecodingKey;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod memory_store;
+
+mod redis_store;
+
+/// Whether a request can be handled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decision {
+    Allowed,
+    /// The bucket is empty, a token is refilled after the duration
+    Limited {
+        retry_after: Duration,
+    },
+}
+
+/** Stores the token buckets of the clients */
+#[async_trait::async_trait]
+pub trait IRateLimitStore: Send + Sync {
+    /** Take a token from the bucket of the key, the bucket is full when it's first used */
+    async fn acquire(&self, key: &str, policy: &RateLimitPolicy) -> anyhow::Result<Decision>;
+}
+
+/// How many tokens are refilled per second
+fn refill_rate(policy: &RateLimitPolicy) -> f64 {
+    policy.per_minute as f64 / 60.0
+}
+
+/// The state of a token bucket, the tokens are refilled when the bucket is used
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    /// When the tokens are counted, in seconds
+    updated_at: f64,
+}
+
+impl Bucket {
+    fn full(policy: &RateLimitPolicy, now: f64) -> Self {
+        Self {
+            tokens: policy.burst as f64,
+            updated_at: now,
+        }
+    }
+
+    fn take(&mut self, policy: &RateLimitPolicy, now: f64) -> Decision {
+        let rate = refill_rate(policy);
+        let elapsed = (now - self.updated_at).max(0.0);
+        self.tokens = (self.tokens + elapsed * rate).min(policy.burst as f64);
+        self.updated_at = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Decision::Allowed;
+        }
+
+        // a bucket which is never refilled is tried again a minute later
+        let retry_after = match rate > 0.0 {
+            true => (1.0 - self.tokens) / rate,
+            false => 60.0,
+        };
+        Decision::Limited {
+            retry_after: Duration::from_secs_f64(retry_after),
+        }
+    }
+
+    /// Whether the bucket has been refilled, it's the same as a new one then
+    fn is_full(&self, policy: &RateLimitPolicy, now: f64) -> bool {
+        self.tokens + (now - self.updated_at) * refill_rate(policy) >= policy.burst as f64
+    }
+}
+
+/** Identifies the clients and keeps their buckets.
+*
+* It's shared by the route groups through the extensions of the requests.
+*/
+pub struct RateLimiter {
+    store: Arc<dyn IRateLimitStore>,
+    decoding_key: Arc<DecodingKey>,
+}
+
+impl RateLimiter {
+    pub fn new(store: Arc<dyn IRateLimitStore>, decoding_key: Arc<DecodingKey>) -> Self {
+        Self {
+            store,
+            decoding_key,
+        }
+    }
+
+    /// The user of a valid bearer token, the session isn't checked
+    fn user(&self, request: &Request) -> Option<String> {
+        let bearer = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split_once(' '))?
+            .1;
+
+        jsonwebtoken::decode::<Claims>(
+            bearer,
+            &self.decoding_key,
+            &jsonwebtoken::Validation::default(),
+        )
+        .ok()
+        .map(|token| token.claims.sub)
+    }
+
+    fn client(&self, request: &Request, key: RateLimitKey) -> String {
+        let user = match key {
+            RateLimitKey::User => self.user(request),
+            RateLimitKey::Ip => None,
+        };
+
+        match (user, client_ip(request.headers(), request.extensions())) {
+            (Some(user), _) => format!("user:{}", user),
+            (None, Some(ip)) => format!("ip:{}", ip),
+            (None, None) => "unknown".to_string(),
+        }
+    }
+}
+
+/// The rate limit of a route group
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    group: &'static str,
+    policy: Option<RateLimitPolicy>,
+}
+
+impl RateLimit {
+    pub fn new(group: &'static str, settings: &RateLimitSettings) -> Self {
+        Self {
+            group,
+            policy: settings.for_group(group),
+        }
+    }
+}
+
+/** Limits the request rate of the clients in the route group with a token bucket.
+*
+* The limited requests are answered with `429 Too Many Requests` and `Retry-After`. The limit is
+* only a protection, the requests are handled if the store fails.
+*/
+pub async fn rate_limit(State(limit): State<RateLimit>, request: Request, next: Next) -> Response {
+    let limiter = request.extensions().get::<Arc<RateLimiter>>().cloned();
+    let (Some(policy), Some(limiter)) = (limit.policy, limiter) else {
+        return next.run(request).await;
+    };
+
+    let key = format!("{}:{}", limit.group, limiter.client(&request, policy.key));
+    match limiter.store.acquire(&key, &policy).await {
+        Ok(Decision::Allowed) => next.run(request).await,
+        Ok(Decision::Limited { retry_after }) => {
+            // the clients have to wait at least a second
+            let seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+            tracing::info!(key, seconds, "The request is limited");
+
+            ApiError::TooManyRequests(seconds).into_response()
+        }
+        Err(e) => {
+            tracing::warn!(key, error = %e, "Failed to check the rate limit");
+            next.run(request).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::client_ip::ClientIpHeader;
+    use axum::body::Body;
+    use axum::http::{HeaderName, StatusCode};
+    use axum::routing::get;
+    use axum::{Extension, Router};
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+
+    const POLICY: RateLimitPolicy = RateLimitPolicy {
+        burst: 2,
+        per_minute: 6,
+        key: RateLimitKey::Ip,
+    };
+
+    #[test]
+    fn it_should_refill_the_tokens_over_time() {
+        let mut bucket = Bucket::full(&POLICY, 0.0);
+
+        assert_eq!(bucket.take(&POLICY, 0.0), Decision::Allowed);
+        assert_eq!(bucket.take(&POLICY, 0.0), Decision::Allowed);
+        assert_eq!(
+            bucket.take(&POLICY, 0.0),
+            Decision::Limited {
+                retry_after: Duration::from_secs(10)
+            }
+        );
+        assert_eq!(bucket.take(&POLICY, 10.0), Decision::Allowed);
+        assert!(!bucket.is_full(&POLICY, 10.0));
+        assert!(bucket.is_full(&POLICY, 30.0));
+    }
+
+    async fn send(app: Router, ip: &str) -> Response {
+        app.oneshot(
+            Request::builder()
+                .uri("/")
+                .header("x-real-ip", ip)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn it_should_limit_the_requests_of_a_client_with_429() {
+        let settings = RateLimitSettings {
+            groups: HashMap::from([("login".to_string(), POLICY)]),
+            ..Default::default()
+        };
+        let limiter = RateLimiter::new(
+            Arc::new(InMemoryRateLimitStore::default()),
+            Arc::new(DecodingKey::from_secret(b"secret")),
+        );
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(
+                RateLimit::new("login", &settings),
+                rate_limit,
+            ))
+            .layer(Extension(Arc::new(limiter)))
+            .layer(Extension(ClientIpHeader(Some(HeaderName::from_static(
+                "x-real-ip",
+            )))));
+
+        for _ in 0..2 {
+            let response = send(app.clone(), "10.0.0.1").await;
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let response = send(app.clone(), "10.0.0.1").await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "10");
+
+        let response = send(app, "10.0.0.2").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,74 @@
+use crate::api::rate_limit::{refill_rate, Decision, IRateLimitStore};
+use crate::configuration::RateLimitPolicy;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Takes a token atomically, it returns how many seconds to wait, or 0 when a token is taken
+const TAKE_TOKEN: &str = r#"
+local burst = tonumber(ARGV[1])
+local rate = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) + tonumber(time[2]) / 1000000
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+local tokens = tonumber(bucket[1]) or burst
+local updated_at = tonumber(bucket[2]) or now
+tokens = math.min(burst, tokens + math.max(0, now - updated_at) * rate)
+
+local retry_after = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+elseif rate > 0 then
+    retry_after = (1 - tokens) / rate
+else
+    retry_after = 60
+end
+
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', tostring(now))
+if rate > 0 then
+    redis.call('EXPIRE', KEYS[1], math.ceil(burst / rate) + 1)
+else
+    redis.call('EXPIRE', KEYS[1], 3600)
+end
+
+return tostring(retry_after)
+"#;
+
+/// Keeps the buckets in Redis, so the instances of the server share the limits
+pub struct RedisRateLimitStore {
+    client: Arc<redis::Client>,
+    script: redis::Script,
+}
+
+impl RedisRateLimitStore {
+    pub fn new(client: Arc<redis::Client>) -> Self {
+        Self {
+            client,
+            script: redis::Script::new(TAKE_TOKEN),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl IRateLimitStore for RedisRateLimitStore {
+    async fn acquire(&self, key: &str, policy: &RateLimitPolicy) -> anyhow::Result<Decision> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+
+        // the numbers are returned as strings, Redis truncates the numbers of the scripts
+        let retry_after: String = self
+            .script
+            .key(format!("rate_limit:{}", key))
+            .arg(policy.burst)
+            .arg(refill_rate(policy))
+            .invoke_async(&mut conn)
+            .await?;
+        let retry_after = retry_after.parse::<f64>()?;
+
+        match retry_after > 0.0 {
+            true => Ok(Decision::Limited {
+                retry_after: Duration::from_secs_f64(retry_after),
+            }),
+            false => Ok(Decision::Allowed),
+        }
+    }
+}
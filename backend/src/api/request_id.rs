@@ -0,0 +1,99 @@
+use axum::extract::Request;
+use axum::http::{HeaderName, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use tracing::Instrument;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/** Identifies every request.
+*
+* The ID is taken from the `X-Request-ID` header of the request if the proxy has given one,
+* otherwise a new one is generated. It's returned in the same header, recorded in the span of
+* the request and put in the error responses, so a reported error can be found in the logs.
+*/
+pub async fn request_id(request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= 128)
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %id,
+        method = %request.method(),
+        uri = %request.uri(),
+    );
+
+    let mut response = REQUEST_ID
+        .scope(id.clone(), next.run(request).instrument(span))
+        .await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    response
+}
+
+/// The ID of the request which is being handled
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::api_error::ApiError;
+    use axum::body::{to_bytes, Body};
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    async fn send(request_id: Option<&str>) -> (Response, serde_json::Value) {
+        let app = Router::new()
+            .route("/", get(|| async { ApiError::NotFound }))
+            .layer(axum::middleware::from_fn(super::request_id));
+
+        let mut request = Request::builder().uri("/");
+        if let Some(id) = request_id {
+            request = request.header(REQUEST_ID_HEADER, id);
+        }
+
+        let response = app
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let (parts, body) = response.into_parts();
+        let body = to_bytes(body, usize::MAX).await.unwrap();
+
+        (
+            Response::from_parts(parts, Body::empty()),
+            serde_json::from_slice(&body).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn it_should_put_the_given_request_id_in_the_error() {
+        let (response, body) = send(Some("7f0c2b1e")).await;
+
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "7f0c2b1e");
+        assert_eq!(body["request_id"], "7f0c2b1e");
+    }
+
+    #[tokio::test]
+    async fn it_should_generate_a_request_id_otherwise() {
+        let (response, body) = send(None).await;
+
+        let id = response.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+        assert!(Uuid::parse_str(id).is_ok());
+        assert_eq!(body["request_id"], id);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::api::api_error::ApiError;
+use crate::api::auth::Claims;
+use crate::api::client_ip::ClientIp;
+use crate::api::resources::accept_language;
+use crate::domain::audit::Actor;
+use crate::domain::entities::Language;
+use crate::domain::registry::ResourceKind;
+use crate::domain::resources::create::Position;
+use crate::startup::AppState;
+use crate::uow::InDatabase;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::Json;
+use axum_extra::extract::WithRejection;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use ulid::Ulid;
+
+#[derive(Debug, Deserialize)]
+#[serde(bound = "")]
+pub(crate) struct CreateResourceRequest<K: ResourceKind> {
+    #[serde(flatten)]
+    data: K::Data,
+    language: String,
+    /// The resource is put at the end of the list when neither the sequence nor the position is given
+    seq: Option<i32>,
+    /// Puts the resource at the position and shifts the others back
+    insert_at: Option<i32>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct CreateResourceResponse {
+    id: String,
+}
+
+pub async fn create_resource<K: ResourceKind>(
+    claims: Claims,
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    WithRejection(Json(req), _): WithRejection<Json<CreateResourceRequest<K>>, ApiError>,
+) -> Result<Json<CreateResourceResponse>, ApiError> {
+    let position = match (req.seq, req.insert_at) {
+        (None, None) => Position::Last,
+        (Some(seq), None) => Position::Seq(seq),
+        (None, Some(seq)) => Position::At(seq),
+        (Some(_), Some(_)) => return Err(ApiError::BadRequest),
+    };
+
+    let request = crate::domain::resources::create::Request::<K> {
+        id: Ulid::new().to_string(),
+        data: req.data,
+        language: req.language,
+        position,
+        created_by: Some(claims.user_id()?),
+        actor: Some(Actor {
+            user_id: claims.user_id()?,
+            ip,
+        }),
+    };
+
+    // the messages of the failing fields are in the requested language
+    let language = Language::try_from(accept_language(&headers)).unwrap_or(Language::ZH);
+
+    let uow = InDatabase::new(&state.pool)
+        .await
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+    let uow = Mutex::new(uow);
+
+    match crate::domain::resources::create::execute(uow, &*state.cache, request).await {
+        Ok(id) => Ok(Json(CreateResourceResponse { id: id.to_string() })),
+        Err(crate::domain::resources::create::Error::BadRequest) => Err(ApiError::BadRequest),
+        Err(crate::domain::resources::create::Error::Invalid(fields)) => {
+            Err(ApiError::invalid_fields(fields, &language))
+        }
+        Err(crate::domain::resources::create::Error::InvalidReference(field)) => {
+            Err(ApiError::InvalidReference(field))
+        }
+        Err(crate::domain::resources::create::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
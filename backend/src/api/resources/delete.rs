@@ -0,0 +1,82 @@
+use crate::api::api_error::ApiError;
+use crate::api::auth::Claims;
+use crate::api::client_ip::ClientIp;
+use crate::domain::audit::Actor;
+use crate::domain::registry::ResourceKind;
+use crate::domain::resources::delete::Strategy;
+use crate::domain::users::permissions::{Action, Permission, Subject};
+use crate::startup::AppState;
+use crate::uow::InDatabase;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+
+/// How the resources which refer to the deleted one are handled, either of them can be given.
+/// The gap in the sequences can be closed as well.
+#[derive(Debug, Deserialize)]
+pub(crate) struct DeleteResourceParams {
+    reassign_to: Option<String>,
+    #[serde(default)]
+    detach: bool,
+    /// Moves the resources after the deleted one forward
+    #[serde(default)]
+    close_gap: bool,
+}
+
+impl DeleteResourceParams {
+    fn strategy(&self) -> Result<Option<Strategy>, ApiError> {
+        match (self.reassign_to.clone(), self.detach) {
+            (Some(_), true) => Err(ApiError::BadRequest),
+            (Some(target), false) => Ok(Some(Strategy::Reassign(target))),
+            (None, true) => Ok(Some(Strategy::Detach)),
+            (None, false) => Ok(None),
+        }
+    }
+}
+
+pub async fn delete_resource<K: ResourceKind>(
+    claims: Claims,
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    Path(params): Path<HashMap<String, String>>,
+    Query(query): Query<DeleteResourceParams>,
+) -> Result<StatusCode, ApiError> {
+    let uow = InDatabase::new(&state.pool)
+        .await
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+    let uow = Mutex::new(uow);
+    let id = params.get("id").ok_or(ApiError::BadRequest)?;
+
+    let req = crate::domain::resources::delete::Request {
+        id: id.to_string(),
+        strategy: query.strategy()?,
+        close_gap: query.close_gap,
+        owner: claims.owner(&Permission::new(Subject::Resource(K::TYPE), Action::Delete))?,
+        actor: Some(Actor {
+            user_id: claims.user_id()?,
+            ip,
+        }),
+    };
+
+    match crate::domain::resources::delete::execute::<_, K>(uow, &*state.cache, req).await {
+        Ok(_) => Ok(StatusCode::OK),
+        Err(crate::domain::resources::delete::Error::BadRequest) => Err(ApiError::BadRequest),
+        Err(crate::domain::resources::delete::Error::NotFound) => Err(ApiError::NotFound),
+        Err(crate::domain::resources::delete::Error::Forbidden) => Err(ApiError::Forbidden),
+        Err(crate::domain::resources::delete::Error::InUse(count)) => {
+            Err(ApiError::Conflict(format!(
+                "The {} is referred by {} resources, reassign or detach them to delete it",
+                K::SINGULAR,
+                count
+            )))
+        }
+        Err(crate::domain::resources::delete::Error::InvalidTarget) => {
+            Err(ApiError::InvalidReference("reassign_to"))
+        }
+        Err(crate::domain::resources::delete::Error::Unknown(reason)) => {
+            Err(ApiError::InternalServerError(reason))
+        }
+    }
+}
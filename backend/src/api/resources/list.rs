@@ -0,0 +1,97 @@
+use crate::api::api_error::ApiError;
+use crate::api::auth::Claims;
+use crate::api::resources::{accept_language, visibility_filters, KeyedResponse};
+use crate::cache;
+use crate::domain::entities::{Language, Page, Pagination};
+use crate::domain::registry::{filters_from_params, Filter, ResourceKind};
+use crate::startup::AppState;
+use crate::uow::InDatabase;
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::Json;
+use serde_json::Value;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+
+/// Reads the `page` and `page_size` parameters when the resource type is listed by pages
+fn pagination<K: ResourceKind>(params: &HashMap<String, String>) -> Result<Pagination, ApiError> {
+    let parse = |key: &str, default: u32| match params.get(key) {
+        Some(value) => value.parse::<u32>().map_err(|_| ApiError::BadRequest),
+        None => Ok(default),
+    };
+
+    match K::PAGINATION {
+        Pagination::Page(default) => Ok(Pagination::Page(Page {
+            page: parse("page", default.page)?,
+            size: parse("page_size", default.size)?,
+        })),
+        pagination => Ok(pagination),
+    }
+}
+
+async fn list<K: ResourceKind>(
+    state: AppState,
+    headers: HeaderMap,
+    params: HashMap<String, String>,
+    mut filters: Vec<Filter>,
+) -> Result<Json<KeyedResponse<Vec<K::ListItem>>>, ApiError> {
+    let pagination = pagination::<K>(&params)?;
+    filters.extend(filters_from_params::<K>(&params));
+
+    let uow = InDatabase::new(&state.pool)
+        .await
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+    let uow = Mutex::new(uow);
+
+    let req = crate::domain::resources::list::Request {
+        filters,
+        language: accept_language(&headers),
+        default_language: Language::ZH,
+        pagination,
+    };
+
+    match crate::domain::resources::list::execute::<_, K>(uow, req).await {
+        Ok((items, total)) => Ok(Json(KeyedResponse {
+            key: K::PLURAL,
+            value: items,
+            total: matches!(pagination, Pagination::Page(_)).then_some(total),
+        })),
+        Err(crate::domain::resources::list::Error::BadRequest) => Err(ApiError::BadRequest),
+        Err(crate::domain::resources::list::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
+
+/// Lists the visible resources, the pages are read through the cache
+pub async fn list_resources<K: ResourceKind>(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, ApiError> {
+    let mut filters = visibility_filters::<K>();
+    filters.extend(filters_from_params::<K>(&params));
+    let pagination = pagination::<K>(&params)?;
+    let key = cache::list_key::<K>(&accept_language(&headers), &filters, &pagination);
+    let tags = cache::list_tags(&K::TYPE);
+
+    let cache = state.cache.clone();
+    let load = async {
+        let Json(response) = list::<K>(state, headers, params, visibility_filters::<K>()).await?;
+        serde_json::to_value(response).map_err(|e| ApiError::InternalServerError(e.to_string()))
+    };
+
+    cache::read_through(&*cache, &key, &tags, load)
+        .await
+        .map(Json)
+}
+
+/// Lists the resources regardless of their visibility
+pub async fn list_resources_for_admin<K: ResourceKind>(
+    _: Claims,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<KeyedResponse<Vec<K::ListItem>>>, ApiError> {
+    list::<K>(state, headers, params, vec![]).await
+}
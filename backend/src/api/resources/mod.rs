@@ -0,0 +1,211 @@
+use crate::domain::registry::{Filter, ResourceKind};
+use axum::http::HeaderMap;
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
+
+pub mod create;
+
+pub mod delete;
+
+pub mod list;
+
+pub mod retrieve;
+
+pub mod routes;
+
+pub mod schema;
+
+pub mod update;
+
+/// Gets the requested language, the default language is used when it's missing
+pub(crate) fn accept_language(headers: &HeaderMap) -> String {
+    headers
+        .get("Accept-Language")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("zh")
+        .to_string()
+}
+
+/// The filters which hide the invisible resources from the public
+fn visibility_filters<K: ResourceKind>() -> Vec<Filter> {
+    K::VISIBILITY_FIELD
+        .map(|field| vec![Filter::IsTrue { field }])
+        .unwrap_or_default()
+}
+
+/// A response whose payload is keyed by the name of the resource type, e.g. `{"member": {...}}`
+pub(crate) struct KeyedResponse<T> {
+    key: &'static str,
+    value: T,
+    total: Option<usize>,
+}
+
+impl<T: Serialize> Serialize for KeyedResponse<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry(self.key, &self.value)?;
+        if let Some(total) = self.total {
+            map.serialize_entry("total", &total)?;
+        }
+        map.end()
+    }
+}
+
+/// The schema of a [`KeyedResponse`] whose value is described by the given schema
+fn keyed_schema(key: &'static str, value: Value, with_total: bool) -> Value {
+    let mut schema = json!({
+        "type": "object",
+        "properties": { key: value },
+        "required": [key],
+    });
+    if with_total {
+        schema["properties"]["total"] = json!({ "type": "integer", "minimum": 0 });
+        schema["required"] = json!([key, "total"]);
+    }
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::NoCache;
+    use crate::domain::entities::{ContentData, ContentID, Language, Pagination, ResourceID};
+    use crate::domain::registry::{OnDelete, Reference};
+    use crate::domain::resources::test_helpers::tests::{
+        create_some_fake_data_and_return_uow, for_each_resource_kind, insert_fake_resource,
+        FakeResource,
+    };
+    use crate::domain::resources::{create, delete, list, update};
+    use crate::repositories::{IContentRepository, IResourceRepository};
+    use crate::uow::{IResourceUnitOfWork, InMemory};
+    use tokio::sync::Mutex;
+    use ulid::Ulid;
+
+    /// The lists of IDs are named in the plural, e.g. `service_ids`
+    fn referring_value(reference: &Reference, id: &str) -> Value {
+        if reference.field.ends_with("_ids") {
+            json!([id])
+        } else {
+            json!(id)
+        }
+    }
+
+    /// Replaces a field of the fake data
+    fn fake_with<K: FakeResource>(field: &str, value: Value) -> K::Data {
+        let mut data = serde_json::to_value(K::fake()).unwrap();
+        data[field] = value;
+        serde_json::from_value(data).unwrap()
+    }
+
+    async fn assert_unknown_references_refused<K: FakeResource>() {
+        for reference in K::REFERENCES {
+            let data = fake_with::<K>(
+                reference.field,
+                referring_value(reference, &Ulid::new().to_string()),
+            );
+            let req = create::Request::<K> {
+                id: Ulid::new().to_string(),
+                data: data.clone(),
+                language: "zh".to_string(),
+                position: create::Position::Last,
+                created_by: None,
+                actor: None,
+            };
+            let (uow, _) = create_some_fake_data_and_return_uow::<K>().await;
+            match create::execute(Mutex::new(uow), &NoCache, req).await {
+                Err(create::Error::InvalidReference(field)) => assert_eq!(field, reference.field),
+                _ => unreachable!(),
+            }
+
+            let (uow, id) = create_some_fake_data_and_return_uow::<K>().await;
+            let req = update::Request::<K> {
+                id: id.to_string(),
+                data,
+                language: "zh".to_string(),
+                seq: 0,
+                version: None,
+                owner: None,
+                actor: None,
+            };
+            match update::execute(Mutex::new(uow), &NoCache, req).await {
+                Err(update::Error::InvalidReference(field)) => assert_eq!(field, reference.field),
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    async fn assert_referred_resources_kept<K: FakeResource>() {
+        for reference in K::REFERENCED_BY {
+            for strategy in [None, Some(delete::Strategy::Detach)] {
+                let (mut uow, id) = create_some_fake_data_and_return_uow::<K>().await;
+                // only the referring field of the data matters
+                let referring = ResourceID::try_from(Ulid::new().to_string()).unwrap();
+                let data = ContentData::try_from_data::<K>(&K::fake())
+                    .unwrap()
+                    .with_field(reference.field, referring_value(reference, id.as_str()));
+                uow.resource_repository()
+                    .insert(referring.clone(), reference.from, 0, None)
+                    .await
+                    .unwrap();
+                uow.content_repository()
+                    .insert(ContentID::from(referring), data, Language::ZH)
+                    .await
+                    .unwrap();
+
+                let refused = strategy.is_none() && reference.on_delete == OnDelete::Refuse;
+                let req = delete::Request {
+                    id: id.to_string(),
+                    strategy,
+                    close_gap: false,
+                    owner: None,
+                    actor: None,
+                };
+                match delete::execute::<_, K>(Mutex::new(uow), &NoCache, req).await {
+                    Err(delete::Error::InUse(1)) if refused => {}
+                    Ok(_) if !refused => {}
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+
+    async fn assert_invisible_resources_hidden<K: FakeResource>() {
+        let Some(field) = K::VISIBILITY_FIELD else {
+            return;
+        };
+        let mut uow = InMemory::new();
+        for visible in [true, false] {
+            insert_fake_resource::<K>(&mut uow, &fake_with::<K>(field, json!(visible))).await;
+        }
+
+        let req = list::Request {
+            filters: visibility_filters::<K>(),
+            language: "zh".to_string(),
+            default_language: Language::ZH,
+            pagination: Pagination::All,
+        };
+        let (items, total) = list::execute::<_, K>(Mutex::new(uow), req).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(
+            serde_json::to_value(&items[0]).unwrap()["data"][field],
+            json!(true)
+        );
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_the_unknown_references_on_create_and_update() {
+        for_each_resource_kind!(assert_unknown_references_refused);
+    }
+
+    #[tokio::test]
+    async fn it_should_keep_the_referred_resources_unless_the_references_are_detached() {
+        // the references which are detached on deletion don't keep the resources
+        for_each_resource_kind!(assert_referred_resources_kept);
+    }
+
+    #[tokio::test]
+    async fn it_should_hide_the_invisible_resources_from_the_public() {
+        for_each_resource_kind!(assert_invisible_resources_hidden);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::api::api_error::ApiError;
+use crate::api::auth::Claims;
+use crate::api::caching::{not_modified, version_tag};
+use crate::api::resources::{accept_language, visibility_filters, KeyedResponse};
+use crate::cache;
+use crate::domain::entities::Language;
+use crate::domain::registry::{Filter, ResourceKind};
+use crate::startup::AppState;
+use crate::uow::InDatabase;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+
+async fn retrieve<K: ResourceKind>(
+    state: AppState,
+    params: HashMap<String, String>,
+    headers: HeaderMap,
+    filters: Vec<Filter>,
+) -> Result<Json<KeyedResponse<K::Entity>>, ApiError> {
+    let uow = InDatabase::new(&state.pool)
+        .await
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+    let uow = Mutex::new(uow);
+
+    let id = params.get("id").ok_or(ApiError::BadRequest)?;
+
+    let req = crate::domain::resources::retrieve::Request {
+        id: id.to_string(),
+        language: accept_language(&headers),
+        default_language: Language::ZH,
+        filters,
+    };
+
+    match crate::domain::resources::retrieve::execute::<_, K>(uow, req).await {
+        Ok(entity) => Ok(Json(KeyedResponse {
+            key: K::SINGULAR,
+            value: entity,
+            total: None,
+        })),
+        Err(crate::domain::resources::retrieve::Error::BadRequest) => Err(ApiError::BadRequest),
+        Err(crate::domain::resources::retrieve::Error::NotFound) => Err(ApiError::NotFound),
+        Err(crate::domain::resources::retrieve::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
+
+/** Retrieves a resource, the invisible ones are treated as not found, it's read through the cache.
+*
+* It's tagged with its version, unless it falls back to another language, whose version would
+* stay the same once the requested language is translated.
+*/
+pub async fn retrieve_resource<K: ResourceKind>(
+    State(state): State<AppState>,
+    Path(params): Path<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let id = params.get("id").ok_or(ApiError::BadRequest)?;
+    let language = accept_language(&headers);
+    let filters = visibility_filters::<K>();
+    let key = cache::resource_key::<K>(id, &language, &filters);
+    let tags = cache::resource_tags(&K::TYPE, id);
+
+    let cache = state.cache.clone();
+    let load = async {
+        let Json(response) = retrieve::<K>(state, params, headers.clone(), filters).await?;
+        serde_json::to_value(response).map_err(|e| ApiError::InternalServerError(e.to_string()))
+    };
+
+    let value = cache::read_through(&*cache, &key, &tags, load).await?;
+    let entity = &value[K::SINGULAR];
+    let version = entity["version"]
+        .as_i64()
+        .and_then(|version| i32::try_from(version).ok())
+        .filter(|_| entity["language"].as_str() == Some(language.as_str()));
+    let Some(etag) = version.map(version_tag) else {
+        return Ok(Json(value).into_response());
+    };
+
+    if not_modified(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+    }
+
+    Ok(([(header::ETAG, etag)], Json(value)).into_response())
+}
+
+/// Retrieves a resource regardless of its visibility
+pub async fn retrieve_resource_for_admin<K: ResourceKind>(
+    _: Claims,
+    State(state): State<AppState>,
+    Path(params): Path<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Json<KeyedResponse<K::Entity>>, ApiError> {
+    retrieve::<K>(state, params, headers, vec![]).await
+}
@@ -0,0 +1,279 @@
+use crate::api::auth::authorization::require;
+use crate::api::openapi::{ApiDoc, Operation};
+use crate::api::resources::create::{create_resource, CreateResourceResponse};
+use crate::api::resources::delete::delete_resource;
+use crate::api::resources::keyed_schema;
+use crate::api::resources::list::{list_resources, list_resources_for_admin};
+use crate::api::resources::retrieve::{retrieve_resource, retrieve_resource_for_admin};
+use crate::api::resources::schema::retrieve_resource_schema;
+use crate::api::resources::update::{update_resource, UpdateResourceResponse};
+use crate::api::router::ApiRouter;
+use crate::domain::entities::Pagination;
+use crate::domain::registry::{OnDelete, ResourceKind};
+use crate::domain::users::permissions::{Action, Permission, Subject};
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+/** Derives the routes of the registered resource types.
+*
+* Every resource type gets
+* - public: `GET /{path}` and `GET /{path}/{id}`
+* - admin: `POST /{path}` and `PUT /{path}`
+* - admin: `DELETE /{path}/{id}` if it's deletable
+* - admin: `GET /{path}` and `GET /{path}/{id}` if it has invisible resources
+* - admin: `GET /schema/{resource_type}`
+*
+* The admin routes require the permission of their action on the resource type.
+*/
+#[derive(Default)]
+pub struct ResourceRoutes {
+    public: ApiRouter,
+    admin: ApiRouter,
+    documents: Vec<fn(&mut ApiDoc)>,
+}
+
+impl ResourceRoutes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<K: ResourceKind>(mut self) -> Self {
+        let collection = format!("/{}", K::PATH);
+        let item = format!("/{}/{{id}}", K::PATH);
+
+        let public = self
+            .public
+            .get(&collection, list_resources::<K>)
+            .get(&item, retrieve_resource::<K>);
+
+        let require = |action| {
+            axum::middleware::from_fn_with_state(
+                Permission::new(Subject::Resource(K::TYPE), action),
+                require,
+            )
+        };
+
+        let mut read = ApiRouter::new().get(
+            &format!("/schema/{}", K::TYPE),
+            retrieve_resource_schema::<K>,
+        );
+        if K::VISIBILITY_FIELD.is_some() {
+            read = read
+                .get(&collection, list_resources_for_admin::<K>)
+                .get(&item, retrieve_resource_for_admin::<K>);
+        }
+
+        let mut admin = self
+            .admin
+            .merge(read.layer(require(Action::Read)))
+            .merge(
+                ApiRouter::new()
+                    .post(&collection, create_resource::<K>)
+                    .layer(require(Action::Create)),
+            )
+            .merge(
+                ApiRouter::new()
+                    .put(&collection, update_resource::<K>)
+                    .layer(require(Action::Update)),
+            );
+
+        if K::DELETABLE {
+            admin = admin.merge(
+                ApiRouter::new()
+                    .delete(&item, delete_resource::<K>)
+                    .layer(require(Action::Delete)),
+            );
+        }
+
+        self.documents.push(document::<K>);
+
+        Self {
+            public,
+            admin,
+            documents: self.documents,
+        }
+    }
+
+    /// Adds the operations of the registered resource types to the document
+    pub fn document(&self, doc: &mut ApiDoc) {
+        for document in &self.documents {
+            document(doc);
+        }
+    }
+
+    /// Returns the public routes and the admin routes
+    pub fn into_routers(self) -> (ApiRouter, ApiRouter) {
+        (self.public, self.admin)
+    }
+}
+
+/// Documents the operations of a resource type, they mirror the routes of [`ResourceRoutes::register`]
+fn document<K: ResourceKind>(doc: &mut ApiDoc) {
+    let collection = format!("/{}", K::PATH);
+    let item = format!("/{}/{{id}}", K::PATH);
+    let tag = K::PLURAL;
+
+    let data = doc.schema::<K::Data>();
+    let entity = keyed_schema(K::SINGULAR, doc.schema::<K::Entity>(), false);
+    let list = keyed_schema(
+        K::PLURAL,
+        json!({ "type": "array", "items": doc.schema::<K::ListItem>() }),
+        matches!(K::PAGINATION, Pagination::Page(_)),
+    );
+    let created = doc.schema::<CreateResourceResponse>();
+    let updated = doc.schema::<UpdateResourceResponse>();
+
+    let list_operation = |summary: String| {
+        let mut operation = Operation::new(Method::GET, tag, summary).localized();
+        if let Pagination::Page(_) = K::PAGINATION {
+            operation = operation
+                .query("page", "The page which starts from 0")
+                .query("page_size", "How many resources are in a page");
+        }
+        for filter in K::FILTERS {
+            operation = operation.query(filter.param, "Only the matched resources are listed");
+        }
+        operation
+            .json(StatusCode::OK, "The resources", list.clone())
+            .error(StatusCode::BAD_REQUEST, "The parameters are invalid")
+    };
+    let retrieve_operation = |summary: String| {
+        Operation::new(Method::GET, tag, summary)
+            .localized()
+            .json(StatusCode::OK, "The resource", entity.clone())
+            .error(StatusCode::BAD_REQUEST, "The ID is invalid")
+            .error(StatusCode::NOT_FOUND, "The resource doesn't exist")
+    };
+
+    doc.add(
+        &collection,
+        list_operation(format!("List the {}", K::PLURAL)).conditional(),
+    );
+    doc.add(
+        &item,
+        retrieve_operation(format!("Retrieve a {}", K::SINGULAR)).conditional(),
+    );
+
+    doc.add(
+        &format!("/admin{}", collection),
+        Operation::new(Method::POST, tag, format!("Create a {}", K::SINGULAR))
+            .restricted()
+            .json_body(request_schema(data.clone(), false))
+            .json(StatusCode::OK, "The resource is created", created),
+    );
+    doc.add(
+        &format!("/admin{}", collection),
+        Operation::new(Method::PUT, tag, format!("Update a {}", K::SINGULAR))
+            .restricted()
+            .header(
+                "If-Match",
+                "The version which the change is based on, e.g. `\"3\"`",
+            )
+            .json_body(request_schema(data, true))
+            .json(StatusCode::OK, "The resource is updated", updated)
+            .error(StatusCode::NOT_FOUND, "The resource doesn't exist")
+            .error(
+                StatusCode::PRECONDITION_FAILED,
+                "The resource has been changed since the version",
+            ),
+    );
+    doc.add(
+        &format!("/admin/schema/{}", K::TYPE),
+        Operation::new(
+            Method::GET,
+            tag,
+            format!("Retrieve the JSON Schema of the {} data", K::SINGULAR),
+        )
+        .restricted()
+        .json(
+            StatusCode::OK,
+            "The JSON Schema",
+            json!({ "type": "object" }),
+        ),
+    );
+
+    if K::VISIBILITY_FIELD.is_some() {
+        doc.add(
+            &format!("/admin{}", collection),
+            list_operation(format!(
+                "List the {} including the invisible ones",
+                K::PLURAL
+            ))
+            .restricted(),
+        );
+        doc.add(
+            &format!("/admin{}", item),
+            retrieve_operation(format!("Retrieve a {} even if it's invisible", K::SINGULAR))
+                .restricted(),
+        );
+    }
+
+    if K::DELETABLE {
+        let mut operation =
+            Operation::new(Method::DELETE, tag, format!("Delete a {}", K::SINGULAR))
+                .restricted()
+                .response(StatusCode::OK, "The resource is deleted")
+                .error(
+                    StatusCode::BAD_REQUEST,
+                    "The ID or the reassignment target is invalid",
+                )
+                .error(StatusCode::NOT_FOUND, "The resource doesn't exist")
+                .query(
+                    "close_gap",
+                    "Move the resources after this resource forward if it's `true`",
+                );
+        if !K::REFERENCED_BY.is_empty() {
+            operation = operation
+                .query(
+                    "reassign_to",
+                    "Point the referring resources at this resource",
+                )
+                .query(
+                    "detach",
+                    "Clear the references of the referring resources if it's `true`",
+                );
+        }
+        if K::REFERENCED_BY
+            .iter()
+            .any(|reference| reference.on_delete == OnDelete::Refuse)
+        {
+            operation = operation.error(
+                StatusCode::CONFLICT,
+                "The resource is referred and neither strategy is given",
+            );
+        }
+
+        doc.add(&format!("/admin{}", item), operation);
+    }
+}
+
+/// The data is flattened into the request along with the language and the sequence
+fn request_schema(data: Value, with_id: bool) -> Value {
+    let mut fields = json!({
+        "type": "object",
+        "properties": {
+            "language": { "type": "string", "enum": ["zh", "en"] },
+            "seq": { "type": "integer", "format": "int32" },
+        },
+        "required": ["language", "seq"],
+    });
+    if with_id {
+        fields["properties"]["id"] = json!({ "type": "string" });
+        fields["properties"]["version"] = json!({
+            "type": "integer",
+            "format": "int32",
+            "description": "The version which the change is based on, it can be sent as `If-Match`",
+        });
+        fields["required"] = json!(["id", "language", "seq"]);
+    } else {
+        // the new resource is appended when neither is given
+        fields["properties"]["insert_at"] = json!({
+            "type": "integer",
+            "format": "int32",
+            "description": "Put the resource at the position and shift the others back",
+        });
+        fields["required"] = json!(["language"]);
+    }
+
+    json!({ "allOf": [data, fields] })
+}
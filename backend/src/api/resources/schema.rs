@@ -0,0 +1,8 @@
+use crate::api::auth::Claims;
+use crate::domain::registry::ResourceKind;
+use axum::Json;
+
+/// Describes the data of a resource type by JSON Schema, so the forms can be built dynamically
+pub async fn retrieve_resource_schema<K: ResourceKind>(_: Claims) -> Json<serde_json::Value> {
+    Json(K::schema())
+}
@@ -0,0 +1,114 @@
+use crate::api::api_error::ApiError;
+use crate::api::auth::Claims;
+use crate::api::caching::version_tag;
+use crate::api::client_ip::ClientIp;
+use crate::api::resources::accept_language;
+use crate::domain::audit::Actor;
+use crate::domain::entities::Language;
+use crate::domain::registry::ResourceKind;
+use crate::domain::users::permissions::{Action, Permission, Subject};
+use crate::startup::AppState;
+use crate::uow::InDatabase;
+use axum::extract::State;
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use axum_extra::extract::WithRejection;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+#[derive(Debug, Deserialize)]
+#[serde(bound = "")]
+pub(crate) struct UpdateResourceRequest<K: ResourceKind> {
+    id: String,
+    #[serde(flatten)]
+    data: K::Data,
+    language: String,
+    seq: i32,
+    /// The version which the change is based on, it can be sent as `If-Match` instead
+    version: Option<i32>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct UpdateResourceResponse {
+    /// The version after the update, the next update is based on it
+    version: i32,
+}
+
+/// Reads the version from `If-Match`, e.g. `"3"`, any version matches `*`
+fn if_match(headers: &HeaderMap) -> Result<Option<i32>, ApiError> {
+    let Some(value) = headers.get(header::IF_MATCH) else {
+        return Ok(None);
+    };
+
+    let value = value.to_str().map_err(|_| ApiError::BadRequest)?.trim();
+    if value == "*" {
+        return Ok(None);
+    }
+
+    value
+        .strip_prefix("W/")
+        .unwrap_or(value)
+        .trim_matches('"')
+        .parse::<i32>()
+        .map(Some)
+        .map_err(|_| ApiError::BadRequest)
+}
+
+pub async fn update_resource<K: ResourceKind>(
+    claims: Claims,
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    WithRejection(Json(req), _): WithRejection<Json<UpdateResourceRequest<K>>, ApiError>,
+) -> Result<Response, ApiError> {
+    let version = match (if_match(&headers)?, req.version) {
+        (Some(header), Some(body)) if header != body => return Err(ApiError::BadRequest),
+        (header, body) => header.or(body),
+    };
+
+    let req = crate::domain::resources::update::Request::<K> {
+        id: req.id,
+        data: req.data,
+        language: req.language,
+        seq: req.seq,
+        version,
+        owner: claims.owner(&Permission::new(Subject::Resource(K::TYPE), Action::Update))?,
+        actor: Some(Actor {
+            user_id: claims.user_id()?,
+            ip,
+        }),
+    };
+
+    // the messages of the failing fields are in the requested language
+    let language = Language::try_from(accept_language(&headers)).unwrap_or(Language::ZH);
+
+    let uow = InDatabase::new(&state.pool)
+        .await
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+    let uow = Mutex::new(uow);
+
+    match crate::domain::resources::update::execute(uow, &*state.cache, req).await {
+        Ok((_, version)) => Ok((
+            [(header::ETAG, version_tag(version))],
+            Json(UpdateResourceResponse { version }),
+        )
+            .into_response()),
+        Err(crate::domain::resources::update::Error::NotFound) => Err(ApiError::NotFound),
+        Err(crate::domain::resources::update::Error::BadRequest) => Err(ApiError::BadRequest),
+        Err(crate::domain::resources::update::Error::Forbidden) => Err(ApiError::Forbidden),
+        Err(crate::domain::resources::update::Error::Invalid(fields)) => {
+            Err(ApiError::invalid_fields(fields, &language))
+        }
+        Err(crate::domain::resources::update::Error::InvalidReference(field)) => {
+            Err(ApiError::InvalidReference(field))
+        }
+        Err(crate::domain::resources::update::Error::VersionMismatch { current }) => {
+            Err(ApiError::VersionMismatch(current))
+        }
+        Err(crate::domain::resources::update::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
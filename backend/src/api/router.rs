@@ -0,0 +1,109 @@
+use crate::startup::AppState;
+use axum::extract::Request;
+use axum::handler::Handler;
+use axum::http::Method;
+use axum::response::IntoResponse;
+use axum::routing::{on, MethodFilter, Route};
+use axum::Router;
+use std::convert::Infallible;
+use tower::{Layer, Service};
+
+/** A router which remembers the method and the path of every route.
+*
+* The recorded routes are checked against the OpenAPI document, so a route can't be added
+* without being documented.
+*/
+#[derive(Default)]
+pub struct ApiRouter {
+    router: Router<AppState>,
+    routes: Vec<(Method, String)>,
+}
+
+impl ApiRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn route<H, T>(mut self, method: Method, path: &str, handler: H) -> Self
+    where
+        H: Handler<T, AppState>,
+        T: 'static,
+    {
+        let filter = MethodFilter::try_from(method.clone()).expect("unsupported method");
+
+        self.router = self.router.route(path, on(filter, handler));
+        self.routes.push((method, path.to_string()));
+        self
+    }
+
+    pub fn get<H, T>(self, path: &str, handler: H) -> Self
+    where
+        H: Handler<T, AppState>,
+        T: 'static,
+    {
+        self.route(Method::GET, path, handler)
+    }
+
+    pub fn post<H, T>(self, path: &str, handler: H) -> Self
+    where
+        H: Handler<T, AppState>,
+        T: 'static,
+    {
+        self.route(Method::POST, path, handler)
+    }
+
+    pub fn put<H, T>(self, path: &str, handler: H) -> Self
+    where
+        H: Handler<T, AppState>,
+        T: 'static,
+    {
+        self.route(Method::PUT, path, handler)
+    }
+
+    pub fn delete<H, T>(self, path: &str, handler: H) -> Self
+    where
+        H: Handler<T, AppState>,
+        T: 'static,
+    {
+        self.route(Method::DELETE, path, handler)
+    }
+
+    pub fn merge(mut self, other: ApiRouter) -> Self {
+        self.router = self.router.merge(other.router);
+        self.routes.extend(other.routes);
+        self
+    }
+
+    pub fn nest(mut self, prefix: &str, other: ApiRouter) -> Self {
+        self.router = self.router.nest(prefix, other.router);
+        self.routes.extend(
+            other
+                .routes
+                .into_iter()
+                .map(|(method, path)| (method, format!("{}{}", prefix, path))),
+        );
+        self
+    }
+
+    /// Applies the layer to the routes which have been added
+    pub fn layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer<Route> + Clone + Send + Sync + 'static,
+        L::Service: Service<Request> + Clone + Send + Sync + 'static,
+        <L::Service as Service<Request>>::Response: IntoResponse + 'static,
+        <L::Service as Service<Request>>::Error: Into<Infallible> + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+    {
+        self.router = self.router.layer(layer);
+        self
+    }
+
+    /// The method and the full path of the routes
+    pub fn routes(&self) -> &[(Method, String)] {
+        &self.routes
+    }
+
+    pub fn into_router(self) -> Router<AppState> {
+        self.router
+    }
+}
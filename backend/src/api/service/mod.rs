@@ -1,9 +0,0 @@
-pub mod create;
-
-pub mod retrieve;
-
-pub mod update;
-
-pub mod delete;
-
-pub mod list;
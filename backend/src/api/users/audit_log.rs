@@ -0,0 +1,91 @@
+use crate::api::api_error::ApiError;
+use crate::api::auth::Claims;
+use crate::domain::entities::{AuditAction, AuditEntry};
+use crate::repositories::{Connection, SqlxAuditRepository};
+use crate::startup::AppState;
+use axum::extract::{Query, State};
+use axum::Json;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use tokio::sync::Mutex;
+
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    user_id: Option<String>,
+    action: Option<String>,
+    target_type: Option<String>,
+    target_id: Option<String>,
+    language: Option<String>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    limit: Option<usize>,
+}
+
+/// Who did what to which
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct AuditEntryResponse {
+    /// It's missing if it's done on the server
+    user_id: Option<String>,
+    ip: Option<IpAddr>,
+    action: AuditAction,
+    target_type: String,
+    target_id: String,
+    language: Option<String>,
+    /// The target before the change, it's missing if it's created
+    before: Option<serde_json::Value>,
+    /// The target after the change, it's missing if it's deleted
+    after: Option<serde_json::Value>,
+    #[schemars(with = "String")]
+    created_at: DateTime<Utc>,
+}
+
+impl From<AuditEntry> for AuditEntryResponse {
+    fn from(entry: AuditEntry) -> Self {
+        Self {
+            user_id: entry.user_id.map(|user_id| user_id.to_string()),
+            ip: entry.ip,
+            action: entry.action,
+            target_type: entry.target_type,
+            target_id: entry.target_id,
+            language: entry.language.map(|language| language.as_str().to_string()),
+            before: entry.before,
+            after: entry.after,
+            created_at: entry.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct AuditEntriesResponse {
+    entries: Vec<AuditEntryResponse>,
+}
+
+/// Browses the audit log, the latest entry first
+pub async fn list_audit_log(
+    _: Claims,
+    State(state): State<AppState>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<Json<AuditEntriesResponse>, ApiError> {
+    let audit_repo = SqlxAuditRepository::new(Connection::Pool(state.pool));
+
+    let req = crate::domain::audit::list::Request {
+        user_id: query.user_id,
+        action: query.action,
+        target_type: query.target_type,
+        target_id: query.target_id,
+        language: query.language,
+        since: query.since,
+        until: query.until,
+        limit: query.limit.unwrap_or(100),
+    };
+
+    match crate::domain::audit::list::execute(req, Mutex::new(audit_repo)).await {
+        Ok(entries) => Ok(Json(AuditEntriesResponse {
+            entries: entries.into_iter().map(AuditEntryResponse::from).collect(),
+        })),
+        Err(crate::domain::audit::list::Error::BadRequest) => Err(ApiError::BadRequest),
+        Err(crate::domain::audit::list::Error::Unknown(e)) => Err(ApiError::InternalServerError(e)),
+    }
+}
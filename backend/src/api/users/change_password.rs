@@ -1,38 +1,82 @@
 use crate::api::api_error::ApiError;
 use crate::api::auth::Claims;
-use crate::domain::entities::UserID;
-use crate::repositories::{Connection, SqlxUserRepository};
+use crate::api::client_ip::ClientIp;
+use crate::api::resources::accept_language;
+use crate::domain::entities::Language;
+use crate::domain::users::authentication::Attempt;
+use crate::repositories::{
+    Connection, RedisSessionRepository, SqlxLoginAttemptRepository, SqlxUserRepository,
+};
 use crate::startup::AppState;
 use axum::extract::State;
-use axum::http::StatusCode;
-use axum::Json;
+use axum::http::{HeaderMap, StatusCode};
+use axum::{Extension, Json};
 use axum_extra::extract::WithRejection;
+use axum_extra::headers::UserAgent;
+use axum_extra::TypedHeader;
+use chrono::Utc;
+use schemars::JsonSchema;
 use secrecy::SecretBox;
 use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
 use tokio::sync::Mutex;
-use uuid::Uuid;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub(crate) struct ChangePasswordRequest {
+    current_password: String,
     new_password: String,
 }
 
+/// Changes the password of the user, the other sessions of the user are ended
 pub async fn change_password(
     claims: Claims,
     state: State<AppState>,
+    headers: HeaderMap,
+    Extension(redis_client): Extension<Arc<redis::Client>>,
+    ClientIp(ip): ClientIp,
+    user_agent: Option<TypedHeader<UserAgent>>,
     WithRejection(Json(req), _): WithRejection<Json<ChangePasswordRequest>, ApiError>,
 ) -> Result<StatusCode, ApiError> {
-    let user_id = Uuid::parse_str(&claims.sub).map_err(|_| ApiError::BadRequest)?;
-    let user_id = UserID::from(user_id);
-
     let req = crate::domain::users::change_password::Request {
-        user_id,
+        user_id: claims.user_id()?,
+        session_id: claims.session_id()?,
+        current_password: SecretBox::new(Box::new(req.current_password)),
         new_password: SecretBox::new(Box::new(req.new_password)),
+        attempt: Attempt {
+            ip: ip.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            user_agent: user_agent.map(|TypedHeader(user_agent)| user_agent.to_string()),
+        },
     };
     let user_repo = SqlxUserRepository::new(Connection::Pool(state.pool.clone()));
+    let session_repo = RedisSessionRepository::new(redis_client);
+    let attempt_repo = SqlxLoginAttemptRepository::new(Connection::Pool(state.pool.clone()));
+
+    // the messages of the failing fields are in the requested language
+    let language = Language::try_from(accept_language(&headers)).unwrap_or(Language::ZH);
 
-    match crate::domain::users::change_password::execute(req, Mutex::new(user_repo)).await {
+    match crate::domain::users::change_password::execute(
+        req,
+        &state.passwords,
+        &state.password_hashing,
+        &state.lockout,
+        Mutex::new(user_repo),
+        Mutex::new(session_repo),
+        Mutex::new(attempt_repo),
+    )
+    .await
+    {
         Ok(_) => Ok(StatusCode::OK),
+        Err(crate::domain::users::change_password::Error::InvalidCredentials) => {
+            Err(ApiError::InvalidCredentials)
+        }
+        Err(crate::domain::users::change_password::Error::Locked { until }) => {
+            let seconds = (until - Utc::now()).num_seconds().max(1) as u64;
+            Err(ApiError::AccountLocked(seconds))
+        }
+        Err(crate::domain::users::change_password::Error::Invalid(fields)) => {
+            Err(ApiError::invalid_fields(fields, &language))
+        }
         Err(crate::domain::users::change_password::Error::Unknown(e)) => {
             Err(ApiError::InternalServerError(e))
         }
@@ -0,0 +1,88 @@
+use crate::api::api_error::ApiError;
+use crate::api::auth::Claims;
+use crate::api::client_ip::ClientIp;
+use crate::domain::audit::Actor;
+use crate::domain::entities::LoginAttempt;
+use crate::domain::users::authentication::Attempt;
+use crate::repositories::{Connection, SqlxLoginAttemptRepository};
+use crate::startup::AppState;
+use crate::uow::user::InDatabase;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum_extra::headers::UserAgent;
+use axum_extra::TypedHeader;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr};
+use tokio::sync::Mutex;
+
+#[derive(Debug, Deserialize)]
+pub struct LoginAttemptsQuery {
+    username: Option<String>,
+    ip: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct LoginAttemptsResponse {
+    attempts: Vec<LoginAttempt>,
+}
+
+/// Lists the latest login attempts, including the refused ones and the unlocks
+pub async fn list_login_attempts(
+    _: Claims,
+    State(state): State<AppState>,
+    Query(query): Query<LoginAttemptsQuery>,
+) -> Result<Json<LoginAttemptsResponse>, ApiError> {
+    let attempt_repo = SqlxLoginAttemptRepository::new(Connection::Pool(state.pool));
+
+    let req = crate::domain::users::list_login_attempts::Request {
+        username: query.username,
+        ip: query.ip,
+        limit: query.limit.unwrap_or(100),
+    };
+
+    match crate::domain::users::list_login_attempts::execute(req, Mutex::new(attempt_repo)).await {
+        Ok(attempts) => Ok(Json(LoginAttemptsResponse { attempts })),
+        Err(crate::domain::users::list_login_attempts::Error::BadRequest) => {
+            Err(ApiError::BadRequest)
+        }
+        Err(crate::domain::users::list_login_attempts::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
+
+/// Unlocks a username which has failed too many times
+pub async fn unlock_account(
+    claims: Claims,
+    State(state): State<AppState>,
+    Path(username): Path<String>,
+    ClientIp(ip): ClientIp,
+    user_agent: Option<TypedHeader<UserAgent>>,
+) -> Result<StatusCode, ApiError> {
+    let uow = InDatabase::new(&state.pool)
+        .await
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+
+    let req = crate::domain::users::unlock::Request {
+        username,
+        attempt: Attempt {
+            ip: ip.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            user_agent: user_agent.map(|TypedHeader(user_agent)| user_agent.to_string()),
+        },
+        actor: Some(Actor {
+            user_id: claims.user_id()?,
+            ip,
+        }),
+    };
+
+    match crate::domain::users::unlock::execute(req, Mutex::new(uow)).await {
+        Ok(_) => Ok(StatusCode::OK),
+        Err(crate::domain::users::unlock::Error::BadRequest) => Err(ApiError::BadRequest),
+        Err(crate::domain::users::unlock::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
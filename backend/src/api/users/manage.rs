@@ -0,0 +1,234 @@
+use crate::api::api_error::ApiError;
+use crate::api::auth::Claims;
+use crate::api::client_ip::ClientIp;
+use crate::api::resources::accept_language;
+use crate::domain::audit::Actor;
+use crate::domain::entities::{Language, Role, User};
+use crate::domain::users::manage_user::Change;
+use crate::repositories::{Connection, RedisSessionRepository, SqlxUserRepository};
+use crate::startup::AppState;
+use crate::uow::user::InDatabase;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::{Extension, Json};
+use axum_extra::extract::WithRejection;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use secrecy::SecretBox;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub(crate) struct CreateUserRequest {
+    username: String,
+    password: String,
+    role: Role,
+    /// Where the links to reset the password are sent, the user can't reset it without one
+    email: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct CreateUserResponse {
+    id: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct UserResponse {
+    id: String,
+    username: String,
+    role: Role,
+    email: Option<String>,
+    disabled: bool,
+    /// The user has to change the password on the next login
+    must_reset_password: bool,
+    /// The user logs in with a second factor
+    totp_enabled: bool,
+    #[schemars(with = "String")]
+    created_at: DateTime<Utc>,
+}
+
+impl From<User> for UserResponse {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id.to_string(),
+            username: user.username,
+            role: user.role,
+            email: user.email,
+            disabled: user.disabled,
+            must_reset_password: user.must_reset_password,
+            totp_enabled: user.totp_enabled,
+            created_at: user.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct UsersResponse {
+    users: Vec<UserResponse>,
+}
+
+/// Creates a user who can log in with the password
+pub async fn create_user(
+    claims: Claims,
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    WithRejection(Json(req), _): WithRejection<Json<CreateUserRequest>, ApiError>,
+) -> Result<Json<CreateUserResponse>, ApiError> {
+    let uow = InDatabase::new(&state.pool)
+        .await
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+
+    let req = crate::domain::users::create_user::Request {
+        username: req.username,
+        password: SecretBox::new(Box::new(req.password)),
+        role: req.role,
+        email: req.email,
+        actor: Some(Actor {
+            user_id: claims.user_id()?,
+            ip,
+        }),
+    };
+
+    // the messages of the failing fields are in the requested language
+    let language = Language::try_from(accept_language(&headers)).unwrap_or(Language::ZH);
+
+    match crate::domain::users::create_user::execute(
+        req,
+        &state.passwords,
+        &state.password_hashing,
+        Mutex::new(uow),
+    )
+    .await
+    {
+        Ok(id) => Ok(Json(CreateUserResponse { id: id.to_string() })),
+        Err(crate::domain::users::create_user::Error::BadRequest) => Err(ApiError::BadRequest),
+        Err(crate::domain::users::create_user::Error::UsernameTaken) => {
+            Err(ApiError::Conflict("The username is taken".to_string()))
+        }
+        Err(crate::domain::users::create_user::Error::Invalid(fields)) => {
+            Err(ApiError::invalid_fields(fields, &language))
+        }
+        Err(crate::domain::users::create_user::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
+
+/// Lists all the users, including the disabled ones
+pub async fn list_users(
+    _: Claims,
+    State(state): State<AppState>,
+) -> Result<Json<UsersResponse>, ApiError> {
+    let user_repo = SqlxUserRepository::new(Connection::Pool(state.pool));
+
+    match crate::domain::users::list_users::execute(Mutex::new(user_repo)).await {
+        Ok(users) => Ok(Json(UsersResponse {
+            users: users.into_iter().map(UserResponse::from).collect(),
+        })),
+        Err(crate::domain::users::list_users::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
+
+/// Disables a user, the user is logged out and can't log in again until it's enabled
+pub async fn disable_user(
+    claims: Claims,
+    State(state): State<AppState>,
+    Extension(redis_client): Extension<Arc<redis::Client>>,
+    ClientIp(ip): ClientIp,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    manage_user(claims, &state, redis_client, ip, id, Change::Disable).await
+}
+
+/// Enables a user who has been disabled
+pub async fn enable_user(
+    claims: Claims,
+    State(state): State<AppState>,
+    Extension(redis_client): Extension<Arc<redis::Client>>,
+    ClientIp(ip): ClientIp,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    manage_user(claims, &state, redis_client, ip, id, Change::Enable).await
+}
+
+/// Logs a user out, the user has to change the password after logging in again
+pub async fn force_password_reset(
+    claims: Claims,
+    State(state): State<AppState>,
+    Extension(redis_client): Extension<Arc<redis::Client>>,
+    ClientIp(ip): ClientIp,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    manage_user(
+        claims,
+        &state,
+        redis_client,
+        ip,
+        id,
+        Change::ForcePasswordReset,
+    )
+    .await
+}
+
+/// Removes the second factor of a user who lost it, the user is logged out and can enrol again
+pub async fn reset_totp(
+    claims: Claims,
+    State(state): State<AppState>,
+    Extension(redis_client): Extension<Arc<redis::Client>>,
+    ClientIp(ip): ClientIp,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    manage_user(claims, &state, redis_client, ip, id, Change::ResetTotp).await
+}
+
+/// Deletes a user along with the sessions
+pub async fn delete_user(
+    claims: Claims,
+    State(state): State<AppState>,
+    Extension(redis_client): Extension<Arc<redis::Client>>,
+    ClientIp(ip): ClientIp,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    manage_user(claims, &state, redis_client, ip, id, Change::Delete).await
+}
+
+async fn manage_user(
+    claims: Claims,
+    state: &AppState,
+    redis_client: Arc<redis::Client>,
+    ip: Option<std::net::IpAddr>,
+    id: String,
+    change: Change,
+) -> Result<StatusCode, ApiError> {
+    let uow = InDatabase::new(&state.pool)
+        .await
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+    let session_repo = RedisSessionRepository::new(redis_client);
+
+    let req = crate::domain::users::manage_user::Request {
+        user_id: id,
+        change,
+        actor: Actor {
+            user_id: claims.user_id()?,
+            ip,
+        },
+    };
+
+    match crate::domain::users::manage_user::execute(req, Mutex::new(uow), Mutex::new(session_repo))
+        .await
+    {
+        Ok(()) => Ok(StatusCode::OK),
+        Err(crate::domain::users::manage_user::Error::BadRequest) => Err(ApiError::BadRequest),
+        Err(crate::domain::users::manage_user::Error::NotFound) => Err(ApiError::NotFound),
+        Err(crate::domain::users::manage_user::Error::Oneself) => Err(ApiError::Conflict(
+            "The users can't disable or delete themselves".to_string(),
+        )),
+        Err(crate::domain::users::manage_user::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
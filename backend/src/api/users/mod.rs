@@ -1 +1,11 @@
+pub mod audit_log;
+
 pub mod change_password;
+
+pub mod login_attempts;
+
+pub mod manage;
+
+pub mod reset_password;
+
+pub mod totp;
@@ -0,0 +1,96 @@
+use crate::api::api_error::ApiError;
+use crate::api::resources::accept_language;
+use crate::domain::entities::Language;
+use crate::repositories::{
+    Connection, RedisSessionRepository, SqlxPasswordResetRepository, SqlxUserRepository,
+};
+use crate::startup::AppState;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::{Extension, Json};
+use axum_extra::extract::WithRejection;
+use schemars::JsonSchema;
+use secrecy::SecretBox;
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub(crate) struct ForgotPasswordRequest {
+    username: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub(crate) struct ResetPasswordRequest {
+    /// The token of the emailed link
+    token: String,
+    new_password: String,
+}
+
+/// Emails a link to reset the password, it succeeds whether the username exists or not
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    WithRejection(Json(req), _): WithRejection<Json<ForgotPasswordRequest>, ApiError>,
+) -> Result<StatusCode, ApiError> {
+    let req = crate::domain::users::forgot_password::Request {
+        username: req.username,
+    };
+    let user_repo = SqlxUserRepository::new(Connection::Pool(state.pool.clone()));
+    let reset_repo = SqlxPasswordResetRepository::new(Connection::Pool(state.pool.clone()));
+
+    match crate::domain::users::forgot_password::execute(
+        req,
+        &state.password_reset,
+        Mutex::new(user_repo),
+        Mutex::new(reset_repo),
+        state.mailer.clone(),
+    )
+    .await
+    {
+        Ok(()) => Ok(StatusCode::OK),
+        Err(crate::domain::users::forgot_password::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
+
+/// Resets the password with the emailed token, all the sessions of the user are ended
+pub async fn reset_password(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Extension(redis_client): Extension<Arc<redis::Client>>,
+    WithRejection(Json(req), _): WithRejection<Json<ResetPasswordRequest>, ApiError>,
+) -> Result<StatusCode, ApiError> {
+    let req = crate::domain::users::reset_password::Request {
+        token: req.token,
+        new_password: SecretBox::new(Box::new(req.new_password)),
+    };
+    let reset_repo = SqlxPasswordResetRepository::new(Connection::Pool(state.pool.clone()));
+    let user_repo = SqlxUserRepository::new(Connection::Pool(state.pool.clone()));
+    let session_repo = RedisSessionRepository::new(redis_client);
+
+    // the messages of the failing fields are in the requested language
+    let language = Language::try_from(accept_language(&headers)).unwrap_or(Language::ZH);
+
+    match crate::domain::users::reset_password::execute(
+        req,
+        &state.passwords,
+        &state.password_hashing,
+        Mutex::new(reset_repo),
+        Mutex::new(user_repo),
+        Mutex::new(session_repo),
+    )
+    .await
+    {
+        Ok(_) => Ok(StatusCode::OK),
+        Err(crate::domain::users::reset_password::Error::InvalidToken) => {
+            Err(ApiError::InvalidCredentials)
+        }
+        Err(crate::domain::users::reset_password::Error::Invalid(fields)) => {
+            Err(ApiError::invalid_fields(fields, &language))
+        }
+        Err(crate::domain::users::reset_password::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
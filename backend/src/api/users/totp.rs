@@ -0,0 +1,261 @@
+use crate::api::api_error::ApiError;
+use crate::api::auth::Claims;
+use crate::api::client_ip::ClientIp;
+use crate::domain::audit::Actor;
+use crate::domain::entities::SecuritySettings;
+use crate::repositories::{
+    Connection, SqlxRecoveryCodeRepository, SqlxSecuritySettingsRepository, SqlxUserRepository,
+};
+use crate::startup::AppState;
+use crate::uow::user::InDatabase;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use axum_extra::extract::WithRejection;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct TotpStatusResponse {
+    enabled: bool,
+    /// The second factor is required for all the users, so it can't be disabled
+    required: bool,
+    recovery_codes_left: usize,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct TotpEnrolmentResponse {
+    /// The base32 secret, for the authenticator apps which can't scan the QR code
+    secret: String,
+    /// The `otpauth://` URI which is shown as a QR code
+    uri: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub(crate) struct TotpCodeRequest {
+    /// The code of the authenticator app, the disabling and the regenerating take a recovery code too
+    code: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct RecoveryCodesResponse {
+    /// Each code logs in once without the authenticator app, they're only shown this time
+    recovery_codes: Vec<String>,
+}
+
+/// Whether the user logs in with a second factor
+pub async fn totp_status(
+    claims: Claims,
+    State(state): State<AppState>,
+) -> Result<Json<TotpStatusResponse>, ApiError> {
+    let req = crate::domain::users::totp_status::Request {
+        user_id: claims.user_id()?,
+    };
+
+    match crate::domain::users::totp_status::execute(
+        req,
+        Mutex::new(SqlxUserRepository::new(Connection::Pool(
+            state.pool.clone(),
+        ))),
+        Mutex::new(SqlxRecoveryCodeRepository::new(Connection::Pool(
+            state.pool.clone(),
+        ))),
+        Mutex::new(SqlxSecuritySettingsRepository::new(Connection::Pool(
+            state.pool.clone(),
+        ))),
+    )
+    .await
+    {
+        Ok(status) => Ok(Json(TotpStatusResponse {
+            enabled: status.enabled,
+            required: status.required,
+            recovery_codes_left: status.recovery_codes_left,
+        })),
+        Err(crate::domain::users::totp_status::Error::NotFound) => Err(ApiError::NotFound),
+        Err(crate::domain::users::totp_status::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
+
+/// Starts to enrol a second factor, it's on once a code is confirmed
+pub async fn enroll_totp(
+    claims: Claims,
+    State(state): State<AppState>,
+) -> Result<Json<TotpEnrolmentResponse>, ApiError> {
+    let req = crate::domain::users::enroll_totp::Request {
+        user_id: claims.user_id()?,
+    };
+    let user_repo = SqlxUserRepository::new(Connection::Pool(state.pool.clone()));
+
+    match crate::domain::users::enroll_totp::execute(req, &state.totp, Mutex::new(user_repo)).await
+    {
+        Ok(enrolment) => Ok(Json(TotpEnrolmentResponse {
+            secret: enrolment.secret,
+            uri: enrolment.uri,
+        })),
+        Err(crate::domain::users::enroll_totp::Error::NotFound) => Err(ApiError::NotFound),
+        Err(crate::domain::users::enroll_totp::Error::AlreadyEnabled) => Err(ApiError::Conflict(
+            "The second factor is already enabled".to_string(),
+        )),
+        Err(crate::domain::users::enroll_totp::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
+
+/// Turns the second factor on with a code of the authenticator app
+pub async fn confirm_totp(
+    claims: Claims,
+    State(state): State<AppState>,
+    WithRejection(Json(req), _): WithRejection<Json<TotpCodeRequest>, ApiError>,
+) -> Result<Json<RecoveryCodesResponse>, ApiError> {
+    let req = crate::domain::users::confirm_totp::Request {
+        user_id: claims.user_id()?,
+        code: req.code,
+    };
+
+    match crate::domain::users::confirm_totp::execute(
+        req,
+        Mutex::new(SqlxUserRepository::new(Connection::Pool(
+            state.pool.clone(),
+        ))),
+        Mutex::new(SqlxRecoveryCodeRepository::new(Connection::Pool(
+            state.pool.clone(),
+        ))),
+    )
+    .await
+    {
+        Ok(recovery_codes) => Ok(Json(RecoveryCodesResponse { recovery_codes })),
+        Err(crate::domain::users::confirm_totp::Error::InvalidCode) => {
+            Err(ApiError::InvalidCredentials)
+        }
+        Err(crate::domain::users::confirm_totp::Error::NotEnrolled) => Err(ApiError::Conflict(
+            "The second factor isn't enrolled".to_string(),
+        )),
+        Err(crate::domain::users::confirm_totp::Error::AlreadyEnabled) => Err(ApiError::Conflict(
+            "The second factor is already enabled".to_string(),
+        )),
+        Err(crate::domain::users::confirm_totp::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
+
+/// Turns the second factor off, it's refused if it's required
+pub async fn disable_totp(
+    claims: Claims,
+    State(state): State<AppState>,
+    WithRejection(Json(req), _): WithRejection<Json<TotpCodeRequest>, ApiError>,
+) -> Result<StatusCode, ApiError> {
+    let req = crate::domain::users::disable_totp::Request {
+        user_id: claims.user_id()?,
+        code: req.code,
+    };
+
+    match crate::domain::users::disable_totp::execute(
+        req,
+        Mutex::new(SqlxUserRepository::new(Connection::Pool(
+            state.pool.clone(),
+        ))),
+        Mutex::new(SqlxRecoveryCodeRepository::new(Connection::Pool(
+            state.pool.clone(),
+        ))),
+        Mutex::new(SqlxSecuritySettingsRepository::new(Connection::Pool(
+            state.pool.clone(),
+        ))),
+    )
+    .await
+    {
+        Ok(()) => Ok(StatusCode::OK),
+        Err(crate::domain::users::disable_totp::Error::InvalidCode) => {
+            Err(ApiError::InvalidCredentials)
+        }
+        Err(crate::domain::users::disable_totp::Error::Required) => Err(ApiError::Conflict(
+            "The second factor is required for all the users".to_string(),
+        )),
+        Err(crate::domain::users::disable_totp::Error::NotEnabled) => Err(ApiError::Conflict(
+            "The second factor isn't enabled".to_string(),
+        )),
+        Err(crate::domain::users::disable_totp::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
+
+/// Replaces the recovery codes of the user
+pub async fn regenerate_recovery_codes(
+    claims: Claims,
+    State(state): State<AppState>,
+    WithRejection(Json(req), _): WithRejection<Json<TotpCodeRequest>, ApiError>,
+) -> Result<Json<RecoveryCodesResponse>, ApiError> {
+    let req = crate::domain::users::regenerate_recovery_codes::Request {
+        user_id: claims.user_id()?,
+        code: req.code,
+    };
+
+    match crate::domain::users::regenerate_recovery_codes::execute(
+        req,
+        Mutex::new(SqlxUserRepository::new(Connection::Pool(
+            state.pool.clone(),
+        ))),
+        Mutex::new(SqlxRecoveryCodeRepository::new(Connection::Pool(
+            state.pool.clone(),
+        ))),
+    )
+    .await
+    {
+        Ok(recovery_codes) => Ok(Json(RecoveryCodesResponse { recovery_codes })),
+        Err(crate::domain::users::regenerate_recovery_codes::Error::InvalidCode) => {
+            Err(ApiError::InvalidCredentials)
+        }
+        Err(crate::domain::users::regenerate_recovery_codes::Error::NotEnabled) => Err(
+            ApiError::Conflict("The second factor isn't enabled".to_string()),
+        ),
+        Err(crate::domain::users::regenerate_recovery_codes::Error::Unknown(e)) => {
+            Err(ApiError::InternalServerError(e))
+        }
+    }
+}
+
+pub async fn get_security_settings(
+    _: Claims,
+    State(state): State<AppState>,
+) -> Result<Json<SecuritySettings>, ApiError> {
+    let settings_repo = SqlxSecuritySettingsRepository::new(Connection::Pool(state.pool.clone()));
+
+    crate::domain::users::security_settings::get(Mutex::new(settings_repo))
+        .await
+        .map(Json)
+        .map_err(
+            |crate::domain::users::security_settings::Error::Unknown(e)| {
+                ApiError::InternalServerError(e)
+            },
+        )
+}
+
+/// Updates the security settings, e.g. to require the second factor for all the users
+pub async fn update_security_settings(
+    claims: Claims,
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    WithRejection(Json(settings), _): WithRejection<Json<SecuritySettings>, ApiError>,
+) -> Result<Json<SecuritySettings>, ApiError> {
+    let uow = InDatabase::new(&state.pool)
+        .await
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+    let actor = Actor {
+        user_id: claims.user_id()?,
+        ip,
+    };
+
+    crate::domain::users::security_settings::update(settings, Some(actor), Mutex::new(uow))
+        .await
+        .map(Json)
+        .map_err(
+            |crate::domain::users::security_settings::Error::Unknown(e)| {
+                ApiError::InternalServerError(e)
+            },
+        )
+}
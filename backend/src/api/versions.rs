@@ -0,0 +1,163 @@
+use crate::api::resources::routes::ResourceRoutes;
+use crate::api::router::ApiRouter;
+use crate::configuration::Deprecation;
+use axum::http::{HeaderName, HeaderValue};
+use tower_http::set_header::SetResponseHeaderLayer;
+
+/** A version of the API which is served under `/api/{name}`.
+*
+* Every version has its own set of resource types, the other routes are shared by all versions.
+* The versions which are not registered are not found.
+*/
+pub struct ApiVersion {
+    pub name: &'static str,
+    pub resources: ResourceRoutes,
+}
+
+impl ApiVersion {
+    pub fn new(name: &'static str, resources: ResourceRoutes) -> Self {
+        Self { name, resources }
+    }
+
+    /// The prefix of the routes, e.g. `/api/v1`
+    pub fn prefix(&self) -> String {
+        format!("/api/{}", self.name)
+    }
+}
+
+/// Adds the `Deprecation` and the `Sunset` headers to the responses of a deprecated version
+pub fn deprecate(routes: ApiRouter, deprecation: &Deprecation) -> ApiRouter {
+    // RFC 9745, the date is a structured field
+    let deprecated_at =
+        HeaderValue::from_str(&format!("@{}", deprecation.deprecated_at.timestamp()))
+            .expect("a timestamp is a valid header value");
+
+    let mut routes = routes.layer(SetResponseHeaderLayer::overriding(
+        HeaderName::from_static("deprecation"),
+        deprecated_at,
+    ));
+
+    // RFC 8594, the date is an HTTP-date
+    if let Some(sunset) = deprecation.sunset {
+        let sunset = HeaderValue::from_str(&sunset.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+            .expect("a date is a valid header value");
+
+        routes = routes.layer(SetResponseHeaderLayer::overriding(
+            HeaderName::from_static("sunset"),
+            sunset,
+        ));
+    }
+
+    routes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::NoCache;
+    use crate::configuration::{
+        ApiSettings, Deprecation, LockoutSettings, PasswordHashingSettings, PasswordResetSettings,
+        PasswordSettings, SessionSettings, TotpSettings,
+    };
+    use crate::mailer::OutboxMailer;
+    use crate::startup::{api, AppState};
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::response::Response;
+    use axum::Extension;
+    use chrono::{TimeZone, Utc};
+    use jsonwebtoken::{DecodingKey, EncodingKey};
+    use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    /// Sends the request to the routes, the database is never connected
+    async fn send(settings: &ApiSettings, method: &str, uri: &str) -> Response {
+        let state = AppState {
+            pool: PgPoolOptions::new().connect_lazy_with(PgConnectOptions::new()),
+            upload_folder: Arc::new(String::new()),
+            jwt_encoding_key: Arc::new(EncodingKey::from_secret(b"secret")),
+            jwt_decoding_key: Arc::new(DecodingKey::from_secret(b"secret")),
+            cache: Arc::new(NoCache),
+            lockout: Arc::new(LockoutSettings::default().policy()),
+            sessions: Arc::new(SessionSettings::default().policy()),
+            passwords: Arc::new(PasswordSettings::default().policy()),
+            password_hashing: Arc::new(PasswordHashingSettings::default().hasher()),
+            password_reset: Arc::new(PasswordResetSettings::default().policy()),
+            mailer: Arc::new(OutboxMailer::new(
+                std::env::temp_dir().join("outbox"),
+                "no-reply@localhost",
+            )),
+            totp: Arc::new(TotpSettings::default().policy()),
+        };
+        let (routes, _) = api(settings);
+
+        routes
+            .into_router()
+            .layer(Extension(state.clone()))
+            .with_state(state)
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    fn deprecate_v1() -> ApiSettings {
+        ApiSettings {
+            deprecations: vec![Deprecation {
+                version: "v1".to_string(),
+                deprecated_at: Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+                sunset: Some(Utc.with_ymd_and_hms(2025, 7, 1, 0, 0, 0).unwrap()),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_return_not_found_when_the_version_is_unknown() {
+        let res = send(&ApiSettings::default(), "POST", "/api/v999/admin/logout").await;
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn it_should_serve_the_registered_versions() {
+        // the request is rejected by the handler because it's not logged in
+        for uri in ["/api/v1/admin/logout", "/api/v2/admin/logout"] {
+            let res = send(&ApiSettings::default(), "POST", uri).await;
+
+            assert_ne!(res.status(), StatusCode::NOT_FOUND);
+            assert!(res.headers().get("deprecation").is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_the_restricted_routes_without_a_token() {
+        for (method, uri) in [
+            ("GET", "/api/v1/admin/security"),
+            ("GET", "/api/v1/admin/audit-log"),
+            ("GET", "/api/v2/admin/users"),
+        ] {
+            let res = send(&ApiSettings::default(), method, uri).await;
+
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_add_the_deprecation_headers_to_the_deprecated_version() {
+        let res = send(&deprecate_v1(), "POST", "/api/v1/admin/logout").await;
+
+        assert_eq!(res.headers()["deprecation"], "@1735689600");
+        assert_eq!(res.headers()["sunset"], "Tue, 01 Jul 2025 00:00:00 GMT");
+
+        let res = send(&deprecate_v1(), "POST", "/api/v2/admin/logout").await;
+
+        assert!(res.headers().get("deprecation").is_none());
+        assert!(res.headers().get("sunset").is_none());
+    }
+}
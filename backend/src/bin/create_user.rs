@@ -1,7 +1,8 @@
 use anyhow::anyhow;
+use backend::domain::entities::Role;
 use backend::domain::users;
 use backend::get_configuration;
-use backend::repositories::{Connection, SqlxUserRepository};
+use backend::uow::user::InDatabase;
 use secrecy::SecretBox;
 use sqlx::postgres::PgPoolOptions;
 use std::io;
@@ -17,7 +18,7 @@ async fn main() -> anyhow::Result<()> {
             configuration.database.timeout,
         ))
         .connect_lazy_with(configuration.database.with_db());
-    let user_repo = SqlxUserRepository::new(Connection::Pool(database_connection));
+    let uow = InDatabase::new(&database_connection).await?;
 
     let mut reader = BufReader::new(stdin());
     let mut username = String::new();
@@ -33,12 +34,40 @@ async fn main() -> anyhow::Result<()> {
 
     reader.read_line(&mut password).await?;
 
+    let mut role = String::new();
+    println!("Enter role (owner, editor, author or viewer), owner by default: ");
+    io::stdout().flush()?;
+
+    reader.read_line(&mut role).await?;
+    let role = match role.trim() {
+        "" => Role::Owner,
+        role => Role::try_from(role).map_err(|_| anyhow!("Unknown role: {}", role))?,
+    };
+
+    let mut email = String::new();
+    println!("Enter email to reset the password with, none by default: ");
+    io::stdout().flush()?;
+
+    reader.read_line(&mut email).await?;
+    let email = Some(email.trim().to_string()).filter(|email| !email.is_empty());
+
     let req = users::create_user::Request {
         username: username.trim().to_string(),
         password: SecretBox::new(Box::new(password.trim().to_string())),
+        role,
+        email,
+        // it's recorded without a user, since it's done on the server
+        actor: None,
     };
 
-    match users::create_user::execute(req, Mutex::new(user_repo)).await {
+    match users::create_user::execute(
+        req,
+        &configuration.passwords.policy(),
+        &configuration.password_hashing.hasher(),
+        Mutex::new(uow),
+    )
+    .await
+    {
         Ok(_) => Ok(()),
         Err(err) => Err(anyhow!("Failed to create user, got an error: {:?}", err)),
     }
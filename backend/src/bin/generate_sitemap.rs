@@ -1,11 +1,9 @@
 use backend::domain;
-use backend::domain::entities::{
-    Language, Pagination, ResourceType, SimpleArticleEntity, SimpleMemberEntity,
-};
+use backend::domain::entities::{Language, Pagination, SimpleArticleEntity, SimpleMemberEntity};
+use backend::domain::registry::{ArticleKind, MemberKind, ResourceKind};
 use backend::get_configuration;
 use backend::uow::InDatabase;
-use serde::de::DeserializeOwned;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use sqlx::postgres::PgPoolOptions;
 use sqlx::{Pool, Postgres};
 use std::fs::File;
@@ -93,29 +91,24 @@ fn generate_sitemap_string(
     Ok(xml)
 }
 
-async fn get_resources<T>(
+async fn get_resources<K: ResourceKind>(
     pool: &Pool<Postgres>,
-    resource_type: ResourceType,
     language: Language,
-) -> anyhow::Result<Vec<T>>
-where
-    T: DeserializeOwned + Serialize,
-{
+) -> anyhow::Result<Vec<K::ListItem>> {
     let uow = InDatabase::new(pool).await?;
     let uow = Mutex::new(uow);
 
     let req = domain::resources::list::Request {
-        filter_str: None,
-        resource_type: resource_type.clone(),
+        filters: vec![],
         language: language.as_str().to_string(),
         default_language: Language::ZH,
         pagination: Pagination::All,
     };
-    let (resources, total) = domain::resources::list::execute::<_, T>(uow, req)
+    let (resources, total) = domain::resources::list::execute::<_, K>(uow, req)
         .await
         .map_err(|e| anyhow::anyhow!("{:?}", e))?;
 
-    println!("got {} {resource_type:?}s", total);
+    println!("got {} {}s", total, K::TYPE);
 
     Ok(resources)
 }
@@ -133,16 +126,8 @@ async fn main() -> anyhow::Result<()> {
         .connect_lazy_with(configuration.database.with_db());
 
     let (members, articles) = tokio::try_join!(
-        get_resources::<SimpleMemberEntity>(
-            &database_connection,
-            ResourceType::Member,
-            Language::ZH
-        ),
-        get_resources::<SimpleArticleEntity>(
-            &database_connection,
-            ResourceType::Article,
-            Language::ZH
-        )
+        get_resources::<MemberKind>(&database_connection, Language::ZH),
+        get_resources::<ArticleKind>(&database_connection, Language::ZH)
     )?;
 
     let xml = generate_sitemap_string(&base_url, static_routes, members, articles)?;
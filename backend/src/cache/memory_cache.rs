@@ -0,0 +1,172 @@
+use crate::cache::IResourceCache;
+use lru::LruCache;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct Entry {
+    value: Value,
+    tags: Vec<String>,
+    expires_at: Instant,
+}
+
+struct State {
+    entries: LruCache<String, Entry>,
+    /// The keys of the entries which are tagged with the tag
+    keys_by_tag: HashMap<String, HashSet<String>>,
+}
+
+impl State {
+    fn forget(&mut self, key: &str, tags: &[String]) {
+        for tag in tags {
+            if let Some(keys) = self.keys_by_tag.get_mut(tag) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    self.keys_by_tag.remove(tag);
+                }
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.pop(key) {
+            self.forget(key, &entry.tags);
+        }
+    }
+}
+
+/// Caches the entries in the process, the least recently used ones are evicted when it's full
+pub struct InMemoryCache {
+    ttl: Duration,
+    state: Mutex<State>,
+}
+
+impl InMemoryCache {
+    pub fn new(capacity: NonZeroUsize, ttl: Duration) -> Self {
+        Self {
+            ttl,
+            state: Mutex::new(State {
+                entries: LruCache::new(capacity),
+                keys_by_tag: HashMap::new(),
+            }),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl IResourceCache for InMemoryCache {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Value>> {
+        let mut state = self.state.lock().unwrap();
+
+        match state.entries.get(key) {
+            Some(entry) if entry.expires_at > Instant::now() => Ok(Some(entry.value.clone())),
+            Some(_) => {
+                state.remove(key);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, key: &str, value: &Value, tags: &[String]) -> anyhow::Result<()> {
+        let mut state = self.state.lock().unwrap();
+
+        state.remove(key);
+        for tag in tags {
+            state
+                .keys_by_tag
+                .entry(tag.to_string())
+                .or_default()
+                .insert(key.to_string());
+        }
+
+        let entry = Entry {
+            value: value.clone(),
+            tags: tags.to_vec(),
+            expires_at: Instant::now() + self.ttl,
+        };
+        if let Some((evicted, entry)) = state.entries.push(key.to_string(), entry) {
+            state.forget(&evicted, &entry.tags);
+        }
+
+        Ok(())
+    }
+
+    async fn invalidate(&self, tags: &[String]) -> anyhow::Result<()> {
+        let mut state = self.state.lock().unwrap();
+
+        for tag in tags {
+            for key in state.keys_by_tag.remove(tag).unwrap_or_default() {
+                state.remove(&key);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn cache(capacity: usize, ttl: Duration) -> InMemoryCache {
+        InMemoryCache::new(NonZeroUsize::new(capacity).unwrap(), ttl)
+    }
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn it_should_remove_the_entries_of_the_invalidated_tags_only() {
+        let cache = cache(10, Duration::from_secs(60));
+        cache
+            .set("a", &json!(1), &tags(&["member", "member:a"]))
+            .await
+            .unwrap();
+        cache
+            .set("b", &json!(2), &tags(&["member", "member:b"]))
+            .await
+            .unwrap();
+        cache
+            .set("list", &json!([1, 2]), &tags(&["member", "member:list"]))
+            .await
+            .unwrap();
+
+        cache
+            .invalidate(&tags(&["member:a", "member:list"]))
+            .await
+            .unwrap();
+
+        assert_eq!(cache.get("a").await.unwrap(), None);
+        assert_eq!(cache.get("b").await.unwrap(), Some(json!(2)));
+        assert_eq!(cache.get("list").await.unwrap(), None);
+
+        cache.invalidate(&tags(&["member"])).await.unwrap();
+        assert_eq!(cache.get("b").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn it_should_evict_the_least_recently_used_entry() {
+        let cache = cache(2, Duration::from_secs(60));
+        cache.set("a", &json!(1), &tags(&["a"])).await.unwrap();
+        cache.set("b", &json!(2), &tags(&["b"])).await.unwrap();
+        cache.get("a").await.unwrap();
+        cache.set("c", &json!(3), &tags(&["c"])).await.unwrap();
+
+        assert_eq!(cache.get("a").await.unwrap(), Some(json!(1)));
+        assert_eq!(cache.get("b").await.unwrap(), None);
+        assert!(!cache.state.lock().unwrap().keys_by_tag.contains_key("b"));
+    }
+
+    #[tokio::test]
+    async fn it_should_treat_an_expired_entry_as_missing() {
+        let cache = cache(2, Duration::ZERO);
+        cache.set("a", &json!(1), &tags(&["a"])).await.unwrap();
+
+        assert_eq!(cache.get("a").await.unwrap(), None);
+    }
+}
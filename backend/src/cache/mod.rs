@@ -0,0 +1,146 @@
+pub use memory_cache::InMemoryCache;
+pub use redis_cache::RedisCache;
+
+use crate::domain::entities::{Pagination, ResourceType};
+use crate::domain::registry::{Filter, ResourceKind};
+use serde_json::Value;
+use std::future::Future;
+
+mod memory_cache;
+
+mod redis_cache;
+
+/** Caches the results of the public reads, so a hit doesn't touch the database.
+*
+* Every entry is stored along with its tags, the use cases which change the resources invalidate
+* the tags of the entries they affect.
+*/
+#[async_trait::async_trait]
+pub trait IResourceCache: Send + Sync {
+    /** Get the cached value, an expired entry is treated as missing */
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Value>>;
+
+    /** Store the value, it's removed when any of the tags is invalidated */
+    async fn set(&self, key: &str, value: &Value, tags: &[String]) -> anyhow::Result<()>;
+
+    /** Remove the entries which are tagged with any of the tags */
+    async fn invalidate(&self, tags: &[String]) -> anyhow::Result<()>;
+}
+
+/// A cache which stores nothing, every read goes to the database
+pub struct NoCache;
+
+#[async_trait::async_trait]
+impl IResourceCache for NoCache {
+    async fn get(&self, _: &str) -> anyhow::Result<Option<Value>> {
+        Ok(None)
+    }
+
+    async fn set(&self, _: &str, _: &Value, _: &[String]) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn invalidate(&self, _: &[String]) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// The tags of the entries, an entry of a resource type is always tagged with the type
+pub mod tags {
+    use crate::domain::entities::ResourceType;
+
+    /// Every entry of the resource type
+    pub fn resource_type(resource_type: &ResourceType) -> String {
+        resource_type.to_string()
+    }
+
+    /// The entries of a single resource, in any language
+    pub fn resource(resource_type: &ResourceType, id: &str) -> String {
+        format!("{}:{}", resource_type, id)
+    }
+
+    /// The lists of the resource type, in any language, filters and page
+    pub fn list(resource_type: &ResourceType) -> String {
+        format!("{}:list", resource_type)
+    }
+}
+
+/// The key of a retrieved resource, the kinds of the same type are cached apart
+pub fn resource_key<K: ResourceKind>(id: &str, language: &str, filters: &[Filter]) -> String {
+    format!(
+        "{}:{}:{}:{:?}",
+        std::any::type_name::<K>(),
+        id,
+        language,
+        filters
+    )
+}
+
+/// The key of a page of the listed resources
+pub fn list_key<K: ResourceKind>(
+    language: &str,
+    filters: &[Filter],
+    pagination: &Pagination,
+) -> String {
+    format!(
+        "{}:list:{}:{:?}:{:?}",
+        std::any::type_name::<K>(),
+        language,
+        filters,
+        pagination
+    )
+}
+
+/// The tags of a retrieved resource
+pub fn resource_tags(resource_type: &ResourceType, id: &str) -> Vec<String> {
+    vec![
+        tags::resource_type(resource_type),
+        tags::resource(resource_type, id),
+    ]
+}
+
+/// The tags of a page of the listed resources
+pub fn list_tags(resource_type: &ResourceType) -> Vec<String> {
+    vec![
+        tags::resource_type(resource_type),
+        tags::list(resource_type),
+    ]
+}
+
+/** Returns the cached value, or loads and caches it when it's missing.
+*
+* The cache is only an optimization, its failures are logged and the value is loaded instead.
+* The failures of the loading aren't cached.
+*/
+pub async fn read_through<F, E>(
+    cache: &dyn IResourceCache,
+    key: &str,
+    tags: &[String],
+    load: F,
+) -> Result<Value, E>
+where
+    F: Future<Output = Result<Value, E>>,
+{
+    match cache.get(key).await {
+        Ok(Some(value)) => return Ok(value),
+        Ok(None) => {}
+        Err(e) => tracing::warn!(key, error = %e, "Failed to read the cache"),
+    }
+
+    let value = load.await?;
+    if let Err(e) = cache.set(key, &value, tags).await {
+        tracing::warn!(key, error = %e, "Failed to write the cache");
+    }
+
+    Ok(value)
+}
+
+/** Invalidates the entries after the change is committed.
+*
+* The change can't be undone at this point, so a failure is only logged, the entries expire anyway.
+*/
+pub async fn invalidate(cache: &dyn IResourceCache, tags: &[String]) {
+    if let Err(e) = cache.invalidate(tags).await {
+        tracing::warn!(?tags, error = %e, "Failed to invalidate the cache");
+    }
+}
@@ -0,0 +1,75 @@
+use crate::cache::IResourceCache;
+use redis::AsyncCommands;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+
+/** Caches the entries in Redis, so they are shared by the instances of the server.
+*
+* The keys of the entries are stored in a set per tag, the sets expire along with the entries.
+*/
+pub struct RedisCache {
+    client: Arc<redis::Client>,
+    ttl: Duration,
+}
+
+impl RedisCache {
+    pub fn new(client: Arc<redis::Client>, ttl: Duration) -> Self {
+        Self { client, ttl }
+    }
+
+    fn entry_key(key: &str) -> String {
+        format!("cache:entry:{}", key)
+    }
+
+    fn tag_key(tag: &str) -> String {
+        format!("cache:tag:{}", tag)
+    }
+}
+
+#[async_trait::async_trait]
+impl IResourceCache for RedisCache {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Value>> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let value: Option<String> = conn.get(Self::entry_key(key)).await?;
+
+        match value {
+            Some(value) => Ok(Some(serde_json::from_str(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, key: &str, value: &Value, tags: &[String]) -> anyhow::Result<()> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let key = Self::entry_key(key);
+        let ttl = self.ttl.as_secs().max(1);
+
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .set_ex(&key, serde_json::to_string(value)?, ttl)
+            .ignore();
+        for tag in tags {
+            let tag = Self::tag_key(tag);
+            pipe.sadd(&tag, &key)
+                .ignore()
+                .expire(&tag, ttl as i64)
+                .ignore();
+        }
+        let () = pipe.query_async(&mut conn).await?;
+
+        Ok(())
+    }
+
+    async fn invalidate(&self, tags: &[String]) -> anyhow::Result<()> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+
+        for tag in tags {
+            let tag = Self::tag_key(tag);
+            let mut keys: Vec<String> = conn.smembers(&tag).await?;
+            keys.push(tag);
+            let () = conn.del(keys).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,12 +1,444 @@
+use crate::domain::sessions::SessionPolicy;
+use crate::domain::users::authentication::LockoutPolicy;
+use crate::domain::users::forgot_password::PasswordResetPolicy;
+use crate::domain::users::password_hashing::PasswordHasher;
+use crate::domain::users::password_policy::PasswordPolicy;
+use crate::domain::users::totp::TotpPolicy;
+use chrono::{DateTime, Utc};
 use secrecy::{ExposeSecret, SecretBox};
 use serde::Deserialize;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
+use std::collections::HashMap;
 
 #[derive(Debug, Deserialize)]
 pub struct Settings {
     pub database: DatabaseSettings,
     pub application: Application,
     pub redis_uri: String,
+    #[serde(default)]
+    pub api: ApiSettings,
+    #[serde(default)]
+    pub cache: CacheSettings,
+    #[serde(default)]
+    pub lockout: LockoutSettings,
+    #[serde(default)]
+    pub sessions: SessionSettings,
+    #[serde(default)]
+    pub passwords: PasswordSettings,
+    #[serde(default)]
+    pub password_hashing: PasswordHashingSettings,
+    #[serde(default)]
+    pub password_reset: PasswordResetSettings,
+    #[serde(default)]
+    pub mail: MailSettings,
+    #[serde(default)]
+    pub totp: TotpSettings,
+}
+
+/// Which passwords the users can choose
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PasswordSettings {
+    pub min_length: usize,
+    /// Whether the common passwords of the bundled list are refused
+    pub refuse_common: bool,
+}
+
+impl Default for PasswordSettings {
+    fn default() -> Self {
+        Self {
+            min_length: 10,
+            refuse_common: true,
+        }
+    }
+}
+
+impl PasswordSettings {
+    pub fn policy(&self) -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: self.min_length,
+            refuse_common: self.refuse_common,
+        }
+    }
+}
+
+/// The algorithm of the password hashes
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PasswordHashAlgorithm {
+    #[default]
+    Argon2id,
+    Argon2i,
+    Argon2d,
+}
+
+/// How the passwords are hashed, the hashes of the other settings are replaced on login
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PasswordHashingSettings {
+    pub algorithm: PasswordHashAlgorithm,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for PasswordHashingSettings {
+    fn default() -> Self {
+        Self {
+            algorithm: PasswordHashAlgorithm::Argon2id,
+            memory_kib: 19456,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+impl PasswordHashingSettings {
+    pub fn hasher(&self) -> PasswordHasher {
+        let algorithm = match self.algorithm {
+            PasswordHashAlgorithm::Argon2id => argon2::Algorithm::Argon2id,
+            PasswordHashAlgorithm::Argon2i => argon2::Algorithm::Argon2i,
+            PasswordHashAlgorithm::Argon2d => argon2::Algorithm::Argon2d,
+        };
+
+        PasswordHasher::new(
+            algorithm,
+            self.memory_kib,
+            self.iterations,
+            self.parallelism,
+        )
+        .expect("Invalid password hashing settings")
+    }
+}
+
+/// How the users reset their passwords when they forget them
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PasswordResetSettings {
+    /// How long the emailed token can be used
+    pub token_seconds: i64,
+    /// The page of the UI where the password is reset, the token is its `token` query parameter
+    pub url: String,
+}
+
+impl Default for PasswordResetSettings {
+    fn default() -> Self {
+        Self {
+            token_seconds: 1800,
+            url: "http://localhost:5173/admin/reset-password".to_string(),
+        }
+    }
+}
+
+impl PasswordResetSettings {
+    pub fn policy(&self) -> PasswordResetPolicy {
+        PasswordResetPolicy {
+            token_ttl: chrono::Duration::seconds(self.token_seconds),
+            url: self.url.clone(),
+        }
+    }
+}
+
+/// How the users log in with a second factor
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TotpSettings {
+    /// The name of the accounts in the authenticator apps
+    pub issuer: String,
+    /// How long the code can be given after the password
+    pub challenge_seconds: i64,
+}
+
+impl Default for TotpSettings {
+    fn default() -> Self {
+        Self {
+            issuer: "Law Firm".to_string(),
+            challenge_seconds: 300,
+        }
+    }
+}
+
+impl TotpSettings {
+    pub fn policy(&self) -> TotpPolicy {
+        TotpPolicy {
+            issuer: self.issuer.clone(),
+            challenge_ttl: chrono::Duration::seconds(self.challenge_seconds),
+        }
+    }
+}
+
+/// How the emails are sent
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MailTransport {
+    /// The emails are written into the outbox folder instead, for the local development
+    #[default]
+    Outbox,
+    Smtp,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct MailSettings {
+    pub transport: MailTransport,
+    /// The sender of the emails, e.g. `Attorneys <no-reply@example.com>`
+    pub from: String,
+    pub outbox_folder: String,
+    /// It's required by the SMTP transport
+    pub smtp: Option<SmtpSettings>,
+}
+
+impl Default for MailSettings {
+    fn default() -> Self {
+        Self {
+            transport: MailTransport::default(),
+            from: "no-reply@localhost".to_string(),
+            outbox_folder: "outbox".to_string(),
+            smtp: None,
+        }
+    }
+}
+
+/// The SMTP server, the connection is upgraded with STARTTLS
+#[derive(Debug, Deserialize)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<SecretBox<String>>,
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+/// How long the tokens of the sessions last
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SessionSettings {
+    pub access_token_seconds: i64,
+    /// The session ends if its refresh token isn't used in time
+    pub refresh_token_seconds: i64,
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        Self {
+            access_token_seconds: 900,
+            refresh_token_seconds: 604800,
+        }
+    }
+}
+
+impl SessionSettings {
+    pub fn policy(&self) -> SessionPolicy {
+        SessionPolicy {
+            access_token_ttl: chrono::Duration::seconds(self.access_token_seconds),
+            refresh_token_ttl: chrono::Duration::seconds(self.refresh_token_seconds),
+        }
+    }
+}
+
+/// How the failed logins lock the usernames and the IPs out
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LockoutSettings {
+    /// The failures are counted in the window
+    pub window_seconds: i64,
+    pub max_failures_per_username: usize,
+    pub max_failures_per_ip: usize,
+    /// How long the attempts are refused since the last failure
+    pub lockout_seconds: i64,
+    /// The delay after the first failure, it's doubled by every failure
+    pub base_delay_milliseconds: u64,
+    pub max_delay_milliseconds: u64,
+}
+
+impl Default for LockoutSettings {
+    fn default() -> Self {
+        Self {
+            window_seconds: 900,
+            max_failures_per_username: 5,
+            max_failures_per_ip: 20,
+            lockout_seconds: 900,
+            base_delay_milliseconds: 250,
+            max_delay_milliseconds: 4000,
+        }
+    }
+}
+
+impl LockoutSettings {
+    pub fn policy(&self) -> LockoutPolicy {
+        LockoutPolicy {
+            window: chrono::Duration::seconds(self.window_seconds),
+            max_failures_per_username: self.max_failures_per_username,
+            max_failures_per_ip: self.max_failures_per_ip,
+            lockout: chrono::Duration::seconds(self.lockout_seconds),
+            base_delay: std::time::Duration::from_millis(self.base_delay_milliseconds),
+            max_delay: std::time::Duration::from_millis(self.max_delay_milliseconds),
+        }
+    }
+}
+
+/// Where the results of the public reads are cached
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheBackend {
+    /// Every read goes to the database
+    None,
+    /// In the process, the instances of the server have their own caches
+    Memory,
+    /// In Redis, the instances of the server share the cache
+    Redis,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CacheSettings {
+    #[serde(default = "default_cache_backend")]
+    pub backend: CacheBackend,
+    /// How many entries the in-process cache holds
+    #[serde(default = "default_cache_capacity")]
+    pub capacity: usize,
+    /// How long an entry is kept if it isn't invalidated
+    #[serde(default = "default_cache_ttl_seconds")]
+    pub ttl_seconds: u64,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            backend: default_cache_backend(),
+            capacity: default_cache_capacity(),
+            ttl_seconds: default_cache_ttl_seconds(),
+        }
+    }
+}
+
+fn default_cache_backend() -> CacheBackend {
+    CacheBackend::Memory
+}
+
+fn default_cache_capacity() -> usize {
+    1000
+}
+
+fn default_cache_ttl_seconds() -> u64 {
+    300
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ApiSettings {
+    /// The deprecated versions are still served, along with the `Deprecation` and `Sunset` headers
+    #[serde(default)]
+    pub deprecations: Vec<Deprecation>,
+    #[serde(default)]
+    pub cache_control: CacheControlSettings,
+    #[serde(default)]
+    pub rate_limits: RateLimitSettings,
+    /// The header which holds the IP of the client when the server is behind a proxy, e.g. `x-real-ip`
+    pub client_ip_header: Option<String>,
+}
+
+impl ApiSettings {
+    pub fn deprecation(&self, version: &str) -> Option<&Deprecation> {
+        self.deprecations.iter().find(|d| d.version == version)
+    }
+}
+
+/** The `Cache-Control` headers of the public read routes.
+*
+* A route group is the first segment of the path after the version, e.g. `articles` for
+* `/api/v1/articles/{id}`. The groups which aren't configured use the default.
+*/
+#[derive(Debug, Clone, Deserialize)]
+pub struct CacheControlSettings {
+    #[serde(default = "default_cache_control")]
+    pub default: String,
+    #[serde(default)]
+    pub groups: HashMap<String, String>,
+}
+
+impl Default for CacheControlSettings {
+    fn default() -> Self {
+        Self {
+            default: default_cache_control(),
+            groups: HashMap::new(),
+        }
+    }
+}
+
+impl CacheControlSettings {
+    pub fn for_group(&self, group: &str) -> &str {
+        self.groups.get(group).unwrap_or(&self.default)
+    }
+}
+
+/// The responses can be stored but they have to be revalidated with the ETag
+fn default_cache_control() -> String {
+    "public, no-cache".to_string()
+}
+
+/** The limits of the request rates.
+*
+* The route groups are named by the routes, e.g. `login`, `article_views`, `admin` and `public`.
+* The groups which aren't configured aren't limited.
+*/
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RateLimitSettings {
+    #[serde(default)]
+    pub store: RateLimitStore,
+    #[serde(default)]
+    pub groups: HashMap<String, RateLimitPolicy>,
+}
+
+impl RateLimitSettings {
+    pub fn for_group(&self, group: &str) -> Option<RateLimitPolicy> {
+        self.groups.get(group).copied()
+    }
+}
+
+/// Where the buckets of the rate limits are kept
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitStore {
+    /// In the process, every instance of the server limits the rates on its own
+    #[default]
+    Memory,
+    /// In Redis, the instances of the server share the limits
+    Redis,
+}
+
+/// A token bucket, a request takes a token and the tokens are refilled over time
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+pub struct RateLimitPolicy {
+    /// How many requests can be sent at once
+    pub burst: u32,
+    /// How many tokens are refilled per minute
+    pub per_minute: u32,
+    #[serde(default)]
+    pub key: RateLimitKey,
+}
+
+/// Whom the bucket belongs to
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitKey {
+    /// The IP of the client
+    #[default]
+    Ip,
+    /// The user of the bearer token, the anonymous requests are limited by their IPs
+    User,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Deprecation {
+    /// The deprecated version, e.g. `v1`
+    pub version: String,
+    /// When the version is deprecated
+    pub deprecated_at: DateTime<Utc>,
+    /// When the version is going to be removed
+    pub sunset: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -0,0 +1,130 @@
+use crate::domain::audit::AuditFilter;
+use crate::domain::entities::{AuditAction, AuditEntry, Language, UserID};
+use crate::repositories::IAuditRepository;
+use chrono::{DateTime, Utc};
+use tokio::sync::Mutex;
+
+/// The entries are listed up to this many
+pub const MAX_LIMIT: usize = 500;
+
+pub struct Request {
+    pub user_id: Option<String>,
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub language: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: usize,
+}
+
+pub enum Error {
+    BadRequest,
+    Unknown(String),
+}
+
+/// Lists the latest entries first
+pub async fn execute(
+    req: Request,
+    audit_repo: Mutex<impl IAuditRepository + Sync + Send>,
+) -> Result<Vec<AuditEntry>, Error> {
+    let filter = AuditFilter {
+        user_id: req
+            .user_id
+            .map(UserID::try_from)
+            .transpose()
+            .map_err(|_| Error::BadRequest)?,
+        action: req
+            .action
+            .map(|action| AuditAction::try_from(action.as_str()))
+            .transpose()
+            .map_err(|_| Error::BadRequest)?,
+        target_type: req.target_type,
+        target_id: req.target_id,
+        language: req
+            .language
+            .map(Language::try_from)
+            .transpose()
+            .map_err(|_| Error::BadRequest)?,
+        since: req.since,
+        until: req.until,
+    };
+
+    audit_repo
+        .lock()
+        .await
+        .list(&filter, req.limit.clamp(1, MAX_LIMIT))
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::audit::{entry, Actor};
+    use crate::repositories::InMemoryAuditRepository;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn request() -> Request {
+        Request {
+            user_id: None,
+            action: None,
+            target_type: None,
+            target_id: None,
+            language: None,
+            since: None,
+            until: None,
+            limit: 100,
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_list_the_matching_entries_latest_first() {
+        let audit_repo = InMemoryAuditRepository::new();
+        let actor = Actor {
+            user_id: UserID::from(uuid::Uuid::new_v4()),
+            ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+        };
+        for (action, target_type, target_id) in [
+            (AuditAction::Create, "services", "1"),
+            (AuditAction::Update, "services", "1"),
+            (AuditAction::Delete, "members", "2"),
+        ] {
+            let entry = entry(Some(&actor), action, target_type, target_id.to_string());
+            audit_repo.save(&entry).await.unwrap();
+        }
+
+        let req = Request {
+            target_type: Some("services".to_string()),
+            ..request()
+        };
+        let entries = execute(req, Mutex::new(audit_repo.clone()))
+            .await
+            .ok()
+            .unwrap();
+        let actions = entries.iter().map(|e| e.action).collect::<Vec<_>>();
+        assert_eq!(actions, vec![AuditAction::Update, AuditAction::Create]);
+
+        let req = Request {
+            user_id: Some(actor.user_id.to_string()),
+            action: Some("delete".to_string()),
+            ..request()
+        };
+        let entries = execute(req, Mutex::new(audit_repo)).await.ok().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].target_id, "2");
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_an_unknown_action() {
+        let req = Request {
+            action: Some("rename".to_string()),
+            ..request()
+        };
+
+        match execute(req, Mutex::new(InMemoryAuditRepository::new())).await {
+            Err(Error::BadRequest) => {}
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use crate::domain::entities::{AuditAction, AuditEntry, Language, ResourceID, User, UserID};
+use crate::domain::registry::ResourceKind;
+use crate::uow::IResourceUnitOfWork;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+use std::net::IpAddr;
+
+pub mod list;
+
+/// The admin who changes something, along with where it's done
+#[derive(Debug, Clone)]
+pub struct Actor {
+    pub user_id: UserID,
+    pub ip: Option<IpAddr>,
+}
+
+/// Describes a change which is done by the actor, it's done on the server if there is no actor
+pub fn entry(
+    actor: Option<&Actor>,
+    action: AuditAction,
+    target_type: &str,
+    target_id: String,
+) -> AuditEntry {
+    AuditEntry {
+        user_id: actor.map(|actor| actor.user_id.clone()),
+        ip: actor.and_then(|actor| actor.ip),
+        action,
+        target_type: target_type.to_string(),
+        target_id,
+        language: None,
+        before: None,
+        after: None,
+        created_at: Utc::now(),
+    }
+}
+
+impl AuditEntry {
+    pub fn in_language(self, language: Language) -> Self {
+        Self {
+            language: Some(language),
+            ..self
+        }
+    }
+
+    /// The target before and after the change
+    pub fn with_snapshots(self, before: Option<Value>, after: Option<Value>) -> Self {
+        Self {
+            before,
+            after,
+            ..self
+        }
+    }
+}
+
+/// The resources which are moved by the offset, along with their new sequences
+pub fn reorders(
+    actor: Option<&Actor>,
+    target_type: &str,
+    moved: &[(ResourceID, i32)],
+    offset: i32,
+) -> Vec<AuditEntry> {
+    moved
+        .iter()
+        .map(|(id, seq)| {
+            entry(actor, AuditAction::Reorder, target_type, id.to_string()).with_snapshots(
+                Some(json!({ "seq": seq - offset })),
+                Some(json!({ "seq": seq })),
+            )
+        })
+        .collect()
+}
+
+/// Which entries are browsed, the ones which match all the given conditions
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    pub user_id: Option<UserID>,
+    pub action: Option<AuditAction>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub language: Option<Language>,
+    pub since: Option<DateTime<Utc>>,
+    /// Only the entries before the time, the older pages are browsed by the time of the last entry
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl AuditFilter {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        self.user_id
+            .as_ref()
+            .is_none_or(|user_id| entry.user_id.as_ref() == Some(user_id))
+            && self.action.is_none_or(|action| entry.action == action)
+            && self
+                .target_type
+                .as_ref()
+                .is_none_or(|target_type| &entry.target_type == target_type)
+            && self
+                .target_id
+                .as_ref()
+                .is_none_or(|target_id| &entry.target_id == target_id)
+            && self
+                .language
+                .as_ref()
+                .is_none_or(|language| entry.language.as_ref() == Some(language))
+            && self.since.is_none_or(|since| entry.created_at >= since)
+            && self.until.is_none_or(|until| entry.created_at < until)
+    }
+}
+
+/// The user as it's kept in the audit entries, the credentials are left out
+pub fn user_snapshot(user: &User) -> Value {
+    json!({
+        "username": user.username,
+        "role": user.role,
+        "email": user.email,
+        "disabled": user.disabled,
+        "must_reset_password": user.must_reset_password,
+        "totp_enabled": user.totp_enabled,
+    })
+}
+
+/// The resource in the language as it's read in the transaction, it's none if it doesn't exist
+pub async fn snapshot<K: ResourceKind>(
+    uow: &impl IResourceUnitOfWork,
+    id: &ResourceID,
+    language: &Language,
+) -> anyhow::Result<Option<Value>> {
+    match uow.get_resource::<K>(id, language, &[]).await? {
+        Some(entity) => Ok(Some(serde_json::to_value(entity)?)),
+        None => Ok(None),
+    }
+}
+
+/// The resource in every language which it's translated into, keyed by the languages
+pub async fn snapshots<K: ResourceKind>(
+    uow: &impl IResourceUnitOfWork,
+    id: &ResourceID,
+) -> anyhow::Result<Option<Value>> {
+    let mut translations = Map::new();
+    for language in Language::ALL {
+        if let Some(snapshot) = snapshot::<K>(uow, id, &language).await? {
+            translations.insert(language.as_str().to_string(), snapshot);
+        }
+    }
+
+    Ok((!translations.is_empty()).then_some(Value::Object(translations)))
+}
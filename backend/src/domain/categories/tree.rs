@@ -0,0 +1,294 @@
+use crate::domain::entities::{CategoryData, CategoryEntity, Language};
+use crate::domain::registry::{CategoryKind, ARTICLE_CATEGORY};
+use crate::uow::IResourceUnitOfWork;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use tokio::sync::Mutex;
+
+#[derive(Debug)]
+pub struct Request {
+    pub language: String,
+    pub default_language: Language,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    BadRequest,
+    Unknown(String),
+}
+
+/// A category along with its sub-categories and how many articles are in it
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CategoryNode {
+    pub id: String,
+    pub language: String,
+    pub data: CategoryData,
+    pub seq: i16,
+    /// The articles which are directly in the category
+    pub article_count: usize,
+    /// The articles which are in the category or in any of its sub-categories
+    pub total_article_count: usize,
+    pub children: Vec<CategoryNode>,
+}
+
+/** Lists the categories as a tree.
+*
+* The articles are counted in the requested language, the categories fall back to the default
+* language if they aren't translated. A category whose parent doesn't exist, or which is its
+* own ancestor, is on the top level.
+* The categories are sorted by the sequence on every level.
+*/
+pub async fn execute<IUnitOfWork>(
+    uow: Mutex<IUnitOfWork>,
+    req: Request,
+) -> Result<Vec<CategoryNode>, Error>
+where
+    IUnitOfWork: IResourceUnitOfWork,
+{
+    let language = Language::try_from(req.language).map_err(|_| Error::BadRequest)?;
+
+    let categories = {
+        let lock = uow.lock().await;
+        lock.list_resources_with_reference_counts::<CategoryKind>(
+            &language,
+            &req.default_language,
+            &ARTICLE_CATEGORY,
+        )
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?
+    };
+
+    Ok(build_tree(categories))
+}
+
+fn build_tree(mut categories: Vec<(CategoryEntity, usize)>) -> Vec<CategoryNode> {
+    categories.sort_by_key(|(category, _)| category.seq);
+
+    let ids = categories
+        .iter()
+        .map(|(category, _)| category.id.clone())
+        .collect::<HashSet<_>>();
+    let parents = categories
+        .iter()
+        .filter_map(|(category, _)| {
+            let parent = category.data.parent_id.as_ref()?;
+            ids.contains(parent)
+                .then(|| (category.id.clone(), parent.clone()))
+        })
+        .collect::<HashMap<_, _>>();
+
+    // follows the parents until the top level or a cycle, the categories in a cycle are all on
+    // the top level
+    let is_root = |id: &String| {
+        let mut visited = HashSet::from([id]);
+        let mut current = id;
+        while let Some(parent) = parents.get(current) {
+            if !visited.insert(parent) {
+                return parent == id;
+            }
+            current = parent;
+        }
+        current == id
+    };
+
+    let mut children = HashMap::<String, Vec<(CategoryEntity, usize)>>::new();
+    let mut roots = vec![];
+    for (category, count) in categories {
+        if is_root(&category.id) {
+            roots.push((category, count));
+        } else {
+            let parent = parents[&category.id].clone();
+            children.entry(parent).or_default().push((category, count));
+        }
+    }
+
+    roots
+        .into_iter()
+        .map(|(category, count)| to_node(category, count, &mut children))
+        .collect()
+}
+
+fn to_node(
+    category: CategoryEntity,
+    article_count: usize,
+    children: &mut HashMap<String, Vec<(CategoryEntity, usize)>>,
+) -> CategoryNode {
+    let children = children
+        .remove(&category.id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(child, count)| to_node(child, count, children))
+        .collect::<Vec<_>>();
+    let total_article_count = article_count
+        + children
+            .iter()
+            .map(|child| child.total_article_count)
+            .sum::<usize>();
+
+    CategoryNode {
+        id: category.id,
+        language: category.language,
+        data: category.data,
+        seq: category.seq,
+        article_count,
+        total_article_count,
+        children,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{ArticleData, ContentData, ContentID, ResourceID};
+    use crate::domain::registry::ArticleKind;
+    use crate::domain::resources::test_helpers::tests::{
+        create_some_fake_data_and_return_uow, insert_fake_resource,
+    };
+    use crate::repositories::IContentRepository;
+    use crate::uow::InMemory;
+
+    async fn insert_category(
+        uow: &mut InMemory,
+        name: &str,
+        parent: Option<&ContentID>,
+    ) -> ContentID {
+        let data = CategoryData::new(None, name.to_string(), parent.map(|id| id.to_string()));
+        insert_fake_resource::<CategoryKind>(uow, &data).await
+    }
+
+    async fn insert_article(uow: &mut InMemory, category: &ContentID) -> ContentID {
+        let data = ArticleData::new(
+            Some(category.to_string()),
+            "title".to_string(),
+            "content".to_string(),
+        );
+        insert_fake_resource::<ArticleKind>(uow, &data).await
+    }
+
+    async fn translate_article(uow: &mut InMemory, article: &ContentID, category: &ContentID) {
+        let data = ArticleData::new(
+            Some(category.to_string()),
+            "title".to_string(),
+            "content".to_string(),
+        );
+        uow.content_repository()
+            .insert(
+                article.clone(),
+                ContentData::try_from_data::<ArticleKind>(&data).unwrap(),
+                Language::EN,
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn tree(mut uow: InMemory, language: &str) -> Result<Vec<CategoryNode>, Error> {
+        let req = Request {
+            language: language.to_string(),
+            default_language: Language::ZH,
+        };
+        // Workaround: touch the repository for initialization
+        let _ = uow.avatar_repository();
+
+        execute(Mutex::new(uow), req).await
+    }
+
+    #[tokio::test]
+    async fn it_should_nest_the_categories_and_count_the_articles() {
+        let mut uow = InMemory::new();
+        let law = insert_category(&mut uow, "law", None).await;
+        let family = insert_category(&mut uow, "family", Some(&law)).await;
+        let divorce = insert_category(&mut uow, "divorce", Some(&family)).await;
+        let news = insert_category(&mut uow, "news", None).await;
+        insert_article(&mut uow, &law).await;
+        insert_article(&mut uow, &family).await;
+        insert_article(&mut uow, &family).await;
+        let article = insert_article(&mut uow, &news).await;
+        // the translation is in another category which is only counted in english
+        translate_article(&mut uow, &article, &divorce).await;
+
+        let categories = tree(uow, "zh").await.unwrap();
+        assert_eq!(categories.len(), 2);
+
+        let law = categories.iter().find(|c| c.data.name == "law").unwrap();
+        assert_eq!(law.article_count, 1);
+        assert_eq!(law.total_article_count, 3);
+        assert_eq!(law.children.len(), 1);
+
+        let family = &law.children[0];
+        assert_eq!(family.data.name, "family");
+        assert_eq!(family.article_count, 2);
+        assert_eq!(family.total_article_count, 2);
+        assert_eq!(family.children[0].id, divorce.to_string());
+        assert_eq!(family.children[0].total_article_count, 0);
+
+        let news = categories.iter().find(|c| c.data.name == "news").unwrap();
+        assert_eq!(news.article_count, 1);
+    }
+
+    #[tokio::test]
+    async fn it_should_count_the_articles_in_the_requested_language() {
+        let mut uow = InMemory::new();
+        let law = insert_category(&mut uow, "law", None).await;
+        let family = insert_category(&mut uow, "family", Some(&law)).await;
+        let article = insert_article(&mut uow, &law).await;
+        translate_article(&mut uow, &article, &family).await;
+
+        let categories = tree(uow, "en").await.unwrap();
+        assert_eq!(categories.len(), 1);
+
+        // the categories aren't translated so they fall back to the default language
+        let law = &categories[0];
+        assert_eq!(law.language, "zh");
+        assert_eq!(law.article_count, 0);
+        assert_eq!(law.total_article_count, 1);
+        assert_eq!(law.children[0].article_count, 1);
+    }
+
+    #[tokio::test]
+    async fn it_should_put_the_orphans_and_the_cycles_on_the_top_level() {
+        let mut uow = InMemory::new();
+        let missing = ContentID::from(ResourceID::try_from(ulid::Ulid::new().to_string()).unwrap());
+        insert_category(&mut uow, "orphan", Some(&missing)).await;
+
+        let categories = tree(uow, "zh").await.unwrap();
+        assert_eq!(categories.len(), 1);
+        assert!(categories[0].children.is_empty());
+
+        let categories = build_tree(vec![
+            (category("a", Some("b")), 0),
+            (category("b", Some("a")), 0),
+            (category("c", Some("a")), 1),
+        ]);
+        assert_eq!(categories.len(), 2);
+        let a = categories.iter().find(|c| c.id == "a").unwrap();
+        assert_eq!(a.children[0].id, "c");
+        assert_eq!(a.total_article_count, 1);
+    }
+
+    fn category(id: &str, parent: Option<&str>) -> CategoryEntity {
+        CategoryEntity::new(
+            id.to_string(),
+            "zh".to_string(),
+            CategoryData::new(None, id.to_string(), parent.map(|p| p.to_string())),
+            0,
+            1,
+        )
+    }
+
+    #[tokio::test]
+    async fn it_should_return_a_bad_request_when_the_language_is_unsupported() {
+        let res = tree(InMemory::new(), "fr").await;
+
+        assert!(matches!(res, Err(Error::BadRequest)));
+    }
+
+    #[tokio::test]
+    async fn it_should_return_an_unknown_error_otherwise() {
+        let (uow, _) = create_some_fake_data_and_return_uow::<CategoryKind>().await;
+
+        let res = tree(uow.with_error(), "zh").await;
+
+        assert!(matches!(res, Err(Error::Unknown(_))));
+    }
+}
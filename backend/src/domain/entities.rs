@@ -1,7 +1,8 @@
 use crate::domain::member::entities::AvatarData;
-use serde::{Deserialize, Serialize};
-use serde_json::json;
-use sqlx::FromRow;
+use crate::domain::registry::ResourceKind;
+use crate::domain::validation::FieldError;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
 use std::fmt::Formatter;
 use validator::Validate;
 
@@ -38,6 +39,8 @@ pub enum Language {
 }
 
 impl Language {
+    pub const ALL: [Language; 2] = [Language::ZH, Language::EN];
+
     pub fn as_str(&self) -> &'static str {
         match self {
             Self::ZH => "zh",
@@ -90,42 +93,47 @@ impl ContentData {
     pub fn as_json(&self) -> &serde_json::Value {
         &self.0
     }
-}
 
-impl TryFrom<Resource> for ContentData {
-    type Error = ();
+    /// Replaces a field of the data, the data isn't validated again
+    pub fn with_field(mut self, field: &str, value: serde_json::Value) -> Self {
+        self.0[field] = value;
+        self
+    }
 
-    fn try_from(resource: Resource) -> Result<Self, Self::Error> {
-        match resource {
-            Resource::Member(m) => try_parse_to_value(m),
-            Resource::Service(s) => try_parse_to_value(s),
-            Resource::Home(h) => try_parse_to_value(h),
-            Resource::Contact(c) => Ok(ContentData(json!({
-                "data": c.data
-            }))),
-            Resource::Article(a) => try_parse_to_value(a),
-            Resource::Category(c) => try_parse_to_value(c),
-        }
+    /// Validates the data of a resource type and converts it into the content
+    pub fn try_from_data<K: ResourceKind>(data: &K::Data) -> Result<Self, ResourceError> {
+        K::validate(data).map_err(|errors| {
+            ResourceError::ValidationError(FieldError::from_validation_errors(&errors))
+        })?;
+
+        serde_json::value::to_value(data)
+            .map(ContentData)
+            .map_err(|_| ResourceError::SerializationError)
     }
 }
 
-fn try_parse_to_value<T>(value: T) -> Result<ContentData, ()>
+/// Trims the string while deserializing, so the validation can reject the blank ones
+fn trimmed<'de, D>(deserializer: D) -> Result<String, D::Error>
 where
-    T: Validate + Serialize,
+    D: Deserializer<'de>,
 {
-    match value.validate() {
-        Ok(_) => match serde_json::value::to_value(value) {
-            Ok(v) => Ok(ContentData(v)),
-            Err(_) => Err(()),
-        },
-        Err(_) => Err(()),
-    }
+    String::deserialize(deserializer).map(|s| s.trim().to_string())
 }
 
-#[derive(Debug, Serialize, Validate, Deserialize, Clone, Eq, PartialEq)]
+/// Trims the ids while deserializing and drops the blank ones
+fn trimmed_ids<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer).map(trim_ids)
+}
+
+#[derive(Debug, Serialize, Validate, Deserialize, JsonSchema, Clone, Eq, PartialEq)]
 pub struct MemberData {
+    #[serde(deserialize_with = "trimmed")]
     #[validate(length(min = 1))]
     pub name: String,
+    #[serde(deserialize_with = "trimmed")]
     #[validate(length(min = 1))]
     pub description: String,
 }
@@ -139,10 +147,12 @@ impl MemberData {
     }
 }
 
-#[derive(Debug, Serialize, Validate, Deserialize, Clone, Eq, PartialEq)]
+#[derive(Debug, Serialize, Validate, Deserialize, JsonSchema, Clone, Eq, PartialEq)]
 pub struct ServiceData {
+    #[serde(deserialize_with = "trimmed")]
     #[validate(length(min = 1))]
     pub title: String,
+    #[serde(deserialize_with = "trimmed")]
     #[validate(length(min = 1))]
     pub data: String,
     pub icon: Option<String>,
@@ -158,8 +168,9 @@ impl ServiceData {
     }
 }
 
-#[derive(Debug, Serialize, Validate, Deserialize, Clone, Eq, PartialEq)]
+#[derive(Debug, Serialize, Validate, Deserialize, JsonSchema, Clone, Eq, PartialEq)]
 pub struct HomeData {
+    #[serde(deserialize_with = "trimmed")]
     #[validate(length(min = 1))]
     pub data: String,
 }
@@ -172,7 +183,7 @@ impl HomeData {
     }
 }
 
-#[derive(Debug, Serialize, Validate, Deserialize, Clone, Eq, PartialEq)]
+#[derive(Debug, Serialize, Validate, Deserialize, JsonSchema, Clone, Eq, PartialEq)]
 pub struct ContactData {
     pub data: serde_json::Value,
 }
@@ -183,11 +194,13 @@ impl ContactData {
     }
 }
 
-#[derive(Debug, Serialize, Validate, Deserialize, Clone, Eq, PartialEq)]
+#[derive(Debug, Serialize, Validate, Deserialize, JsonSchema, Clone, Eq, PartialEq)]
 pub struct ArticleData {
     pub category_id: Option<String>,
+    #[serde(deserialize_with = "trimmed")]
     #[validate(length(min = 1))]
     pub title: String,
+    #[serde(deserialize_with = "trimmed")]
     #[validate(length(min = 1))]
     pub content: String,
 }
@@ -202,250 +215,303 @@ impl ArticleData {
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Clone)]
-pub enum ResourceType {
-    Member,
-    Service,
-    Home,
-    Contact,
-    Article,
-    Category,
+#[derive(Debug, Serialize, Validate, Deserialize, JsonSchema, Clone, Eq, PartialEq)]
+pub struct FaqData {
+    #[serde(deserialize_with = "trimmed")]
+    #[validate(length(min = 1))]
+    pub question: String,
+    #[serde(deserialize_with = "trimmed")]
+    #[validate(length(min = 1))]
+    pub answer: String,
+    /// The services this question is related to
+    #[serde(default, deserialize_with = "trimmed_ids")]
+    pub service_ids: Vec<String>,
 }
 
-impl ResourceType {
-    pub fn as_str(&self) -> &str {
-        match self {
-            Self::Member => "member",
-            Self::Service => "service",
-            Self::Home => "home",
-            Self::Contact => "contact",
-            Self::Article => "article",
-            Self::Category => "category",
+impl FaqData {
+    pub fn new(question: String, answer: String, service_ids: Vec<String>) -> Self {
+        Self {
+            question: question.trim().to_string(),
+            answer: answer.trim().to_string(),
+            service_ids: trim_ids(service_ids),
         }
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
-pub enum Resource {
-    Member(MemberData),
-    Service(ServiceData),
-    Home(HomeData),
-    Contact(ContactData),
-    Article(ArticleData),
-    Category(CategoryData),
+#[derive(Debug, Serialize, Validate, Deserialize, JsonSchema, Clone, Eq, PartialEq)]
+pub struct TestimonialData {
+    /// The client's name, usually anonymized (e.g., "Mr. Wang")
+    #[serde(deserialize_with = "trimmed")]
+    #[validate(length(min = 1))]
+    pub client_name: String,
+    #[serde(deserialize_with = "trimmed")]
+    #[validate(length(min = 1))]
+    pub content: String,
+    #[serde(default, deserialize_with = "trimmed_ids")]
+    pub service_ids: Vec<String>,
+    #[serde(default, deserialize_with = "trimmed_ids")]
+    pub member_ids: Vec<String>,
+    /// Only approved testimonials are shown on the public website
+    #[serde(default)]
+    pub approved: bool,
+}
+
+impl TestimonialData {
+    pub fn new(
+        client_name: String,
+        content: String,
+        service_ids: Vec<String>,
+        member_ids: Vec<String>,
+        approved: bool,
+    ) -> Self {
+        Self {
+            client_name: client_name.trim().to_string(),
+            content: content.trim().to_string(),
+            service_ids: trim_ids(service_ids),
+            member_ids: trim_ids(member_ids),
+            approved,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Validate, Deserialize, JsonSchema, Clone, Eq, PartialEq)]
+pub struct CaseResultData {
+    #[serde(deserialize_with = "trimmed")]
+    #[validate(length(min = 1))]
+    pub matter_type: String,
+    #[serde(deserialize_with = "trimmed")]
+    #[validate(length(min = 1))]
+    pub outcome: String,
+    #[validate(range(min = 1900, max = 2100))]
+    pub year: i32,
+    #[serde(default, deserialize_with = "trimmed_ids")]
+    pub service_ids: Vec<String>,
+    #[serde(default, deserialize_with = "trimmed_ids")]
+    pub member_ids: Vec<String>,
+    /// Only the results which the client consents to publish are shown on the public website
+    #[serde(default)]
+    pub approved: bool,
+}
+
+impl CaseResultData {
+    pub fn new(
+        matter_type: String,
+        outcome: String,
+        year: i32,
+        service_ids: Vec<String>,
+        member_ids: Vec<String>,
+        approved: bool,
+    ) -> Self {
+        Self {
+            matter_type: matter_type.trim().to_string(),
+            outcome: outcome.trim().to_string(),
+            year,
+            service_ids: trim_ids(service_ids),
+            member_ids: trim_ids(member_ids),
+            approved,
+        }
+    }
+}
+
+fn trim_ids(ids: Vec<String>) -> Vec<String> {
+    ids.into_iter()
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .collect()
+}
+
+/// The name of a resource type, which is stored along with the resource.
+///
+/// Every resource type declares it once in its [`ResourceKind`] implementation.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub struct ResourceType(&'static str);
+
+impl ResourceType {
+    pub const fn new(name: &'static str) -> Self {
+        Self(name)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl std::fmt::Display for ResourceType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum ResourceError {
     #[error("Failed to validate resource")]
-    ValidationError,
+    ValidationError(Vec<FieldError>),
     #[error("Failed to serialize resource")]
     SerializationError,
 }
 
-impl Resource {
-    /// Converts resource into its type and content data
-    pub fn into_typed_content(self) -> Result<(ResourceType, ContentData), ResourceError> {
-        let resource_type = match self {
-            Resource::Member(_) => ResourceType::Member,
-            Resource::Service(_) => ResourceType::Service,
-            Resource::Home(_) => ResourceType::Home,
-            Resource::Contact(_) => ResourceType::Contact,
-            Resource::Article(_) => ResourceType::Article,
-            Resource::Category(_) => ResourceType::Category,
-        };
-
-        ContentData::try_from(self)
-            .map_err(|_| ResourceError::SerializationError)
-            .map(|content| (resource_type, content))
-    }
+/// A resource joined with its content in one language.
+///
+/// The entities and list items of every resource type are projected from it.
+#[derive(Debug, Clone)]
+pub struct ResourceRecord<D> {
+    pub id: String,
+    pub language: String,
+    pub data: D,
+    pub seq: i16,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// The version of the content, it's increased on every update
+    pub version: i32,
+    pub avatar: Option<AvatarData>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct MemberEntity {
+/// The entity shared by the resource types which don't need a special projection
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "{D}Entity")]
+pub struct ResourceEntity<D> {
     pub id: String,
     pub language: String,
-    pub data: MemberData,
-    pub avatar: Option<AvatarData>,
+    pub data: D,
     pub seq: i16,
+    /// The version of the content, an update has to be based on it
+    pub version: i32,
 }
 
-impl MemberEntity {
-    pub fn new(
-        id: String,
-        language: String,
-        data: MemberData,
-        avatar: Option<AvatarData>,
-        seq: i16,
-    ) -> Self {
+impl<D> ResourceEntity<D> {
+    pub fn new(id: String, language: String, data: D, seq: i16, version: i32) -> Self {
         Self {
             id,
             language,
             data,
-            avatar,
             seq,
+            version,
         }
     }
 }
 
-#[derive(Debug, FromRow)]
-pub struct MemberEntityFromSQLx {
-    pub id: String,
-    pub language: String,
-    pub data: sqlx::types::Json<MemberData>,
-    pub avatar: Option<sqlx::types::Json<AvatarData>>,
-    pub seq: i16,
-}
-
-impl From<MemberEntityFromSQLx> for MemberEntity {
-    fn from(value: MemberEntityFromSQLx) -> Self {
-        Self {
-            id: value.id.trim().to_owned(),
-            language: value.language.trim().to_owned(),
-            data: value.data.0,
-            avatar: value.avatar.map(|a| a.0),
-            seq: value.seq,
-        }
+impl<D> From<ResourceRecord<D>> for ResourceEntity<D> {
+    fn from(value: ResourceRecord<D>) -> Self {
+        Self::new(
+            value.id,
+            value.language,
+            value.data,
+            value.seq,
+            value.version,
+        )
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ServiceEntity {
+pub type ServiceEntity = ResourceEntity<ServiceData>;
+
+pub type ArticleEntity = ResourceEntity<ArticleData>;
+
+pub type CategoryEntity = ResourceEntity<CategoryData>;
+
+pub type FaqEntity = ResourceEntity<FaqData>;
+
+pub type TestimonialEntity = ResourceEntity<TestimonialData>;
+
+pub type CaseResultEntity = ResourceEntity<CaseResultData>;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct MemberEntity {
     pub id: String,
     pub language: String,
-    pub data: ServiceData,
+    pub data: MemberData,
+    pub avatar: Option<AvatarData>,
     pub seq: i16,
+    /// The version of the content, an update has to be based on it
+    pub version: i32,
 }
 
-impl ServiceEntity {
-    pub fn new(id: String, language: String, data: ServiceData, seq: i16) -> Self {
+impl MemberEntity {
+    pub fn new(
+        id: String,
+        language: String,
+        data: MemberData,
+        avatar: Option<AvatarData>,
+        seq: i16,
+        version: i32,
+    ) -> Self {
         Self {
             id,
             language,
             data,
+            avatar,
             seq,
+            version,
         }
     }
 }
 
-#[derive(Debug, FromRow)]
-pub struct ServiceEntityFromSQLx {
+/// The full profile of a member, it's both the entity and the list item since the second version
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct MemberProfileEntity {
     pub id: String,
     pub language: String,
-    pub data: sqlx::types::Json<ServiceData>,
+    pub data: MemberData,
+    pub avatar: Option<AvatarData>,
     pub seq: i16,
+    pub created_at: i64,
+    /// The version of the content, an update has to be based on it
+    pub version: i32,
 }
 
-impl From<ServiceEntityFromSQLx> for ServiceEntity {
-    fn from(value: ServiceEntityFromSQLx) -> Self {
+impl From<ResourceRecord<MemberData>> for MemberProfileEntity {
+    fn from(value: ResourceRecord<MemberData>) -> Self {
         Self {
-            id: value.id.trim().to_owned(),
-            language: value.language.trim().to_owned(),
-            data: value.data.0,
+            id: value.id,
+            language: value.language,
+            data: value.data,
+            avatar: value.avatar,
             seq: value.seq,
+            created_at: value.created_at.timestamp_millis(),
+            version: value.version,
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct HomeEntity {
     pub id: String,
     pub language: String,
     pub data: HomeData,
+    /// The version of the content, an update has to be based on it
+    pub version: i32,
 }
 
 impl HomeEntity {
-    pub fn new(id: String, language: String, data: HomeData) -> Self {
-        Self { id, language, data }
-    }
-}
-
-#[derive(Debug, FromRow)]
-pub struct HomeEntityFromSQLx {
-    pub id: String,
-    pub language: String,
-    pub data: sqlx::types::Json<HomeData>,
-}
-
-impl From<HomeEntityFromSQLx> for HomeEntity {
-    fn from(value: HomeEntityFromSQLx) -> Self {
+    pub fn new(id: String, language: String, data: HomeData, version: i32) -> Self {
         Self {
-            id: value.id.trim().to_owned(),
-            language: value.language.trim().to_owned(),
-            data: value.data.0,
+            id,
+            language,
+            data,
+            version,
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ContactEntity {
     pub id: String,
     pub language: String,
     pub data: serde_json::Value,
+    /// The version of the content, an update has to be based on it
+    pub version: i32,
 }
 
 impl ContactEntity {
-    pub fn new(id: String, language: String, data: serde_json::Value) -> Self {
-        Self { id, language, data }
-    }
-}
-
-#[derive(Debug, FromRow)]
-pub struct ContactEntityFromSQLx {
-    pub id: String,
-    pub language: String,
-    pub data: sqlx::types::Json<ContactData>,
-}
-
-impl From<ContactEntityFromSQLx> for ContactEntity {
-    fn from(value: ContactEntityFromSQLx) -> Self {
-        Self {
-            id: value.id.trim().to_owned(),
-            language: value.language.trim().to_owned(),
-            data: value.data.0.data,
-        }
-    }
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ArticleEntity {
-    pub id: String,
-    pub language: String,
-    pub data: ArticleData,
-    pub seq: i16,
-}
-
-impl ArticleEntity {
-    pub fn new(id: String, language: String, data: ArticleData, seq: i16) -> Self {
+    pub fn new(id: String, language: String, data: serde_json::Value, version: i32) -> Self {
         Self {
             id,
             language,
             data,
-            seq,
+            version,
         }
     }
 }
 
-#[derive(Debug, FromRow)]
-pub struct ArticleEntityFromSQLx {
-    pub id: String,
-    pub language: String,
-    pub data: sqlx::types::Json<ArticleData>,
-    pub seq: i16,
-}
-
-impl From<ArticleEntityFromSQLx> for ArticleEntity {
-    fn from(value: ArticleEntityFromSQLx) -> Self {
-        Self {
-            id: value.id.trim().to_owned(),
-            language: value.language.trim().to_owned(),
-            data: value.data.0,
-            seq: value.seq,
-        }
-    }
-}
-
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct SimpleArticleEntity {
     pub id: String,
     pub title: String,
@@ -454,28 +520,7 @@ pub struct SimpleArticleEntity {
     pub seq: i16,
 }
 
-#[derive(Debug, FromRow)]
-pub struct SimpleArticleEntityFromSQLx {
-    pub id: String,
-    pub title: String,
-    pub language: String,
-    pub created_at: chrono::DateTime<chrono::Utc>,
-    pub seq: i16,
-}
-
-impl From<SimpleArticleEntityFromSQLx> for SimpleArticleEntity {
-    fn from(value: SimpleArticleEntityFromSQLx) -> Self {
-        Self {
-            id: value.id.trim().to_owned(),
-            title: value.title.trim().to_owned(),
-            language: value.language.trim().to_owned(),
-            created_at: value.created_at.timestamp_millis(),
-            seq: value.seq,
-        }
-    }
-}
-
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct SimpleMemberEntity {
     pub id: String,
     pub name: String,
@@ -494,33 +539,14 @@ impl SimpleMemberEntity {
     }
 }
 
-#[derive(Debug, FromRow)]
-pub struct SimpleMemberEntityFromSQLx {
-    pub id: String,
-    pub name: String,
-    pub avatar: Option<String>,
-    pub seq: i16,
-}
-
-impl From<SimpleMemberEntityFromSQLx> for SimpleMemberEntity {
-    fn from(value: SimpleMemberEntityFromSQLx) -> Self {
-        Self {
-            id: value.id.to_owned(),
-            name: value.name.to_owned(),
-            avatar: value.avatar,
-            seq: value.seq,
-        }
-    }
-}
-
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum Pagination {
     All,
     Single,
     Page(Page),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Page {
     pub page: u32,
     pub size: u32,
@@ -540,6 +566,12 @@ impl TryFrom<String> for UserID {
     }
 }
 
+impl UserID {
+    pub fn as_uuid(&self) -> &uuid::Uuid {
+        &self.0
+    }
+}
+
 impl From<uuid::Uuid> for UserID {
     fn from(value: uuid::Uuid) -> Self {
         UserID(value)
@@ -552,52 +584,272 @@ impl std::fmt::Display for UserID {
     }
 }
 
-#[derive(Debug, Serialize, Validate, Deserialize, Clone, Eq, PartialEq)]
-pub struct CategoryData {
-    pub icon: Option<String>,
-    pub name: String,
+/// What a user can do, see the permissions of the roles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Does everything, including managing the users
+    Owner,
+    /// Manages all the resources
+    Editor,
+    /// Writes the articles, only the own ones can be changed
+    Author,
+    /// Only reads
+    Viewer,
 }
 
-impl CategoryData {
-    pub fn new(icon: Option<String>, name: String) -> Self {
-        Self { icon, name }
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Owner => "owner",
+            Role::Editor => "editor",
+            Role::Author => "author",
+            Role::Viewer => "viewer",
+        }
     }
 }
 
-#[derive(Debug, FromRow)]
-pub struct CategoryEntityFromSQLx {
-    pub id: String,
-    pub data: sqlx::types::Json<CategoryData>,
-    pub language: String,
-    pub seq: i16,
+impl TryFrom<&str> for Role {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.trim().to_lowercase().as_str() {
+            "owner" => Ok(Role::Owner),
+            "editor" => Ok(Role::Editor),
+            "author" => Ok(Role::Author),
+            "viewer" => Ok(Role::Viewer),
+            _ => Err(()),
+        }
+    }
 }
 
-impl From<CategoryEntityFromSQLx> for CategoryEntity {
-    fn from(value: CategoryEntityFromSQLx) -> Self {
-        Self {
-            id: value.id.trim().to_owned(),
-            language: value.language.trim().to_owned(),
-            data: value.data.0,
-            seq: value.seq,
+/// An admin user, the password hash isn't a part of it
+#[derive(Debug, Clone, PartialEq)]
+pub struct User {
+    pub id: UserID,
+    pub username: String,
+    pub role: Role,
+    /// Where the links to reset the password are sent
+    pub email: Option<String>,
+    /// The disabled users can't log in
+    pub disabled: bool,
+    /// The user can only change the password until it's changed
+    pub must_reset_password: bool,
+    /// The user logs in with a code of an authenticator app after the password
+    pub totp_enabled: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// The security settings which apply to all the users
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SecuritySettings {
+    /// The users have to log in with a second factor, the ones without it have to enrol first
+    pub require_totp: bool,
+}
+
+/// A request to reset the password, only the hash of its token is kept
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasswordReset {
+    pub token_hash: String,
+    pub user_id: UserID,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Identifies a session, it's the `jti` of the access tokens of the session
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct SessionID(uuid::Uuid);
+
+impl SessionID {
+    pub fn new() -> Self {
+        SessionID(uuid::Uuid::new_v4())
+    }
+}
+
+impl Default for SessionID {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TryFrom<String> for SessionID {
+    type Error = ();
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match uuid::Uuid::try_parse(value.as_str()) {
+            Ok(id) => Ok(SessionID(id)),
+            Err(_) => Err(()),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CategoryEntity {
-    pub id: String,
-    pub language: String,
-    pub data: CategoryData,
-    pub seq: i16,
+impl std::fmt::Display for SessionID {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A device which is logged in, it lasts until its refresh token expires or it's revoked
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub id: SessionID,
+    pub user_id: UserID,
+    pub ip: std::net::IpAddr,
+    pub user_agent: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// When the refresh token was last rotated
+    pub refreshed_at: chrono::DateTime<chrono::Utc>,
+    /// When the session was last used, it's updated once a minute at most
+    pub last_seen_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// How a login attempt ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LoginOutcome {
+    Succeeded,
+    Failed,
+    /// The password is right, the code of the second factor is asked for
+    Challenged,
+    /// The attempt is refused since the username or the IP is locked out
+    Locked,
+    /// Not an attempt, the failures before it are forgiven by an admin
+    Unlocked,
+}
+
+impl LoginOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoginOutcome::Succeeded => "succeeded",
+            LoginOutcome::Failed => "failed",
+            LoginOutcome::Challenged => "challenged",
+            LoginOutcome::Locked => "locked",
+            LoginOutcome::Unlocked => "unlocked",
+        }
+    }
+}
+
+impl TryFrom<&str> for LoginOutcome {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "succeeded" => Ok(LoginOutcome::Succeeded),
+            "failed" => Ok(LoginOutcome::Failed),
+            "challenged" => Ok(LoginOutcome::Challenged),
+            "locked" => Ok(LoginOutcome::Locked),
+            "unlocked" => Ok(LoginOutcome::Unlocked),
+            _ => Err(()),
+        }
+    }
 }
 
-impl CategoryEntity {
-    pub fn new(id: String, language: String, data: CategoryData, seq: i16) -> Self {
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct LoginAttempt {
+    pub username: String,
+    pub ip: std::net::IpAddr,
+    pub user_agent: Option<String>,
+    pub outcome: LoginOutcome,
+    #[schemars(with = "String")]
+    pub attempted_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Validate, Deserialize, JsonSchema, Clone, Eq, PartialEq)]
+pub struct CategoryData {
+    pub icon: Option<String>,
+    pub name: String,
+    /// The parent category, the categories without a parent are on the top level
+    pub parent_id: Option<String>,
+}
+
+impl CategoryData {
+    pub fn new(icon: Option<String>, name: String, parent_id: Option<String>) -> Self {
         Self {
-            id,
-            language,
-            data,
-            seq,
+            icon,
+            name,
+            parent_id,
         }
     }
 }
+
+/// What is done by an admin, see the audit log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Disable,
+    Enable,
+    /// The user has to reset the password on the next login
+    ForcePasswordReset,
+    /// The second factor of the user is removed, e.g. the device is lost
+    ResetTotp,
+    /// A file is uploaded, e.g. the avatar of a member
+    Upload,
+    /// The failures of a username are forgiven
+    Unlock,
+    /// All the sessions of a user are ended
+    RevokeSessions,
+    /// The resource is moved, because another one is inserted or removed before it
+    Reorder,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+            AuditAction::Disable => "disable",
+            AuditAction::Enable => "enable",
+            AuditAction::ForcePasswordReset => "force_password_reset",
+            AuditAction::ResetTotp => "reset_totp",
+            AuditAction::Upload => "upload",
+            AuditAction::Unlock => "unlock",
+            AuditAction::RevokeSessions => "revoke_sessions",
+            AuditAction::Reorder => "reorder",
+        }
+    }
+}
+
+impl TryFrom<&str> for AuditAction {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "create" => Ok(AuditAction::Create),
+            "update" => Ok(AuditAction::Update),
+            "delete" => Ok(AuditAction::Delete),
+            "disable" => Ok(AuditAction::Disable),
+            "enable" => Ok(AuditAction::Enable),
+            "force_password_reset" => Ok(AuditAction::ForcePasswordReset),
+            "reset_totp" => Ok(AuditAction::ResetTotp),
+            "upload" => Ok(AuditAction::Upload),
+            "unlock" => Ok(AuditAction::Unlock),
+            "revoke_sessions" => Ok(AuditAction::RevokeSessions),
+            "reorder" => Ok(AuditAction::Reorder),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Who did what to which, the entries are never changed
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    /// It's missing if it's done on the server, e.g. by the `create_user` binary
+    pub user_id: Option<UserID>,
+    pub ip: Option<std::net::IpAddr>,
+    pub action: AuditAction,
+    /// What is changed, e.g. `user` or the type of a resource
+    pub target_type: String,
+    pub target_id: String,
+    /// The language of the changed content, it's missing if the change isn't of a language
+    pub language: Option<Language>,
+    /// The target before the change, it's missing if it's created
+    pub before: Option<serde_json::Value>,
+    /// The target after the change, it's missing if it's deleted
+    pub after: Option<serde_json::Value>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
@@ -1,7 +1,9 @@
-use crate::domain::entities::{ResourceID, ResourceType};
+use crate::cache::{self, tags, IResourceCache};
+use crate::domain::audit::{entry, Actor};
+use crate::domain::entities::{AuditAction, ContentID, ResourceID, ResourceType};
 use crate::domain::member::entities::{AvatarData, AvatarJson};
-use crate::repositories::IAvatarRepository;
 use crate::repositories::IResourceRepository;
+use crate::repositories::{IAuditRepository, IAvatarRepository, IContentRepository};
 use crate::uow::IResourceUnitOfWork;
 use crate::utils::image::{IImage, Size};
 use std::sync::Arc;
@@ -11,6 +13,8 @@ pub struct Request {
     pub(crate) id: String,
     pub(crate) resource_type: ResourceType,
     pub(crate) data: Vec<u8>,
+    /// Who uploads the avatar, it's recorded in the audit log
+    pub(crate) actor: Option<Actor>,
 }
 
 pub enum Error {
@@ -38,6 +42,7 @@ async fn resize_image_and_save_it(
 }
 pub async fn execute<IUnitOfWork>(
     uow: Mutex<IUnitOfWork>,
+    cache: &dyn IResourceCache,
     out: Arc<String>,
     image_util: Arc<dyn IImage + Sync + Send>,
     req: Request,
@@ -83,6 +88,13 @@ where
         small_image: small_image_path,
     };
     let avatar_json = AvatarJson::try_from(avatar_data).map_err(|_| Error::Unknown)?;
+    let entry = entry(
+        req.actor.as_ref(),
+        AuditAction::Upload,
+        req.resource_type.as_str(),
+        member_id.to_string(),
+    )
+    .with_snapshots(None, Some(avatar_json.clone().get()));
 
     let avatar_id = match lock
         .avatar_repository()
@@ -93,18 +105,42 @@ where
         _ => Err(Error::Unknown),
     }?;
 
+    // the avatar is a part of the resource, so the ETags of its reads are changed along with it
+    lock.content_repository()
+        .touch(&ContentID::from(member_id.clone()))
+        .await
+        .map_err(|_| Error::Unknown)?;
+
+    // the entry is written in the same transaction, so there is no change without it
+    lock.audit_repository()
+        .save(&entry)
+        .await
+        .map_err(|_| Error::Unknown)?;
+
     drop(lock);
     uow.into_inner()
         .commit()
         .await
         .map_err(|_| Error::Unknown)?;
 
+    cache::invalidate(
+        cache,
+        &[
+            tags::resource(&req.resource_type, member_id.as_str()),
+            tags::list(&req.resource_type),
+        ],
+    )
+    .await;
+
     Ok(avatar_id.to_string())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::cache::NoCache;
+    use crate::domain::registry::{MemberKind, ResourceKind};
+    use crate::repositories::InMemoryAuditRepository;
     use crate::utils::image::FakeImageUtil;
     use tokio::fs;
     use tokio::fs::File;
@@ -127,11 +163,12 @@ mod test {
     async fn it_should_work_otherwise() {
         // Arrange
         let util = FakeImageUtil::new();
-        let mut uow = crate::uow::InMemory::new();
+        let audit_repo = InMemoryAuditRepository::new();
+        let mut uow = crate::uow::InMemory::new().with_audit_repository(audit_repo.clone());
         let id = Ulid::new().to_string();
         let id = ResourceID::try_from(id).unwrap();
         uow.resource_repository()
-            .insert(id.clone(), ResourceType::Member, 0)
+            .insert(id.clone(), MemberKind::TYPE, 0, None)
             .await
             .expect("can't insert a member");
 
@@ -141,18 +178,24 @@ mod test {
 
         let req = Request {
             id: id.as_str().to_string(),
-            resource_type: ResourceType::Member,
+            resource_type: MemberKind::TYPE,
             data: buffer,
+            actor: None,
         };
 
         let out = Arc::new("".to_string());
 
-        let res = execute(Mutex::new(uow), out, Arc::new(util), req).await;
+        let res = execute(Mutex::new(uow), &NoCache, out, Arc::new(util), req).await;
 
         match res {
             Ok(id) => assert_eq!(id, id.as_str()),
             Err(_) => unreachable!(),
         }
+
+        let entries = audit_repo.entries().await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, AuditAction::Upload);
+        assert_eq!(entries[0].target_id, id.to_string());
     }
 
     #[tokio::test]
@@ -163,19 +206,20 @@ mod test {
         let id = Ulid::new().to_string();
         let id = ResourceID::try_from(id).unwrap();
         uow.resource_repository()
-            .insert(id.clone(), ResourceType::Member, 0)
+            .insert(id.clone(), MemberKind::TYPE, 0, None)
             .await
             .expect("can't insert a member");
 
         let req = Request {
             id: id.as_str().to_string(),
-            resource_type: ResourceType::Member,
+            resource_type: MemberKind::TYPE,
             data: vec![1, 2, 3, 4],
+            actor: None,
         };
 
         let out = Arc::new("".to_string());
 
-        let res = execute(Mutex::new(uow), out, Arc::new(util), req).await;
+        let res = execute(Mutex::new(uow), &NoCache, out, Arc::new(util), req).await;
 
         match res {
             Err(Error::ImageProcess) => {}
@@ -191,7 +235,7 @@ mod test {
         let id = Ulid::new().to_string();
         let id = ResourceID::try_from(id).unwrap();
         uow.resource_repository()
-            .insert(id.clone(), ResourceType::Member, 0)
+            .insert(id.clone(), MemberKind::TYPE, 0, None)
             .await
             .expect("can't insert a member");
 
@@ -201,13 +245,14 @@ mod test {
 
         let req = Request {
             id: id.as_str().to_string(),
-            resource_type: ResourceType::Member,
+            resource_type: MemberKind::TYPE,
             data: buffer,
+            actor: None,
         };
 
         let out = Arc::new("".to_string());
 
-        let res = execute(Mutex::new(uow), out, Arc::new(util), req).await;
+        let res = execute(Mutex::new(uow), &NoCache, out, Arc::new(util), req).await;
 
         match res {
             Err(Error::CreateImage) => {}
@@ -228,13 +273,14 @@ mod test {
 
         let req = Request {
             id: id.clone(),
-            resource_type: ResourceType::Member,
+            resource_type: MemberKind::TYPE,
             data: buffer,
+            actor: None,
         };
 
         let out = Arc::new("".to_string());
 
-        let res = execute(Mutex::new(uow), out, Arc::new(util), req).await;
+        let res = execute(Mutex::new(uow), &NoCache, out, Arc::new(util), req).await;
 
         match res {
             Err(Error::NotFound) => {}
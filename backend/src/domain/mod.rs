@@ -1,3 +1,5 @@
+pub mod audit;
+
 pub mod member;
 
 pub mod entities;
@@ -6,4 +8,12 @@ pub mod resources;
 
 pub mod users;
 
+pub mod sessions;
+
 pub mod articles;
+
+pub mod categories;
+
+pub mod registry;
+
+pub mod validation;
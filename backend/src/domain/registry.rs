@@ -0,0 +1,646 @@
+use crate::domain::entities::{
+    ArticleData, ArticleEntity, CaseResultData, CaseResultEntity, CategoryData, CategoryEntity,
+    ContactData, ContactEntity, FaqData, FaqEntity, HomeData, HomeEntity, MemberData, MemberEntity,
+    MemberProfileEntity, Page, Pagination, ResourceEntity, ResourceRecord, ResourceType,
+    ServiceData, ServiceEntity, SimpleArticleEntity, SimpleMemberEntity, TestimonialData,
+    TestimonialEntity,
+};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use validator::{Validate, ValidationErrors};
+
+/** Declares a resource type once.
+*
+* The data type, the projections, the validation and the routes of a resource type are all derived
+* from its implementation, so adding a new resource type only needs a new data type and a new
+* implementation of this trait which is registered in the routes.
+*/
+pub trait ResourceKind: Send + Sync + 'static {
+    /** The name of the resource type which is stored along with the resource */
+    const TYPE: ResourceType;
+
+    /** The path of the routes, e.g. `/members` and `/members/{id}` */
+    const PATH: &'static str;
+
+    /** The key of a single entity in the responses */
+    const SINGULAR: &'static str;
+
+    /** The key of the list items in the responses */
+    const PLURAL: &'static str;
+
+    /** Whether the resources can be deleted by the admins */
+    const DELETABLE: bool = true;
+
+    /** How the resources are listed */
+    const PAGINATION: Pagination = Pagination::All;
+
+    /** The query parameters which are accepted to filter the resources */
+    const FILTERS: &'static [FilterParam] = &[];
+
+    /** The boolean field which has to be true for the public, the admins can see all resources */
+    const VISIBILITY_FIELD: Option<&'static str> = None;
+
+    /** The fields which refer to other resources, the referred resources have to exist */
+    const REFERENCES: &'static [Reference] = &[];
+
+    /** The fields of other resource types which refer to this one, they are checked on deletion */
+    const REFERENCED_BY: &'static [Reference] = &[];
+
+    type Data: Validate
+        + Serialize
+        + DeserializeOwned
+        + JsonSchema
+        + Clone
+        + Debug
+        + Send
+        + Sync
+        + 'static;
+
+    /** The projection of a single resource */
+    type Entity: Serialize + JsonSchema + Send + 'static;
+
+    /** The projection of a resource in the list */
+    type ListItem: Serialize + JsonSchema + Send + 'static;
+
+    fn to_entity(record: ResourceRecord<Self::Data>) -> Self::Entity;
+
+    fn to_list_item(record: ResourceRecord<Self::Data>) -> Self::ListItem;
+
+    fn validate(data: &Self::Data) -> Result<(), ValidationErrors> {
+        data.validate()
+    }
+
+    /** The JSON Schema of the data, including the constraints of the validation */
+    fn schema() -> serde_json::Value {
+        schemars::schema_for!(Self::Data).to_value()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Matcher {
+    /** The field equals to the value */
+    Equals,
+    /** The field is an array which contains the value */
+    Contains,
+}
+
+/// A query parameter which filters the resources by a field of their data
+#[derive(Debug, Clone, Copy)]
+pub struct FilterParam {
+    pub param: &'static str,
+    pub field: &'static str,
+    pub matcher: Matcher,
+}
+
+impl FilterParam {
+    pub const fn equals(param: &'static str, field: &'static str) -> Self {
+        Self {
+            param,
+            field,
+            matcher: Matcher::Equals,
+        }
+    }
+
+    pub const fn contains(param: &'static str, field: &'static str) -> Self {
+        Self {
+            param,
+            field,
+            matcher: Matcher::Contains,
+        }
+    }
+
+    pub fn to_filter(&self, value: String) -> Filter {
+        match self.matcher {
+            Matcher::Equals => Filter::Equals {
+                field: self.field,
+                value,
+            },
+            Matcher::Contains => Filter::Contains {
+                field: self.field,
+                value,
+            },
+        }
+    }
+}
+
+/// A field of a resource type which holds the ID of a resource of another type, or a list of them
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Reference {
+    /// The referring resource type
+    pub from: ResourceType,
+    pub field: &'static str,
+    /// The referred resource type
+    pub to: ResourceType,
+    pub on_delete: OnDelete,
+}
+
+/// What happens to the references when the referred resource is deleted without a strategy
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum OnDelete {
+    /// The deletion is refused while the resource is referred
+    Refuse,
+    /// The references are cleared, e.g. a testimonial doesn't need the member to stay
+    Detach,
+}
+
+impl Reference {
+    /// Gets the referred ID from the data, a missing or empty field refers to nothing
+    pub fn referred_id<'a>(&self, data: &'a serde_json::Value) -> Option<&'a str> {
+        data.get(self.field)
+            .and_then(|v| v.as_str())
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+    }
+
+    /// Gets all the referred IDs from the data, whether the field holds a single ID or a list
+    pub fn referred_ids<'a>(&self, data: &'a serde_json::Value) -> Vec<&'a str> {
+        match data.get(self.field) {
+            Some(serde_json::Value::Array(ids)) => ids
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+                .collect(),
+            _ => self.referred_id(data).into_iter().collect(),
+        }
+    }
+
+    /// Gets the value of the field once the ID is replaced by the target, or removed from a list
+    /// if there is no target
+    pub fn replaced(
+        &self,
+        data: &serde_json::Value,
+        id: &str,
+        target: Option<&str>,
+    ) -> serde_json::Value {
+        let Some(serde_json::Value::Array(ids)) = data.get(self.field) else {
+            return target.map(serde_json::Value::from).unwrap_or_default();
+        };
+
+        let mut ids: Vec<_> = ids
+            .iter()
+            .filter(|v| v.as_str().map(|v| v.trim()) != Some(id))
+            .filter(|v| target.is_none() || v.as_str().map(|v| v.trim()) != target)
+            .cloned()
+            .collect();
+        ids.extend(target.map(serde_json::Value::from));
+
+        serde_json::Value::Array(ids)
+    }
+}
+
+/// The category of an article
+pub const ARTICLE_CATEGORY: Reference = Reference {
+    from: ArticleKind::TYPE,
+    field: "category_id",
+    to: CategoryKind::TYPE,
+    on_delete: OnDelete::Refuse,
+};
+
+/// The parent of a category
+pub const CATEGORY_PARENT: Reference = Reference {
+    from: CategoryKind::TYPE,
+    field: "parent_id",
+    to: CategoryKind::TYPE,
+    on_delete: OnDelete::Refuse,
+};
+
+/// The services which an FAQ is about
+pub const FAQ_SERVICES: Reference = Reference {
+    from: FaqKind::TYPE,
+    field: "service_ids",
+    to: ServiceKind::TYPE,
+    on_delete: OnDelete::Refuse,
+};
+
+/// The services which a testimonial is about
+pub const TESTIMONIAL_SERVICES: Reference = Reference {
+    from: TestimonialKind::TYPE,
+    field: "service_ids",
+    to: ServiceKind::TYPE,
+    on_delete: OnDelete::Detach,
+};
+
+/// The members who a testimonial is about
+pub const TESTIMONIAL_MEMBERS: Reference = Reference {
+    from: TestimonialKind::TYPE,
+    field: "member_ids",
+    to: MemberKind::TYPE,
+    on_delete: OnDelete::Detach,
+};
+
+/// The services which a case result is about
+pub const CASE_RESULT_SERVICES: Reference = Reference {
+    from: CaseResultKind::TYPE,
+    field: "service_ids",
+    to: ServiceKind::TYPE,
+    on_delete: OnDelete::Detach,
+};
+
+/// The members who handled a case
+pub const CASE_RESULT_MEMBERS: Reference = Reference {
+    from: CaseResultKind::TYPE,
+    field: "member_ids",
+    to: MemberKind::TYPE,
+    on_delete: OnDelete::Detach,
+};
+
+/// A condition on the data of the resources
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Filter {
+    Equals { field: &'static str, value: String },
+    Contains { field: &'static str, value: String },
+    IsTrue { field: &'static str },
+}
+
+impl Filter {
+    /// Checks the condition against the data, it's used by the in-memory unit of work
+    pub fn matches(&self, data: &serde_json::Value) -> bool {
+        match self {
+            Filter::Equals { field, value } => {
+                data.get(field).and_then(|v| v.as_str()) == Some(value.as_str())
+            }
+            Filter::Contains { field, value } => data
+                .get(field)
+                .and_then(|v| v.as_array())
+                .is_some_and(|ids| ids.iter().any(|id| id.as_str() == Some(value.as_str()))),
+            Filter::IsTrue { field } => data.get(field).and_then(|v| v.as_bool()) == Some(true),
+        }
+    }
+}
+
+/// Converts the given parameters into the filters which are declared by the resource type
+pub fn filters_from_params<K: ResourceKind>(params: &HashMap<String, String>) -> Vec<Filter> {
+    K::FILTERS
+        .iter()
+        .filter_map(|filter| {
+            params
+                .get(filter.param)
+                .map(|value| filter.to_filter(value.trim().to_string()))
+        })
+        .collect()
+}
+
+pub struct MemberKind;
+
+impl ResourceKind for MemberKind {
+    const TYPE: ResourceType = ResourceType::new("member");
+    const PATH: &'static str = "members";
+    const SINGULAR: &'static str = "member";
+    const PLURAL: &'static str = "members";
+    const REFERENCED_BY: &'static [Reference] = &[TESTIMONIAL_MEMBERS, CASE_RESULT_MEMBERS];
+
+    type Data = MemberData;
+    type Entity = MemberEntity;
+    type ListItem = SimpleMemberEntity;
+
+    fn to_entity(record: ResourceRecord<Self::Data>) -> Self::Entity {
+        MemberEntity::new(
+            record.id,
+            record.language,
+            record.data,
+            record.avatar,
+            record.seq,
+            record.version,
+        )
+    }
+
+    fn to_list_item(record: ResourceRecord<Self::Data>) -> Self::ListItem {
+        SimpleMemberEntity::new(
+            record.id,
+            record.data.name,
+            record.avatar.map(|avatar| avatar.small_image),
+            record.seq,
+        )
+    }
+}
+
+/// The members of the second version of the API, the full profiles are listed
+pub struct MemberV2Kind;
+
+impl ResourceKind for MemberV2Kind {
+    const TYPE: ResourceType = MemberKind::TYPE;
+    const PATH: &'static str = MemberKind::PATH;
+    const SINGULAR: &'static str = MemberKind::SINGULAR;
+    const PLURAL: &'static str = MemberKind::PLURAL;
+
+    type Data = MemberData;
+    type Entity = MemberProfileEntity;
+    type ListItem = MemberProfileEntity;
+
+    fn to_entity(record: ResourceRecord<Self::Data>) -> Self::Entity {
+        MemberProfileEntity::from(record)
+    }
+
+    fn to_list_item(record: ResourceRecord<Self::Data>) -> Self::ListItem {
+        MemberProfileEntity::from(record)
+    }
+}
+
+pub struct ServiceKind;
+
+impl ResourceKind for ServiceKind {
+    const TYPE: ResourceType = ResourceType::new("service");
+    const PATH: &'static str = "services";
+    const SINGULAR: &'static str = "service";
+    const PLURAL: &'static str = "services";
+    const REFERENCED_BY: &'static [Reference] =
+        &[FAQ_SERVICES, TESTIMONIAL_SERVICES, CASE_RESULT_SERVICES];
+
+    type Data = ServiceData;
+    type Entity = ServiceEntity;
+    type ListItem = ServiceEntity;
+
+    fn to_entity(record: ResourceRecord<Self::Data>) -> Self::Entity {
+        ResourceEntity::from(record)
+    }
+
+    fn to_list_item(record: ResourceRecord<Self::Data>) -> Self::ListItem {
+        ResourceEntity::from(record)
+    }
+}
+
+pub struct HomeKind;
+
+impl ResourceKind for HomeKind {
+    const TYPE: ResourceType = ResourceType::new("home");
+    const PATH: &'static str = "home";
+    const SINGULAR: &'static str = "home";
+    const PLURAL: &'static str = "home";
+    const DELETABLE: bool = false;
+    const PAGINATION: Pagination = Pagination::Single;
+
+    type Data = HomeData;
+    type Entity = HomeEntity;
+    type ListItem = HomeEntity;
+
+    fn to_entity(record: ResourceRecord<Self::Data>) -> Self::Entity {
+        HomeEntity::new(record.id, record.language, record.data, record.version)
+    }
+
+    fn to_list_item(record: ResourceRecord<Self::Data>) -> Self::ListItem {
+        HomeEntity::new(record.id, record.language, record.data, record.version)
+    }
+}
+
+pub struct ContactKind;
+
+impl ResourceKind for ContactKind {
+    const TYPE: ResourceType = ResourceType::new("contact");
+    const PATH: &'static str = "contact";
+    const SINGULAR: &'static str = "contact";
+    const PLURAL: &'static str = "contact";
+    const DELETABLE: bool = false;
+    const PAGINATION: Pagination = Pagination::Single;
+
+    type Data = ContactData;
+    type Entity = ContactEntity;
+    type ListItem = ContactEntity;
+
+    fn to_entity(record: ResourceRecord<Self::Data>) -> Self::Entity {
+        ContactEntity::new(record.id, record.language, record.data.data, record.version)
+    }
+
+    fn to_list_item(record: ResourceRecord<Self::Data>) -> Self::ListItem {
+        ContactEntity::new(record.id, record.language, record.data.data, record.version)
+    }
+}
+
+pub struct ArticleKind;
+
+impl ResourceKind for ArticleKind {
+    const TYPE: ResourceType = ResourceType::new("article");
+    const PATH: &'static str = "articles";
+    const SINGULAR: &'static str = "article";
+    const PLURAL: &'static str = "articles";
+    const PAGINATION: Pagination = Pagination::Page(Page { page: 0, size: 10 });
+    const FILTERS: &'static [FilterParam] = &[FilterParam::equals("category_id", "category_id")];
+    const REFERENCES: &'static [Reference] = &[ARTICLE_CATEGORY];
+
+    type Data = ArticleData;
+    type Entity = ArticleEntity;
+    type ListItem = SimpleArticleEntity;
+
+    fn to_entity(record: ResourceRecord<Self::Data>) -> Self::Entity {
+        ResourceEntity::from(record)
+    }
+
+    fn to_list_item(record: ResourceRecord<Self::Data>) -> Self::ListItem {
+        SimpleArticleEntity {
+            id: record.id,
+            title: record.data.title,
+            language: record.language,
+            created_at: record.created_at.timestamp_millis(),
+            seq: record.seq,
+        }
+    }
+}
+
+pub struct CategoryKind;
+
+impl ResourceKind for CategoryKind {
+    const TYPE: ResourceType = ResourceType::new("category");
+    const PATH: &'static str = "categories";
+    const SINGULAR: &'static str = "category";
+    const PLURAL: &'static str = "categories";
+    const REFERENCES: &'static [Reference] = &[CATEGORY_PARENT];
+    const REFERENCED_BY: &'static [Reference] = &[ARTICLE_CATEGORY, CATEGORY_PARENT];
+
+    type Data = CategoryData;
+    type Entity = CategoryEntity;
+    type ListItem = CategoryEntity;
+
+    fn to_entity(record: ResourceRecord<Self::Data>) -> Self::Entity {
+        ResourceEntity::from(record)
+    }
+
+    fn to_list_item(record: ResourceRecord<Self::Data>) -> Self::ListItem {
+        ResourceEntity::from(record)
+    }
+}
+
+pub struct FaqKind;
+
+impl ResourceKind for FaqKind {
+    const TYPE: ResourceType = ResourceType::new("faq");
+    const PATH: &'static str = "faqs";
+    const SINGULAR: &'static str = "faq";
+    const PLURAL: &'static str = "faqs";
+    const FILTERS: &'static [FilterParam] = &[FilterParam::contains("service_id", "service_ids")];
+    const REFERENCES: &'static [Reference] = &[FAQ_SERVICES];
+
+    type Data = FaqData;
+    type Entity = FaqEntity;
+    type ListItem = FaqEntity;
+
+    fn to_entity(record: ResourceRecord<Self::Data>) -> Self::Entity {
+        ResourceEntity::from(record)
+    }
+
+    fn to_list_item(record: ResourceRecord<Self::Data>) -> Self::ListItem {
+        ResourceEntity::from(record)
+    }
+}
+
+pub struct TestimonialKind;
+
+impl ResourceKind for TestimonialKind {
+    const TYPE: ResourceType = ResourceType::new("testimonial");
+    const PATH: &'static str = "testimonials";
+    const SINGULAR: &'static str = "testimonial";
+    const PLURAL: &'static str = "testimonials";
+    const FILTERS: &'static [FilterParam] = &[
+        FilterParam::contains("service_id", "service_ids"),
+        FilterParam::contains("member_id", "member_ids"),
+    ];
+    const REFERENCES: &'static [Reference] = &[TESTIMONIAL_SERVICES, TESTIMONIAL_MEMBERS];
+    const VISIBILITY_FIELD: Option<&'static str> = Some("approved");
+
+    type Data = TestimonialData;
+    type Entity = TestimonialEntity;
+    type ListItem = TestimonialEntity;
+
+    fn to_entity(record: ResourceRecord<Self::Data>) -> Self::Entity {
+        ResourceEntity::from(record)
+    }
+
+    fn to_list_item(record: ResourceRecord<Self::Data>) -> Self::ListItem {
+        ResourceEntity::from(record)
+    }
+}
+
+pub struct CaseResultKind;
+
+impl ResourceKind for CaseResultKind {
+    const TYPE: ResourceType = ResourceType::new("case_result");
+    const PATH: &'static str = "case-results";
+    const SINGULAR: &'static str = "case_result";
+    const PLURAL: &'static str = "case_results";
+    const FILTERS: &'static [FilterParam] = &[
+        FilterParam::contains("service_id", "service_ids"),
+        FilterParam::contains("member_id", "member_ids"),
+    ];
+    const REFERENCES: &'static [Reference] = &[CASE_RESULT_SERVICES, CASE_RESULT_MEMBERS];
+    const VISIBILITY_FIELD: Option<&'static str> = Some("approved");
+
+    type Data = CaseResultData;
+    type Entity = CaseResultEntity;
+    type ListItem = CaseResultEntity;
+
+    fn to_entity(record: ResourceRecord<Self::Data>) -> Self::Entity {
+        ResourceEntity::from(record)
+    }
+
+    fn to_list_item(record: ResourceRecord<Self::Data>) -> Self::ListItem {
+        ResourceEntity::from(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn it_should_match_the_data_by_filters() {
+        let data = json!({
+            "category_id": "category",
+            "service_ids": ["service"],
+            "approved": true,
+        });
+
+        assert!(FilterParam::equals("category_id", "category_id")
+            .to_filter("category".to_string())
+            .matches(&data));
+        assert!(!FilterParam::equals("category_id", "category_id")
+            .to_filter("other".to_string())
+            .matches(&data));
+        assert!(FilterParam::contains("service_id", "service_ids")
+            .to_filter("service".to_string())
+            .matches(&data));
+        assert!(!FilterParam::contains("member_id", "member_ids")
+            .to_filter("member".to_string())
+            .matches(&data));
+        assert!(Filter::IsTrue { field: "approved" }.matches(&data));
+        assert!(!Filter::IsTrue { field: "missing" }.matches(&data));
+    }
+
+    #[test]
+    fn it_should_only_convert_the_declared_params_to_filters() {
+        let params = HashMap::from([
+            ("service_id".to_string(), " service ".to_string()),
+            ("category_id".to_string(), "category".to_string()),
+        ]);
+
+        assert_eq!(
+            filters_from_params::<FaqKind>(&params),
+            vec![Filter::Contains {
+                field: "service_ids",
+                value: "service".to_string(),
+            }]
+        );
+        assert!(filters_from_params::<MemberKind>(&params).is_empty());
+    }
+
+    #[test]
+    fn it_should_read_the_referred_ids_of_single_and_list_fields() {
+        let data = json!({
+            "parent_id": " category ",
+            "category_id": "",
+            "service_ids": ["service", " ", "another"],
+        });
+
+        assert_eq!(CATEGORY_PARENT.referred_ids(&data), vec!["category"]);
+        assert!(ARTICLE_CATEGORY.referred_ids(&data).is_empty());
+        assert_eq!(FAQ_SERVICES.referred_ids(&data), vec!["service", "another"]);
+        assert!(FAQ_SERVICES.referred_ids(&json!({})).is_empty());
+    }
+
+    #[test]
+    fn it_should_replace_the_referred_id_in_single_and_list_fields() {
+        let data = json!({
+            "parent_id": "category",
+            "service_ids": ["service", "another"],
+        });
+
+        assert_eq!(
+            CATEGORY_PARENT.replaced(&data, "category", Some("target")),
+            json!("target")
+        );
+        assert_eq!(
+            CATEGORY_PARENT.replaced(&data, "category", None),
+            json!(null)
+        );
+        assert_eq!(
+            FAQ_SERVICES.replaced(&data, "service", Some("target")),
+            json!(["another", "target"])
+        );
+        // the target isn't listed twice
+        assert_eq!(
+            FAQ_SERVICES.replaced(&data, "service", Some("another")),
+            json!(["another"])
+        );
+        assert_eq!(
+            FAQ_SERVICES.replaced(&data, "service", None),
+            json!(["another"])
+        );
+    }
+
+    #[test]
+    fn it_should_describe_the_validation_in_the_schema() {
+        let schema = MemberKind::schema();
+
+        assert_eq!(schema["required"], json!(["name", "description"]));
+        assert_eq!(schema["properties"]["name"]["type"], json!("string"));
+        assert_eq!(schema["properties"]["name"]["minLength"], json!(1));
+
+        let schema = CaseResultKind::schema();
+
+        assert_eq!(schema["properties"]["year"]["minimum"], json!(1900));
+        assert_eq!(schema["properties"]["year"]["maximum"], json!(2100));
+        assert_eq!(schema["properties"]["approved"]["default"], json!(false));
+    }
+}
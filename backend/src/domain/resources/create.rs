@@ -1,58 +1,142 @@
-use crate::domain::entities::{ContentID, Language, Resource, ResourceID};
-use crate::repositories::IContentRepository;
+use crate::cache::{self, tags, IResourceCache};
+use crate::domain::audit::{self, entry, Actor};
+use crate::domain::entities::{
+    AuditAction, ContentData, ContentID, Language, ResourceError, ResourceID, UserID,
+};
+use crate::domain::registry::ResourceKind;
+use crate::domain::resources::references;
+use crate::domain::validation::FieldError;
 use crate::repositories::IResourceRepository;
+use crate::repositories::{IAuditRepository, IContentRepository};
 use crate::uow::IResourceUnitOfWork;
 use tokio::sync::Mutex;
 
-pub struct Request {
+pub struct Request<K: ResourceKind> {
     pub id: String,
-    pub data: Resource,
+    pub data: K::Data,
     pub language: String,
-    pub seq: i32,
+    pub position: Position,
+    /// The user who creates the resource, the authors can only change their own resources
+    pub created_by: Option<UserID>,
+    /// Who creates the resource, it's recorded in the audit log
+    pub actor: Option<Actor>,
+}
+
+/// Where the new resource is put in the list of its resource type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    /// After the other resources
+    Last,
+    /// At the sequence, the resources which are at or after it are shifted back
+    At(i32),
+    /// At the sequence, the other resources are kept even if they have the same sequence
+    Seq(i32),
 }
 
 pub enum Error {
     BadRequest,
+    /// The fields violate the rules of the validation
+    Invalid(Vec<FieldError>),
+    /// The field refers to a resource which doesn't exist
+    InvalidReference(&'static str),
     Unknown(String),
 }
 
-pub async fn execute<IUnitOfWork>(uow: Mutex<IUnitOfWork>, req: Request) -> Result<ContentID, Error>
+pub async fn execute<IUnitOfWork, K>(
+    uow: Mutex<IUnitOfWork>,
+    cache: &dyn IResourceCache,
+    req: Request<K>,
+) -> Result<ContentID, Error>
 where
     IUnitOfWork: IResourceUnitOfWork,
+    K: ResourceKind,
 {
     let id = {
         let mut lock = uow.lock().await;
 
-        // extract the resource and convert it to a resource type
-        let (kind, data) = req
-            .data
-            .into_typed_content()
-            .map_err(|_| Error::BadRequest)?;
+        // validate the data and convert it to the content
+        let data = match ContentData::try_from_data::<K>(&req.data) {
+            Ok(data) => data,
+            Err(ResourceError::ValidationError(fields)) => return Err(Error::Invalid(fields)),
+            Err(e) => return Err(Error::Unknown(e.to_string())),
+        };
 
         // parse the given id and language to the specified type for type safety
-        let (id, language) = match (
-            ResourceID::try_from(req.id),
-            Language::try_from(req.language),
-        ) {
-            (Ok(id), Ok(language)) => (id, language),
-            _ => return Err(Error::BadRequest),
+        let id = ResourceID::try_from(req.id).map_err(|_| Error::BadRequest)?;
+        let language = Language::try_from(req.language)
+            .map_err(|_| Error::Invalid(vec![FieldError::new("language", "language")]))?;
+
+        // the resource can't refer to itself, and the referred resources have to exist
+        let checked = match references::check_cycles::<_, K>(&*lock, &id, &language, &data).await {
+            Ok(_) => references::check::<_, K>(&mut *lock, &data).await,
+            Err(e) => Err(e),
         };
+        match checked {
+            Ok(_) => {}
+            Err(references::Error::Invalid(field)) => return Err(Error::InvalidReference(field)),
+            Err(references::Error::Cycle(field)) => {
+                return Err(Error::Invalid(vec![FieldError::new(field, "cycle")]))
+            }
+            Err(references::Error::Unknown(e)) => return Err(Error::Unknown(e)),
+        }
+
+        // make room for the resource
+        let (seq, moved) = match req.position {
+            Position::Last => lock
+                .resource_repository()
+                .next_seq(&K::TYPE)
+                .await
+                .map(|seq| (seq, vec![])),
+            Position::At(seq) => lock
+                .resource_repository()
+                .shift_seq(&K::TYPE, seq, 1)
+                .await
+                .map(|moved| (seq, moved)),
+            Position::Seq(seq) => Ok((seq, vec![])),
+        }
+        .map_err(|e| Error::Unknown(e.to_string()))?;
 
         // insert the resource into the resource repository and retrieve the content id
-        let content_id = match lock.resource_repository().insert(id, kind, req.seq).await {
+        let content_id = match lock
+            .resource_repository()
+            .insert(id.clone(), K::TYPE, seq, req.created_by)
+            .await
+        {
             Ok(id) => ContentID::from(id),
             Err(e) => return Err(Error::Unknown(e.to_string())),
         };
 
         // insert the content into the content repository and retrieve the content id
-        match lock
+        let content_id = match lock
             .content_repository()
-            .insert(content_id, data, language)
+            .insert(content_id, data, language.clone())
             .await
         {
             Ok(id) => id,
             Err(e) => return Err(Error::Unknown(e.to_string())),
+        };
+
+        // the entry is written in the same transaction, so there is no change without it
+        let created = audit::snapshot::<K>(&*lock, &id, &language)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
+        let entry = entry(
+            req.actor.as_ref(),
+            AuditAction::Create,
+            K::TYPE.as_str(),
+            id.to_string(),
+        )
+        .in_language(language)
+        .with_snapshots(None, created);
+        let reorders = audit::reorders(req.actor.as_ref(), K::TYPE.as_str(), &moved, 1);
+        for entry in std::iter::once(&entry).chain(&reorders) {
+            lock.audit_repository()
+                .save(entry)
+                .await
+                .map_err(|e| Error::Unknown(e.to_string()))?;
         }
+
+        content_id
     };
 
     // commit the transaction
@@ -61,150 +145,359 @@ where
         .await
         .map_err(|e| Error::Unknown(e.to_string()))?;
 
+    // the shifted resources have new sequences, otherwise only the lists have changed
+    let tag = match req.position {
+        Position::At(_) => tags::resource_type(&K::TYPE),
+        _ => tags::list(&K::TYPE),
+    };
+    cache::invalidate(cache, &[tag]).await;
+
     Ok(id)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::entities::{ArticleData, ContactData, HomeData, MemberData, ServiceData};
+    use crate::cache::NoCache;
+    use crate::domain::entities::{
+        ArticleData, CaseResultData, CategoryData, FaqData, HomeData, MemberData, ServiceData,
+        TestimonialData,
+    };
+    use crate::domain::registry::{
+        ArticleKind, CaseResultKind, CategoryKind, FaqKind, HomeKind, MemberKind, ServiceKind,
+        TestimonialKind,
+    };
+    use crate::domain::resources::test_helpers::tests::{
+        create_some_fake_data_and_return_uow, for_each_resource_kind, insert_fake_resource,
+        FakeResource,
+    };
+    use crate::repositories::InMemoryAuditRepository;
     use crate::uow::InMemory;
-    use serde_json::json;
     use ulid::Ulid;
 
-    #[tokio::test]
-    async fn it_should_create_a_resource_successful_otherwise() {
-        let member_data = MemberData::new("boris".to_string(), "description".to_string());
-        let service_data =
-            ServiceData::new("title".to_string(), "data".to_string(), "icon".to_string());
-        let home_data = HomeData::new("data".to_string());
-        let contact = json!({
-            "address": "address".to_string(),
-            "phone": "1234".to_string(),
-            "email": "info@example.com".to_string(),
-        });
-        let contact_data = ContactData::new(contact);
-        let article_data = ArticleData::new(None, "title".to_string(), "data".to_string());
-
-        let different_data = vec![
-            Resource::Member(member_data),
-            Resource::Service(service_data),
-            Resource::Home(home_data),
-            Resource::Contact(contact_data),
-            Resource::Article(article_data),
-        ];
-
-        for d in different_data {
-            let uow = InMemory::new();
-            let id = Ulid::new().to_string();
-
-            let req = Request {
-                id: id.clone(),
-                data: d,
-                language: "zh".to_string(),
-                seq: 0,
-            };
+    async fn create<K: ResourceKind>(uow: InMemory, data: K::Data) -> Result<ContentID, Error> {
+        let req = Request::<K> {
+            id: Ulid::new().to_string(),
+            data,
+            language: "zh".to_string(),
+            position: Position::Seq(0),
+            created_by: None,
+            actor: None,
+        };
 
-            let res = execute(Mutex::new(uow), req).await;
+        execute(Mutex::new(uow), &NoCache, req).await
+    }
 
-            match res {
-                Ok(id) => {
-                    assert_eq!(id.as_str(), id.as_str())
-                }
-                Err(_) => unreachable!(),
+    async fn assert_created<K: FakeResource>() {
+        match create::<K>(InMemory::new(), K::fake()).await {
+            Ok(id) => assert!(!id.as_str().is_empty()),
+            Err(_) => unreachable!(),
+        }
+    }
+
+    async fn assert_bad_request<K: ResourceKind>(missing_or_invalid_data: Vec<K::Data>) {
+        for d in missing_or_invalid_data {
+            match create::<K>(InMemory::new(), d).await {
+                Err(Error::Invalid(fields)) => assert!(!fields.is_empty()),
+                _ => unreachable!(),
             }
         }
     }
 
+    #[tokio::test]
+    async fn it_should_create_a_resource_successful_otherwise() {
+        for_each_resource_kind!(assert_created);
+
+        let (uow, service) = create_some_fake_data_and_return_uow::<ServiceKind>().await;
+        let faq_data = FaqData::new(
+            "question".to_string(),
+            "answer".to_string(),
+            vec![service.to_string()],
+        );
+        assert!(create::<FaqKind>(uow, faq_data).await.is_ok());
+
+        let (mut uow, service) = create_some_fake_data_and_return_uow::<ServiceKind>().await;
+        let member = insert_fake_resource::<MemberKind>(&mut uow, &MemberKind::fake()).await;
+        let testimonial_data = TestimonialData::new(
+            "Mr. Wang".to_string(),
+            "content".to_string(),
+            vec![service.to_string()],
+            vec![member.to_string()],
+            false,
+        );
+        assert!(create::<TestimonialKind>(uow, testimonial_data)
+            .await
+            .is_ok());
+    }
+
     #[tokio::test]
     async fn it_should_return_bad_request_when_data_is_missing_or_invalid() {
-        let missing_or_invalid_data = vec![
+        assert_bad_request::<MemberKind>(vec![
             // name is missing
-            Resource::Member(MemberData::new("".to_string(), "description".to_string())),
+            MemberData::new("".to_string(), "description".to_string()),
             // description is missing
-            Resource::Member(MemberData::new("boris".to_string(), "".to_string())),
+            MemberData::new("boris".to_string(), "".to_string()),
             // The name is conducted by spaces
-            Resource::Member(MemberData::new("  ".to_string(), "description".to_string())),
+            MemberData::new("  ".to_string(), "description".to_string()),
             // The description is conducted by spaces
-            Resource::Member(MemberData::new("boris".to_string(), "  ".to_string())),
+            MemberData::new("boris".to_string(), "  ".to_string()),
+        ])
+        .await;
+
+        assert_bad_request::<ServiceKind>(vec![
             // title is missing
-            Resource::Service(ServiceData::new(
-                "".to_string(),
-                "data".to_string(),
-                "icon".to_string(),
-            )),
+            ServiceData::new("".to_string(), "data".to_string(), "icon".to_string()),
             // data is missing
-            Resource::Service(ServiceData::new(
-                "title".to_string(),
-                "".to_string(),
-                "icon".to_string(),
-            )),
+            ServiceData::new("title".to_string(), "".to_string(), "icon".to_string()),
             // The title is conducted by spaces
-            Resource::Service(ServiceData::new(
-                "  ".to_string(),
-                "data".to_string(),
-                "icon".to_string(),
-            )),
+            ServiceData::new("  ".to_string(), "data".to_string(), "icon".to_string()),
             // The data is conducted by spaces
-            Resource::Service(ServiceData::new(
-                "title".to_string(),
-                " ".to_string(),
-                "icon".to_string(),
-            )),
+            ServiceData::new("title".to_string(), " ".to_string(), "icon".to_string()),
+        ])
+        .await;
+
+        assert_bad_request::<HomeKind>(vec![
             // data is missing
-            Resource::Home(HomeData::new("".to_string())),
+            HomeData::new("".to_string()),
             // data is conducted by spaces
-            Resource::Home(HomeData::new(" ".to_string())),
+            HomeData::new(" ".to_string()),
+        ])
+        .await;
+
+        assert_bad_request::<ArticleKind>(vec![
             // title is missing
-            Resource::Article(ArticleData::new(None, "".to_string(), "data".to_string())),
+            ArticleData::new(None, "".to_string(), "data".to_string()),
             // data is missing
-            Resource::Article(ArticleData::new(None, "title".to_string(), "".to_string())),
+            ArticleData::new(None, "title".to_string(), "".to_string()),
             // The title is conducted by spaces
-            Resource::Article(ArticleData::new(None, "  ".to_string(), "data".to_string())),
+            ArticleData::new(None, "  ".to_string(), "data".to_string()),
             // The data is conducted by spaces
-            Resource::Article(ArticleData::new(None, "title".to_string(), " ".to_string())),
-        ];
+            ArticleData::new(None, "title".to_string(), " ".to_string()),
+        ])
+        .await;
 
-        for d in missing_or_invalid_data {
-            let uow = InMemory::new();
-            let id = Ulid::new().to_string();
+        assert_bad_request::<FaqKind>(vec![
+            // question is missing
+            FaqData::new("".to_string(), "answer".to_string(), vec![]),
+            // answer is missing
+            FaqData::new("question".to_string(), "".to_string(), vec![]),
+            // The question is conducted by spaces
+            FaqData::new("  ".to_string(), "answer".to_string(), vec![]),
+        ])
+        .await;
 
-            let req = Request {
-                id: id.clone(),
-                data: d,
-                language: "zh".to_string(),
-                seq: 0,
-            };
+        assert_bad_request::<TestimonialKind>(vec![
+            // client name is missing
+            TestimonialData::new("".to_string(), "content".to_string(), vec![], vec![], true),
+            // content is conducted by spaces
+            TestimonialData::new(
+                "Mr. Wang".to_string(),
+                " ".to_string(),
+                vec![],
+                vec![],
+                true,
+            ),
+        ])
+        .await;
+
+        assert_bad_request::<CaseResultKind>(vec![
+            // matter type is missing
+            CaseResultData::new(
+                "".to_string(),
+                "outcome".to_string(),
+                2024,
+                vec![],
+                vec![],
+                true,
+            ),
+            // year is out of range
+            CaseResultData::new(
+                "matter type".to_string(),
+                "outcome".to_string(),
+                24,
+                vec![],
+                vec![],
+                true,
+            ),
+        ])
+        .await;
+    }
 
-            let res = execute(Mutex::new(uow), req).await;
+    #[tokio::test]
+    async fn it_should_return_an_unknown_error_when_unexpected_error_is_encountered() {
+        let res = create::<MemberKind>(InMemory::new().with_error(), MemberKind::fake()).await;
 
-            match res {
-                Err(Error::BadRequest) => {}
-                _ => unreachable!(),
+        match res {
+            Err(Error::Unknown(_)) => {}
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_list_the_failing_fields() {
+        let data = MemberData::new("boris".to_string(), " ".to_string());
+
+        match create::<MemberKind>(InMemory::new(), data).await {
+            Err(Error::Invalid(fields)) => {
+                assert_eq!(fields.len(), 1);
+                assert_eq!(fields[0].path, "description");
+                assert_eq!(fields[0].rule, "length");
             }
+            _ => unreachable!(),
+        }
+
+        let req = Request::<MemberKind> {
+            id: Ulid::new().to_string(),
+            data: MemberKind::fake(),
+            language: "fr".to_string(),
+            position: Position::Last,
+            created_by: None,
+            actor: None,
+        };
+
+        match execute(Mutex::new(InMemory::new()), &NoCache, req).await {
+            Err(Error::Invalid(fields)) => {
+                assert_eq!(fields, vec![FieldError::new("language", "language")])
+            }
+            _ => unreachable!(),
         }
     }
 
     #[tokio::test]
-    async fn it_should_return_an_unknown_error_when_unexpected_error_is_encountered() {
-        let data = Resource::Member(MemberData::new(
-            "boris".to_string(),
-            "description".to_string(),
-        ));
-        let uow = InMemory::new().with_error();
-        let id = Ulid::new().to_string();
+    async fn it_should_create_a_resource_at_every_position() {
+        for position in [Position::Last, Position::At(0), Position::Seq(0)] {
+            let (uow, _) = create_some_fake_data_and_return_uow::<ServiceKind>().await;
+            let req = Request::<ServiceKind> {
+                id: Ulid::new().to_string(),
+                data: ServiceKind::fake(),
+                language: "zh".to_string(),
+                position,
+                created_by: None,
+                actor: None,
+            };
+
+            assert!(execute(Mutex::new(uow), &NoCache, req).await.is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_create_an_article_in_an_existing_category() {
+        let mut uow = InMemory::new();
+        let category = insert_fake_resource::<CategoryKind>(&mut uow, &CategoryKind::fake()).await;
+
+        let data = ArticleData::new(
+            Some(category.to_string()),
+            "title".to_string(),
+            "data".to_string(),
+        );
 
-        let req = Request {
+        assert!(create::<ArticleKind>(uow, data).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn it_should_audit_the_created_resource() {
+        let audit_repo = InMemoryAuditRepository::new();
+        let uow = InMemory::new().with_audit_repository(audit_repo.clone());
+
+        assert!(create::<ServiceKind>(uow, ServiceKind::fake())
+            .await
+            .is_ok());
+
+        let entries = audit_repo.entries().await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, AuditAction::Create);
+        assert_eq!(entries[0].target_type, "service");
+        assert_eq!(entries[0].language, Some(Language::ZH));
+        assert!(entries[0].before.is_none());
+        assert!(entries[0].after.is_some());
+    }
+
+    #[tokio::test]
+    async fn it_should_audit_the_resources_which_are_moved_by_the_created_one() {
+        let (uow, moved) = create_some_fake_data_and_return_uow::<ServiceKind>().await;
+        let audit_repo = InMemoryAuditRepository::new();
+        let uow = uow.with_audit_repository(audit_repo.clone());
+        let req = Request::<ServiceKind> {
+            id: Ulid::new().to_string(),
+            data: ServiceKind::fake(),
+            language: "zh".to_string(),
+            position: Position::At(0),
+            created_by: None,
+            actor: None,
+        };
+
+        assert!(execute(Mutex::new(uow), &NoCache, req).await.is_ok());
+
+        let entries = audit_repo.entries().await;
+        let actions: Vec<_> = entries.iter().map(|entry| entry.action).collect();
+        assert_eq!(actions, vec![AuditAction::Create, AuditAction::Reorder]);
+        assert_eq!(entries[1].target_type, "service");
+        assert_eq!(entries[1].target_id, moved.to_string());
+        assert_eq!(entries[1].before, Some(serde_json::json!({ "seq": 0 })));
+        assert_eq!(entries[1].after, Some(serde_json::json!({ "seq": 1 })));
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_a_category_which_is_its_own_parent() {
+        let id = Ulid::new().to_string();
+        let req = Request::<CategoryKind> {
             id: id.clone(),
-            data,
+            data: CategoryData::new(None, "category".to_string(), Some(id)),
             language: "zh".to_string(),
-            seq: 0,
+            position: Position::Last,
+            created_by: None,
+            actor: None,
         };
 
-        let res = execute(Mutex::new(uow), req).await;
+        match execute(Mutex::new(InMemory::new()), &NoCache, req).await {
+            Err(Error::Invalid(fields)) => {
+                assert_eq!(fields, vec![FieldError::new("parent_id", "cycle")])
+            }
+            _ => unreachable!(),
+        }
+    }
 
-        match res {
-            Err(Error::Unknown(_)) => {}
+    #[tokio::test]
+    async fn it_should_return_an_invalid_reference_error_when_the_category_does_not_exist() {
+        let (uow, _) = create_some_fake_data_and_return_uow::<CategoryKind>().await;
+        let data = ArticleData::new(
+            Some(Ulid::new().to_string()),
+            "title".to_string(),
+            "data".to_string(),
+        );
+
+        match create::<ArticleKind>(uow, data).await {
+            Err(Error::InvalidReference("category_id")) => {}
+            _ => unreachable!(),
+        }
+
+        // a resource of another type isn't a category
+        let (uow, member) = create_some_fake_data_and_return_uow::<MemberKind>().await;
+        let data = ArticleData::new(
+            Some(member.to_string()),
+            "title".to_string(),
+            "data".to_string(),
+        );
+
+        match create::<ArticleKind>(uow, data).await {
+            Err(Error::InvalidReference("category_id")) => {}
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_return_an_invalid_reference_error_when_a_service_of_the_faq_does_not_exist()
+    {
+        let (uow, service) = create_some_fake_data_and_return_uow::<ServiceKind>().await;
+        // every service of the list has to exist, not only the first one
+        let data = FaqData::new(
+            "question".to_string(),
+            "answer".to_string(),
+            vec![service.to_string(), Ulid::new().to_string()],
+        );
+
+        match create::<FaqKind>(uow, data).await {
+            Err(Error::InvalidReference("service_ids")) => {}
             _ => unreachable!(),
         }
     }
@@ -1,25 +1,58 @@
-use crate::domain::entities::{ResourceID, ResourceType};
-use crate::repositories::IResourceRepository;
+use crate::cache::{self, tags, IResourceCache};
+use crate::domain::audit::{self, entry, Actor};
+use crate::domain::entities::{AuditAction, Language, ResourceID, UserID};
+use crate::domain::registry::{OnDelete, ResourceKind};
+use crate::domain::resources::references;
+use crate::repositories::{IAuditRepository, IResourceRepository};
 use crate::uow::IResourceUnitOfWork;
+use serde_json::json;
 use tokio::sync::Mutex;
 
+/// What happens to the resources which refer to the deleted one
+#[derive(Debug)]
+pub enum Strategy {
+    /// The references are pointed at another resource
+    Reassign(String),
+    /// The references are cleared
+    Detach,
+}
+
 #[derive(Debug)]
 pub struct Request {
     pub(crate) id: String,
-    pub(crate) resource_type: ResourceType,
+    /// The deletion is refused if the resource is referred and there is no strategy, unless the
+    /// references are detached on deletion
+    pub(crate) strategy: Option<Strategy>,
+    /// The resources after the deleted one are moved forward to close the gap
+    pub(crate) close_gap: bool,
+    /// Only the resources created by the user can be deleted if it's given
+    pub(crate) owner: Option<UserID>,
+    /// Who deletes the resource, it's recorded in the audit log
+    pub(crate) actor: Option<Actor>,
 }
 
 pub enum Error {
     BadRequest,
+    /// The resource isn't created by the owner of the request
+    Forbidden,
     NotFound,
+    /// The resource is referred by the resources
+    InUse(usize),
+    /// The reassignment target doesn't exist
+    InvalidTarget,
     Unknown(String),
 }
 
-pub async fn execute<IUnitOfWork>(uow: Mutex<IUnitOfWork>, req: Request) -> Result<(), Error>
+pub async fn execute<IUnitOfWork, K>(
+    uow: Mutex<IUnitOfWork>,
+    cache: &dyn IResourceCache,
+    req: Request,
+) -> Result<(), Error>
 where
     IUnitOfWork: IResourceUnitOfWork,
+    K: ResourceKind,
 {
-    {
+    let stale = {
         let mut lock = uow.lock().await;
 
         // parse the given id and language to the specified type for type safety
@@ -27,23 +60,154 @@ where
             Ok(id) => id,
             _ => return Err(Error::BadRequest),
         };
+        let mut stale = vec![tags::resource(&K::TYPE, id.as_str()), tags::list(&K::TYPE)];
 
-        match lock
-            .resource_repository()
-            .contains(&id, &req.resource_type)
-            .await
-        {
-            Ok(exist) if exist => {
-                // delete the resource from the repository
-                lock.resource_repository()
-                    .delete(&id, &req.resource_type)
-                    .await
-                    .map_err(|e| Error::Unknown(e.to_string()))?;
-            }
+        match lock.resource_repository().contains(&id, &K::TYPE).await {
+            Ok(exist) if exist => {}
             Ok(_) => return Err(Error::NotFound),
             Err(e) => return Err(Error::Unknown(e.to_string())),
         }
-    }
+
+        if let Some(owner) = &req.owner {
+            let creator = lock
+                .resource_repository()
+                .creator(&id)
+                .await
+                .map_err(|e| Error::Unknown(e.to_string()))?;
+            if creator.as_ref() != Some(owner) {
+                return Err(Error::Forbidden);
+            }
+        }
+
+        // the resource is kept in every language by the entry
+        let before = audit::snapshots::<K>(&*lock, &id)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
+        let mut entries = vec![];
+
+        // the referring resources are reassigned or detached in the same transaction
+        for reference in K::REFERENCED_BY {
+            let count = lock
+                .count_references(reference, &id)
+                .await
+                .map_err(|e| Error::Unknown(e.to_string()))?;
+            if count == 0 {
+                continue;
+            }
+
+            let target = match &req.strategy {
+                None if reference.on_delete == OnDelete::Detach => None,
+                None => return Err(Error::InUse(count)),
+                Some(Strategy::Detach) => None,
+                Some(Strategy::Reassign(target)) => {
+                    let target = ResourceID::try_from(target.to_string())
+                        .map_err(|_| Error::InvalidTarget)?;
+
+                    let exist = lock
+                        .resource_repository()
+                        .contains(&target, &reference.to)
+                        .await
+                        .map_err(|e| Error::Unknown(e.to_string()))?;
+                    if !exist || target == id {
+                        return Err(Error::InvalidTarget);
+                    }
+                    // the target can't be one of the resources which are reassigned to it
+                    if reference.from == reference.to {
+                        for language in Language::ALL {
+                            match references::check_ancestors::<_, K>(
+                                &*lock,
+                                reference,
+                                &id,
+                                target.clone(),
+                                &language,
+                            )
+                            .await
+                            {
+                                Ok(_) => {}
+                                Err(references::Error::Unknown(e)) => {
+                                    return Err(Error::Unknown(e))
+                                }
+                                Err(_) => return Err(Error::InvalidTarget),
+                            }
+                        }
+                    }
+
+                    Some(target)
+                }
+            };
+
+            let changed = lock
+                .replace_references(reference, &id, target.as_ref())
+                .await
+                .map_err(|e| Error::Unknown(e.to_string()))?;
+            stale.push(tags::list(&reference.from));
+            stale.extend(
+                changed
+                    .iter()
+                    .map(|referring| tags::resource(&reference.from, referring.as_str())),
+            );
+            entries.extend(changed.iter().map(|referring| {
+                entry(
+                    req.actor.as_ref(),
+                    AuditAction::Update,
+                    reference.from.as_str(),
+                    referring.to_string(),
+                )
+                .with_snapshots(
+                    Some(json!({ reference.field: id.as_str() })),
+                    Some(json!({ reference.field: target.as_ref().map(|t| t.as_str()) })),
+                )
+            }));
+        }
+
+        let seq = lock
+            .resource_repository()
+            .seq(&id)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
+
+        // delete the resource from the repository
+        lock.resource_repository()
+            .delete(&id, &K::TYPE)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
+
+        let mut moved = vec![];
+        if let (true, Some(seq)) = (req.close_gap, seq) {
+            moved = lock
+                .resource_repository()
+                .shift_seq(&K::TYPE, seq + 1, -1)
+                .await
+                .map_err(|e| Error::Unknown(e.to_string()))?;
+            // the resources after it have new sequences
+            stale.push(tags::resource_type(&K::TYPE));
+        }
+
+        // the entries are written in the same transaction, so there is no change without them
+        entries.push(
+            entry(
+                req.actor.as_ref(),
+                AuditAction::Delete,
+                K::TYPE.as_str(),
+                id.to_string(),
+            )
+            .with_snapshots(before, None),
+        );
+        entries.extend(audit::reorders(
+            req.actor.as_ref(),
+            K::TYPE.as_str(),
+            &moved,
+            -1,
+        ));
+        for entry in &entries {
+            lock.audit_repository()
+                .save(entry)
+                .await
+                .map_err(|e| Error::Unknown(e.to_string()))?;
+        }
+
+        stale
+    };
 
     // commit the transaction
     uow.into_inner()
@@ -51,89 +215,374 @@ where
         .await
         .map_err(|e| Error::Unknown(e.to_string()))?;
 
+    cache::invalidate(cache, &stale).await;
+
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::entities::Resource;
+    use crate::cache::NoCache;
+    use crate::domain::entities::{ArticleData, ContentID, FaqData, TestimonialData};
+    use crate::domain::registry::{
+        ArticleKind, CategoryKind, FaqKind, MemberKind, ServiceKind, TestimonialKind, FAQ_SERVICES,
+    };
     use crate::domain::resources::test_helpers::tests::{
-        create_resources, create_some_fake_data_and_return_uow,
+        create_a_category_tree, create_some_fake_data_and_return_uow, for_each_resource_kind,
+        insert_fake_resource, FakeResource,
     };
+    use crate::repositories::InMemoryAuditRepository;
+    use crate::uow::InMemory;
     use ulid::Ulid;
 
-    fn to_resource_type(resource: &Resource) -> ResourceType {
-        match resource {
-            Resource::Member(_) => ResourceType::Member,
-            Resource::Service(_) => ResourceType::Service,
-            Resource::Home(_) => ResourceType::Home,
-            Resource::Contact(_) => ResourceType::Contact,
-            Resource::Article(_) => ResourceType::Article,
-            Resource::Category(_) => ResourceType::Category,
+    async fn assert_deleted<K: FakeResource>() {
+        let (uow, id) = create_some_fake_data_and_return_uow::<K>().await;
+
+        let req = Request {
+            id: id.to_string(),
+            strategy: None,
+            close_gap: false,
+            owner: None,
+            actor: None,
+        };
+
+        let res = execute::<_, K>(Mutex::new(uow), &NoCache, req).await;
+        assert!(res.is_ok());
+    }
+
+    async fn assert_not_found<K: FakeResource>() {
+        let (uow, _) = create_some_fake_data_and_return_uow::<K>().await;
+
+        let req = Request {
+            id: Ulid::new().to_string(),
+            strategy: None,
+            close_gap: false,
+            owner: None,
+            actor: None,
+        };
+
+        let res = execute::<_, K>(Mutex::new(uow), &NoCache, req).await;
+        match res {
+            Err(Error::NotFound) => {}
+            _ => unreachable!(),
+        }
+    }
+
+    async fn assert_unknown_error<K: FakeResource>() {
+        let (uow, id) = create_some_fake_data_and_return_uow::<K>().await;
+
+        let req = Request {
+            id: id.to_string(),
+            strategy: None,
+            close_gap: false,
+            owner: None,
+            actor: None,
+        };
+
+        let res = execute::<_, K>(Mutex::new(uow.with_error()), &NoCache, req).await;
+        match res {
+            Err(Error::Unknown(_)) => {}
+            _ => unreachable!(),
         }
     }
 
     #[tokio::test]
     async fn it_should_delete_a_resource_successfully_otherwise() {
-        let resources = create_resources();
+        for_each_resource_kind!(assert_deleted);
+    }
 
-        for resource in resources.clone() {
-            let (uow, r) = create_some_fake_data_and_return_uow(vec![resource]).await;
+    #[tokio::test]
+    async fn it_should_return_a_not_found_error_when_resource_does_not_exist() {
+        for_each_resource_kind!(assert_not_found);
+    }
 
-            let (id, resource) = r[0].clone();
+    #[tokio::test]
+    async fn it_should_return_an_unknown_error_when_unexpected_error_has_encountered() {
+        for_each_resource_kind!(assert_unknown_error);
+    }
 
-            let req = Request {
-                id: id.to_string(),
-                resource_type: to_resource_type(&resource),
-            };
+    #[tokio::test]
+    async fn it_should_delete_a_resource_and_close_the_gap() {
+        let (uow, id) = create_some_fake_data_and_return_uow::<CategoryKind>().await;
+
+        let req = Request {
+            id: id.to_string(),
+            strategy: None,
+            close_gap: true,
+            owner: None,
+            actor: None,
+        };
 
-            let res = execute(Mutex::new(uow), req).await;
-            assert!(res.is_ok());
+        let res = execute::<_, CategoryKind>(Mutex::new(uow), &NoCache, req).await;
+        assert!(res.is_ok());
+    }
+
+    #[tokio::test]
+    async fn it_should_audit_the_resources_which_are_moved_to_close_the_gap() {
+        let mut uow = InMemory::new();
+        let deleted = insert_fake_resource::<CategoryKind>(&mut uow, &CategoryKind::fake()).await;
+        let moved = insert_fake_resource::<CategoryKind>(&mut uow, &CategoryKind::fake()).await;
+        let moved_id = ResourceID::try_from(moved.to_string()).unwrap();
+        uow.resource_repository()
+            .update_seq(&moved_id, 1)
+            .await
+            .unwrap();
+        let audit_repo = InMemoryAuditRepository::new();
+        let uow = uow.with_audit_repository(audit_repo.clone());
+
+        let req = Request {
+            id: deleted.to_string(),
+            strategy: None,
+            close_gap: true,
+            owner: None,
+            actor: None,
+        };
+
+        let res = execute::<_, CategoryKind>(Mutex::new(uow), &NoCache, req).await;
+        assert!(res.is_ok());
+
+        let entries = audit_repo.entries().await;
+        let actions: Vec<_> = entries.iter().map(|entry| entry.action).collect();
+        assert_eq!(actions, vec![AuditAction::Delete, AuditAction::Reorder]);
+        assert_eq!(entries[1].target_id, moved.to_string());
+        assert_eq!(entries[1].before, Some(serde_json::json!({ "seq": 1 })));
+        assert_eq!(entries[1].after, Some(serde_json::json!({ "seq": 0 })));
+    }
+
+    /// Creates two categories, the first one is referred by an article
+    async fn create_a_category_in_use() -> (InMemory, ContentID, ContentID) {
+        let mut uow = InMemory::new();
+        let category = insert_fake_resource::<CategoryKind>(&mut uow, &CategoryKind::fake()).await;
+        let another = insert_fake_resource::<CategoryKind>(&mut uow, &CategoryKind::fake()).await;
+        let article = ArticleData::new(
+            Some(category.to_string()),
+            "title".to_string(),
+            "data".to_string(),
+        );
+        insert_fake_resource::<ArticleKind>(&mut uow, &article).await;
+
+        (uow, category, another)
+    }
+
+    async fn delete_category(
+        uow: InMemory,
+        id: &ContentID,
+        strategy: Option<Strategy>,
+    ) -> Result<(), Error> {
+        let req = Request {
+            id: id.to_string(),
+            strategy,
+            close_gap: false,
+            owner: None,
+            actor: None,
+        };
+
+        execute::<_, CategoryKind>(Mutex::new(uow), &NoCache, req).await
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_to_delete_a_category_in_use() {
+        let (uow, category, _) = create_a_category_in_use().await;
+
+        match delete_category(uow, &category, None).await {
+            Err(Error::InUse(1)) => {}
+            _ => unreachable!(),
         }
     }
 
     #[tokio::test]
-    async fn it_should_return_a_not_found_error_when_resource_does_not_exist() {
-        let resources = create_resources();
+    async fn it_should_delete_a_category_in_use_by_detaching_the_articles() {
+        let (uow, category, _) = create_a_category_in_use().await;
+        let audit_repo = InMemoryAuditRepository::new();
+        let uow = uow.with_audit_repository(audit_repo.clone());
 
-        for resource in resources.clone() {
-            let (uow, r) = create_some_fake_data_and_return_uow(vec![resource]).await;
+        let res = delete_category(uow, &category, Some(Strategy::Detach)).await;
+        assert!(res.is_ok());
 
-            let (_, resource) = r[0].clone();
+        let entries = audit_repo.entries().await;
+        let actions: Vec<_> = entries.iter().map(|entry| entry.action).collect();
+        assert_eq!(actions, vec![AuditAction::Update, AuditAction::Delete]);
+        assert_eq!(entries[0].target_type, "article");
+        assert_eq!(
+            entries[0].after,
+            Some(serde_json::json!({ "category_id": null }))
+        );
+        assert_eq!(entries[1].target_id, category.to_string());
+        assert!(entries[1].before.is_some());
+        assert!(entries[1].after.is_none());
+    }
 
-            let req = Request {
-                id: Ulid::new().to_string(),
-                resource_type: to_resource_type(&resource),
-            };
+    #[tokio::test]
+    async fn it_should_delete_a_category_in_use_by_reassigning_the_articles() {
+        let (uow, category, another) = create_a_category_in_use().await;
+
+        let strategy = Strategy::Reassign(another.to_string());
+        let res = delete_category(uow, &category, Some(strategy)).await;
+        assert!(res.is_ok());
+    }
 
-            let res = execute(Mutex::new(uow), req).await;
-            match res {
-                Err(Error::NotFound) => {}
+    #[tokio::test]
+    async fn it_should_refuse_to_reassign_the_children_to_a_descendant() {
+        for target in 1..3 {
+            let (uow, tree) = create_a_category_tree().await;
+            let strategy = Strategy::Reassign(tree[target].to_string());
+
+            match delete_category(uow, &tree[0], Some(strategy)).await {
+                Err(Error::InvalidTarget) => {}
                 _ => unreachable!(),
             }
         }
     }
 
     #[tokio::test]
-    async fn it_should_return_an_unknown_error_when_unexpected_error_has_encountered() {
-        let resources = create_resources();
+    async fn it_should_return_an_invalid_target_error_when_the_target_is_not_another_category() {
+        let (uow, category, _) = create_a_category_in_use().await;
+        let strategy = Strategy::Reassign(Ulid::new().to_string());
 
-        for resource in resources.clone() {
-            let (uow, r) = create_some_fake_data_and_return_uow(vec![resource]).await;
+        match delete_category(uow, &category, Some(strategy)).await {
+            Err(Error::InvalidTarget) => {}
+            _ => unreachable!(),
+        }
 
-            let (id, resource) = r[0].clone();
+        let (uow, category, _) = create_a_category_in_use().await;
+        let strategy = Strategy::Reassign(category.to_string());
 
-            let req = Request {
-                id: id.to_string(),
-                resource_type: to_resource_type(&resource),
-            };
+        match delete_category(uow, &category, Some(strategy)).await {
+            Err(Error::InvalidTarget) => {}
+            _ => unreachable!(),
+        }
+    }
 
-            let res = execute(Mutex::new(uow.with_error()), req).await;
-            match res {
-                Err(Error::Unknown(_)) => {}
-                _ => unreachable!(),
-            }
+    /// Creates two services, both of them are listed by an FAQ
+    async fn create_services_of_a_faq() -> (InMemory, ContentID, ContentID, ContentID) {
+        let mut uow = InMemory::new();
+        let service = insert_fake_resource::<ServiceKind>(&mut uow, &ServiceKind::fake()).await;
+        let another = insert_fake_resource::<ServiceKind>(&mut uow, &ServiceKind::fake()).await;
+        let faq = FaqData::new(
+            "question".to_string(),
+            "answer".to_string(),
+            vec![service.to_string(), another.to_string()],
+        );
+        let faq = insert_fake_resource::<FaqKind>(&mut uow, &faq).await;
+
+        (uow, service, another, faq)
+    }
+
+    async fn delete_service(
+        uow: InMemory,
+        id: &ContentID,
+        strategy: Option<Strategy>,
+    ) -> Result<(), Error> {
+        let req = Request {
+            id: id.to_string(),
+            strategy,
+            close_gap: false,
+            owner: None,
+            actor: None,
+        };
+
+        execute::<_, ServiceKind>(Mutex::new(uow), &NoCache, req).await
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_to_delete_a_service_listed_by_a_faq() {
+        let (uow, service, _, _) = create_services_of_a_faq().await;
+
+        match delete_service(uow, &service, None).await {
+            Err(Error::InUse(1)) => {}
+            _ => unreachable!(),
         }
     }
+
+    #[tokio::test]
+    async fn it_should_delete_a_service_listed_by_a_faq_by_detaching_it() {
+        let (uow, service, _, faq) = create_services_of_a_faq().await;
+        let audit_repo = InMemoryAuditRepository::new();
+        let uow = uow.with_audit_repository(audit_repo.clone());
+
+        let res = delete_service(uow, &service, Some(Strategy::Detach)).await;
+        assert!(res.is_ok());
+
+        let entries = audit_repo.entries().await;
+        assert_eq!(entries[0].action, AuditAction::Update);
+        assert_eq!(entries[0].target_type, "faq");
+        assert_eq!(entries[0].target_id, faq.to_string());
+    }
+
+    #[tokio::test]
+    async fn it_should_replace_the_service_in_the_list_of_the_faq() {
+        let (uow, service, another, faq) = create_services_of_a_faq().await;
+        let service = ResourceID::try_from(service.to_string()).unwrap();
+        let another = ResourceID::try_from(another.to_string()).unwrap();
+        let faq = ResourceID::try_from(faq.to_string()).unwrap();
+        let service_ids = |uow: InMemory| async move {
+            uow.get_resource::<FaqKind>(&faq, &Language::ZH, &[])
+                .await
+                .unwrap()
+                .unwrap()
+                .data
+                .service_ids
+        };
+
+        // the listed target isn't listed twice
+        let changed = uow
+            .replace_references(&FAQ_SERVICES, &service, Some(&another))
+            .await
+            .unwrap();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(service_ids(uow).await, vec![another.to_string()]);
+
+        let (uow, service, another, _) = create_services_of_a_faq().await;
+        let service = ResourceID::try_from(service.to_string()).unwrap();
+        uow.replace_references(&FAQ_SERVICES, &service, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            uow.count_references(&FAQ_SERVICES, &service).await.unwrap(),
+            0
+        );
+        assert_eq!(
+            uow.count_references(
+                &FAQ_SERVICES,
+                &ResourceID::try_from(another.to_string()).unwrap()
+            )
+            .await
+            .unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn it_should_detach_the_testimonials_of_a_deleted_member() {
+        let mut uow = InMemory::new();
+        let member = insert_fake_resource::<MemberKind>(&mut uow, &MemberKind::fake()).await;
+        let testimonial = TestimonialData::new(
+            "Mr. Wang".to_string(),
+            "content".to_string(),
+            vec![],
+            vec![member.to_string()],
+            true,
+        );
+        let testimonial = insert_fake_resource::<TestimonialKind>(&mut uow, &testimonial).await;
+        let audit_repo = InMemoryAuditRepository::new();
+        let uow = uow.with_audit_repository(audit_repo.clone());
+        let req = Request {
+            id: member.to_string(),
+            strategy: None,
+            close_gap: false,
+            owner: None,
+            actor: None,
+        };
+
+        // the testimonial doesn't need the member to stay
+        let res = execute::<_, MemberKind>(Mutex::new(uow), &NoCache, req).await;
+        assert!(res.is_ok());
+
+        let entries = audit_repo.entries().await;
+        let actions: Vec<_> = entries.iter().map(|entry| entry.action).collect();
+        assert_eq!(actions, vec![AuditAction::Update, AuditAction::Delete]);
+        assert_eq!(entries[0].target_type, "testimonial");
+        assert_eq!(entries[0].target_id, testimonial.to_string());
+    }
 }
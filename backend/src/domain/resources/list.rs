@@ -1,14 +1,12 @@
-use crate::domain::entities::{Language, Pagination, ResourceType};
+use crate::domain::entities::{Language, Pagination};
+use crate::domain::registry::{Filter, ResourceKind};
 use crate::uow::IResourceUnitOfWork;
-use serde::de::DeserializeOwned;
-use serde::Serialize;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
 #[derive(Debug)]
 pub struct Request {
-    pub filter_str: Option<String>,
-    pub resource_type: ResourceType,
+    pub filters: Vec<Filter>,
     pub language: String,
     pub default_language: Language,
     pub pagination: Pagination,
@@ -20,29 +18,28 @@ pub enum Error {
     Unknown(String),
 }
 
-pub async fn execute<IUnitOfWork, T>(
+pub async fn execute<IUnitOfWork, K>(
     uow: Mutex<IUnitOfWork>,
     req: Request,
-) -> Result<(Vec<T>, usize), Error>
+) -> Result<(Vec<K::ListItem>, usize), Error>
 where
     IUnitOfWork: IResourceUnitOfWork,
-    T: DeserializeOwned + Serialize,
+    K: ResourceKind,
 {
-    async fn inner_execute<IUnitOfWork, T>(
+    async fn inner_execute<IUnitOfWork, K>(
         uow: Arc<Mutex<IUnitOfWork>>,
         lang: &Language,
-        filter_str: &Option<String>,
-        resource_type: &ResourceType,
+        filters: &[Filter],
         pagination: &Pagination,
-    ) -> Result<(Vec<T>, usize), Error>
+    ) -> Result<(Vec<K::ListItem>, usize), Error>
     where
         IUnitOfWork: IResourceUnitOfWork,
-        T: DeserializeOwned + Serialize,
+        K: ResourceKind,
     {
         let lock = uow.lock().await;
 
         let data = lock
-            .list_resources::<T>(lang, filter_str, resource_type, pagination)
+            .list_resources::<K>(lang, filters, pagination)
             .await
             .map_err(|e| Error::Unknown(e.to_string()))?;
 
@@ -50,7 +47,7 @@ where
             Pagination::All => data.len(),
             Pagination::Single => data.len(),
             Pagination::Page(_) => lock
-                .count_resources(lang, filter_str, resource_type)
+                .count_resources::<K>(lang, filters)
                 .await
                 .map_err(|e| Error::Unknown(e.to_string()))?,
         };
@@ -62,22 +59,13 @@ where
 
     let uow = Arc::new(uow);
 
-    match inner_execute(
-        uow.clone(),
-        &language,
-        &req.filter_str,
-        &req.resource_type,
-        &req.pagination,
-    )
-    .await
-    {
+    match inner_execute::<_, K>(uow.clone(), &language, &req.filters, &req.pagination).await {
         Ok((data, total)) => {
             if data.is_empty() {
-                inner_execute(
+                inner_execute::<_, K>(
                     uow.clone(),
                     &req.default_language,
-                    &req.filter_str,
-                    &req.resource_type,
+                    &req.filters,
                     &req.pagination,
                 )
                 .await
@@ -92,25 +80,47 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::entities::SimpleMemberEntity;
+    use crate::domain::entities::{CaseResultData, FaqData, Page, TestimonialData};
+    use crate::domain::registry::{
+        CaseResultKind, FaqKind, FilterParam, MemberKind, ServiceKind, TestimonialKind,
+    };
     use crate::domain::resources::test_helpers::tests::{
-        create_resources, create_some_fake_data_and_return_uow,
+        create_some_fake_data_and_return_uow, insert_fake_resource, FakeResource,
     };
     use crate::uow::InMemory;
 
-    #[tokio::test]
-    async fn it_should_list_resource_otherwise() {
-        let (uow, _) = create_some_fake_data_and_return_uow(create_resources()).await;
+    async fn create_uow() -> InMemory {
+        let (mut uow, _) = create_some_fake_data_and_return_uow::<MemberKind>().await;
+        insert_fake_resource::<ServiceKind>(&mut uow, &ServiceKind::fake()).await;
+        insert_fake_resource::<ServiceKind>(&mut uow, &ServiceKind::fake()).await;
+        insert_fake_resource::<FaqKind>(
+            &mut uow,
+            &FaqData::new(
+                "question".to_string(),
+                "answer".to_string(),
+                vec!["service".to_string()],
+            ),
+        )
+        .await;
+        insert_fake_resource::<FaqKind>(&mut uow, &FaqKind::fake()).await;
 
-        let req = Request {
-            filter_str: None,
-            resource_type: ResourceType::Member,
-            language: "zh".to_string(),
+        uow
+    }
+
+    fn create_request(language: &str, filters: Vec<Filter>, pagination: Pagination) -> Request {
+        Request {
+            filters,
+            language: language.to_string(),
             default_language: Language::ZH,
-            pagination: Pagination::All,
-        };
+            pagination,
+        }
+    }
 
-        let res = execute::<InMemory, SimpleMemberEntity>(Mutex::new(uow), req).await;
+    #[tokio::test]
+    async fn it_should_list_resource_otherwise() {
+        let req = create_request("zh", vec![], Pagination::All);
+
+        let res = execute::<_, MemberKind>(Mutex::new(create_uow().await), req).await;
 
         match res {
             Ok((list, total)) => {
@@ -120,26 +130,132 @@ mod tests {
             Err(_) => unreachable!(),
         }
     }
+
     #[tokio::test]
     async fn it_should_list_default_language_resource_otherwise() {
-        let (uow, _) = create_some_fake_data_and_return_uow(create_resources()).await;
+        let req = create_request("en", vec![], Pagination::All);
 
-        let req = Request {
-            filter_str: None,
-            resource_type: ResourceType::Member,
-            language: "en".to_string(),
-            default_language: Language::ZH,
-            pagination: Pagination::All,
-        };
+        let res = execute::<_, MemberKind>(Mutex::new(create_uow().await), req).await;
 
-        let res = execute::<InMemory, SimpleMemberEntity>(Mutex::new(uow), req).await;
+        match res {
+            Ok((list, total)) => {
+                assert_eq!(list.len(), 1);
+                assert_eq!(total, 1);
+            }
+            Err(_) => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_list_the_resources_matched_the_filters() {
+        let filter = FilterParam::contains("service_id", "service_ids");
+        let req = create_request(
+            "zh",
+            vec![filter.to_filter("service".to_string())],
+            Pagination::All,
+        );
+
+        let res = execute::<_, FaqKind>(Mutex::new(create_uow().await), req).await;
 
         match res {
             Ok((list, total)) => {
                 assert_eq!(list.len(), 1);
                 assert_eq!(total, 1);
+                assert_eq!(list[0].data.service_ids, vec!["service".to_string()]);
             }
             Err(_) => unreachable!(),
         }
     }
+
+    #[tokio::test]
+    async fn it_should_count_all_resources_when_listing_a_page() {
+        let req = create_request("zh", vec![], Pagination::Page(Page { page: 0, size: 1 }));
+
+        let res = execute::<_, ServiceKind>(Mutex::new(create_uow().await), req).await;
+
+        match res {
+            Ok((list, total)) => {
+                assert_eq!(list.len(), 1);
+                assert_eq!(total, 2);
+            }
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Creates an approved and an unapproved testimonial and case result of the member
+    async fn create_approval_uow() -> InMemory {
+        let mut uow = create_uow().await;
+        for approved in [true, false] {
+            insert_fake_resource::<TestimonialKind>(
+                &mut uow,
+                &TestimonialData::new(
+                    "Mr. Wang".to_string(),
+                    "content".to_string(),
+                    vec![],
+                    vec!["member".to_string()],
+                    approved,
+                ),
+            )
+            .await;
+            insert_fake_resource::<CaseResultKind>(
+                &mut uow,
+                &CaseResultData::new(
+                    "matter type".to_string(),
+                    "outcome".to_string(),
+                    2024,
+                    vec![],
+                    vec!["member".to_string()],
+                    approved,
+                ),
+            )
+            .await;
+        }
+
+        uow
+    }
+
+    #[tokio::test]
+    async fn it_should_list_the_approved_testimonials_and_case_results_only_when_visible() {
+        let visible = vec![Filter::IsTrue { field: "approved" }];
+
+        let req = create_request("zh", visible.clone(), Pagination::All);
+        let res = execute::<_, TestimonialKind>(Mutex::new(create_approval_uow().await), req).await;
+        match res {
+            Ok((list, total)) => {
+                assert_eq!(total, 1);
+                assert!(list[0].data.approved);
+            }
+            Err(_) => unreachable!(),
+        }
+
+        let req = create_request("zh", visible, Pagination::All);
+        let res = execute::<_, CaseResultKind>(Mutex::new(create_approval_uow().await), req).await;
+        match res {
+            Ok((list, total)) => {
+                assert_eq!(total, 1);
+                assert!(list[0].data.approved);
+            }
+            Err(_) => unreachable!(),
+        }
+
+        // the admins see the unapproved ones as well
+        let req = create_request("zh", vec![], Pagination::All);
+        let res = execute::<_, CaseResultKind>(Mutex::new(create_approval_uow().await), req).await;
+        assert_eq!(res.map(|(_, total)| total).ok(), Some(2));
+    }
+
+    #[tokio::test]
+    async fn it_should_list_the_testimonials_and_case_results_of_a_member() {
+        let of_member = |member: &str| {
+            vec![FilterParam::contains("member_id", "member_ids").to_filter(member.to_string())]
+        };
+
+        let req = create_request("zh", of_member("member"), Pagination::All);
+        let res = execute::<_, TestimonialKind>(Mutex::new(create_approval_uow().await), req).await;
+        assert_eq!(res.map(|(_, total)| total).ok(), Some(2));
+
+        let req = create_request("zh", of_member("another"), Pagination::All);
+        let res = execute::<_, CaseResultKind>(Mutex::new(create_approval_uow().await), req).await;
+        assert_eq!(res.map(|(_, total)| total).ok(), Some(0));
+    }
 }
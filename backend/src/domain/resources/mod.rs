@@ -5,6 +5,8 @@ pub mod list;
 
 pub mod delete;
 
+mod references;
+
 pub mod test_helpers;
 
 pub mod update;
@@ -0,0 +1,238 @@
+use crate::domain::entities::{ContentData, Language, ResourceID};
+use crate::domain::registry::{Reference, ResourceKind};
+use crate::repositories::IResourceRepository;
+use crate::uow::IResourceUnitOfWork;
+
+pub enum Error {
+    /// The field refers to a resource which doesn't exist
+    Invalid(&'static str),
+    /// The field leads back to the resource itself, e.g. a category would be its own ancestor
+    Cycle(&'static str),
+    Unknown(String),
+}
+
+/// Checks that the resources referred by the data exist
+pub async fn check<IUnitOfWork, K>(uow: &mut IUnitOfWork, data: &ContentData) -> Result<(), Error>
+where
+    IUnitOfWork: IResourceUnitOfWork,
+    K: ResourceKind,
+{
+    for reference in K::REFERENCES {
+        for id in reference.referred_ids(data.as_json()) {
+            let id = ResourceID::try_from(id.to_string())
+                .map_err(|_| Error::Invalid(reference.field))?;
+
+            if !uow
+                .resource_repository()
+                .contains(&id, &reference.to)
+                .await
+                .map_err(|e| Error::Unknown(e.to_string()))?
+            {
+                return Err(Error::Invalid(reference.field));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that the fields which refer to the same resource type don't lead back to the resource
+pub async fn check_cycles<IUnitOfWork, K>(
+    uow: &IUnitOfWork,
+    id: &ResourceID,
+    language: &Language,
+    data: &ContentData,
+) -> Result<(), Error>
+where
+    IUnitOfWork: IResourceUnitOfWork,
+    K: ResourceKind,
+{
+    for reference in K::REFERENCES
+        .iter()
+        .filter(|reference| reference.to == K::TYPE)
+    {
+        let Some(referred) = reference.referred_id(data.as_json()) else {
+            continue;
+        };
+        let referred = ResourceID::try_from(referred.to_string())
+            .map_err(|_| Error::Invalid(reference.field))?;
+
+        check_ancestors::<_, K>(uow, reference, id, referred, language).await?;
+    }
+
+    Ok(())
+}
+
+/** Walks up the chain of the reference from the referred resource in the language.
+*
+* It fails if the chain reaches the resource, so the resource can't refer to itself or to one of
+* the resources which refer to it, directly or not.
+*/
+pub async fn check_ancestors<IUnitOfWork, K>(
+    uow: &IUnitOfWork,
+    reference: &Reference,
+    id: &ResourceID,
+    referred: ResourceID,
+    language: &Language,
+) -> Result<(), Error>
+where
+    IUnitOfWork: IResourceUnitOfWork,
+    K: ResourceKind,
+{
+    let mut visited = vec![];
+    let mut next = Some(referred);
+
+    while let Some(ancestor) = next {
+        if &ancestor == id {
+            return Err(Error::Cycle(reference.field));
+        }
+        // the chain is already broken above, it doesn't pass through the resource though
+        if visited.contains(&ancestor) {
+            break;
+        }
+
+        let entity = uow
+            .get_resource::<K>(&ancestor, language, &[])
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
+        // the entities keep the data of the resources under `data`
+        next = entity
+            .map(serde_json::to_value)
+            .transpose()
+            .map_err(|e| Error::Unknown(e.to_string()))?
+            .and_then(|entity| {
+                reference
+                    .referred_id(&entity["data"])
+                    .and_then(|id| ResourceID::try_from(id.to_string()).ok())
+            });
+        visited.push(ancestor);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{ArticleData, CategoryData, ContentID, FaqData};
+    use crate::domain::registry::{ArticleKind, CategoryKind, FaqKind, MemberKind, ServiceKind};
+    use crate::domain::resources::test_helpers::tests::{
+        create_a_category_tree, insert_fake_resource, FakeResource,
+    };
+    use crate::uow::InMemory;
+    use ulid::Ulid;
+
+    async fn check_data<K: ResourceKind>(uow: &mut InMemory, data: &K::Data) -> Result<(), Error> {
+        check::<_, K>(uow, &ContentData::try_from_data::<K>(data).unwrap()).await
+    }
+
+    fn faq(service_ids: Vec<String>) -> FaqData {
+        FaqData::new("question".to_string(), "answer".to_string(), service_ids)
+    }
+
+    #[tokio::test]
+    async fn it_should_check_every_service_of_a_faq() {
+        let mut uow = InMemory::new();
+        let service = insert_fake_resource::<ServiceKind>(&mut uow, &ServiceKind::fake()).await;
+        let member = insert_fake_resource::<MemberKind>(&mut uow, &MemberKind::fake()).await;
+
+        assert!(check_data::<FaqKind>(&mut uow, &faq(vec![])).await.is_ok());
+        assert!(
+            check_data::<FaqKind>(&mut uow, &faq(vec![service.to_string()]))
+                .await
+                .is_ok()
+        );
+
+        for service_ids in [
+            vec![Ulid::new().to_string()],
+            vec![service.to_string(), Ulid::new().to_string()],
+            // a member isn't a service
+            vec![member.to_string()],
+            vec!["not an id".to_string()],
+        ] {
+            match check_data::<FaqKind>(&mut uow, &faq(service_ids)).await {
+                Err(Error::Invalid("service_ids")) => {}
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_check_the_category_of_an_article_and_the_parent_of_a_category() {
+        let mut uow = InMemory::new();
+        let category = insert_fake_resource::<CategoryKind>(&mut uow, &CategoryKind::fake()).await;
+        let article =
+            |category_id| ArticleData::new(category_id, "title".to_string(), "data".to_string());
+        let category_of = |parent_id| CategoryData::new(None, "name".to_string(), parent_id);
+
+        assert!(check_data::<ArticleKind>(&mut uow, &article(None))
+            .await
+            .is_ok());
+        assert!(
+            check_data::<ArticleKind>(&mut uow, &article(Some(category.to_string())))
+                .await
+                .is_ok()
+        );
+        match check_data::<ArticleKind>(&mut uow, &article(Some(Ulid::new().to_string()))).await {
+            Err(Error::Invalid("category_id")) => {}
+            _ => unreachable!(),
+        }
+
+        assert!(check_data::<CategoryKind>(&mut uow, &category_of(None))
+            .await
+            .is_ok());
+        assert!(
+            check_data::<CategoryKind>(&mut uow, &category_of(Some(category.to_string())))
+                .await
+                .is_ok()
+        );
+        match check_data::<CategoryKind>(&mut uow, &category_of(Some(Ulid::new().to_string())))
+            .await
+        {
+            Err(Error::Invalid("parent_id")) => {}
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_find_the_cycles_of_the_category_parents_only() {
+        let (uow, [root, child, grandchild]) = create_a_category_tree().await;
+        let root_id = ResourceID::try_from(root.to_string()).unwrap();
+        let under = |parent: &ContentID| {
+            ContentData::try_from_data::<CategoryKind>(&CategoryData::new(
+                None,
+                "root".to_string(),
+                Some(parent.to_string()),
+            ))
+            .unwrap()
+        };
+
+        for parent in [&root, &child, &grandchild] {
+            match check_cycles::<_, CategoryKind>(&uow, &root_id, &Language::ZH, &under(parent))
+                .await
+            {
+                Err(Error::Cycle("parent_id")) => {}
+                _ => unreachable!(),
+            }
+        }
+
+        // the grandchild can move up under the root
+        let grandchild_id = ResourceID::try_from(grandchild.to_string()).unwrap();
+        assert!(check_cycles::<_, CategoryKind>(
+            &uow,
+            &grandchild_id,
+            &Language::ZH,
+            &under(&root)
+        )
+        .await
+        .is_ok());
+
+        // the services of an FAQ aren't FAQs, they can't form a cycle
+        let faq_data = ContentData::try_from_data::<FaqKind>(&faq(vec![root.to_string()])).unwrap();
+        assert!(
+            check_cycles::<_, FaqKind>(&uow, &root_id, &Language::ZH, &faq_data)
+                .await
+                .is_ok()
+        );
+    }
+}
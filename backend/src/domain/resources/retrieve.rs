@@ -1,7 +1,6 @@
-use crate::domain::entities::{Language, ResourceID, ResourceType};
+use crate::domain::entities::{Language, ResourceID};
+use crate::domain::registry::{Filter, ResourceKind};
 use crate::uow::IResourceUnitOfWork;
-use serde::de::DeserializeOwned;
-use serde::Serialize;
 use std::fmt::Formatter;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -9,9 +8,9 @@ use tokio::sync::Mutex;
 #[derive(Debug)]
 pub struct Request {
     pub id: String,
-    pub resource_type: ResourceType,
     pub language: String,
     pub default_language: Language,
+    pub filters: Vec<Filter>,
 }
 
 #[derive(Debug)]
@@ -37,23 +36,26 @@ impl std::fmt::Display for Error {
     }
 }
 
-pub async fn execute<IUnitOfWork, T>(uow: Mutex<IUnitOfWork>, req: Request) -> Result<T, Error>
+pub async fn execute<IUnitOfWork, K>(
+    uow: Mutex<IUnitOfWork>,
+    req: Request,
+) -> Result<K::Entity, Error>
 where
     IUnitOfWork: IResourceUnitOfWork,
-    T: DeserializeOwned + Serialize,
+    K: ResourceKind,
 {
-    async fn inner_execute<IUnitOfWork, T>(
+    async fn inner_execute<IUnitOfWork, K>(
         uow: Arc<Mutex<IUnitOfWork>>,
         id: &ResourceID,
         lang: &Language,
-        resource_type: &ResourceType,
-    ) -> Result<T, Error>
+        filters: &[Filter],
+    ) -> Result<K::Entity, Error>
     where
         IUnitOfWork: IResourceUnitOfWork,
-        T: DeserializeOwned + Serialize,
+        K: ResourceKind,
     {
         let lock = uow.lock().await;
-        match lock.get_resource(id, lang, resource_type).await {
+        match lock.get_resource::<K>(id, lang, filters).await {
             Ok(Some(res)) => Ok(res),
             Ok(None) => Err(Error::NotFound),
             Err(e) => Err(Error::Unknown(e.to_string())),
@@ -65,10 +67,10 @@ where
 
     let uow = Arc::new(uow);
 
-    match inner_execute(uow.clone(), &id, &language, &req.resource_type).await {
+    match inner_execute::<_, K>(uow.clone(), &id, &language, &req.filters).await {
         Ok(res) => Ok(res),
         Err(Error::NotFound) => {
-            inner_execute(uow.clone(), &id, &req.default_language, &req.resource_type).await
+            inner_execute::<_, K>(uow.clone(), &id, &req.default_language, &req.filters).await
         }
         Err(e) => Err(e),
     }
@@ -77,259 +79,138 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::entities::{
-        ArticleData, ArticleEntity, CategoryData, CategoryEntity, ContactData, ContactEntity,
-        ContentData, ContentID, HomeData, HomeEntity, MemberData, MemberEntity, Resource,
-        ServiceData, ServiceEntity,
-    };
     use crate::domain::member::entities::{AvatarData, AvatarJson};
+    use crate::domain::registry::{
+        ArticleKind, CaseResultKind, CategoryKind, ContactKind, FaqKind, HomeKind, MemberKind,
+        ServiceKind, TestimonialKind,
+    };
+    use crate::domain::resources::test_helpers::tests::{
+        create_some_fake_data_and_return_uow, for_each_resource_kind, FakeResource,
+    };
     use crate::repositories::IAvatarRepository;
-    use crate::repositories::IContentRepository;
     use crate::uow::InMemory;
-    use serde_json::json;
-    use ulid::Ulid;
-
-    fn create_testcases() -> Vec<(String, ResourceType, Resource, Option<AvatarData>)> {
-        let member = MemberData::new("boris".to_string(), "description".to_string());
-        let service = ServiceData::new("title".to_string(), "data".to_string(), "icon".to_string());
-        let home = HomeData::new("home".to_string());
-        let contact = json!({
-            "data": {
-                "address": "address".to_string(),
-                "phone": "1234".to_string(),
-                "email": "info@example.com".to_string(),
-            }
-        });
-        let contact = ContactData::new(contact);
-        let avatar = AvatarData {
-            large_image: "large".to_string(),
-            small_image: "small".to_string(),
-        };
-        let article = ArticleData::new(None, "title".to_string(), "data".to_string());
-        let category = CategoryData::new(None, "category".to_string());
-        vec![
-            (
-                Ulid::new().to_string(),
-                ResourceType::Member,
-                Resource::Member(member.clone()),
-                Some(avatar),
-            ),
-            (
-                Ulid::new().to_string(),
-                ResourceType::Member,
-                Resource::Member(member.clone()),
-                None,
-            ),
-            (
-                Ulid::new().to_string(),
-                ResourceType::Service,
-                Resource::Service(service.clone()),
-                None,
-            ),
-            (
-                Ulid::new().to_string(),
-                ResourceType::Home,
-                Resource::Home(home.clone()),
-                None,
-            ),
-            (
-                Ulid::new().to_string(),
-                ResourceType::Contact,
-                Resource::Contact(contact.clone()),
-                None,
-            ),
-            (
-                Ulid::new().to_string(),
-                ResourceType::Article,
-                Resource::Article(article.clone()),
-                None,
-            ),
-            (
-                Ulid::new().to_string(),
-                ResourceType::Category,
-                Resource::Category(category.clone()),
-                None,
-            ),
-        ]
+
+    fn create_request(id: String, language: &str, filters: Vec<Filter>) -> Request {
+        Request {
+            id,
+            language: language.to_string(),
+            default_language: Language::ZH,
+            filters,
+        }
     }
 
-    async fn create_a_fake_resource_and_return_the_unit_of_work(
-        id: String,
-        resource: Resource,
-        avatar: Option<AvatarData>,
-    ) -> InMemory {
-        let content_data = ContentData::try_from(resource).unwrap();
-        let resource_id = ResourceID::try_from(id.clone()).unwrap();
-        let content_id = ContentID::from(resource_id.clone());
+    async fn arrange_and_act<K: FakeResource>(
+        language: &str,
+    ) -> (K::Data, Result<K::Entity, Error>) {
+        let (uow, id) = create_some_fake_data_and_return_uow::<K>().await;
 
-        let mut uow = InMemory::new();
+        let req = create_request(id.to_string(), language, vec![]);
 
-        let _ = uow
-            .content_repository()
-            .insert(content_id.clone(), content_data, Language::ZH)
-            .await
-            .unwrap();
+        (K::fake(), execute::<_, K>(Mutex::new(uow), req).await)
+    }
 
-        match avatar {
-            Some(avatar) => {
-                let json = AvatarJson::try_from(avatar).unwrap();
-                uow.avatar_repository()
-                    .save(resource_id.clone(), json)
-                    .await
-                    .unwrap();
-            }
-            None => {
-                // Workaround: touch the repository for initialization
-                let _ = uow.avatar_repository();
-            }
-        }
-        // Workaround: touch the repository for initialization
-        let _ = uow.resource_repository();
+    async fn assert_retrieved(language: &str) {
+        let (data, res) = arrange_and_act::<MemberKind>(language).await;
+        let res = res.expect("should execute successfully");
+        assert_eq!(res.data, data);
+        assert_eq!(res.avatar, None);
+
+        let (data, res) = arrange_and_act::<ServiceKind>(language).await;
+        assert_eq!(res.expect("should execute successfully").data, data);
+
+        let (data, res) = arrange_and_act::<HomeKind>(language).await;
+        assert_eq!(res.expect("should execute successfully").data, data);
+
+        let (data, res) = arrange_and_act::<ContactKind>(language).await;
+        assert_eq!(res.expect("should execute successfully").data, data.data);
+
+        let (data, res) = arrange_and_act::<ArticleKind>(language).await;
+        assert_eq!(res.expect("should execute successfully").data, data);
+
+        let (data, res) = arrange_and_act::<CategoryKind>(language).await;
+        assert_eq!(res.expect("should execute successfully").data, data);
+
+        let (data, res) = arrange_and_act::<FaqKind>(language).await;
+        assert_eq!(res.expect("should execute successfully").data, data);
 
-        uow
+        let (data, res) = arrange_and_act::<TestimonialKind>(language).await;
+        assert_eq!(res.expect("should execute successfully").data, data);
+
+        let (data, res) = arrange_and_act::<CaseResultKind>(language).await;
+        assert_eq!(res.expect("should execute successfully").data, data);
     }
 
-    async fn act_and_assert_successfully(
-        uow: InMemory,
-        req: Request,
-        resource: Resource,
-        avatar: Option<AvatarData>,
-    ) {
-        match resource {
-            Resource::Member(m) => {
-                let res: MemberEntity = execute(Mutex::new(uow), req)
-                    .await
-                    .expect("should execute successfully");
-
-                assert_eq!(res.data, m);
-                assert_eq!(avatar, res.avatar);
-            }
-            Resource::Service(s) => {
-                let res: ServiceEntity = execute(Mutex::new(uow), req)
-                    .await
-                    .expect("should execute successfully");
-                assert_eq!(res.data, s)
-            }
-            Resource::Home(h) => {
-                let res: HomeEntity = execute(Mutex::new(uow), req)
-                    .await
-                    .expect("should execute successfully");
-                assert_eq!(res.data, h)
-            }
-            Resource::Contact(c) => {
-                let res: ContactEntity = execute(Mutex::new(uow), req)
-                    .await
-                    .expect("should execute successfully");
-                assert_eq!(res.data, c.data);
-            }
-            Resource::Article(a) => {
-                let res: ArticleEntity = execute(Mutex::new(uow), req)
-                    .await
-                    .expect("should execute successfully");
-                assert_eq!(res.data, a)
-            }
-            Resource::Category(c) => {
-                let res: CategoryEntity = execute(Mutex::new(uow), req)
-                    .await
-                    .expect("should execute successfully");
-                assert_eq!(res.data, c)
-            }
-        }
+    async fn assert_unknown_error<K: FakeResource>() {
+        let (uow, id) = create_some_fake_data_and_return_uow::<K>().await;
+
+        let req = create_request(id.to_string(), "zh", vec![]);
+
+        let res = execute::<_, K>(Mutex::new(uow.with_error()), req).await;
+        assert!(res.is_err());
     }
 
     #[tokio::test]
     async fn it_should_return_a_resource_otherwise() {
-        let testcases = create_testcases();
-
-        for (id, resource_type, resource, avatar) in testcases {
-            let uow = create_a_fake_resource_and_return_the_unit_of_work(
-                id.clone(),
-                resource.clone(),
-                avatar.clone(),
-            )
-            .await;
-
-            let req = Request {
-                id: id.clone(),
-                resource_type: resource_type.clone(),
-                language: "zh".to_string(),
-                default_language: Language::ZH,
-            };
-
-            act_and_assert_successfully(uow, req, resource, avatar).await;
-        }
+        assert_retrieved("zh").await;
     }
 
     #[tokio::test]
     async fn it_should_return_a_resource_with_default_language_otherwise() {
-        let testcases = create_testcases();
-
-        for (id, resource_type, resource, avatar) in testcases {
-            let uow = create_a_fake_resource_and_return_the_unit_of_work(
-                id.clone(),
-                resource.clone(),
-                avatar.clone(),
-            )
-            .await;
-
-            let req = Request {
-                id: id.clone(),
-                resource_type: resource_type.clone(),
-                language: "en".to_string(),
-                default_language: Language::ZH,
-            };
-
-            act_and_assert_successfully(uow, req, resource, avatar).await;
-        }
+        assert_retrieved("en").await;
     }
 
     #[tokio::test]
-    async fn it_should_return_an_error_when_unexpected_error_encountered() {
-        let testcases = create_testcases();
-
-        for (id, resource_type, resource, avatar) in testcases {
-            let uow = create_a_fake_resource_and_return_the_unit_of_work(
-                id.clone(),
-                resource.clone(),
-                avatar,
-            )
+    async fn it_should_return_a_member_with_avatar_otherwise() {
+        let (mut uow, id) = create_some_fake_data_and_return_uow::<MemberKind>().await;
+        let avatar = AvatarData {
+            large_image: "large".to_string(),
+            small_image: "small".to_string(),
+        };
+        let resource_id = ResourceID::try_from(id.to_string()).unwrap();
+        uow.avatar_repository()
+            .save(resource_id, AvatarJson::try_from(avatar.clone()).unwrap())
             .await
-            .with_error();
-
-            let req = Request {
-                id: id.clone(),
-                resource_type: resource_type.clone(),
-                language: "zh".to_string(),
-                default_language: Language::ZH,
-            };
-
-            match resource {
-                Resource::Member(_) => {
-                    let res: Result<MemberEntity, Error> = execute(Mutex::new(uow), req).await;
-
-                    assert!(res.is_err());
-                }
-                Resource::Service(_) => {
-                    let res: Result<ServiceEntity, Error> = execute(Mutex::new(uow), req).await;
-                    assert!(res.is_err());
-                }
-                Resource::Home(_) => {
-                    let res: Result<HomeEntity, Error> = execute(Mutex::new(uow), req).await;
-                    assert!(res.is_err());
-                }
-                Resource::Contact(_) => {
-                    let res: Result<ContactEntity, Error> = execute(Mutex::new(uow), req).await;
-                    assert!(res.is_err());
-                }
-                Resource::Article(_) => {
-                    let res: Result<ArticleEntity, Error> = execute(Mutex::new(uow), req).await;
-                    assert!(res.is_err());
-                }
-                Resource::Category(_) => {
-                    let res: Result<CategoryEntity, Error> = execute(Mutex::new(uow), req).await;
-                    assert!(res.is_err())
-                }
-            }
+            .unwrap();
+
+        let req = create_request(id.to_string(), "zh", vec![]);
+
+        let res = execute::<_, MemberKind>(Mutex::new(uow), req)
+            .await
+            .expect("should execute successfully");
+        assert_eq!(res.avatar, Some(avatar));
+    }
+
+    #[tokio::test]
+    async fn it_should_return_a_not_found_error_when_resource_is_another_type() {
+        let (uow, id) = create_some_fake_data_and_return_uow::<MemberKind>().await;
+
+        let req = create_request(id.to_string(), "zh", vec![]);
+
+        match execute::<InMemory, ServiceKind>(Mutex::new(uow), req).await {
+            Err(Error::NotFound) => {}
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_return_a_not_found_error_when_filters_are_not_matched() {
+        // the fake case result is not approved
+        let (uow, id) = create_some_fake_data_and_return_uow::<CaseResultKind>().await;
+
+        let req = create_request(
+            id.to_string(),
+            "zh",
+            vec![Filter::IsTrue { field: "approved" }],
+        );
+
+        match execute::<InMemory, CaseResultKind>(Mutex::new(uow), req).await {
+            Err(Error::NotFound) => {}
+            _ => unreachable!(),
         }
     }
+
+    #[tokio::test]
+    async fn it_should_return_an_error_when_unexpected_error_encountered() {
+        for_each_resource_kind!(assert_unknown_error);
+    }
 }
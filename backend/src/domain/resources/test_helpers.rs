@@ -1,97 +1,212 @@
 #[cfg(test)]
 pub(crate) mod tests {
     use crate::domain::entities::{
-        ArticleData, CategoryData, ContactData, ContentData, ContentID, HomeData, Language,
-        MemberData, Resource, ResourceID, ResourceType, ServiceData,
+        ArticleData, CaseResultData, CategoryData, ContactData, ContentData, ContentID, FaqData,
+        HomeData, Language, MemberData, ResourceID, ServiceData, TestimonialData,
+    };
+    use crate::domain::registry::{
+        ArticleKind, CaseResultKind, CategoryKind, ContactKind, FaqKind, HomeKind, MemberKind,
+        ResourceKind, ServiceKind, TestimonialKind,
     };
     use crate::repositories::IContentRepository;
     use crate::repositories::IResourceRepository;
     use crate::uow::{IResourceUnitOfWork, InMemory};
     use serde_json::json;
 
-    pub fn create_resources() -> Vec<Resource> {
-        let member = MemberData::new("boris".to_string(), "description".to_string());
-        let service = ServiceData::new("title".to_string(), "data".to_string(), "icon".to_string());
-        let home = HomeData::new("home".to_string());
-        let contact = json!({
-            "address": "address".to_string(),
-            "phone": "1234".to_string(),
-            "email": "info@example.com".to_string(),
-        });
-        let contact = ContactData::new(contact);
-        let article = ArticleData::new(None, "title".to_string(), "data".to_string());
-        let category = CategoryData::new(None, "category".to_string());
-
-        vec![
-            Resource::Article(article),
-            Resource::Service(service),
-            Resource::Home(home),
-            Resource::Member(member),
-            Resource::Contact(contact),
-            Resource::Category(category),
-        ]
+    /// Provides the fake data of a resource type for testing
+    pub trait FakeResource: ResourceKind {
+        fn fake() -> Self::Data;
+
+        /// Changes some fields of the data
+        fn modify(data: Self::Data) -> Self::Data;
     }
-    pub async fn create_some_fake_data_and_return_uow(
-        resources: Vec<Resource>,
-    ) -> (InMemory, Vec<(ContentID, Resource)>) {
-        let mut uow = InMemory::new();
-        let mut resources_with_id = vec![];
-
-        for resource in resources {
-            let id = ulid::Ulid::new().to_string();
-            let resource_id = ResourceID::try_from(id.clone()).unwrap();
-            let content_id = ContentID::from(resource_id.clone());
-            let content_data = ContentData::try_from(resource.clone()).unwrap();
-
-            match &resource {
-                Resource::Member(_) => {
-                    uow.resource_repository()
-                        .insert(resource_id.clone(), ResourceType::Member, 0)
-                        .await
-                        .unwrap();
-                }
-                Resource::Service(_) => {
-                    uow.resource_repository()
-                        .insert(resource_id.clone(), ResourceType::Service, 1)
-                        .await
-                        .unwrap();
-                }
-                Resource::Home(_) => {
-                    uow.resource_repository()
-                        .insert(resource_id.clone(), ResourceType::Home, 2)
-                        .await
-                        .unwrap();
-                }
-                Resource::Contact(_) => {
-                    uow.resource_repository()
-                        .insert(resource_id.clone(), ResourceType::Contact, 3)
-                        .await
-                        .unwrap();
-                }
-                Resource::Article(_) => {
-                    uow.resource_repository()
-                        .insert(resource_id.clone(), ResourceType::Article, 4)
-                        .await
-                        .unwrap();
-                }
-                Resource::Category(_) => {
-                    uow.resource_repository()
-                        .insert(resource_id.clone(), ResourceType::Category, 5)
-                        .await
-                        .unwrap();
-                }
-            };
-
-            uow.content_repository()
-                .insert(content_id.clone(), content_data, Language::ZH)
-                .await
-                .unwrap();
-
-            resources_with_id.push((content_id, resource));
+
+    impl FakeResource for MemberKind {
+        fn fake() -> Self::Data {
+            MemberData::new("boris".to_string(), "description".to_string())
+        }
+
+        fn modify(data: Self::Data) -> Self::Data {
+            MemberData {
+                name: "new name".to_string(),
+                ..data
+            }
+        }
+    }
+
+    impl FakeResource for ServiceKind {
+        fn fake() -> Self::Data {
+            ServiceData::new("title".to_string(), "data".to_string(), "icon".to_string())
+        }
+
+        fn modify(data: Self::Data) -> Self::Data {
+            ServiceData {
+                title: "new title".to_string(),
+                ..data
+            }
+        }
+    }
+
+    impl FakeResource for HomeKind {
+        fn fake() -> Self::Data {
+            HomeData::new("home".to_string())
         }
 
+        fn modify(_: Self::Data) -> Self::Data {
+            HomeData::new("new data".to_string())
+        }
+    }
+
+    impl FakeResource for ContactKind {
+        fn fake() -> Self::Data {
+            ContactData::new(json!({
+                "address": "address".to_string(),
+                "phone": "1234".to_string(),
+                "email": "info@example.com".to_string(),
+            }))
+        }
+
+        fn modify(_: Self::Data) -> Self::Data {
+            ContactData::new(json!({
+                "address": "new address",
+            }))
+        }
+    }
+
+    impl FakeResource for ArticleKind {
+        fn fake() -> Self::Data {
+            ArticleData::new(None, "title".to_string(), "data".to_string())
+        }
+
+        fn modify(data: Self::Data) -> Self::Data {
+            ArticleData {
+                title: "new title".to_string(),
+                ..data
+            }
+        }
+    }
+
+    impl FakeResource for CategoryKind {
+        fn fake() -> Self::Data {
+            CategoryData::new(None, "category".to_string(), None)
+        }
+
+        fn modify(_: Self::Data) -> Self::Data {
+            CategoryData::new(None, "new category".to_string(), None)
+        }
+    }
+
+    impl FakeResource for FaqKind {
+        fn fake() -> Self::Data {
+            FaqData::new("question".to_string(), "answer".to_string(), vec![])
+        }
+
+        fn modify(data: Self::Data) -> Self::Data {
+            FaqData {
+                question: "new question".to_string(),
+                ..data
+            }
+        }
+    }
+
+    impl FakeResource for TestimonialKind {
+        fn fake() -> Self::Data {
+            TestimonialData::new(
+                "Mr. Wang".to_string(),
+                "content".to_string(),
+                vec![],
+                vec![],
+                true,
+            )
+        }
+
+        fn modify(data: Self::Data) -> Self::Data {
+            TestimonialData {
+                approved: !data.approved,
+                ..data
+            }
+        }
+    }
+
+    impl FakeResource for CaseResultKind {
+        fn fake() -> Self::Data {
+            CaseResultData::new(
+                "matter type".to_string(),
+                "outcome".to_string(),
+                2024,
+                vec![],
+                vec![],
+                false,
+            )
+        }
+
+        fn modify(data: Self::Data) -> Self::Data {
+            CaseResultData {
+                outcome: "new outcome".to_string(),
+                ..data
+            }
+        }
+    }
+
+    /// Runs a generic async test function for every resource type
+    macro_rules! for_each_resource_kind {
+        ($test:ident) => {
+            $test::<$crate::domain::registry::MemberKind>().await;
+            $test::<$crate::domain::registry::ServiceKind>().await;
+            $test::<$crate::domain::registry::HomeKind>().await;
+            $test::<$crate::domain::registry::ContactKind>().await;
+            $test::<$crate::domain::registry::ArticleKind>().await;
+            $test::<$crate::domain::registry::CategoryKind>().await;
+            $test::<$crate::domain::registry::FaqKind>().await;
+            $test::<$crate::domain::registry::TestimonialKind>().await;
+            $test::<$crate::domain::registry::CaseResultKind>().await;
+        };
+    }
+
+    pub(crate) use for_each_resource_kind;
+
+    /// Inserts a resource with the given data in the default language
+    pub async fn insert_fake_resource<K: ResourceKind>(
+        uow: &mut InMemory,
+        data: &K::Data,
+    ) -> ContentID {
+        let id = ulid::Ulid::new().to_string();
+        let resource_id = ResourceID::try_from(id).unwrap();
+        let content_id = ContentID::from(resource_id.clone());
+        let content_data = ContentData::try_from_data::<K>(data).unwrap();
+
+        uow.resource_repository()
+            .insert(resource_id, K::TYPE, 0, None)
+            .await
+            .unwrap();
+
+        uow.content_repository()
+            .insert(content_id.clone(), content_data, Language::ZH)
+            .await
+            .unwrap();
+
+        content_id
+    }
+
+    pub async fn create_some_fake_data_and_return_uow<K: FakeResource>() -> (InMemory, ContentID) {
+        let mut uow = InMemory::new();
+        let id = insert_fake_resource::<K>(&mut uow, &K::fake()).await;
+
+        // Workaround: touch the repository for initialization
         let _ = uow.avatar_repository();
 
-        (uow, resources_with_id)
+        (uow, id)
+    }
+
+    /// Creates a root category along with its child and grandchild
+    pub async fn create_a_category_tree() -> (InMemory, [ContentID; 3]) {
+        let mut uow = InMemory::new();
+        let root = insert_fake_resource::<CategoryKind>(&mut uow, &CategoryKind::fake()).await;
+        let child = CategoryData::new(None, "child".to_string(), Some(root.to_string()));
+        let child = insert_fake_resource::<CategoryKind>(&mut uow, &child).await;
+        let grandchild = CategoryData::new(None, "grandchild".to_string(), Some(child.to_string()));
+        let grandchild = insert_fake_resource::<CategoryKind>(&mut uow, &grandchild).await;
+
+        (uow, [root, child, grandchild])
     }
 }
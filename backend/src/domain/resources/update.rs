@@ -1,76 +1,163 @@
-use crate::domain::entities::{ContentID, Language, Resource, ResourceID};
-use crate::repositories::IContentRepository;
+use crate::cache::{self, tags, IResourceCache};
+use crate::domain::audit::{self, entry, Actor};
+use crate::domain::entities::{
+    AuditAction, ContentData, ContentID, Language, ResourceError, ResourceID, UserID,
+};
+use crate::domain::registry::ResourceKind;
+use crate::domain::resources::references;
+use crate::domain::validation::FieldError;
 use crate::repositories::IResourceRepository;
+use crate::repositories::{IAuditRepository, IContentRepository, UpdateOutcome};
 use crate::uow::IResourceUnitOfWork;
 use tokio::sync::Mutex;
 
-pub struct Request {
+pub struct Request<K: ResourceKind> {
     pub id: String,
-    pub data: Resource,
+    pub data: K::Data,
     pub language: String,
     pub seq: i32,
+    /// The version of the content which the change is based on, it's not checked if it's missing
+    pub version: Option<i32>,
+    /// Only the resources created by the user can be changed if it's given
+    pub owner: Option<UserID>,
+    /// Who changes the resource, it's recorded in the audit log
+    pub actor: Option<Actor>,
 }
 
 pub enum Error {
     BadRequest,
+    /// The resource isn't created by the owner of the request
+    Forbidden,
+    /// The fields violate the rules of the validation
+    Invalid(Vec<FieldError>),
+    /// The field refers to a resource which doesn't exist
+    InvalidReference(&'static str),
     NotFound,
+    /// The content has been changed since the given version
+    VersionMismatch {
+        current: i32,
+    },
     Unknown(String),
 }
 
-pub async fn execute<IUnitOfWork>(uow: Mutex<IUnitOfWork>, req: Request) -> Result<ContentID, Error>
+pub async fn execute<IUnitOfWork, K>(
+    uow: Mutex<IUnitOfWork>,
+    cache: &dyn IResourceCache,
+    req: Request<K>,
+) -> Result<(ContentID, i32), Error>
 where
     IUnitOfWork: IResourceUnitOfWork,
+    K: ResourceKind,
 {
     let id = {
         let mut lock = uow.lock().await;
 
-        let (kind, data) = req
-            .data
-            .into_typed_content()
-            .map_err(|_| Error::BadRequest)?;
+        let data = match ContentData::try_from_data::<K>(&req.data) {
+            Ok(data) => data,
+            Err(ResourceError::ValidationError(fields)) => return Err(Error::Invalid(fields)),
+            Err(e) => return Err(Error::Unknown(e.to_string())),
+        };
 
         let id = ResourceID::try_from(req.id).map_err(|_| Error::BadRequest)?;
-        let language = Language::try_from(req.language).map_err(|_| Error::BadRequest)?;
+        let language = Language::try_from(req.language)
+            .map_err(|_| Error::Invalid(vec![FieldError::new("language", "language")]))?;
 
         if !lock
             .resource_repository()
-            .contains(&id, &kind)
+            .contains(&id, &K::TYPE)
             .await
             .map_err(|e| Error::Unknown(e.to_string()))?
         {
             return Err(Error::NotFound);
         }
 
-        match lock.resource_repository().update_seq(&id, req.seq).await {
+        // the resource can't refer to itself, and the referred resources have to exist
+        let checked = match references::check_cycles::<_, K>(&*lock, &id, &language, &data).await {
+            Ok(_) => references::check::<_, K>(&mut *lock, &data).await,
+            Err(e) => Err(e),
+        };
+        match checked {
             Ok(_) => {}
-            Err(e) => return Err(Error::Unknown(e.to_string())),
+            Err(references::Error::Invalid(field)) => return Err(Error::InvalidReference(field)),
+            Err(references::Error::Cycle(field)) => {
+                return Err(Error::Invalid(vec![FieldError::new(field, "cycle")]))
+            }
+            Err(references::Error::Unknown(e)) => return Err(Error::Unknown(e)),
+        }
+
+        if let Some(owner) = &req.owner {
+            let creator = lock
+                .resource_repository()
+                .creator(&id)
+                .await
+                .map_err(|e| Error::Unknown(e.to_string()))?;
+            if creator.as_ref() != Some(owner) {
+                return Err(Error::Forbidden);
+            }
         }
 
-        let id = ContentID::from(id);
+        let before = audit::snapshot::<K>(&*lock, &id, &language)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
 
-        if !lock
+        let content_id = ContentID::from(id.clone());
+
+        let version = if !lock
             .content_repository()
-            .contains(&id, &language)
+            .contains(&content_id, &language)
             .await
             .map_err(|e| Error::Unknown(e.to_string()))?
         {
             // insert the content into the content repository and retrieve the content id
             match lock
                 .content_repository()
-                .insert(id.clone(), data, language)
+                .insert(content_id.clone(), data, language.clone())
                 .await
             {
-                Ok(_) => Ok(id),
+                Ok(_) => 1,
                 Err(e) => return Err(Error::Unknown(e.to_string())),
             }
         } else {
-            // insert the content into the content repository and retrieve the content id
-            match lock.content_repository().update(&id, data, language).await {
-                Ok(_) => Ok(id),
+            // the content is only updated if nobody else has updated it since the given version
+            match lock
+                .content_repository()
+                .update(&content_id, data, language.clone(), req.version)
+                .await
+            {
+                Ok(UpdateOutcome::Updated { version }) => version,
+                Ok(UpdateOutcome::VersionMismatch { current }) => {
+                    return Err(Error::VersionMismatch { current })
+                }
                 Err(e) => return Err(Error::Unknown(e.to_string())),
             }
-        }
-    }?;
+        };
+
+        // moving the resource bumps the versions of its contents, after the version has been checked
+        let version = match lock.resource_repository().update_seq(&id, req.seq).await {
+            Ok(true) => version + 1,
+            Ok(false) => version,
+            Err(e) => return Err(Error::Unknown(e.to_string())),
+        };
+
+        // the entry is written in the same transaction, so there is no change without it
+        let after = audit::snapshot::<K>(&*lock, &id, &language)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
+        let entry = entry(
+            req.actor.as_ref(),
+            AuditAction::Update,
+            K::TYPE.as_str(),
+            id.to_string(),
+        )
+        .in_language(language)
+        .with_snapshots(before, after);
+        lock.audit_repository()
+            .save(&entry)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
+
+        (content_id, version)
+    };
 
     // commit the transaction
     uow.into_inner()
@@ -78,145 +165,325 @@ where
         .await
         .map_err(|e| Error::Unknown(e.to_string()))?;
 
+    // the resource is changed in every language, since the others might fall back to it
+    let (content_id, _) = &id;
+    cache::invalidate(
+        cache,
+        &[
+            tags::resource(&K::TYPE, &content_id.to_string()),
+            tags::list(&K::TYPE),
+        ],
+    )
+    .await;
+
     Ok(id)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::entities::{
-        ArticleData, CategoryData, ContactData, HomeData, MemberData, ServiceData,
-    };
+    use crate::cache::{resource_tags, InMemoryCache, NoCache};
+    use crate::domain::entities::{ArticleData, CategoryData, FaqData};
+    use crate::domain::registry::{ArticleKind, CategoryKind, FaqKind, MemberKind};
     use crate::domain::resources::test_helpers::tests::{
-        create_resources, create_some_fake_data_and_return_uow,
+        create_a_category_tree, create_some_fake_data_and_return_uow, for_each_resource_kind,
+        FakeResource,
     };
-    use serde_json::json;
+    use crate::uow::InMemory;
+    use std::num::NonZeroUsize;
+    use std::time::Duration;
     use ulid::Ulid;
 
-    fn update_resource(resource: Resource) -> Resource {
-        match resource {
-            Resource::Member(m) => Resource::Member(MemberData {
-                name: "new name".to_string(),
-                ..m
-            }),
-            Resource::Service(s) => Resource::Service(ServiceData {
-                title: "new title".to_string(),
-                ..s
-            }),
-            Resource::Home(_) => Resource::Home(HomeData {
-                data: "new data".to_string(),
-            }),
-            Resource::Contact(_) => Resource::Contact(ContactData {
-                data: json!({
-                    "address": "new address",
-                }),
-            }),
-            Resource::Article(a) => Resource::Article(ArticleData {
-                title: "new title".to_string(),
-                ..a
-            }),
-            Resource::Category(_) => {
-                Resource::Category(CategoryData::new(None, "new category".to_string()))
-            }
+    async fn update<K: ResourceKind>(
+        uow: InMemory,
+        id: String,
+        data: K::Data,
+        language: &str,
+    ) -> Result<(ContentID, i32), Error> {
+        update_version::<K>(uow, id, data, language, None).await
+    }
+
+    async fn update_version<K: ResourceKind>(
+        uow: InMemory,
+        id: String,
+        data: K::Data,
+        language: &str,
+        version: Option<i32>,
+    ) -> Result<(ContentID, i32), Error> {
+        update_cached::<K>(uow, &NoCache, id, data, language, version).await
+    }
+
+    async fn update_cached<K: ResourceKind>(
+        uow: InMemory,
+        cache: &dyn IResourceCache,
+        id: String,
+        data: K::Data,
+        language: &str,
+        version: Option<i32>,
+    ) -> Result<(ContentID, i32), Error> {
+        let req = Request::<K> {
+            id,
+            data,
+            language: language.to_string(),
+            seq: 0,
+            version,
+            owner: None,
+            actor: None,
+        };
+
+        execute(Mutex::new(uow), cache, req).await
+    }
+
+    async fn assert_updated<K: FakeResource>() {
+        let (uow, id) = create_some_fake_data_and_return_uow::<K>().await;
+
+        let res = update::<K>(uow, id.to_string(), K::modify(K::fake()), "zh").await;
+        // TODO: check the updated data
+        assert!(res.is_ok());
+    }
+
+    async fn assert_not_found<K: FakeResource>() {
+        let (uow, _) = create_some_fake_data_and_return_uow::<K>().await;
+
+        let res = update::<K>(uow, Ulid::new().to_string(), K::modify(K::fake()), "zh").await;
+        match res {
+            Err(Error::NotFound) => {}
+            _ => unreachable!(),
+        }
+    }
+
+    async fn assert_unknown_error<K: FakeResource>() {
+        let (uow, id) = create_some_fake_data_and_return_uow::<K>().await;
+
+        let res = update::<K>(uow.with_error(), id.to_string(), K::modify(K::fake()), "zh").await;
+        match res {
+            Err(Error::Unknown(_)) => {}
+            _ => unreachable!(),
         }
     }
 
     #[tokio::test]
     async fn it_should_update_successfully_otherwise() {
-        let resources = create_resources();
+        for_each_resource_kind!(assert_updated);
+    }
 
-        for resource in resources.clone() {
-            let (uow, r) = create_some_fake_data_and_return_uow(vec![resource]).await;
+    #[tokio::test]
+    async fn it_should_return_a_not_found_error_when_resource_does_not_exist() {
+        for_each_resource_kind!(assert_not_found);
+    }
 
-            let (id, resource) = r[0].clone();
+    #[tokio::test]
+    async fn it_should_return_an_unknown_error_when_unexpected_error_has_encountered() {
+        for_each_resource_kind!(assert_unknown_error);
+    }
 
-            let updated_resource = update_resource(resource);
+    #[tokio::test]
+    async fn it_should_create_a_new_content_for_different_language() {
+        let (uow, id) = create_some_fake_data_and_return_uow::<MemberKind>().await;
+
+        let res = update::<MemberKind>(uow, id.to_string(), MemberKind::fake(), "en").await;
+        // TODO: check the updated data
+        assert!(res.is_ok());
+    }
 
-            let req = Request {
-                id: id.to_string().clone(),
-                data: updated_resource.clone(),
-                language: "zh".to_string(),
-                seq: 0,
-            };
+    #[tokio::test]
+    async fn it_should_update_when_the_version_matches() {
+        let (uow, id) = create_some_fake_data_and_return_uow::<MemberKind>().await;
+        let data = MemberKind::modify(MemberKind::fake());
 
-            let res = execute(Mutex::new(uow), req).await;
-            // TODO: check the updated data
-            assert!(res.is_ok());
+        match update_version::<MemberKind>(uow, id.to_string(), data, "zh", Some(1)).await {
+            Ok((_, version)) => assert_eq!(version, 2),
+            _ => unreachable!(),
         }
     }
 
     #[tokio::test]
-    async fn it_should_return_a_not_found_error_when_resource_does_not_exist() {
-        let resources = create_resources();
-
-        for resource in resources.clone() {
-            let (uow, r) = create_some_fake_data_and_return_uow(vec![resource]).await;
+    async fn it_should_bump_the_version_when_the_resource_is_moved() {
+        let (uow, id) = create_some_fake_data_and_return_uow::<MemberKind>().await;
+        let req = Request::<MemberKind> {
+            id: id.to_string(),
+            data: MemberKind::modify(MemberKind::fake()),
+            language: "zh".to_string(),
+            seq: 1,
+            version: Some(1),
+            owner: None,
+            actor: None,
+        };
 
-            let (_, resource) = r[0].clone();
+        // the content is changed and moved
+        match execute(Mutex::new(uow), &NoCache, req).await {
+            Ok((_, version)) => assert_eq!(version, 3),
+            _ => unreachable!(),
+        }
+    }
 
-            let updated_resource = update_resource(resource);
+    #[tokio::test]
+    async fn it_should_bump_the_versions_of_the_moved_resources_in_every_language() {
+        let (mut uow, id) = create_some_fake_data_and_return_uow::<MemberKind>().await;
+        let resource_id = ResourceID::try_from(id.to_string()).unwrap();
+        let data = ContentData::try_from_data::<MemberKind>(&MemberKind::fake()).unwrap();
+        uow.content_repository()
+            .insert(id.clone(), data, Language::EN)
+            .await
+            .unwrap();
 
-            let req = Request {
-                id: Ulid::new().to_string(),
-                data: updated_resource,
-                language: "zh".to_string(),
-                seq: 0,
-            };
+        let moved = uow
+            .resource_repository()
+            .shift_seq(&MemberKind::TYPE, 0, 1)
+            .await
+            .unwrap();
+        assert_eq!(moved, vec![(resource_id.clone(), 1)]);
+        // the sequence stays the same, so do the versions
+        assert!(!uow
+            .resource_repository()
+            .update_seq(&resource_id, 1)
+            .await
+            .unwrap());
 
-            let res = execute(Mutex::new(uow), req).await;
-            match res {
-                Err(Error::NotFound) => {}
-                _ => unreachable!(),
-            }
+        for language in [Language::ZH, Language::EN] {
+            let entity = uow
+                .get_resource::<MemberKind>(&resource_id, &language, &[])
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(entity.version, 2);
         }
     }
 
     #[tokio::test]
-    async fn it_should_return_an_unknown_error_when_unexpected_error_has_encountered() {
-        let resources = create_resources();
+    async fn it_should_return_a_version_mismatch_error_when_the_content_has_been_changed() {
+        let (uow, id) = create_some_fake_data_and_return_uow::<MemberKind>().await;
+        let data = MemberKind::modify(MemberKind::fake());
 
-        for resource in resources.clone() {
-            let (uow, r) = create_some_fake_data_and_return_uow(vec![resource]).await;
+        match update_version::<MemberKind>(uow, id.to_string(), data, "zh", Some(3)).await {
+            Err(Error::VersionMismatch { current: 1 }) => {}
+            _ => unreachable!(),
+        }
+    }
 
-            let (id, resource) = r[0].clone();
+    #[tokio::test]
+    async fn it_should_invalidate_the_cached_entries_of_the_resource_only() {
+        let (uow, id) = create_some_fake_data_and_return_uow::<MemberKind>().await;
+        let another = Ulid::new().to_string();
+        let cache = InMemoryCache::new(NonZeroUsize::new(10).unwrap(), Duration::from_secs(60));
+        let value = serde_json::json!({});
+        for key in [id.to_string(), another.clone()] {
+            let tags = resource_tags(&MemberKind::TYPE, &key);
+            cache.set(&key, &value, &tags).await.unwrap();
+        }
 
-            let updated_resource = update_resource(resource);
+        let data = MemberKind::modify(MemberKind::fake());
+        let res = update_cached::<MemberKind>(uow, &cache, id.to_string(), data, "zh", None).await;
+        assert!(res.is_ok());
 
-            let req = Request {
-                id: id.to_string().clone(),
-                data: updated_resource.clone(),
-                language: "zh".to_string(),
-                seq: 0,
-            };
+        assert_eq!(cache.get(&id.to_string()).await.unwrap(), None);
+        assert_eq!(cache.get(&another).await.unwrap(), Some(value));
+    }
 
-            let res = execute(Mutex::new(uow.with_error()), req).await;
-            match res {
-                Err(Error::Unknown(_)) => {}
-                _ => unreachable!(),
-            }
+    #[tokio::test]
+    async fn it_should_return_an_invalid_reference_error_when_the_category_does_not_exist() {
+        let (uow, id) = create_some_fake_data_and_return_uow::<ArticleKind>().await;
+        let data = ArticleData::new(
+            Some(Ulid::new().to_string()),
+            "title".to_string(),
+            "data".to_string(),
+        );
+
+        match update::<ArticleKind>(uow, id.to_string(), data, "zh").await {
+            Err(Error::InvalidReference("category_id")) => {}
+            _ => unreachable!(),
         }
     }
 
     #[tokio::test]
-    async fn it_should_create_a_new_content_for_different_language() {
-        let resources = create_resources();
-        let resource = resources.first().unwrap().clone();
+    async fn it_should_return_an_invalid_reference_error_when_a_service_of_the_faq_does_not_exist()
+    {
+        let (uow, id) = create_some_fake_data_and_return_uow::<FaqKind>().await;
+        let data = FaqData::new(
+            "question".to_string(),
+            "answer".to_string(),
+            vec![Ulid::new().to_string()],
+        );
+
+        match update::<FaqKind>(uow, id.to_string(), data, "zh").await {
+            Err(Error::InvalidReference("service_ids")) => {}
+            _ => unreachable!(),
+        }
+    }
 
-        let (uow, r) = create_some_fake_data_and_return_uow(vec![resource]).await;
+    #[tokio::test]
+    async fn it_should_refuse_a_parent_which_leads_back_to_the_category() {
+        // the category itself, its child and its grandchild can't be its parent
+        for parent in 0..3 {
+            let (uow, tree) = create_a_category_tree().await;
+            let data = CategoryData::new(None, "root".to_string(), Some(tree[parent].to_string()));
+
+            match update::<CategoryKind>(uow, tree[0].to_string(), data, "zh").await {
+                Err(Error::Invalid(fields)) => {
+                    assert_eq!(fields, vec![FieldError::new("parent_id", "cycle")])
+                }
+                _ => unreachable!(),
+            }
+        }
 
-        let (id, resource) = r[0].clone();
+        // the grandchild can be moved up to the root though
+        let (uow, tree) = create_a_category_tree().await;
+        let data = CategoryData::new(None, "grandchild".to_string(), Some(tree[0].to_string()));
+        assert!(update::<CategoryKind>(uow, tree[2].to_string(), data, "zh")
+            .await
+            .is_ok());
+    }
 
-        let req = Request {
-            id: id.clone().to_string(),
-            data: resource,
-            language: "en".to_string(),
+    async fn update_owned(
+        uow: InMemory,
+        id: String,
+        owner: UserID,
+    ) -> Result<(ContentID, i32), Error> {
+        let req = Request::<ArticleKind> {
+            id,
+            data: ArticleKind::modify(ArticleKind::fake()),
+            language: "zh".to_string(),
             seq: 0,
+            version: None,
+            owner: Some(owner),
+            actor: None,
         };
 
-        let uow = Mutex::new(uow);
+        execute(Mutex::new(uow), &NoCache, req).await
+    }
 
-        let res = execute(uow, req).await;
-        // TODO: check the updated data
-        assert!(res.is_ok());
+    /// Creates an article which is written by the author
+    async fn create_an_article_of(author: &UserID) -> (InMemory, ResourceID) {
+        let mut uow = InMemory::new();
+        let id = ResourceID::try_from(Ulid::new().to_string()).unwrap();
+        uow.resource_repository()
+            .insert(id.clone(), ArticleKind::TYPE, 0, Some(author.clone()))
+            .await
+            .unwrap();
+        uow.content_repository()
+            .insert(
+                ContentID::from(id.clone()),
+                ContentData::try_from_data::<ArticleKind>(&ArticleKind::fake()).unwrap(),
+                Language::ZH,
+            )
+            .await
+            .unwrap();
+
+        (uow, id)
+    }
+
+    #[tokio::test]
+    async fn it_should_let_the_owner_change_the_own_resource_only() {
+        let author = UserID::from(uuid::Uuid::new_v4());
+
+        let (uow, id) = create_an_article_of(&author).await;
+        let someone = UserID::from(uuid::Uuid::new_v4());
+        match update_owned(uow, id.to_string(), someone).await {
+            Err(Error::Forbidden) => {}
+            _ => unreachable!(),
+        }
+
+        let (uow, id) = create_an_article_of(&author).await;
+        assert!(update_owned(uow, id.to_string(), author).await.is_ok());
     }
 }
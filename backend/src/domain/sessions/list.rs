@@ -0,0 +1,28 @@
+use crate::domain::entities::{Session, UserID};
+use crate::repositories::ISessionRepository;
+use tokio::sync::Mutex;
+
+pub struct Request {
+    pub user_id: UserID,
+}
+
+pub enum Error {
+    Unknown(String),
+}
+
+/// Lists where the user is logged in, the latest used session first
+pub async fn execute(
+    req: Request,
+    session_repo: Mutex<impl ISessionRepository + Sync + Send>,
+) -> Result<Vec<Session>, Error> {
+    let mut sessions = session_repo
+        .lock()
+        .await
+        .list(&req.user_id)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.last_seen_at));
+
+    Ok(sessions)
+}
@@ -0,0 +1,86 @@
+use crate::domain::entities::{Session, SessionID, User, UserID};
+use crate::repositories::ISessionRepository;
+use sha2::{Digest, Sha256};
+
+pub mod list;
+
+pub mod refresh;
+
+pub mod revoke;
+
+pub mod revoke_all;
+
+pub mod start;
+
+/** How long the tokens of the sessions last.
+*
+* The access tokens are short-lived, the session lasts as long as its refresh token is rotated
+* before it expires.
+*/
+#[derive(Debug, Clone)]
+pub struct SessionPolicy {
+    pub access_token_ttl: chrono::Duration,
+    pub refresh_token_ttl: chrono::Duration,
+}
+
+/// A session along with its new refresh token, the token is only known by the client
+pub struct Issued {
+    pub session: Session,
+    pub refresh_token: String,
+    /// The user as it is now, its role is embedded in the access token
+    pub user: User,
+}
+
+/// Ends the sessions of the user except the given one, it returns how many sessions are ended
+pub(crate) async fn end_sessions(
+    session_repo: &impl ISessionRepository,
+    user_id: &UserID,
+    except: Option<&SessionID>,
+) -> anyhow::Result<usize> {
+    let sessions = session_repo.list(user_id).await?;
+
+    let mut ended = 0;
+    for session in sessions
+        .iter()
+        .filter(|session| except != Some(&session.id))
+    {
+        session_repo.revoke(&session.id).await?;
+        ended += 1;
+    }
+
+    Ok(ended)
+}
+
+/// The refresh token is `{session ID}.{secret}`, only the hash of the secret is kept
+struct RefreshToken {
+    session_id: SessionID,
+    secret: String,
+}
+
+impl RefreshToken {
+    fn generate(session_id: SessionID) -> Self {
+        Self {
+            session_id,
+            secret: hex::encode(rand::random::<[u8; 32]>()),
+        }
+    }
+
+    fn parse(token: &str) -> Option<Self> {
+        let (session_id, secret) = token.split_once('.')?;
+
+        Some(Self {
+            session_id: SessionID::try_from(session_id.to_string()).ok()?,
+            secret: secret.to_string(),
+        })
+    }
+
+    fn hash(&self) -> String {
+        hex::encode(Sha256::digest(self.secret.as_bytes()))
+    }
+}
+
+impl std::fmt::Display for RefreshToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.session_id, self.secret)
+    }
+}
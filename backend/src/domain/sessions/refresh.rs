@@ -0,0 +1,257 @@
+use crate::domain::entities::Session;
+use crate::domain::sessions::{Issued, RefreshToken, SessionPolicy};
+use crate::repositories::{ISessionRepository, IUserRepository, Rotation};
+use chrono::Utc;
+use tokio::sync::Mutex;
+
+pub struct Request {
+    pub refresh_token: String,
+}
+
+pub enum Error {
+    /// The token is malformed, expired, revoked or it has been used
+    InvalidToken,
+    Unknown(String),
+}
+
+/** Rotates the refresh token of a session, so a new access token can be issued.
+*
+* A refresh token can only be used once. If a used one is given again, either the client or an
+* attacker holds a stolen token, so the session is revoked. The user is read again, so the
+* changes of the role take effect on the next refresh.
+*/
+pub async fn execute(
+    req: Request,
+    policy: &SessionPolicy,
+    user_repo: Mutex<impl IUserRepository + Sync + Send>,
+    session_repo: Mutex<impl ISessionRepository + Sync + Send>,
+) -> Result<Issued, Error> {
+    let token = RefreshToken::parse(&req.refresh_token).ok_or(Error::InvalidToken)?;
+    let session_repo = session_repo.lock().await;
+
+    let session = session_repo
+        .get(&token.session_id)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?
+        .ok_or(Error::InvalidToken)?;
+
+    // the sessions of the deleted or disabled users can't be refreshed
+    let user = user_repo
+        .lock()
+        .await
+        .get(&session.user_id)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?
+        .filter(|user| !user.disabled)
+        .ok_or(Error::InvalidToken)?;
+
+    let now = Utc::now();
+    let session = Session {
+        refreshed_at: now,
+        last_seen_at: now,
+        expires_at: now + policy.refresh_token_ttl,
+        ..session
+    };
+    let next = RefreshToken::generate(session.id.clone());
+
+    let rotation = session_repo
+        .rotate(&session, &token.hash(), &next.hash())
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    match rotation {
+        Rotation::Rotated => Ok(Issued {
+            session,
+            refresh_token: next.to_string(),
+            user,
+        }),
+        Rotation::Reused => {
+            tracing::warn!(session_id = %session.id, "A used refresh token is given");
+            Err(Error::InvalidToken)
+        }
+        Rotation::Missing => Err(Error::InvalidToken),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{Role, UserID};
+    use crate::domain::users::authentication::Attempt;
+    use crate::repositories::{InMemorySessionRepository, InMemoryUserRepository};
+    use secrecy::SecretBox;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn policy() -> SessionPolicy {
+        SessionPolicy {
+            access_token_ttl: chrono::Duration::minutes(15),
+            refresh_token_ttl: chrono::Duration::days(7),
+        }
+    }
+
+    fn user_id() -> UserID {
+        UserID::try_from("47ff8e18-7732-4e8c-a377-ec7491bd93d1".to_string()).unwrap()
+    }
+
+    async fn user_repo() -> Mutex<InMemoryUserRepository> {
+        let user_repo = InMemoryUserRepository::new();
+        let password = SecretBox::new(Box::new("password".to_string()));
+        user_repo
+            .add_credentials(user_id(), "username".to_string(), password)
+            .await;
+        user_repo.add_role(user_id(), Role::Editor).await;
+
+        Mutex::new(user_repo)
+    }
+
+    async fn start(session_repo: &InMemorySessionRepository) -> Issued {
+        let req = crate::domain::sessions::start::Request {
+            user_id: user_id(),
+            attempt: Attempt {
+                ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+                user_agent: None,
+            },
+        };
+
+        match crate::domain::sessions::start::execute(
+            req,
+            &policy(),
+            user_repo().await,
+            Mutex::new(session_repo.clone()),
+        )
+        .await
+        {
+            Ok(issued) => issued,
+            Err(_) => unreachable!(),
+        }
+    }
+
+    async fn refresh(
+        session_repo: &InMemorySessionRepository,
+        token: &str,
+    ) -> Result<Issued, Error> {
+        let req = Request {
+            refresh_token: token.to_string(),
+        };
+
+        execute(
+            req,
+            &policy(),
+            user_repo().await,
+            Mutex::new(session_repo.clone()),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_should_rotate_the_refresh_token() {
+        let session_repo = InMemorySessionRepository::new();
+        let started = start(&session_repo).await;
+
+        let refreshed = match refresh(&session_repo, &started.refresh_token).await {
+            Ok(refreshed) => refreshed,
+            Err(_) => unreachable!(),
+        };
+        assert_eq!(refreshed.session.id, started.session.id);
+        assert_ne!(refreshed.refresh_token, started.refresh_token);
+        assert_eq!(refreshed.user.role, Role::Editor);
+
+        assert!(refresh(&session_repo, &refreshed.refresh_token)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn it_should_revoke_the_session_when_a_used_refresh_token_is_given() {
+        let session_repo = InMemorySessionRepository::new();
+        let started = start(&session_repo).await;
+        let other = start(&session_repo).await;
+
+        let refreshed = match refresh(&session_repo, &started.refresh_token).await {
+            Ok(refreshed) => refreshed,
+            Err(_) => unreachable!(),
+        };
+
+        let res = refresh(&session_repo, &started.refresh_token).await;
+        assert!(matches!(res, Err(Error::InvalidToken)));
+
+        // the newest token of the session is refused too
+        let res = refresh(&session_repo, &refreshed.refresh_token).await;
+        assert!(matches!(res, Err(Error::InvalidToken)));
+        assert!(session_repo
+            .get(&started.session.id)
+            .await
+            .unwrap()
+            .is_none());
+
+        assert!(refresh(&session_repo, &other.refresh_token).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_the_malformed_refresh_tokens() {
+        let session_repo = InMemorySessionRepository::new();
+
+        for token in ["", "token", "not-a-uuid.secret"] {
+            let res = refresh(&session_repo, token).await;
+            assert!(matches!(res, Err(Error::InvalidToken)));
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_return_an_unknown_error_when_unexpected_error_encountered() {
+        let session_repo = InMemorySessionRepository::new();
+        let started = start(&session_repo).await;
+
+        let req = Request {
+            refresh_token: started.refresh_token,
+        };
+        let res = execute(
+            req,
+            &policy(),
+            user_repo().await,
+            Mutex::new(session_repo.with_error()),
+        )
+        .await;
+
+        assert!(matches!(res, Err(Error::Unknown(_))));
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_the_refresh_tokens_of_the_disabled_users() {
+        let session_repo = InMemorySessionRepository::new();
+        let started = start(&session_repo).await;
+
+        let user_repo = user_repo().await;
+        user_repo
+            .lock()
+            .await
+            .set_disabled(&user_id(), true)
+            .await
+            .unwrap();
+        let req = Request {
+            refresh_token: started.refresh_token,
+        };
+        let res = execute(req, &policy(), user_repo, Mutex::new(session_repo.clone())).await;
+
+        assert!(matches!(res, Err(Error::InvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_the_refresh_tokens_of_the_deleted_users() {
+        let session_repo = InMemorySessionRepository::new();
+        let started = start(&session_repo).await;
+
+        let req = Request {
+            refresh_token: started.refresh_token,
+        };
+        let res = execute(
+            req,
+            &policy(),
+            Mutex::new(InMemoryUserRepository::new()),
+            Mutex::new(session_repo.clone()),
+        )
+        .await;
+
+        assert!(matches!(res, Err(Error::InvalidToken)));
+    }
+}
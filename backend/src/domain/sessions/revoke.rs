@@ -0,0 +1,109 @@
+use crate::domain::entities::{SessionID, UserID};
+use crate::repositories::ISessionRepository;
+use tokio::sync::Mutex;
+
+pub struct Request {
+    pub session_id: String,
+    /// The user who owns the session
+    pub user_id: UserID,
+}
+
+pub enum Error {
+    BadRequest,
+    /// The session has ended, or it's not of the user
+    NotFound,
+    Unknown(String),
+}
+
+/// Ends a session of the user, its access token and refresh token can't be used anymore
+pub async fn execute(
+    req: Request,
+    session_repo: Mutex<impl ISessionRepository + Sync + Send>,
+) -> Result<(), Error> {
+    let session_id = SessionID::try_from(req.session_id).map_err(|_| Error::BadRequest)?;
+    let session_repo = session_repo.lock().await;
+
+    let session = session_repo
+        .get(&session_id)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+    if session.is_none_or(|session| session.user_id != req.user_id) {
+        return Err(Error::NotFound);
+    }
+
+    session_repo
+        .revoke(&session_id)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{Session, UserID};
+    use crate::repositories::InMemorySessionRepository;
+    use chrono::Utc;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn session(user_id: &UserID) -> Session {
+        let now = Utc::now();
+
+        Session {
+            id: SessionID::new(),
+            user_id: user_id.clone(),
+            ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            user_agent: None,
+            created_at: now,
+            refreshed_at: now,
+            last_seen_at: now,
+            expires_at: now + chrono::Duration::days(7),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_revoke_only_the_session() {
+        let session_repo = InMemorySessionRepository::new();
+        let user_id = UserID::from(uuid::Uuid::new_v4());
+        let current = session(&user_id);
+        let other = session(&user_id);
+        session_repo.save(&current, "current").await.unwrap();
+        session_repo.save(&other, "other").await.unwrap();
+
+        let req = Request {
+            session_id: current.id.to_string(),
+            user_id,
+        };
+        let res = execute(req, Mutex::new(session_repo.clone())).await;
+
+        assert!(res.is_ok());
+        assert!(session_repo.get(&current.id).await.unwrap().is_none());
+        assert_eq!(session_repo.get(&other.id).await.unwrap(), Some(other));
+    }
+
+    #[tokio::test]
+    async fn it_should_not_revoke_the_sessions_of_the_other_users() {
+        let session_repo = InMemorySessionRepository::new();
+        let other = session(&UserID::from(uuid::Uuid::new_v4()));
+        session_repo.save(&other, "other").await.unwrap();
+
+        let req = Request {
+            session_id: other.id.to_string(),
+            user_id: UserID::from(uuid::Uuid::new_v4()),
+        };
+        let res = execute(req, Mutex::new(session_repo.clone())).await;
+
+        assert!(matches!(res, Err(Error::NotFound)));
+        assert!(session_repo.get(&other.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn it_should_be_bad_request_when_the_session_id_is_invalid() {
+        let req = Request {
+            session_id: "session".to_string(),
+            user_id: UserID::from(uuid::Uuid::new_v4()),
+        };
+        let res = execute(req, Mutex::new(InMemorySessionRepository::new())).await;
+
+        assert!(matches!(res, Err(Error::BadRequest)));
+    }
+}
@@ -0,0 +1,198 @@
+use crate::domain::audit::{entry, Actor};
+use crate::domain::entities::{AuditAction, SessionID, UserID};
+use crate::domain::sessions::end_sessions;
+use crate::repositories::{IAuditRepository, ISessionRepository};
+use crate::uow::IUserUnitOfWork;
+use serde_json::json;
+use tokio::sync::Mutex;
+
+pub struct Request {
+    /// The user whose sessions are ended
+    pub user_id: String,
+    /// The session which is kept, usually the current one
+    pub except: Option<SessionID>,
+    /// The user who ends the sessions
+    pub by: Actor,
+    /// Whether the user can end the sessions of the other users
+    pub privileged: bool,
+}
+
+pub enum Error {
+    BadRequest,
+    /// Only the privileged users can end the sessions of the other users
+    Forbidden,
+    Unknown(String),
+}
+
+/// Ends the sessions of a user, it returns how many sessions are ended.
+///
+/// Ending the sessions of another user is audited, the entry is written before the sessions are
+/// ended and it's committed after them.
+pub async fn execute<IUnitOfWork>(
+    req: Request,
+    session_repo: Mutex<impl ISessionRepository + Sync + Send>,
+    uow: Mutex<IUnitOfWork>,
+) -> Result<usize, Error>
+where
+    IUnitOfWork: IUserUnitOfWork,
+{
+    let user_id = uuid::Uuid::parse_str(&req.user_id)
+        .map(UserID::from)
+        .map_err(|_| Error::BadRequest)?;
+    let oneself = user_id == req.by.user_id;
+    if !oneself && !req.privileged {
+        return Err(Error::Forbidden);
+    }
+
+    let session_repo = session_repo.lock().await;
+    if !oneself {
+        let revoking = session_repo
+            .list(&user_id)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?
+            .len();
+        let audit = entry(
+            Some(&req.by),
+            AuditAction::RevokeSessions,
+            "user",
+            user_id.to_string(),
+        )
+        .with_snapshots(None, Some(json!({ "revoked": revoking })));
+        uow.lock()
+            .await
+            .audit_repository()
+            .save(&audit)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
+    }
+
+    let revoked = end_sessions(&*session_repo, &user_id, req.except.as_ref())
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    uow.into_inner()
+        .commit()
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    Ok(revoked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::Session;
+    use crate::repositories::{
+        InMemoryAuditRepository, InMemorySessionRepository, InMemoryUserRepository,
+    };
+    use crate::uow::user::InMemory;
+    use chrono::Utc;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn actor(user_id: &UserID) -> Actor {
+        Actor {
+            user_id: user_id.clone(),
+            ip: None,
+        }
+    }
+
+    async fn start(session_repo: &InMemorySessionRepository, user_id: &UserID) -> Session {
+        let now = Utc::now();
+        let session = Session {
+            id: SessionID::new(),
+            user_id: user_id.clone(),
+            ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            user_agent: None,
+            created_at: now,
+            refreshed_at: now,
+            last_seen_at: now,
+            expires_at: now + chrono::Duration::days(7),
+        };
+        session_repo.save(&session, "token").await.unwrap();
+
+        session
+    }
+
+    #[tokio::test]
+    async fn it_should_revoke_the_other_sessions_of_the_user() {
+        let session_repo = InMemorySessionRepository::new();
+        let user_id = UserID::from(uuid::Uuid::new_v4());
+        let current = start(&session_repo, &user_id).await;
+        let _ = start(&session_repo, &user_id).await;
+        let _ = start(&session_repo, &user_id).await;
+        let someone = start(&session_repo, &UserID::from(uuid::Uuid::new_v4())).await;
+
+        let req = Request {
+            user_id: user_id.to_string(),
+            except: Some(current.id.clone()),
+            by: actor(&user_id),
+            privileged: false,
+        };
+        let audit_repo = InMemoryAuditRepository::new();
+        let res = execute(
+            req,
+            Mutex::new(session_repo.clone()),
+            Mutex::new(InMemory::new(
+                InMemoryUserRepository::new(),
+                audit_repo.clone(),
+            )),
+        )
+        .await;
+
+        assert!(matches!(res, Ok(2)));
+        assert!(audit_repo.entries().await.is_empty());
+        assert_eq!(session_repo.list(&user_id).await.unwrap(), vec![current]);
+        assert!(session_repo.get(&someone.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn it_should_let_only_the_privileged_users_revoke_the_sessions_of_the_others() {
+        let session_repo = InMemorySessionRepository::new();
+        let admin = UserID::from(uuid::Uuid::new_v4());
+        let user_id = UserID::from(uuid::Uuid::new_v4());
+        let _ = start(&session_repo, &user_id).await;
+
+        let req = Request {
+            user_id: user_id.to_string(),
+            except: None,
+            by: actor(&admin),
+            privileged: false,
+        };
+        let audit_repo = InMemoryAuditRepository::new();
+        let res = execute(
+            req,
+            Mutex::new(session_repo.clone()),
+            Mutex::new(InMemory::new(
+                InMemoryUserRepository::new(),
+                audit_repo.clone(),
+            )),
+        )
+        .await;
+        assert!(matches!(res, Err(Error::Forbidden)));
+
+        let req = Request {
+            user_id: user_id.to_string(),
+            except: None,
+            by: actor(&admin),
+            privileged: true,
+        };
+        let res = execute(
+            req,
+            Mutex::new(session_repo.clone()),
+            Mutex::new(InMemory::new(
+                InMemoryUserRepository::new(),
+                audit_repo.clone(),
+            )),
+        )
+        .await;
+        assert!(matches!(res, Ok(1)));
+        assert!(session_repo.list(&user_id).await.unwrap().is_empty());
+
+        let entries = audit_repo.entries().await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, AuditAction::RevokeSessions);
+        assert_eq!(entries[0].user_id, Some(admin));
+        assert_eq!(entries[0].target_id, user_id.to_string());
+        assert_eq!(entries[0].after, Some(json!({ "revoked": 1 })));
+    }
+}
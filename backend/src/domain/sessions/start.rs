@@ -0,0 +1,58 @@
+use crate::domain::entities::{Session, SessionID, UserID};
+use crate::domain::sessions::{Issued, RefreshToken, SessionPolicy};
+use crate::domain::users::authentication::Attempt;
+use crate::repositories::{ISessionRepository, IUserRepository};
+use chrono::Utc;
+use tokio::sync::Mutex;
+
+pub struct Request {
+    pub user_id: UserID,
+    /// Where the user logs in
+    pub attempt: Attempt,
+}
+
+pub enum Error {
+    Unknown(String),
+}
+
+/// Starts a session of the user who has logged in, the other sessions of the user are kept
+pub async fn execute(
+    req: Request,
+    policy: &SessionPolicy,
+    user_repo: Mutex<impl IUserRepository + Sync + Send>,
+    session_repo: Mutex<impl ISessionRepository + Sync + Send>,
+) -> Result<Issued, Error> {
+    let user = user_repo
+        .lock()
+        .await
+        .get(&req.user_id)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?
+        .ok_or_else(|| Error::Unknown("The user doesn't exist".to_string()))?;
+
+    let now = Utc::now();
+    let session = Session {
+        id: SessionID::new(),
+        user_id: req.user_id,
+        ip: req.attempt.ip,
+        user_agent: req.attempt.user_agent,
+        created_at: now,
+        refreshed_at: now,
+        last_seen_at: now,
+        expires_at: now + policy.refresh_token_ttl,
+    };
+    let refresh_token = RefreshToken::generate(session.id.clone());
+
+    session_repo
+        .lock()
+        .await
+        .save(&session, &refresh_token.hash())
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    Ok(Issued {
+        session,
+        refresh_token: refresh_token.to_string(),
+        user,
+    })
+}
@@ -1,8 +1,14 @@
-use crate::domain::entities::UserID;
-use crate::repositories::IUserRepository;
-use anyhow::{anyhow, Context};
-use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use crate::domain::entities::{LoginAttempt, LoginOutcome, User, UserID};
+use crate::domain::users::password_hashing::PasswordHasher;
+use crate::domain::users::totp;
+use crate::repositories::{
+    Failures, ILoginAttemptRepository, IRecoveryCodeRepository, IUserRepository,
+};
+use chrono::{DateTime, Utc};
 use secrecy::{ExposeSecret, SecretBox};
+use std::future::Future;
+use std::net::IpAddr;
+use std::time::Duration;
 use tokio::sync::Mutex;
 
 pub struct Credentials {
@@ -10,67 +16,275 @@ pub struct Credentials {
     pub password: SecretBox<String>,
 }
 
+/// Where the attempt comes from
+#[derive(Debug, Clone)]
+pub struct Attempt {
+    pub ip: IpAddr,
+    pub user_agent: Option<String>,
+}
+
+/** How the failed logins lock the usernames and the IPs out.
+*
+* The failures are counted in the window, the failures of a username are forgiven when it logs in
+* or it's unlocked by an admin. Every failure delays the next attempt twice as long as the last one.
+*/
+#[derive(Debug, Clone)]
+pub struct LockoutPolicy {
+    pub window: chrono::Duration,
+    pub max_failures_per_username: usize,
+    pub max_failures_per_ip: usize,
+    /// How long the attempts are refused since the last failure
+    pub lockout: chrono::Duration,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl LockoutPolicy {
+    fn delay(&self, failures: usize) -> Duration {
+        match failures {
+            0 => Duration::ZERO,
+            n => self
+                .base_delay
+                .saturating_mul(2u32.saturating_pow(n as u32 - 1))
+                .min(self.max_delay),
+        }
+    }
+
+    /// Until when the attempts are refused, if the failures reach the limit
+    fn locked_until(&self, failures: &Failures, limit: usize) -> Option<DateTime<Utc>> {
+        match failures.last_at {
+            Some(last_at) if failures.count >= limit => Some(last_at + self.lockout),
+            _ => None,
+        }
+    }
+}
+
 pub enum Error {
     InvalidCredentials,
+    /// The username or the IP has failed too many times
+    Locked {
+        until: DateTime<Utc>,
+    },
     Unknown(String),
 }
 
+/** Verifies the password of the user, the attempt is refused during the lockout.
+*
+* The password of a user with a second factor only challenges the user for a code, so the failures
+* of the username aren't forgiven until the code is given too. The outdated hash of the password is
+* replaced once it's verified.
+*/
 pub async fn validate_credentials(
     credentials: Credentials,
+    attempt: Attempt,
+    policy: &LockoutPolicy,
+    hasher: &PasswordHasher,
     user_repo: Mutex<impl IUserRepository + Sync + Send>,
-) -> Result<UserID, Error> {
-    let mut id = None;
-    let mut expected_password_hash = SecretBox::new(Box::new(
-        "$argon2id$v=19$m=15000,t=2,p=1$\
-        gZiV/M1gPc22ElAH/Jh1Hw$\
-        CWOrkoo7oJBQ/iyh7uJ0LO2aLEfrHwTWllSAxT0zRno"
-            .to_string(),
-    ));
-
-    if let Some((user_id, password_hash)) = user_repo
-        .lock()
+    attempt_repo: Mutex<impl ILoginAttemptRepository + Sync + Send>,
+) -> Result<User, Error> {
+    let verified = async {
+        let user_repo = user_repo.lock().await;
+        let id = verify(&credentials, hasher, &*user_repo).await?;
+        let user = user_repo
+            .get(&id)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?
+            .ok_or(Error::InvalidCredentials)?;
+
+        let outcome = match user.totp_enabled {
+            true => LoginOutcome::Challenged,
+            false => LoginOutcome::Succeeded,
+        };
+        Ok((user, outcome))
+    };
+
+    guard(
+        &credentials.username,
+        &attempt,
+        policy,
+        attempt_repo,
+        verified,
+    )
+    .await
+}
+
+/** Verifies the code of the second factor after the password, it's either the code of the
+* authenticator app or a recovery code. The failures count towards the lockout of the username too.
+*/
+pub async fn validate_code(
+    user_id: &UserID,
+    code: &str,
+    attempt: Attempt,
+    policy: &LockoutPolicy,
+    user_repo: Mutex<impl IUserRepository + Sync + Send>,
+    recovery_repo: Mutex<impl IRecoveryCodeRepository + Sync + Send>,
+    attempt_repo: Mutex<impl ILoginAttemptRepository + Sync + Send>,
+) -> Result<User, Error> {
+    let user_repo = user_repo.lock().await;
+    let user = user_repo
+        .get(user_id)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?
+        .filter(|user| user.totp_enabled && !user.disabled)
+        .ok_or(Error::InvalidCredentials)?;
+
+    let verified = async {
+        let recovery_repo = recovery_repo.lock().await;
+        let valid = check_code(&user.id, code, &*user_repo, &*recovery_repo)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
+
+        match valid {
+            true => Ok((user.clone(), LoginOutcome::Succeeded)),
+            false => Err(Error::InvalidCredentials),
+        }
+    };
+
+    guard(&user.username, &attempt, policy, attempt_repo, verified).await
+}
+
+/// Whether the code of the second factor is right, a recovery code is used up by it
+pub(crate) async fn check_code(
+    user_id: &UserID,
+    code: &str,
+    user_repo: &(impl IUserRepository + Sync + Send),
+    recovery_repo: &(impl IRecoveryCodeRepository + Sync + Send),
+) -> anyhow::Result<bool> {
+    let code = code.trim();
+    if !totp::is_code(code) {
+        let code_hash = totp::hash_recovery_code(code);
+        return recovery_repo.consume(user_id, &code_hash).await;
+    }
+
+    let step = user_repo
+        .get_totp_secret(user_id)
+        .await?
+        .and_then(|secret| totp::verify(secret.expose_secret(), code, Utc::now()));
+    match step {
+        Some(step) => user_repo.use_totp_step(user_id, step).await,
+        None => Ok(false),
+    }
+}
+
+/// Refuses the attempt during the lockout, otherwise the attempt is delayed by the failures, then
+/// it's verified and recorded along with its outcome
+pub(crate) async fn guard<T>(
+    username: &str,
+    attempt: &Attempt,
+    policy: &LockoutPolicy,
+    attempt_repo: Mutex<impl ILoginAttemptRepository + Sync + Send>,
+    verified: impl Future<Output = Result<(T, LoginOutcome), Error>>,
+) -> Result<T, Error> {
+    let attempt_repo = attempt_repo.lock().await;
+    let now = Utc::now();
+    let since = now - policy.window;
+
+    let failures_of_username = attempt_repo
+        .failures_of_username(username, since)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+    let failures_of_ip = attempt_repo
+        .failures_of_ip(attempt.ip, since)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    let record = |outcome: LoginOutcome| LoginAttempt {
+        username: username.to_string(),
+        ip: attempt.ip,
+        user_agent: attempt.user_agent.clone(),
+        outcome,
+        attempted_at: Utc::now(),
+    };
+
+    // the attempts are refused without checking the password during the lockout
+    let locked_until = [
+        policy.locked_until(&failures_of_username, policy.max_failures_per_username),
+        policy.locked_until(&failures_of_ip, policy.max_failures_per_ip),
+    ]
+    .into_iter()
+    .flatten()
+    .max();
+    if let Some(until) = locked_until.filter(|until| *until > now) {
+        attempt_repo
+            .save(record(LoginOutcome::Locked))
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
+        return Err(Error::Locked { until });
+    }
+
+    tokio::time::sleep(policy.delay(failures_of_username.count.max(failures_of_ip.count))).await;
+
+    let (verified, outcome) = match verified.await {
+        Ok((value, outcome)) => (Ok(value), outcome),
+        Err(Error::InvalidCredentials) => (Err(Error::InvalidCredentials), LoginOutcome::Failed),
+        Err(e) => return Err(e),
+    };
+    attempt_repo
+        .save(record(outcome))
         .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    verified
+}
+
+async fn verify(
+    credentials: &Credentials,
+    hasher: &PasswordHasher,
+    user_repo: &(impl IUserRepository + Sync + Send),
+) -> Result<UserID, Error> {
+    let (id, expected_password_hash) = match user_repo
         .get_credentials(credentials.username.as_str())
         .await
         .map_err(|e| Error::Unknown(e.to_string()))?
     {
-        id = Some(user_id);
-        expected_password_hash = password_hash;
-    }
+        Some((user_id, password_hash)) => (Some(user_id), password_hash),
+        None => (
+            None,
+            hasher
+                .dummy_hash()
+                .map_err(|e| Error::Unknown(e.to_string()))?,
+        ),
+    };
+
+    let password = SecretBox::new(Box::new(credentials.password.expose_secret().to_string()));
+    let blocking_hasher = hasher.clone();
+    let rehashed = tokio::task::spawn_blocking(move || {
+        blocking_hasher.verify(&expected_password_hash, &password)?;
 
-    tokio::task::spawn_blocking(move || {
-        verify_password_hash(expected_password_hash, credentials.password)
+        let rehashed = blocking_hasher
+            .needs_rehash(&expected_password_hash)
+            .then(|| blocking_hasher.hash(&password));
+        anyhow::Ok(rehashed)
     })
     .await
     .map_err(|e| Error::Unknown(e.to_string()))?
     .map_err(|_| Error::InvalidCredentials)?;
 
-    id.ok_or_else(|| anyhow!("Unknown username"))
-        .map_err(|_| Error::InvalidCredentials)
-}
+    let id = id.ok_or(Error::InvalidCredentials)?;
+    if let Some(rehashed) = rehashed {
+        // the user still logs in with the outdated hash, it's replaced the next time
+        let replaced = match rehashed {
+            Ok(password_hash) => user_repo.rehash_password(&id, password_hash).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = replaced {
+            tracing::warn!(user_id = %id, "Failed to rehash the password: {:?}", e);
+        }
+    }
 
-fn verify_password_hash(
-    expected_password_hash: SecretBox<String>,
-    password_candidate: SecretBox<String>,
-) -> anyhow::Result<()> {
-    let expected_password_hash = PasswordHash::new(expected_password_hash.expose_secret())
-        .context("Failed to parse hash in PHC string format.")?;
-
-    Argon2::default()
-        .verify_password(
-            password_candidate.expose_secret().as_bytes(),
-            &expected_password_hash,
-        )
-        .context("Invalid password.")
+    Ok(id)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::repositories::InMemoryUserRepository;
+    use crate::repositories::{
+        InMemoryLoginAttemptRepository, InMemoryRecoveryCodeRepository, InMemoryUserRepository,
+    };
     use argon2::password_hash::{rand_core, SaltString};
-    use argon2::{Algorithm, Params, PasswordHasher, Version};
+    use argon2::{Algorithm, Argon2, Params, PasswordHasher as _, Version};
     use rand_core::OsRng;
+    use std::net::Ipv4Addr;
 
     async fn helper() -> (InMemoryUserRepository, UserID, String, String) {
         let salt = SaltString::generate(&mut OsRng);
@@ -98,37 +312,95 @@ mod tests {
         (user_repo, user_id, username, password)
     }
 
-    #[tokio::test]
-    async fn it_should_be_valid_password_otherwise() {
-        let (repo, user_id, username, password) = helper().await;
-        let repo = Mutex::new(repo);
+    fn policy() -> LockoutPolicy {
+        LockoutPolicy {
+            window: chrono::Duration::minutes(15),
+            max_failures_per_username: 2,
+            max_failures_per_ip: 3,
+            lockout: chrono::Duration::minutes(15),
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        }
+    }
 
+    async fn login(
+        user_repo: InMemoryUserRepository,
+        attempt_repo: &InMemoryLoginAttemptRepository,
+        username: &str,
+        password: &str,
+        ip: [u8; 4],
+    ) -> Result<User, Error> {
         let credentials = Credentials {
-            username: username.clone(),
-            password: SecretBox::new(Box::new(password)),
+            username: username.to_string(),
+            password: SecretBox::new(Box::new(password.to_string())),
         };
+        let attempt = Attempt {
+            ip: IpAddr::V4(Ipv4Addr::from(ip)),
+            user_agent: None,
+        };
+
+        validate_credentials(
+            credentials,
+            attempt,
+            &policy(),
+            &PasswordHasher::default(),
+            Mutex::new(user_repo),
+            Mutex::new(attempt_repo.clone()),
+        )
+        .await
+    }
 
-        let res = validate_credentials(credentials, repo).await;
+    #[tokio::test]
+    async fn it_should_be_valid_password_otherwise() {
+        let (repo, user_id, username, password) = helper().await;
+        let attempts = InMemoryLoginAttemptRepository::new();
+
+        let res = login(repo, &attempts, &username, &password, [10, 0, 0, 1]).await;
 
         match res {
-            Ok(id) => {
-                assert_eq!(id, user_id)
+            Ok(user) => {
+                assert_eq!(user.id, user_id)
             }
             Err(_) => unreachable!(),
         }
     }
 
+    #[tokio::test]
+    async fn it_should_rehash_the_outdated_password_hash() {
+        let (repo, _, username, password) = helper().await;
+        let attempts = InMemoryLoginAttemptRepository::new();
+
+        let res = login(repo.clone(), &attempts, &username, &password, [10, 0, 0, 1]).await;
+        assert!(res.is_ok());
+
+        let (_, hash) = repo.get_credentials(&username).await.unwrap().unwrap();
+        assert!(hash.expose_secret().starts_with("$argon2id$"));
+        assert!(!PasswordHasher::default().needs_rehash(&hash));
+
+        let res = login(repo, &attempts, &username, &password, [10, 0, 0, 1]).await;
+        assert!(res.is_ok());
+    }
+
     #[tokio::test]
     async fn it_should_be_invalid_password_otherwise() {
         let (repo, _, username, _) = helper().await;
-        let repo = Mutex::new(repo);
+        let attempts = InMemoryLoginAttemptRepository::new();
 
-        let credentials = Credentials {
-            username: username.clone(),
-            password: SecretBox::new(Box::new("wrong-password".to_string())),
-        };
+        let res = login(repo, &attempts, &username, "wrong-password", [10, 0, 0, 1]).await;
 
-        let res = validate_credentials(credentials, repo).await;
+        match res {
+            Err(Error::InvalidCredentials) => {}
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_the_disabled_users() {
+        let (repo, user_id, username, password) = helper().await;
+        let attempts = InMemoryLoginAttemptRepository::new();
+        repo.set_disabled(&user_id, true).await.unwrap();
+
+        let res = login(repo, &attempts, &username, &password, [10, 0, 0, 1]).await;
 
         match res {
             Err(Error::InvalidCredentials) => {}
@@ -139,17 +411,231 @@ mod tests {
     #[tokio::test]
     async fn it_should_return_an_unknown_error_when_unexpected_error_encountered() {
         let (repo, _, username, password) = helper().await;
-        let repo = Mutex::new(repo.with_error());
+        let attempts = InMemoryLoginAttemptRepository::new();
 
-        let credentials = Credentials {
+        let res = login(
+            repo.with_error(),
+            &attempts,
+            &username,
+            &password,
+            [10, 0, 0, 1],
+        )
+        .await;
+
+        match res {
+            Err(Error::Unknown(_)) => {}
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_lock_the_username_out_after_too_many_failures() {
+        let (_, _, username, password) = helper().await;
+        let attempts = InMemoryLoginAttemptRepository::new();
+
+        for ip in [[10, 0, 0, 1], [10, 0, 0, 2]] {
+            let _ = login(helper().await.0, &attempts, &username, "wrong", ip).await;
+        }
+        // even the right password is refused during the lockout
+        match login(
+            helper().await.0,
+            &attempts,
+            &username,
+            &password,
+            [10, 0, 0, 3],
+        )
+        .await
+        {
+            Err(Error::Locked { until }) => assert!(until > Utc::now()),
+            _ => unreachable!(),
+        }
+
+        let listed = attempts.list(None, None, 10).await.unwrap();
+        let outcomes = listed.iter().map(|a| a.outcome).collect::<Vec<_>>();
+        assert_eq!(
+            outcomes,
+            vec![
+                LoginOutcome::Locked,
+                LoginOutcome::Failed,
+                LoginOutcome::Failed
+            ]
+        );
+
+        // an admin forgives the failures
+        let unlocked = LoginAttempt {
             username: username.clone(),
-            password: SecretBox::new(Box::new(password)),
+            ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            user_agent: None,
+            outcome: LoginOutcome::Unlocked,
+            attempted_at: Utc::now(),
         };
+        attempts.save(unlocked).await.unwrap();
 
-        let res = validate_credentials(credentials, repo).await;
+        let res = login(
+            helper().await.0,
+            &attempts,
+            &username,
+            &password,
+            [10, 0, 0, 3],
+        )
+        .await;
+        assert!(res.is_ok());
+    }
 
-        match res {
-            Err(Error::Unknown(_)) => {}
+    #[tokio::test]
+    async fn it_should_lock_the_ip_out_after_too_many_failures() {
+        let (_, _, username, password) = helper().await;
+        let attempts = InMemoryLoginAttemptRepository::new();
+
+        for name in ["a", "b", "c"] {
+            let _ = login(helper().await.0, &attempts, name, "wrong", [10, 0, 0, 1]).await;
+        }
+
+        let res = login(
+            helper().await.0,
+            &attempts,
+            &username,
+            &password,
+            [10, 0, 0, 1],
+        )
+        .await;
+        assert!(matches!(res, Err(Error::Locked { .. })));
+
+        let res = login(
+            helper().await.0,
+            &attempts,
+            &username,
+            &password,
+            [10, 0, 0, 2],
+        )
+        .await;
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn it_should_double_the_delay_up_to_the_max() {
+        let policy = LockoutPolicy {
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(1),
+            ..policy()
+        };
+
+        assert_eq!(policy.delay(0), Duration::ZERO);
+        assert_eq!(policy.delay(1), Duration::from_millis(250));
+        assert_eq!(policy.delay(2), Duration::from_millis(500));
+        assert_eq!(policy.delay(10), Duration::from_secs(1));
+    }
+
+    async fn enable_totp(user_repo: &InMemoryUserRepository, user_id: &UserID) -> String {
+        let secret = totp::generate_secret();
+        user_repo
+            .set_totp_secret(user_id, Some(SecretBox::new(Box::new(secret.clone()))))
+            .await
+            .unwrap();
+        user_repo.enable_totp(user_id).await.unwrap();
+
+        secret
+    }
+
+    async fn login_with_code(
+        user_repo: &InMemoryUserRepository,
+        recovery_repo: &InMemoryRecoveryCodeRepository,
+        attempt_repo: &InMemoryLoginAttemptRepository,
+        user_id: &UserID,
+        code: &str,
+    ) -> Result<User, Error> {
+        let attempt = Attempt {
+            ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            user_agent: None,
+        };
+
+        validate_code(
+            user_id,
+            code,
+            attempt,
+            &policy(),
+            Mutex::new(user_repo.clone()),
+            Mutex::new(recovery_repo.clone()),
+            Mutex::new(attempt_repo.clone()),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_should_challenge_the_users_with_a_second_factor() {
+        let (repo, user_id, username, password) = helper().await;
+        let attempts = InMemoryLoginAttemptRepository::new();
+        enable_totp(&repo, &user_id).await;
+
+        let _ = login(repo.clone(), &attempts, &username, "wrong", [10, 0, 0, 1]).await;
+        let user = login(repo.clone(), &attempts, &username, &password, [10, 0, 0, 1])
+            .await
+            .ok()
+            .unwrap();
+        assert!(user.totp_enabled);
+
+        // the right password doesn't forgive the failures until the code is given
+        let _ = login(repo, &attempts, &username, "wrong", [10, 0, 0, 1]).await;
+        let listed = attempts.list(None, None, 10).await.unwrap();
+        assert_eq!(listed[1].outcome, LoginOutcome::Challenged);
+        match login(
+            helper().await.0,
+            &attempts,
+            &username,
+            &password,
+            [10, 0, 0, 2],
+        )
+        .await
+        {
+            Err(Error::Locked { .. }) => {}
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_accept_the_code_of_the_authenticator_app_once() {
+        let (repo, user_id, _, _) = helper().await;
+        let recovery = InMemoryRecoveryCodeRepository::new();
+        let attempts = InMemoryLoginAttemptRepository::new();
+        let secret = enable_totp(&repo, &user_id).await;
+        let code = totp::code_at(&secret, Utc::now());
+
+        let res = login_with_code(&repo, &recovery, &attempts, &user_id, &code).await;
+        assert!(res.is_ok());
+
+        // the code can't be replayed
+        match login_with_code(&repo, &recovery, &attempts, &user_id, &code).await {
+            Err(Error::InvalidCredentials) => {}
+            _ => unreachable!(),
+        }
+        let listed = attempts.list(None, None, 10).await.unwrap();
+        let outcomes = listed.iter().map(|a| a.outcome).collect::<Vec<_>>();
+        assert_eq!(
+            outcomes,
+            vec![LoginOutcome::Failed, LoginOutcome::Succeeded]
+        );
+    }
+
+    #[tokio::test]
+    async fn it_should_accept_a_recovery_code_once() {
+        let (repo, user_id, _, _) = helper().await;
+        let recovery = InMemoryRecoveryCodeRepository::new();
+        let attempts = InMemoryLoginAttemptRepository::new();
+        enable_totp(&repo, &user_id).await;
+        let codes = totp::generate_recovery_codes();
+        let hashes = codes
+            .iter()
+            .map(|code| totp::hash_recovery_code(code))
+            .collect::<Vec<_>>();
+        recovery.replace(&user_id, &hashes).await.unwrap();
+
+        let code = codes[0].to_uppercase();
+        let res = login_with_code(&repo, &recovery, &attempts, &user_id, &code).await;
+        assert!(res.is_ok());
+        assert_eq!(recovery.count(&user_id).await.unwrap(), codes.len() - 1);
+
+        match login_with_code(&repo, &recovery, &attempts, &user_id, &code).await {
+            Err(Error::InvalidCredentials) => {}
             _ => unreachable!(),
         }
     }
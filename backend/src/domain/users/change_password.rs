@@ -1,37 +1,345 @@
-use crate::domain::entities::UserID;
-use crate::repositories::IUserRepository;
-use argon2::password_hash::rand_core::OsRng;
-use argon2::password_hash::SaltString;
-use argon2::{Algorithm, Argon2, Params, PasswordHasher, Version};
+use crate::domain::entities::{LoginOutcome, SessionID, UserID};
+use crate::domain::sessions::end_sessions;
+use crate::domain::users::authentication::{self, guard, Attempt, LockoutPolicy};
+use crate::domain::users::password_hashing::PasswordHasher;
+use crate::domain::users::password_policy::PasswordPolicy;
+use crate::domain::validation::FieldError;
+use crate::repositories::{ILoginAttemptRepository, ISessionRepository, IUserRepository};
+use chrono::{DateTime, Utc};
 use secrecy::{ExposeSecret, SecretBox};
+use tokio::sync::Mutex;
 
 pub struct Request {
     pub user_id: UserID,
+    /// The session where the password is changed, it's kept
+    pub session_id: SessionID,
+    pub current_password: SecretBox<String>,
     pub new_password: SecretBox<String>,
+    /// Where the password is changed, the wrong current passwords count towards the lockout
+    pub attempt: Attempt,
 }
 
 pub enum Error {
+    /// The current password is wrong
+    InvalidCredentials,
+    /// The username or the IP has failed too many times
+    Locked {
+        until: DateTime<Utc>,
+    },
+    /// The new password violates the policy
+    Invalid(Vec<FieldError>),
     Unknown(String),
 }
 
+/** Changes the password of the user who knows the current one.
+*
+* The current password is guarded like the logins, so a stolen session can't be used to guess it.
+* The other sessions of the user are ended, since the password might have been stolen. It returns
+* how many sessions are ended.
+*/
 pub async fn execute(
     req: Request,
-    user_repo: tokio::sync::Mutex<impl IUserRepository + Sync + Send>,
-) -> Result<(), Error> {
-    let salt = SaltString::generate(&mut OsRng);
-    let password_hash = Argon2::new(
-        Algorithm::Argon2d,
-        Version::V0x13,
-        Params::new(15000, 2, 1, None).unwrap(),
+    policy: &PasswordPolicy,
+    hasher: &PasswordHasher,
+    lockout: &LockoutPolicy,
+    user_repo: Mutex<impl IUserRepository + Sync + Send>,
+    session_repo: Mutex<impl ISessionRepository + Sync + Send>,
+    attempt_repo: Mutex<impl ILoginAttemptRepository + Sync + Send>,
+) -> Result<usize, Error> {
+    let user_repo = user_repo.lock().await;
+
+    let user = user_repo
+        .get(&req.user_id)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?
+        .ok_or(Error::InvalidCredentials)?;
+    let (_, password_hash) = user_repo
+        .get_credentials(&user.username)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?
+        .ok_or(Error::InvalidCredentials)?;
+
+    let current_password = SecretBox::new(Box::new(req.current_password.expose_secret().clone()));
+    let blocking_hasher = hasher.clone();
+    let verified = async {
+        tokio::task::spawn_blocking(move || {
+            blocking_hasher.verify(&password_hash, &current_password)
+        })
+        .await
+        .map_err(|e| authentication::Error::Unknown(e.to_string()))?
+        .map_err(|_| authentication::Error::InvalidCredentials)?;
+
+        Ok(((), LoginOutcome::Succeeded))
+    };
+    guard(
+        &user.username,
+        &req.attempt,
+        lockout,
+        attempt_repo,
+        verified,
     )
-    .hash_password(req.new_password.expose_secret().as_str().as_bytes(), &salt)
-    .unwrap()
-    .to_string();
+    .await
+    .map_err(|e| match e {
+        authentication::Error::InvalidCredentials => Error::InvalidCredentials,
+        authentication::Error::Locked { until } => Error::Locked { until },
+        authentication::Error::Unknown(e) => Error::Unknown(e),
+    })?;
 
-    let lock = user_repo.lock().await;
-    lock.change_password(req.user_id, SecretBox::new(Box::new(password_hash)))
+    let errors = policy.check(
+        "new_password",
+        &user.username,
+        req.new_password.expose_secret(),
+    );
+    if !errors.is_empty() {
+        return Err(Error::Invalid(errors));
+    }
+
+    let password_hash = hasher
+        .hash_blocking(&req.new_password)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+    user_repo
+        .change_password(req.user_id.clone(), password_hash)
         .await
         .map_err(|e| Error::Unknown(e.to_string()))?;
 
-    Ok(())
+    end_sessions(
+        &*session_repo.lock().await,
+        &req.user_id,
+        Some(&req.session_id),
+    )
+    .await
+    .map_err(|e| Error::Unknown(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::Session;
+    use crate::repositories::{
+        InMemoryLoginAttemptRepository, InMemorySessionRepository, InMemoryUserRepository,
+    };
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::Duration;
+
+    const CURRENT_PASSWORD: &str = "current password";
+
+    fn policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: 10,
+            refuse_common: true,
+        }
+    }
+
+    fn lockout() -> LockoutPolicy {
+        LockoutPolicy {
+            window: chrono::Duration::minutes(15),
+            max_failures_per_username: 2,
+            max_failures_per_ip: 10,
+            lockout: chrono::Duration::minutes(15),
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        }
+    }
+
+    fn secret(value: &str) -> SecretBox<String> {
+        SecretBox::new(Box::new(value.to_string()))
+    }
+
+    async fn start(session_repo: &InMemorySessionRepository, user_id: &UserID) -> Session {
+        let now = Utc::now();
+        let session = Session {
+            id: SessionID::new(),
+            user_id: user_id.clone(),
+            ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            user_agent: None,
+            created_at: now,
+            refreshed_at: now,
+            last_seen_at: now,
+            expires_at: now + chrono::Duration::days(7),
+        };
+        session_repo.save(&session, "token").await.unwrap();
+
+        session
+    }
+
+    async fn helper() -> (InMemoryUserRepository, InMemorySessionRepository, UserID) {
+        let user_id = UserID::from(uuid::Uuid::new_v4());
+        let password_hash = PasswordHasher::default()
+            .hash(&secret(CURRENT_PASSWORD))
+            .unwrap();
+
+        let user_repo = InMemoryUserRepository::new();
+        user_repo
+            .add_credentials(user_id.clone(), "boris".to_string(), password_hash)
+            .await;
+
+        (user_repo, InMemorySessionRepository::new(), user_id)
+    }
+
+    async fn change(
+        user_repo: &InMemoryUserRepository,
+        session_repo: &InMemorySessionRepository,
+        session: &Session,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<usize, Error> {
+        let attempt_repo = InMemoryLoginAttemptRepository::new();
+
+        change_attempted(
+            user_repo,
+            session_repo,
+            &attempt_repo,
+            session,
+            current_password,
+            new_password,
+        )
+        .await
+    }
+
+    async fn change_attempted(
+        user_repo: &InMemoryUserRepository,
+        session_repo: &InMemorySessionRepository,
+        attempt_repo: &InMemoryLoginAttemptRepository,
+        session: &Session,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<usize, Error> {
+        let req = Request {
+            user_id: session.user_id.clone(),
+            session_id: session.id.clone(),
+            current_password: secret(current_password),
+            new_password: secret(new_password),
+            attempt: Attempt {
+                ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+                user_agent: None,
+            },
+        };
+
+        execute(
+            req,
+            &policy(),
+            &PasswordHasher::default(),
+            &lockout(),
+            Mutex::new(user_repo.clone()),
+            Mutex::new(session_repo.clone()),
+            Mutex::new(attempt_repo.clone()),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_should_change_the_password_and_end_the_other_sessions() {
+        let (user_repo, session_repo, user_id) = helper().await;
+        let current = start(&session_repo, &user_id).await;
+        let _ = start(&session_repo, &user_id).await;
+
+        let res = change(
+            &user_repo,
+            &session_repo,
+            &current,
+            CURRENT_PASSWORD,
+            "correct horse battery",
+        )
+        .await;
+
+        assert!(matches!(res, Ok(1)));
+        assert_eq!(session_repo.list(&user_id).await.unwrap(), vec![current]);
+
+        let (_, hash) = user_repo.get_credentials("boris").await.unwrap().unwrap();
+        assert!(PasswordHasher::default()
+            .verify(&hash, &secret("correct horse battery"))
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_a_wrong_current_password() {
+        let (user_repo, session_repo, user_id) = helper().await;
+        let current = start(&session_repo, &user_id).await;
+
+        let res = change(
+            &user_repo,
+            &session_repo,
+            &current,
+            "wrong password",
+            "correct horse battery",
+        )
+        .await;
+
+        assert!(matches!(res, Err(Error::InvalidCredentials)));
+    }
+
+    #[tokio::test]
+    async fn it_should_lock_the_username_out_after_too_many_wrong_current_passwords() {
+        let (user_repo, session_repo, user_id) = helper().await;
+        let attempt_repo = InMemoryLoginAttemptRepository::new();
+        let current = start(&session_repo, &user_id).await;
+
+        for _ in 0..2 {
+            let res = change_attempted(
+                &user_repo,
+                &session_repo,
+                &attempt_repo,
+                &current,
+                "wrong password",
+                "correct horse battery",
+            )
+            .await;
+            assert!(matches!(res, Err(Error::InvalidCredentials)));
+        }
+
+        // even the right password is refused during the lockout
+        let res = change_attempted(
+            &user_repo,
+            &session_repo,
+            &attempt_repo,
+            &current,
+            CURRENT_PASSWORD,
+            "correct horse battery",
+        )
+        .await;
+        assert!(matches!(res, Err(Error::Locked { .. })));
+
+        let outcomes: Vec<_> = attempt_repo
+            .list(Some("boris"), None, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|attempt| attempt.outcome)
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                LoginOutcome::Locked,
+                LoginOutcome::Failed,
+                LoginOutcome::Failed
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_a_password_which_violates_the_policy() {
+        let (user_repo, session_repo, user_id) = helper().await;
+        let current = start(&session_repo, &user_id).await;
+        let _ = start(&session_repo, &user_id).await;
+
+        for new_password in ["", "short", "password123", "Boris"] {
+            let res = change(
+                &user_repo,
+                &session_repo,
+                &current,
+                CURRENT_PASSWORD,
+                new_password,
+            )
+            .await;
+
+            match res {
+                Err(Error::Invalid(errors)) => {
+                    assert!(errors.iter().all(|error| error.path == "new_password"))
+                }
+                _ => unreachable!(),
+            }
+        }
+        assert_eq!(session_repo.list(&user_id).await.unwrap().len(), 2);
+    }
 }
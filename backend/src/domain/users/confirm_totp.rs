@@ -0,0 +1,163 @@
+use crate::domain::entities::UserID;
+use crate::domain::users::totp;
+use crate::repositories::{IRecoveryCodeRepository, IUserRepository};
+use chrono::Utc;
+use secrecy::ExposeSecret;
+use tokio::sync::Mutex;
+
+pub struct Request {
+    pub user_id: UserID,
+    /// A code of the authenticator app which the secret is added to
+    pub code: String,
+}
+
+pub enum Error {
+    /// The enrolment hasn't started
+    NotEnrolled,
+    AlreadyEnabled,
+    InvalidCode,
+    Unknown(String),
+}
+
+/** Turns the second factor on once the authenticator app gives a right code.
+*
+* It returns the recovery codes, they're only shown once.
+*/
+pub async fn execute(
+    req: Request,
+    user_repo: Mutex<impl IUserRepository + Sync + Send>,
+    recovery_repo: Mutex<impl IRecoveryCodeRepository + Sync + Send>,
+) -> Result<Vec<String>, Error> {
+    let user_repo = user_repo.lock().await;
+    let user = user_repo
+        .get(&req.user_id)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?
+        .ok_or(Error::NotEnrolled)?;
+    if user.totp_enabled {
+        return Err(Error::AlreadyEnabled);
+    }
+
+    let secret = user_repo
+        .get_totp_secret(&user.id)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?
+        .ok_or(Error::NotEnrolled)?;
+    let step = totp::verify(secret.expose_secret(), req.code.trim(), Utc::now())
+        .ok_or(Error::InvalidCode)?;
+    user_repo
+        .use_totp_step(&user.id, step)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    let codes = totp::generate_recovery_codes();
+    let hashes = codes
+        .iter()
+        .map(|code| totp::hash_recovery_code(code))
+        .collect::<Vec<_>>();
+    recovery_repo
+        .lock()
+        .await
+        .replace(&user.id, &hashes)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    user_repo
+        .enable_totp(&user.id)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    Ok(codes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::users::enroll_totp;
+    use crate::domain::users::totp::TotpPolicy;
+    use crate::repositories::{InMemoryRecoveryCodeRepository, InMemoryUserRepository};
+    use secrecy::SecretBox;
+
+    async fn enroll(user_repo: &InMemoryUserRepository) -> (UserID, String) {
+        let user_id = UserID::from(uuid::Uuid::new_v4());
+        let password = SecretBox::new(Box::new("password".to_string()));
+        user_repo
+            .add_credentials(user_id.clone(), "username".to_string(), password)
+            .await;
+
+        let policy = TotpPolicy {
+            issuer: "Law Firm".to_string(),
+            challenge_ttl: chrono::Duration::minutes(5),
+        };
+        let req = enroll_totp::Request {
+            user_id: user_id.clone(),
+        };
+        let enrolment = enroll_totp::execute(req, &policy, Mutex::new(user_repo.clone()))
+            .await
+            .ok()
+            .unwrap();
+        assert!(enrolment
+            .uri
+            .starts_with("otpauth://totp/Law%20Firm:username?secret="));
+
+        (user_id, enrolment.secret)
+    }
+
+    async fn confirm(
+        user_repo: &InMemoryUserRepository,
+        recovery_repo: &InMemoryRecoveryCodeRepository,
+        user_id: &UserID,
+        code: String,
+    ) -> Result<Vec<String>, Error> {
+        let req = Request {
+            user_id: user_id.clone(),
+            code,
+        };
+
+        execute(
+            req,
+            Mutex::new(user_repo.clone()),
+            Mutex::new(recovery_repo.clone()),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_should_enable_the_second_factor_with_a_right_code() {
+        let user_repo = InMemoryUserRepository::new();
+        let recovery_repo = InMemoryRecoveryCodeRepository::new();
+        let (user_id, secret) = enroll(&user_repo).await;
+
+        let code = totp::code_at(&secret, Utc::now());
+        let codes = confirm(&user_repo, &recovery_repo, &user_id, code)
+            .await
+            .ok()
+            .unwrap();
+
+        assert_eq!(codes.len(), 10);
+        assert_eq!(recovery_repo.count(&user_id).await.unwrap(), 10);
+        let user = user_repo.get(&user_id).await.unwrap().unwrap();
+        assert!(user.totp_enabled);
+
+        match confirm(&user_repo, &recovery_repo, &user_id, "000000".to_string()).await {
+            Err(Error::AlreadyEnabled) => {}
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_a_wrong_code() {
+        let user_repo = InMemoryUserRepository::new();
+        let recovery_repo = InMemoryRecoveryCodeRepository::new();
+        let (user_id, secret) = enroll(&user_repo).await;
+
+        let wrong = totp::code_at(&secret, Utc::now() + chrono::Duration::minutes(10));
+        match confirm(&user_repo, &recovery_repo, &user_id, wrong).await {
+            Err(Error::InvalidCode) => {}
+            _ => unreachable!(),
+        }
+
+        let user = user_repo.get(&user_id).await.unwrap().unwrap();
+        assert!(!user.totp_enabled);
+    }
+}
@@ -1,37 +1,229 @@
-use crate::domain::entities::UserID;
-use crate::repositories::IUserRepository;
-use argon2::password_hash::rand_core::OsRng;
-use argon2::password_hash::SaltString;
-use argon2::{Algorithm, Argon2, Params, PasswordHasher, Version};
+use crate::domain::audit::{entry, user_snapshot, Actor};
+use crate::domain::entities::{AuditAction, Role, UserID};
+use crate::domain::users::password_hashing::PasswordHasher;
+use crate::domain::users::password_policy::PasswordPolicy;
+use crate::domain::validation::FieldError;
+use crate::repositories::{IAuditRepository, IUserRepository};
+use crate::uow::IUserUnitOfWork;
 use secrecy::{ExposeSecret, SecretBox};
+use tokio::sync::Mutex;
+use validator::ValidateEmail;
 
 #[derive(Debug)]
 pub struct Request {
     pub username: String,
     pub password: SecretBox<String>,
+    pub role: Role,
+    /// Where the links to reset the password are sent
+    pub email: Option<String>,
+    /// Who creates the user, it's missing if it's created on the server
+    pub actor: Option<Actor>,
 }
 
 #[derive(Debug)]
 pub enum Error {
+    /// The username or the password is empty
+    BadRequest,
+    UsernameTaken,
+    /// The password violates the policy or the email is malformed
+    Invalid(Vec<FieldError>),
     Unknown(String),
 }
 
-pub async fn execute(
+pub async fn execute<IUnitOfWork>(
     req: Request,
-    user_repo: tokio::sync::Mutex<impl IUserRepository + Sync + Send>,
-) -> Result<UserID, Error> {
-    let salt = SaltString::generate(&mut OsRng);
-    let password_hash = Argon2::new(
-        Algorithm::Argon2d,
-        Version::V0x13,
-        Params::new(15000, 2, 1, None).unwrap(),
-    )
-    .hash_password(req.password.expose_secret().as_str().as_bytes(), &salt)
-    .unwrap()
-    .to_string();
-
-    let lock = user_repo.lock().await;
-    lock.create_user(req.username, SecretBox::new(Box::new(password_hash)))
+    policy: &PasswordPolicy,
+    hasher: &PasswordHasher,
+    uow: Mutex<IUnitOfWork>,
+) -> Result<UserID, Error>
+where
+    IUnitOfWork: IUserUnitOfWork,
+{
+    let username = req.username.trim().to_string();
+    if username.is_empty() || req.password.expose_secret().is_empty() {
+        return Err(Error::BadRequest);
+    }
+
+    let email = req
+        .email
+        .map(|email| email.trim().to_string())
+        .filter(|email| !email.is_empty());
+
+    let mut errors = policy.check("password", &username, req.password.expose_secret());
+    if email.as_ref().is_some_and(|email| !email.validate_email()) {
+        errors.push(FieldError::new("email", "email"));
+    }
+    if !errors.is_empty() {
+        return Err(Error::Invalid(errors));
+    }
+
+    let password_hash = hasher
+        .hash_blocking(&req.password)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    let id = {
+        let mut lock = uow.lock().await;
+
+        if lock
+            .user_repository()
+            .get_by_username(&username)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?
+            .is_some()
+        {
+            return Err(Error::UsernameTaken);
+        }
+
+        let id = lock
+            .user_repository()
+            .create_user(username, password_hash, req.role, email)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
+        let created = lock
+            .user_repository()
+            .get(&id)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
+
+        let entry = entry(
+            req.actor.as_ref(),
+            AuditAction::Create,
+            "user",
+            id.to_string(),
+        )
+        .with_snapshots(None, created.as_ref().map(user_snapshot));
+        lock.audit_repository()
+            .save(&entry)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
+
+        id
+    };
+
+    uow.into_inner()
+        .commit()
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::{InMemoryAuditRepository, InMemoryUserRepository};
+    use crate::uow::user::InMemory;
+
+    fn policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: 10,
+            refuse_common: true,
+        }
+    }
+
+    fn request(username: &str) -> Request {
+        Request {
+            username: username.to_string(),
+            password: SecretBox::new(Box::new("correct horse battery".to_string())),
+            role: Role::Author,
+            email: Some("author@example.com".to_string()),
+            actor: Some(Actor {
+                user_id: UserID::from(uuid::Uuid::new_v4()),
+                ip: None,
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_create_a_user_and_record_it() {
+        let user_repo = InMemoryUserRepository::new();
+        let audit_repo = InMemoryAuditRepository::new();
+        let uow = InMemory::new(user_repo.clone(), audit_repo.clone());
+
+        let id = match execute(
+            request("author"),
+            &policy(),
+            &PasswordHasher::default(),
+            Mutex::new(uow),
+        )
+        .await
+        {
+            Ok(id) => id,
+            Err(_) => unreachable!(),
+        };
+
+        let user = user_repo.get(&id).await.unwrap().unwrap();
+        assert_eq!(user.username, "author");
+        assert_eq!(user.role, Role::Author);
+        assert_eq!(user.email.as_deref(), Some("author@example.com"));
+
+        let entries = audit_repo.entries().await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, AuditAction::Create);
+        assert_eq!(entries[0].target_id, id.to_string());
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_a_taken_username() {
+        let user_repo = InMemoryUserRepository::new();
+        let audit_repo = InMemoryAuditRepository::new();
+
+        let uow = InMemory::new(user_repo.clone(), audit_repo.clone());
+        assert!(execute(
+            request("author"),
+            &policy(),
+            &PasswordHasher::default(),
+            Mutex::new(uow)
+        )
         .await
-        .map_err(|e| Error::Unknown(e.to_string()))
+        .is_ok());
+
+        let uow = InMemory::new(user_repo.clone(), audit_repo.clone());
+        match execute(
+            request(" author "),
+            &policy(),
+            &PasswordHasher::default(),
+            Mutex::new(uow),
+        )
+        .await
+        {
+            Err(Error::UsernameTaken) => {}
+            _ => unreachable!(),
+        }
+
+        let uow = InMemory::new(user_repo, audit_repo);
+        match execute(
+            request(" "),
+            &policy(),
+            &PasswordHasher::default(),
+            Mutex::new(uow),
+        )
+        .await
+        {
+            Err(Error::BadRequest) => {}
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_a_weak_password_or_a_malformed_email() {
+        let user_repo = InMemoryUserRepository::new();
+        let audit_repo = InMemoryAuditRepository::new();
+
+        let mut req = request("author");
+        req.password = SecretBox::new(Box::new("qwerty".to_string()));
+        req.email = Some("author".to_string());
+        let uow = InMemory::new(user_repo.clone(), audit_repo.clone());
+        match execute(req, &policy(), &PasswordHasher::default(), Mutex::new(uow)).await {
+            Err(Error::Invalid(errors)) => {
+                let paths = errors.iter().map(|error| error.path.as_str());
+                assert_eq!(paths.collect::<Vec<_>>(), ["password", "password", "email"]);
+            }
+            _ => unreachable!(),
+        }
+
+        assert!(user_repo.get_by_username("author").await.unwrap().is_none());
+        assert!(audit_repo.entries().await.is_empty());
+    }
 }
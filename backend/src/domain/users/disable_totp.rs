@@ -0,0 +1,159 @@
+use crate::domain::entities::UserID;
+use crate::domain::users::authentication::check_code;
+use crate::repositories::{IRecoveryCodeRepository, ISecuritySettingsRepository, IUserRepository};
+use tokio::sync::Mutex;
+
+pub struct Request {
+    pub user_id: UserID,
+    /// A code of the authenticator app or a recovery code, so a stolen token can't turn it off
+    pub code: String,
+}
+
+pub enum Error {
+    NotEnabled,
+    /// The second factor is required for all the users
+    Required,
+    InvalidCode,
+    Unknown(String),
+}
+
+/// Turns the second factor off, the secret and the recovery codes are removed
+pub async fn execute(
+    req: Request,
+    user_repo: Mutex<impl IUserRepository + Sync + Send>,
+    recovery_repo: Mutex<impl IRecoveryCodeRepository + Sync + Send>,
+    settings_repo: Mutex<impl ISecuritySettingsRepository + Sync + Send>,
+) -> Result<(), Error> {
+    let settings = settings_repo
+        .lock()
+        .await
+        .get()
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+    if settings.require_totp {
+        return Err(Error::Required);
+    }
+
+    let user_repo = user_repo.lock().await;
+    let user = user_repo
+        .get(&req.user_id)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?
+        .filter(|user| user.totp_enabled)
+        .ok_or(Error::NotEnabled)?;
+
+    let recovery_repo = recovery_repo.lock().await;
+    if !check_code(&user.id, &req.code, &*user_repo, &*recovery_repo)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?
+    {
+        return Err(Error::InvalidCode);
+    }
+
+    user_repo
+        .set_totp_secret(&user.id, None)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+    recovery_repo
+        .replace(&user.id, &[])
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::SecuritySettings;
+    use crate::domain::users::totp;
+    use crate::repositories::{
+        InMemoryRecoveryCodeRepository, InMemorySecuritySettingsRepository, InMemoryUserRepository,
+    };
+    use secrecy::SecretBox;
+
+    struct Fixture {
+        user_repo: InMemoryUserRepository,
+        recovery_repo: InMemoryRecoveryCodeRepository,
+        settings_repo: InMemorySecuritySettingsRepository,
+        user_id: UserID,
+    }
+
+    async fn fixture() -> Fixture {
+        let user_repo = InMemoryUserRepository::new();
+        let user_id = UserID::from(uuid::Uuid::new_v4());
+        let password = SecretBox::new(Box::new("password".to_string()));
+        user_repo
+            .add_credentials(user_id.clone(), "username".to_string(), password)
+            .await;
+        let secret = SecretBox::new(Box::new(totp::generate_secret()));
+        user_repo
+            .set_totp_secret(&user_id, Some(secret))
+            .await
+            .unwrap();
+        user_repo.enable_totp(&user_id).await.unwrap();
+
+        let recovery_repo = InMemoryRecoveryCodeRepository::new();
+        recovery_repo
+            .replace(&user_id, &[totp::hash_recovery_code("abcde-fghij")])
+            .await
+            .unwrap();
+
+        Fixture {
+            user_repo,
+            recovery_repo,
+            settings_repo: InMemorySecuritySettingsRepository::new(),
+            user_id,
+        }
+    }
+
+    async fn disable(fixture: &Fixture, code: &str) -> Result<(), Error> {
+        let req = Request {
+            user_id: fixture.user_id.clone(),
+            code: code.to_string(),
+        };
+
+        execute(
+            req,
+            Mutex::new(fixture.user_repo.clone()),
+            Mutex::new(fixture.recovery_repo.clone()),
+            Mutex::new(fixture.settings_repo.clone()),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_should_disable_the_second_factor_with_a_recovery_code() {
+        let fixture = fixture().await;
+
+        match disable(&fixture, "wrong-code").await {
+            Err(Error::InvalidCode) => {}
+            _ => unreachable!(),
+        }
+        assert!(disable(&fixture, "abcde-fghij").await.is_ok());
+
+        let user = fixture.user_repo.get(&fixture.user_id).await.unwrap();
+        assert!(!user.unwrap().totp_enabled);
+        assert!(fixture
+            .user_repo
+            .get_totp_secret(&fixture.user_id)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_to_disable_the_required_second_factor() {
+        let fixture = fixture().await;
+        let settings = SecuritySettings { require_totp: true };
+        fixture.settings_repo.save(&settings).await.unwrap();
+
+        match disable(&fixture, "abcde-fghij").await {
+            Err(Error::Required) => {}
+            _ => unreachable!(),
+        }
+        // the recovery code isn't used up
+        assert_eq!(
+            fixture.recovery_repo.count(&fixture.user_id).await.unwrap(),
+            1
+        );
+    }
+}
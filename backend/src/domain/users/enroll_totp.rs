@@ -0,0 +1,50 @@
+use crate::domain::entities::UserID;
+use crate::domain::users::totp::{self, TotpPolicy};
+use crate::repositories::IUserRepository;
+use secrecy::SecretBox;
+use tokio::sync::Mutex;
+
+pub struct Request {
+    pub user_id: UserID,
+}
+
+/// The secret which is added to the authenticator app, either by hand or by the QR code of the URI
+pub struct Enrolment {
+    pub secret: String,
+    pub uri: String,
+}
+
+pub enum Error {
+    NotFound,
+    /// The second factor has to be disabled before another one is enrolled
+    AlreadyEnabled,
+    Unknown(String),
+}
+
+/// Starts to enrol a second factor, it's off until a code of the secret is confirmed
+pub async fn execute(
+    req: Request,
+    policy: &TotpPolicy,
+    user_repo: Mutex<impl IUserRepository + Sync + Send>,
+) -> Result<Enrolment, Error> {
+    let user_repo = user_repo.lock().await;
+    let user = user_repo
+        .get(&req.user_id)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?
+        .ok_or(Error::NotFound)?;
+    if user.totp_enabled {
+        return Err(Error::AlreadyEnabled);
+    }
+
+    let secret = totp::generate_secret();
+    user_repo
+        .set_totp_secret(&user.id, Some(SecretBox::new(Box::new(secret.clone()))))
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    Ok(Enrolment {
+        uri: totp::uri(&policy.issuer, &user.username, &secret),
+        secret,
+    })
+}
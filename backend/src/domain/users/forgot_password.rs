@@ -0,0 +1,237 @@
+use crate::domain::entities::PasswordReset;
+use crate::mailer::{Email, IMailer};
+use crate::repositories::{IPasswordResetRepository, IUserRepository};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// How the users reset their passwords when they forget them
+#[derive(Debug, Clone)]
+pub struct PasswordResetPolicy {
+    /// How long a token can be used
+    pub token_ttl: chrono::Duration,
+    /// The page where the password is reset, the token is appended as the `token` query parameter
+    pub url: String,
+}
+
+pub struct Request {
+    pub username: String,
+}
+
+pub enum Error {
+    Unknown(String),
+}
+
+/// Only the hash of a token is kept, so the leaked hashes can't be used
+pub(crate) fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/** Emails a link to reset the password to the user.
+*
+* It succeeds whether the username exists or not, so nobody can tell which usernames exist. The
+* disabled users and the ones without emails don't get the links. The email is sent in the
+* background, otherwise the time it takes would tell that the username exists.
+*/
+pub async fn execute(
+    req: Request,
+    policy: &PasswordResetPolicy,
+    user_repo: Mutex<impl IUserRepository + Sync + Send>,
+    reset_repo: Mutex<impl IPasswordResetRepository + Sync + Send>,
+    mailer: Arc<dyn IMailer>,
+) -> Result<(), Error> {
+    let user = user_repo
+        .lock()
+        .await
+        .get_by_username(req.username.trim())
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    let (user, email) = match user {
+        Some(user) if !user.disabled => match user.email.clone() {
+            Some(email) => (user, email),
+            None => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+
+    let token = hex::encode(rand::random::<[u8; 32]>());
+    let now = Utc::now();
+    let reset = PasswordReset {
+        token_hash: hash_token(&token),
+        user_id: user.id.clone(),
+        expires_at: now + policy.token_ttl,
+        created_at: now,
+    };
+
+    let reset_repo = reset_repo.lock().await;
+    reset_repo
+        .delete_of_user(&user.id)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+    reset_repo
+        .save(&reset)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    let link = format!("{}?token={}", policy.url, token);
+    let minutes = policy.token_ttl.num_minutes();
+    let email = Email {
+        to: email,
+        subject: "重設密碼 Reset your password".to_string(),
+        body: format!(
+            "{}，您好：\n\n請在 {} 分鐘內開啟以下連結重設密碼，連結只能使用一次：\n{}\n\n\
+            Hi {},\n\nOpen the link within {} minutes to reset your password, it can be used once:\n{}\n\n\
+            如果您沒有要求重設密碼，請忽略這封信。\nIf you didn't ask to reset the password, ignore the email.",
+            user.username, minutes, link, user.username, minutes, link
+        ),
+    };
+
+    // the failure isn't returned, otherwise it would tell that the username exists
+    tokio::spawn(async move {
+        if let Err(e) = mailer.send(&email).await {
+            tracing::error!(user_id = %user.id, "Failed to send the password reset email: {:?}", e);
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{Role, UserID};
+    use crate::mailer::Email;
+    use crate::repositories::{InMemoryPasswordResetRepository, InMemoryUserRepository};
+    use secrecy::SecretBox;
+    use tokio::sync::mpsc;
+
+    /// Hands the sent emails over to the test
+    struct ChannelMailer(mpsc::UnboundedSender<Email>);
+
+    #[async_trait::async_trait]
+    impl IMailer for ChannelMailer {
+        async fn send(&self, email: &Email) -> anyhow::Result<()> {
+            Ok(self.0.send(email.clone())?)
+        }
+    }
+
+    fn policy() -> PasswordResetPolicy {
+        PasswordResetPolicy {
+            token_ttl: chrono::Duration::minutes(30),
+            url: "https://example.com/reset-password".to_string(),
+        }
+    }
+
+    async fn helper() -> (
+        InMemoryUserRepository,
+        InMemoryPasswordResetRepository,
+        UserID,
+    ) {
+        let user_id = UserID::from(uuid::Uuid::new_v4());
+        let user_repo = InMemoryUserRepository::new();
+        user_repo
+            .add_credentials(
+                user_id.clone(),
+                "boris".to_string(),
+                SecretBox::new(Box::new("hash".to_string())),
+            )
+            .await;
+        user_repo
+            .add_email(user_id.clone(), Some("boris@example.com".to_string()))
+            .await;
+        user_repo.add_role(user_id.clone(), Role::Editor).await;
+
+        (user_repo, InMemoryPasswordResetRepository::new(), user_id)
+    }
+
+    /// Returns the email which is sent in the background, it's none if nothing is sent
+    async fn forgot(
+        username: &str,
+        user_repo: &InMemoryUserRepository,
+        reset_repo: &InMemoryPasswordResetRepository,
+    ) -> Result<Option<Email>, Error> {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let req = Request {
+            username: username.to_string(),
+        };
+
+        execute(
+            req,
+            &policy(),
+            Mutex::new(user_repo.clone()),
+            Mutex::new(reset_repo.clone()),
+            Arc::new(ChannelMailer(sender)),
+        )
+        .await?;
+
+        // the channel is closed once the mailer is dropped, whether it's sent or not
+        Ok(receiver.recv().await)
+    }
+
+    #[tokio::test]
+    async fn it_should_email_a_link_with_a_token_which_is_only_kept_hashed() {
+        let (user_repo, reset_repo, user_id) = helper().await;
+
+        let email = match forgot(" boris ", &user_repo, &reset_repo).await {
+            Ok(Some(email)) => email,
+            _ => unreachable!(),
+        };
+        assert_eq!(email.to, "boris@example.com");
+
+        let (_, token) = email
+            .body
+            .split_once("https://example.com/reset-password?token=")
+            .unwrap();
+        let token = token.lines().next().unwrap();
+
+        let resets = reset_repo.resets().await;
+        assert_eq!(resets.len(), 1);
+        assert_eq!(resets[0].user_id, user_id);
+        assert_eq!(resets[0].token_hash, hash_token(token));
+        assert_ne!(resets[0].token_hash, token);
+    }
+
+    #[tokio::test]
+    async fn it_should_keep_only_the_latest_token() {
+        let (user_repo, reset_repo, _) = helper().await;
+
+        for _ in 0..2 {
+            assert!(matches!(
+                forgot("boris", &user_repo, &reset_repo).await,
+                Ok(Some(_))
+            ));
+        }
+
+        assert_eq!(reset_repo.resets().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn it_should_succeed_silently_if_nothing_can_be_sent() {
+        let (user_repo, reset_repo, user_id) = helper().await;
+
+        // the username doesn't exist
+        assert!(matches!(
+            forgot("nobody", &user_repo, &reset_repo).await,
+            Ok(None)
+        ));
+
+        // the user is disabled
+        user_repo.set_disabled(&user_id, true).await.unwrap();
+        assert!(matches!(
+            forgot("boris", &user_repo, &reset_repo).await,
+            Ok(None)
+        ));
+
+        // the user doesn't have an email
+        user_repo.set_disabled(&user_id, false).await.unwrap();
+        user_repo.add_email(user_id, None).await;
+        assert!(matches!(
+            forgot("boris", &user_repo, &reset_repo).await,
+            Ok(None)
+        ));
+
+        assert!(reset_repo.resets().await.is_empty());
+    }
+}
@@ -0,0 +1,37 @@
+use crate::domain::entities::LoginAttempt;
+use crate::repositories::ILoginAttemptRepository;
+use std::net::IpAddr;
+use tokio::sync::Mutex;
+
+/// The attempts are listed up to this many
+pub const MAX_LIMIT: usize = 500;
+
+pub struct Request {
+    pub username: Option<String>,
+    pub ip: Option<String>,
+    pub limit: usize,
+}
+
+pub enum Error {
+    BadRequest,
+    Unknown(String),
+}
+
+/// Lists the latest attempts first
+pub async fn execute(
+    req: Request,
+    attempt_repo: Mutex<impl ILoginAttemptRepository + Sync + Send>,
+) -> Result<Vec<LoginAttempt>, Error> {
+    let ip = req
+        .ip
+        .map(|ip| ip.parse::<IpAddr>())
+        .transpose()
+        .map_err(|_| Error::BadRequest)?;
+
+    attempt_repo
+        .lock()
+        .await
+        .list(req.username.as_deref(), ip, req.limit.clamp(1, MAX_LIMIT))
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))
+}
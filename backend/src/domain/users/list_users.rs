@@ -0,0 +1,19 @@
+use crate::domain::entities::User;
+use crate::repositories::IUserRepository;
+use tokio::sync::Mutex;
+
+pub enum Error {
+    Unknown(String),
+}
+
+/// Lists all the users, including the disabled ones
+pub async fn execute(
+    user_repo: Mutex<impl IUserRepository + Sync + Send>,
+) -> Result<Vec<User>, Error> {
+    user_repo
+        .lock()
+        .await
+        .list()
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))
+}
@@ -0,0 +1,309 @@
+use crate::domain::audit::{entry, user_snapshot, Actor};
+use crate::domain::entities::{AuditAction, UserID};
+use crate::domain::sessions::end_sessions;
+use crate::repositories::{IAuditRepository, ISessionRepository, IUserRepository};
+use crate::uow::IUserUnitOfWork;
+use tokio::sync::Mutex;
+
+/// How a user is changed by an admin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Disable,
+    Enable,
+    /// The user can only change the password after logging in again
+    ForcePasswordReset,
+    /// The user lost the authenticator app and the recovery codes, so the second factor is enrolled again
+    ResetTotp,
+    Delete,
+}
+
+impl Change {
+    fn action(&self) -> AuditAction {
+        match self {
+            Change::Disable => AuditAction::Disable,
+            Change::Enable => AuditAction::Enable,
+            Change::ForcePasswordReset => AuditAction::ForcePasswordReset,
+            Change::ResetTotp => AuditAction::ResetTotp,
+            Change::Delete => AuditAction::Delete,
+        }
+    }
+
+    /// Whether the sessions of the user are ended, so the change takes effect at once
+    fn ends_sessions(&self) -> bool {
+        !matches!(self, Change::Enable)
+    }
+}
+
+pub struct Request {
+    pub user_id: String,
+    pub change: Change,
+    pub actor: Actor,
+}
+
+pub enum Error {
+    BadRequest,
+    NotFound,
+    /// The users can't disable or delete themselves, so there is always someone to manage them
+    Oneself,
+    Unknown(String),
+}
+
+pub async fn execute<IUnitOfWork>(
+    req: Request,
+    uow: Mutex<IUnitOfWork>,
+    session_repo: Mutex<impl ISessionRepository + Sync + Send>,
+) -> Result<(), Error>
+where
+    IUnitOfWork: IUserUnitOfWork,
+{
+    let user_id = uuid::Uuid::parse_str(&req.user_id)
+        .map(UserID::from)
+        .map_err(|_| Error::BadRequest)?;
+    if user_id == req.actor.user_id && matches!(req.change, Change::Disable | Change::Delete) {
+        return Err(Error::Oneself);
+    }
+
+    {
+        let mut lock = uow.lock().await;
+
+        let user = lock
+            .user_repository()
+            .get(&user_id)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?
+            .ok_or(Error::NotFound)?;
+
+        let user_repo = lock.user_repository();
+        match req.change {
+            Change::Disable => user_repo.set_disabled(&user_id, true).await,
+            Change::Enable => user_repo.set_disabled(&user_id, false).await,
+            Change::ForcePasswordReset => user_repo.set_must_reset_password(&user_id, true).await,
+            Change::ResetTotp => user_repo.set_totp_secret(&user_id, None).await,
+            Change::Delete => user_repo.delete(&user_id).await,
+        }
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+        let changed = lock
+            .user_repository()
+            .get(&user_id)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
+
+        let entry = entry(
+            Some(&req.actor),
+            req.change.action(),
+            "user",
+            user_id.to_string(),
+        )
+        .with_snapshots(
+            Some(user_snapshot(&user)),
+            changed.as_ref().map(user_snapshot),
+        );
+        lock.audit_repository()
+            .save(&entry)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
+    }
+
+    uow.into_inner()
+        .commit()
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    if req.change.ends_sessions() {
+        end_sessions(&*session_repo.lock().await, &user_id, None)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::Session;
+    use crate::domain::entities::SessionID;
+    use crate::repositories::{
+        InMemoryAuditRepository, InMemorySessionRepository, InMemoryUserRepository,
+    };
+    use crate::uow::user::InMemory;
+    use chrono::Utc;
+    use secrecy::SecretBox;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    struct Fixture {
+        user_repo: InMemoryUserRepository,
+        audit_repo: InMemoryAuditRepository,
+        session_repo: InMemorySessionRepository,
+        user_id: UserID,
+        admin: Actor,
+    }
+
+    async fn fixture() -> Fixture {
+        let user_repo = InMemoryUserRepository::new();
+        let user_id = UserID::from(uuid::Uuid::new_v4());
+        let password = SecretBox::new(Box::new("password".to_string()));
+        user_repo
+            .add_credentials(user_id.clone(), "username".to_string(), password)
+            .await;
+
+        let session_repo = InMemorySessionRepository::new();
+        let now = Utc::now();
+        let session = Session {
+            id: SessionID::new(),
+            user_id: user_id.clone(),
+            ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            user_agent: None,
+            created_at: now,
+            refreshed_at: now,
+            last_seen_at: now,
+            expires_at: now + chrono::Duration::days(7),
+        };
+        session_repo.save(&session, "token").await.unwrap();
+
+        Fixture {
+            user_repo,
+            audit_repo: InMemoryAuditRepository::new(),
+            session_repo,
+            user_id,
+            admin: Actor {
+                user_id: UserID::from(uuid::Uuid::new_v4()),
+                ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            },
+        }
+    }
+
+    async fn change(fixture: &Fixture, user_id: &UserID, change: Change) -> Result<(), Error> {
+        let req = Request {
+            user_id: user_id.to_string(),
+            change,
+            actor: fixture.admin.clone(),
+        };
+        let uow = InMemory::new(fixture.user_repo.clone(), fixture.audit_repo.clone());
+
+        execute(
+            req,
+            Mutex::new(uow),
+            Mutex::new(fixture.session_repo.clone()),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_should_disable_a_user_and_end_the_sessions() {
+        let fixture = fixture().await;
+
+        assert!(change(&fixture, &fixture.user_id, Change::Disable)
+            .await
+            .is_ok());
+
+        let user = fixture.user_repo.get(&fixture.user_id).await.unwrap();
+        assert!(user.unwrap().disabled);
+        assert!(fixture
+            .session_repo
+            .list(&fixture.user_id)
+            .await
+            .unwrap()
+            .is_empty());
+        // the disabled users can't log in
+        assert!(fixture
+            .user_repo
+            .get_credentials("username")
+            .await
+            .unwrap()
+            .is_none());
+
+        let entries = fixture.audit_repo.entries().await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, AuditAction::Disable);
+        assert_eq!(entries[0].user_id, Some(fixture.admin.user_id.clone()));
+    }
+
+    #[tokio::test]
+    async fn it_should_force_a_password_reset() {
+        let fixture = fixture().await;
+
+        assert!(
+            change(&fixture, &fixture.user_id, Change::ForcePasswordReset)
+                .await
+                .is_ok()
+        );
+
+        let user = fixture.user_repo.get(&fixture.user_id).await.unwrap();
+        assert!(user.unwrap().must_reset_password);
+        assert!(fixture
+            .session_repo
+            .list(&fixture.user_id)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn it_should_reset_the_second_factor() {
+        let fixture = fixture().await;
+        let secret = SecretBox::new(Box::new("JBSWY3DPEHPK3PXP".to_string()));
+        fixture
+            .user_repo
+            .set_totp_secret(&fixture.user_id, Some(secret))
+            .await
+            .unwrap();
+        fixture
+            .user_repo
+            .enable_totp(&fixture.user_id)
+            .await
+            .unwrap();
+
+        assert!(change(&fixture, &fixture.user_id, Change::ResetTotp)
+            .await
+            .is_ok());
+
+        let user = fixture.user_repo.get(&fixture.user_id).await.unwrap();
+        assert!(!user.unwrap().totp_enabled);
+        assert!(fixture
+            .user_repo
+            .get_totp_secret(&fixture.user_id)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            fixture.audit_repo.entries().await[0].action,
+            AuditAction::ResetTotp
+        );
+    }
+
+    #[tokio::test]
+    async fn it_should_delete_a_user() {
+        let fixture = fixture().await;
+
+        assert!(change(&fixture, &fixture.user_id, Change::Delete)
+            .await
+            .is_ok());
+        assert!(fixture
+            .user_repo
+            .get(&fixture.user_id)
+            .await
+            .unwrap()
+            .is_none());
+
+        match change(&fixture, &fixture.user_id, Change::Delete).await {
+            Err(Error::NotFound) => {}
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_to_disable_or_delete_oneself() {
+        let fixture = fixture().await;
+        let admin = fixture.admin.user_id.clone();
+
+        for change_of_oneself in [Change::Disable, Change::Delete] {
+            match change(&fixture, &admin, change_of_oneself).await {
+                Err(Error::Oneself) => {}
+                _ => unreachable!(),
+            }
+        }
+        assert!(fixture.audit_repo.entries().await.is_empty());
+    }
+}
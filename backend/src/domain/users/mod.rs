@@ -1,4 +1,36 @@
 pub mod authentication;
 pub mod change_password;
 
+pub mod confirm_totp;
+
+pub mod disable_totp;
+
+pub mod enroll_totp;
+
 pub mod create_user;
+
+pub mod forgot_password;
+
+pub mod list_login_attempts;
+
+pub mod list_users;
+
+pub mod manage_user;
+
+pub mod password_hashing;
+
+pub mod password_policy;
+
+pub mod permissions;
+
+pub mod regenerate_recovery_codes;
+
+pub mod reset_password;
+
+pub mod security_settings;
+
+pub mod totp;
+
+pub mod totp_status;
+
+pub mod unlock;
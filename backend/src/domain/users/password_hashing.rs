@@ -0,0 +1,180 @@
+use anyhow::{anyhow, Context};
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::SaltString;
+use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordVerifier, Version};
+use secrecy::{ExposeSecret, SecretBox};
+use std::sync::OnceLock;
+
+/** Hashes the passwords with the configured algorithm and parameters.
+*
+* The hashes of the other algorithms or parameters are still verified, they are replaced once the
+* users log in with them.
+*/
+#[derive(Debug, Clone)]
+pub struct PasswordHasher {
+    algorithm: Algorithm,
+    params: Params,
+    /// The hash which is verified for the unknown usernames, so they take as long as the known ones
+    dummy_hash: OnceLock<String>,
+}
+
+impl Default for PasswordHasher {
+    /// Argon2id with the minimum parameters which are recommended by OWASP
+    fn default() -> Self {
+        Self::new(Algorithm::Argon2id, 19456, 2, 1).unwrap()
+    }
+}
+
+impl PasswordHasher {
+    pub fn new(
+        algorithm: Algorithm,
+        memory_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    ) -> anyhow::Result<Self> {
+        let params = Params::new(memory_kib, iterations, parallelism, None)
+            .map_err(|e| anyhow!("Invalid password hashing parameters: {}", e))?;
+
+        Ok(Self {
+            algorithm,
+            params,
+            dummy_hash: OnceLock::new(),
+        })
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(self.algorithm, Version::V0x13, self.params.clone())
+    }
+
+    /// Hashes a new password in the PHC string format
+    pub fn hash(&self, password: &SecretBox<String>) -> anyhow::Result<SecretBox<String>> {
+        let salt = SaltString::generate(&mut OsRng);
+        let password_hash = argon2::PasswordHasher::hash_password(
+            &self.argon2(),
+            password.expose_secret().as_bytes(),
+            &salt,
+        )
+        .map_err(|e| anyhow!("Failed to hash the password: {}", e))?
+        .to_string();
+
+        Ok(SecretBox::new(Box::new(password_hash)))
+    }
+
+    /// Hashes the password on a blocking thread, it takes too long to be done on the async ones
+    pub async fn hash_blocking(
+        &self,
+        password: &SecretBox<String>,
+    ) -> anyhow::Result<SecretBox<String>> {
+        let hasher = self.clone();
+        let password = SecretBox::new(Box::new(password.expose_secret().clone()));
+
+        tokio::task::spawn_blocking(move || hasher.hash(&password)).await?
+    }
+
+    /// Verifies the password against the hash, along with the algorithm and the parameters in it
+    pub fn verify(
+        &self,
+        expected_password_hash: &SecretBox<String>,
+        password_candidate: &SecretBox<String>,
+    ) -> anyhow::Result<()> {
+        let expected_password_hash = PasswordHash::new(expected_password_hash.expose_secret())
+            .context("Failed to parse hash in PHC string format.")?;
+
+        Argon2::default()
+            .verify_password(
+                password_candidate.expose_secret().as_bytes(),
+                &expected_password_hash,
+            )
+            .context("Invalid password.")
+    }
+
+    /// It's hashed once it's needed the first time
+    pub fn dummy_hash(&self) -> anyhow::Result<SecretBox<String>> {
+        if self.dummy_hash.get().is_none() {
+            let dummy_hash = self.hash(&SecretBox::new(Box::new("dummy password".to_string())))?;
+            let _ = self.dummy_hash.set(dummy_hash.expose_secret().to_string());
+        }
+
+        Ok(SecretBox::new(Box::new(
+            self.dummy_hash.get().cloned().unwrap_or_default(),
+        )))
+    }
+
+    /// Whether the hash is of another algorithm, version or parameters than the configured ones
+    pub fn needs_rehash(&self, password_hash: &SecretBox<String>) -> bool {
+        let Ok(password_hash) = PasswordHash::new(password_hash.expose_secret()) else {
+            return true;
+        };
+        let Ok(params) = Params::try_from(&password_hash) else {
+            return true;
+        };
+
+        password_hash.algorithm != self.algorithm.ident()
+            || password_hash.version != Some(Version::V0x13.into())
+            || params.m_cost() != self.params.m_cost()
+            || params.t_cost() != self.params.t_cost()
+            || params.p_cost() != self.params.p_cost()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret(value: &str) -> SecretBox<String> {
+        SecretBox::new(Box::new(value.to_string()))
+    }
+
+    #[test]
+    fn it_should_hash_with_argon2id_by_default() {
+        let hasher = PasswordHasher::default();
+        let hash = hasher.hash(&secret("correct horse battery")).unwrap();
+
+        assert!(hash
+            .expose_secret()
+            .starts_with("$argon2id$v=19$m=19456,t=2,p=1$"));
+        assert!(hasher
+            .verify(&hash, &secret("correct horse battery"))
+            .is_ok());
+        assert!(hasher.verify(&hash, &secret("wrong")).is_err());
+        assert!(!hasher.needs_rehash(&hash));
+    }
+
+    #[tokio::test]
+    async fn it_should_hash_on_a_blocking_thread() {
+        let hasher = PasswordHasher::default();
+        let hash = hasher
+            .hash_blocking(&secret("correct horse battery"))
+            .await
+            .unwrap();
+
+        assert!(hasher
+            .verify(&hash, &secret("correct horse battery"))
+            .is_ok());
+    }
+
+    #[test]
+    fn it_should_rehash_the_other_algorithms_and_parameters() {
+        let hasher = PasswordHasher::default();
+        let outdated = [
+            PasswordHasher::new(Algorithm::Argon2d, 15000, 2, 1).unwrap(),
+            PasswordHasher::new(Algorithm::Argon2id, 15000, 2, 1).unwrap(),
+            PasswordHasher::new(Algorithm::Argon2id, 19456, 3, 1).unwrap(),
+        ];
+
+        for old in outdated {
+            let hash = old.hash(&secret("correct horse battery")).unwrap();
+
+            assert!(hasher
+                .verify(&hash, &secret("correct horse battery"))
+                .is_ok());
+            assert!(hasher.needs_rehash(&hash));
+        }
+        assert!(hasher.needs_rehash(&secret("not a hash")));
+    }
+
+    #[test]
+    fn it_should_refuse_the_invalid_parameters() {
+        assert!(PasswordHasher::new(Algorithm::Argon2id, 1, 2, 1).is_err());
+    }
+}
@@ -0,0 +1,83 @@
+use crate::domain::validation::FieldError;
+use serde_json::json;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+/// The common passwords which are guessed first, they are bundled so nothing is looked up online
+static COMMON_PASSWORDS: LazyLock<HashSet<&'static str>> =
+    LazyLock::new(|| include_str!("common_passwords.txt").lines().collect());
+
+/// Which passwords can be chosen
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    /// How many characters a password has at least
+    pub min_length: usize,
+    /// Whether the common passwords are refused
+    pub refuse_common: bool,
+}
+
+impl PasswordPolicy {
+    /// The rules which the password of the user violates, the field is the one of the request
+    pub fn check(&self, field: &str, username: &str, password: &str) -> Vec<FieldError> {
+        let mut errors = vec![];
+
+        if password.chars().count() < self.min_length {
+            let mut error = FieldError::new(field, "length");
+            error
+                .params
+                .insert("min".to_string(), json!(self.min_length));
+            errors.push(error);
+        }
+        if self.refuse_common && COMMON_PASSWORDS.contains(password.to_lowercase().as_str()) {
+            errors.push(FieldError::new(field, "common_password"));
+        }
+        if password.eq_ignore_ascii_case(username.trim()) {
+            errors.push(FieldError::new(field, "same_as_username"));
+        }
+
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(errors: Vec<FieldError>) -> Vec<String> {
+        errors.into_iter().map(|error| error.rule).collect()
+    }
+
+    #[test]
+    fn it_should_refuse_the_weak_passwords() {
+        let policy = PasswordPolicy {
+            min_length: 8,
+            refuse_common: true,
+        };
+
+        assert_eq!(
+            rules(policy.check("password", "boris", "short")),
+            ["length"]
+        );
+        assert_eq!(
+            rules(policy.check("password", "boris", "Password123")),
+            ["common_password"]
+        );
+        assert_eq!(
+            rules(policy.check("password", "boris.chen", "Boris.Chen")),
+            ["same_as_username"]
+        );
+        assert!(policy
+            .check("password", "boris", "correct horse battery")
+            .is_empty());
+    }
+
+    #[test]
+    fn it_should_allow_the_common_passwords_if_they_are_not_refused() {
+        let policy = PasswordPolicy {
+            min_length: 8,
+            refuse_common: false,
+        };
+
+        assert!(policy.check("password", "boris", "password123").is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::domain::entities::{ResourceType, Role};
+use crate::domain::registry::{ArticleKind, ResourceKind};
+
+/// What is done to the subject
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Read,
+    Create,
+    Update,
+    Delete,
+}
+
+/// What the permissions protect
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subject {
+    Resource(ResourceType),
+    /// The users, their sessions, their login attempts and the audit log
+    Users,
+}
+
+/// Which ones of the subject the permission covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    None,
+    /// Only the ones created by the user
+    Own,
+    All,
+}
+
+/// A permission which is required by a route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Permission {
+    pub subject: Subject,
+    pub action: Action,
+}
+
+impl Permission {
+    pub fn new(subject: Subject, action: Action) -> Self {
+        Self { subject, action }
+    }
+}
+
+impl Role {
+    /** Which ones of the subject the role can act on.
+     *
+     * - owner: everything
+     * - editor: all the resources
+     * - author: reads all the resources, writes the articles and changes the own ones only
+     * - viewer: reads all the resources
+     */
+    pub fn scope(&self, permission: &Permission) -> Scope {
+        match (self, permission.subject, permission.action) {
+            (Role::Owner, _, _) => Scope::All,
+            (_, Subject::Users, _) => Scope::None,
+            (_, Subject::Resource(_), Action::Read) => Scope::All,
+            (Role::Editor, Subject::Resource(_), _) => Scope::All,
+            (Role::Author, Subject::Resource(t), Action::Create) if t == ArticleKind::TYPE => {
+                Scope::All
+            }
+            (Role::Author, Subject::Resource(t), _) if t == ArticleKind::TYPE => Scope::Own,
+            _ => Scope::None,
+        }
+    }
+
+    pub fn can(&self, permission: &Permission) -> bool {
+        self.scope(permission) != Scope::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::registry::ServiceKind;
+
+    fn resource<K: ResourceKind>(action: Action) -> Permission {
+        Permission::new(Subject::Resource(K::TYPE), action)
+    }
+
+    #[test]
+    fn it_should_let_only_the_owners_manage_the_users() {
+        let permission = Permission::new(Subject::Users, Action::Update);
+
+        assert_eq!(Role::Owner.scope(&permission), Scope::All);
+        for role in [Role::Editor, Role::Author, Role::Viewer] {
+            assert_eq!(role.scope(&permission), Scope::None);
+        }
+    }
+
+    #[test]
+    fn it_should_let_the_authors_change_their_own_articles_only() {
+        let author = Role::Author;
+
+        assert_eq!(
+            author.scope(&resource::<ArticleKind>(Action::Create)),
+            Scope::All
+        );
+        assert_eq!(
+            author.scope(&resource::<ArticleKind>(Action::Update)),
+            Scope::Own
+        );
+        assert_eq!(
+            author.scope(&resource::<ArticleKind>(Action::Delete)),
+            Scope::Own
+        );
+        assert_eq!(
+            author.scope(&resource::<ServiceKind>(Action::Read)),
+            Scope::All
+        );
+        assert_eq!(
+            author.scope(&resource::<ServiceKind>(Action::Update)),
+            Scope::None
+        );
+    }
+
+    #[test]
+    fn it_should_let_the_viewers_read_only() {
+        let viewer = Role::Viewer;
+
+        assert!(viewer.can(&resource::<ArticleKind>(Action::Read)));
+        for action in [Action::Create, Action::Update, Action::Delete] {
+            assert!(!viewer.can(&resource::<ArticleKind>(action)));
+            assert!(Role::Editor.can(&resource::<ArticleKind>(action)));
+        }
+    }
+}
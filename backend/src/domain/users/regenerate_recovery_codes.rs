@@ -0,0 +1,52 @@
+use crate::domain::entities::UserID;
+use crate::domain::users::authentication::check_code;
+use crate::domain::users::totp;
+use crate::repositories::{IRecoveryCodeRepository, IUserRepository};
+use tokio::sync::Mutex;
+
+pub struct Request {
+    pub user_id: UserID,
+    /// A code of the authenticator app or a recovery code
+    pub code: String,
+}
+
+pub enum Error {
+    NotEnabled,
+    InvalidCode,
+    Unknown(String),
+}
+
+/// Replaces the recovery codes, e.g. when most of them are used. The new ones are only shown once
+pub async fn execute(
+    req: Request,
+    user_repo: Mutex<impl IUserRepository + Sync + Send>,
+    recovery_repo: Mutex<impl IRecoveryCodeRepository + Sync + Send>,
+) -> Result<Vec<String>, Error> {
+    let user_repo = user_repo.lock().await;
+    let user = user_repo
+        .get(&req.user_id)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?
+        .filter(|user| user.totp_enabled)
+        .ok_or(Error::NotEnabled)?;
+
+    let recovery_repo = recovery_repo.lock().await;
+    if !check_code(&user.id, &req.code, &*user_repo, &*recovery_repo)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?
+    {
+        return Err(Error::InvalidCode);
+    }
+
+    let codes = totp::generate_recovery_codes();
+    let hashes = codes
+        .iter()
+        .map(|code| totp::hash_recovery_code(code))
+        .collect::<Vec<_>>();
+    recovery_repo
+        .replace(&user.id, &hashes)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    Ok(codes)
+}
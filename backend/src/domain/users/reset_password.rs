@@ -0,0 +1,248 @@
+use crate::domain::sessions::end_sessions;
+use crate::domain::users::forgot_password::hash_token;
+use crate::domain::users::password_hashing::PasswordHasher;
+use crate::domain::users::password_policy::PasswordPolicy;
+use crate::domain::validation::FieldError;
+use crate::repositories::{IPasswordResetRepository, ISessionRepository, IUserRepository};
+use chrono::Utc;
+use secrecy::{ExposeSecret, SecretBox};
+use tokio::sync::Mutex;
+
+pub struct Request {
+    /// The token of the emailed link
+    pub token: String,
+    pub new_password: SecretBox<String>,
+}
+
+pub enum Error {
+    /// The token is unknown, used or expired, or the user is disabled
+    InvalidToken,
+    /// The new password violates the policy, the token can still be used
+    Invalid(Vec<FieldError>),
+    Unknown(String),
+}
+
+/** Resets the password with the token of an emailed link.
+*
+* The token is used once, and all the sessions of the user are ended. It returns how many sessions
+* are ended.
+*/
+pub async fn execute(
+    req: Request,
+    policy: &PasswordPolicy,
+    hasher: &PasswordHasher,
+    reset_repo: Mutex<impl IPasswordResetRepository + Sync + Send>,
+    user_repo: Mutex<impl IUserRepository + Sync + Send>,
+    session_repo: Mutex<impl ISessionRepository + Sync + Send>,
+) -> Result<usize, Error> {
+    let token_hash = hash_token(req.token.trim());
+    let reset_repo = reset_repo.lock().await;
+    let user_repo = user_repo.lock().await;
+
+    let reset = reset_repo
+        .get(&token_hash)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?
+        .filter(|reset| reset.expires_at > Utc::now())
+        .ok_or(Error::InvalidToken)?;
+    let user = user_repo
+        .get(&reset.user_id)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?
+        .filter(|user| !user.disabled)
+        .ok_or(Error::InvalidToken)?;
+
+    let errors = policy.check(
+        "new_password",
+        &user.username,
+        req.new_password.expose_secret(),
+    );
+    if !errors.is_empty() {
+        return Err(Error::Invalid(errors));
+    }
+    let password_hash = hasher
+        .hash_blocking(&req.new_password)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    // the token may have been used in the meantime
+    if !reset_repo
+        .consume(&token_hash)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?
+    {
+        return Err(Error::InvalidToken);
+    }
+
+    user_repo
+        .change_password(user.id.clone(), password_hash)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+    reset_repo
+        .delete_of_user(&user.id)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    end_sessions(&*session_repo.lock().await, &user.id, None)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{PasswordReset, Session, SessionID, UserID};
+    use crate::repositories::{
+        InMemoryPasswordResetRepository, InMemorySessionRepository, InMemoryUserRepository,
+    };
+    use std::net::{IpAddr, Ipv4Addr};
+
+    const TOKEN: &str = "token";
+
+    fn policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: 10,
+            refuse_common: true,
+        }
+    }
+
+    fn secret(value: &str) -> SecretBox<String> {
+        SecretBox::new(Box::new(value.to_string()))
+    }
+
+    struct Repositories {
+        reset_repo: InMemoryPasswordResetRepository,
+        user_repo: InMemoryUserRepository,
+        session_repo: InMemorySessionRepository,
+        user_id: UserID,
+    }
+
+    /// A user who is logged in once and has asked for a token which expires in the minutes
+    async fn helper(expires_in_minutes: i64) -> Repositories {
+        let user_id = UserID::from(uuid::Uuid::new_v4());
+        let user_repo = InMemoryUserRepository::new();
+        user_repo
+            .add_credentials(
+                user_id.clone(),
+                "boris".to_string(),
+                PasswordHasher::default()
+                    .hash(&secret("forgotten password"))
+                    .unwrap(),
+            )
+            .await;
+
+        let now = Utc::now();
+        let reset_repo = InMemoryPasswordResetRepository::new();
+        let reset = PasswordReset {
+            token_hash: hash_token(TOKEN),
+            user_id: user_id.clone(),
+            expires_at: now + chrono::Duration::minutes(expires_in_minutes),
+            created_at: now,
+        };
+        reset_repo.save(&reset).await.unwrap();
+
+        let session_repo = InMemorySessionRepository::new();
+        let session = Session {
+            id: SessionID::new(),
+            user_id: user_id.clone(),
+            ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            user_agent: None,
+            created_at: now,
+            refreshed_at: now,
+            last_seen_at: now,
+            expires_at: now + chrono::Duration::days(7),
+        };
+        session_repo.save(&session, "refresh token").await.unwrap();
+
+        Repositories {
+            reset_repo,
+            user_repo,
+            session_repo,
+            user_id,
+        }
+    }
+
+    async fn reset(repos: &Repositories, token: &str, new_password: &str) -> Result<usize, Error> {
+        let req = Request {
+            token: token.to_string(),
+            new_password: secret(new_password),
+        };
+
+        execute(
+            req,
+            &policy(),
+            &PasswordHasher::default(),
+            Mutex::new(repos.reset_repo.clone()),
+            Mutex::new(repos.user_repo.clone()),
+            Mutex::new(repos.session_repo.clone()),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_should_reset_the_password_once_and_end_the_sessions() {
+        let repos = helper(30).await;
+
+        let res = reset(&repos, TOKEN, "correct horse battery").await;
+        assert!(matches!(res, Ok(1)));
+        assert!(repos
+            .session_repo
+            .list(&repos.user_id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let (_, hash) = repos
+            .user_repo
+            .get_credentials("boris")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(PasswordHasher::default()
+            .verify(&hash, &secret("correct horse battery"))
+            .is_ok());
+
+        let res = reset(&repos, TOKEN, "another horse battery").await;
+        assert!(matches!(res, Err(Error::InvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_an_unknown_or_expired_token() {
+        let repos = helper(30).await;
+        let res = reset(&repos, "unknown", "correct horse battery").await;
+        assert!(matches!(res, Err(Error::InvalidToken)));
+
+        let repos = helper(-1).await;
+        let res = reset(&repos, TOKEN, "correct horse battery").await;
+        assert!(matches!(res, Err(Error::InvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn it_should_keep_the_token_if_the_password_violates_the_policy() {
+        let repos = helper(30).await;
+
+        let res = reset(&repos, TOKEN, "boris").await;
+        assert!(matches!(res, Err(Error::Invalid(_))));
+        assert_eq!(repos.reset_repo.resets().await.len(), 1);
+        assert_eq!(
+            repos.session_repo.list(&repos.user_id).await.unwrap().len(),
+            1
+        );
+
+        let res = reset(&repos, TOKEN, "correct horse battery").await;
+        assert!(matches!(res, Ok(1)));
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_the_token_of_a_disabled_user() {
+        let repos = helper(30).await;
+        repos
+            .user_repo
+            .set_disabled(&repos.user_id, true)
+            .await
+            .unwrap();
+
+        let res = reset(&repos, TOKEN, "correct horse battery").await;
+        assert!(matches!(res, Err(Error::InvalidToken)));
+    }
+}
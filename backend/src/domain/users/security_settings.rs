@@ -0,0 +1,91 @@
+use crate::domain::audit::{entry, Actor};
+use crate::domain::entities::{AuditAction, SecuritySettings};
+use crate::repositories::{IAuditRepository, ISecuritySettingsRepository};
+use crate::uow::IUserUnitOfWork;
+use tokio::sync::Mutex;
+
+pub enum Error {
+    Unknown(String),
+}
+
+pub async fn get(
+    settings_repo: Mutex<impl ISecuritySettingsRepository + Sync + Send>,
+) -> Result<SecuritySettings, Error> {
+    settings_repo
+        .lock()
+        .await
+        .get()
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))
+}
+
+/// The users without a second factor have to enrol once they log in again after it's required
+pub async fn update<IUnitOfWork>(
+    settings: SecuritySettings,
+    actor: Option<Actor>,
+    uow: Mutex<IUnitOfWork>,
+) -> Result<SecuritySettings, Error>
+where
+    IUnitOfWork: IUserUnitOfWork,
+{
+    let mut lock = uow.lock().await;
+    let before = lock
+        .security_settings_repository()
+        .get()
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+    lock.security_settings_repository()
+        .save(&settings)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    let audit = entry(
+        actor.as_ref(),
+        AuditAction::Update,
+        "security_settings",
+        "global".to_string(),
+    )
+    .with_snapshots(
+        serde_json::to_value(&before).ok(),
+        serde_json::to_value(&settings).ok(),
+    );
+    lock.audit_repository()
+        .save(&audit)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+    drop(lock);
+
+    uow.into_inner()
+        .commit()
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::{
+        InMemoryAuditRepository, InMemorySecuritySettingsRepository, InMemoryUserRepository,
+    };
+    use crate::uow::user::InMemory;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn it_should_update_the_settings_and_audit_them() {
+        let settings_repo = InMemorySecuritySettingsRepository::new();
+        let audit_repo = InMemoryAuditRepository::new();
+        let uow = InMemory::new(InMemoryUserRepository::new(), audit_repo.clone())
+            .with_security_settings_repository(settings_repo.clone());
+
+        let settings = SecuritySettings { require_totp: true };
+        assert!(update(settings, None, Mutex::new(uow)).await.is_ok());
+
+        assert!(settings_repo.get().await.unwrap().require_totp);
+        let entries = audit_repo.entries().await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].before, Some(json!({ "require_totp": false })));
+        assert_eq!(entries[0].after, Some(json!({ "require_totp": true })));
+    }
+}
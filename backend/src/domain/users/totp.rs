@@ -0,0 +1,171 @@
+use chrono::{DateTime, Utc};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+/// The secrets have 160 bits, the length of the HMAC-SHA1 keys
+const SECRET_BYTES: usize = 20;
+
+const DIGITS: u32 = 6;
+
+const STEP_SECONDS: i64 = 30;
+
+/// The codes of the previous and the next steps are accepted too, since the clocks drift
+const SKEW_STEPS: i64 = 1;
+
+const RECOVERY_CODES: usize = 10;
+
+/// How the users log in with the codes of their authenticator apps, see RFC 6238
+#[derive(Debug, Clone)]
+pub struct TotpPolicy {
+    /// The name of the account in the authenticator apps, e.g. the name of the website
+    pub issuer: String,
+    /// How long the code can be given after the password
+    pub challenge_ttl: chrono::Duration,
+}
+
+/// A new secret, encoded in base32 as the authenticator apps expect
+pub fn generate_secret() -> String {
+    BASE32_NOPAD.encode(&rand::random::<[u8; SECRET_BYTES]>())
+}
+
+/// The URI of the QR code which the authenticator apps scan
+pub fn uri(issuer: &str, username: &str, secret: &str) -> String {
+    let issuer = utf8_percent_encode(issuer, NON_ALPHANUMERIC).to_string();
+    let username = utf8_percent_encode(username, NON_ALPHANUMERIC).to_string();
+
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        issuer, username, secret, issuer, DIGITS, STEP_SECONDS
+    )
+}
+
+/// The code of the time step, see RFC 4226 for the truncation
+fn code(key: &[u8], step: i64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC takes a key of any size");
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(DIGITS),
+        width = DIGITS as usize
+    )
+}
+
+/// The code of the secret at the time, as the authenticator apps show it
+#[cfg(test)]
+pub fn code_at(secret: &str, at: DateTime<Utc>) -> String {
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).unwrap();
+    code(&key, at.timestamp().div_euclid(STEP_SECONDS))
+}
+
+/// Whether the code looks like a code of an authenticator app rather than a recovery code
+pub fn is_code(code: &str) -> bool {
+    code.len() == DIGITS as usize && code.chars().all(|c| c.is_ascii_digit())
+}
+
+/// The time step which the code is of, it's none if the code is wrong at the time
+pub fn verify(secret: &str, code: &str, at: DateTime<Utc>) -> Option<i64> {
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    let current = at.timestamp().div_euclid(STEP_SECONDS);
+
+    // every step is checked, so the time doesn't tell which one matches
+    let mut matched = None;
+    for step in current - SKEW_STEPS..=current + SKEW_STEPS {
+        if bool::from(self::code(&key, step).as_bytes().ct_eq(code.as_bytes())) {
+            matched = Some(step);
+        }
+    }
+
+    matched
+}
+
+/// New recovery codes, e.g. `k3v9q-7mx2d`
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODES)
+        .map(|_| {
+            let code = BASE32_NOPAD
+                .encode(&rand::random::<[u8; 7]>())
+                .to_lowercase();
+            format!("{}-{}", &code[..5], &code[5..10])
+        })
+        .collect()
+}
+
+/// Only the hashes of the recovery codes are kept, they're compared regardless of the case and dashes
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// The secret of the test vectors of RFC 6238
+    const KEY: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn it_should_generate_the_codes_of_the_rfc() {
+        // the last 6 digits of the 8 digit codes of the RFC
+        for (time, expected) in [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+        ] {
+            assert_eq!(code(KEY, time / STEP_SECONDS), expected);
+        }
+    }
+
+    #[test]
+    fn it_should_accept_the_codes_of_the_adjacent_steps_only() {
+        let secret = BASE32_NOPAD.encode(KEY);
+        let at = Utc.timestamp_opt(1111111109, 0).unwrap();
+        let step = 1111111109 / STEP_SECONDS;
+
+        assert_eq!(verify(&secret, "081804", at), Some(step));
+        assert_eq!(verify(&secret, &code(KEY, step - 1), at), Some(step - 1));
+        assert_eq!(verify(&secret, &code(KEY, step + 1), at), Some(step + 1));
+        assert_eq!(verify(&secret, &code(KEY, step - 2), at), None);
+        assert_eq!(verify(&secret, "000000", at), None);
+    }
+
+    #[test]
+    fn it_should_escape_the_names_in_the_uri() {
+        assert_eq!(
+            uri("Law Firm", "boris", "JBSWY3DPEHPK3PXP"),
+            "otpauth://totp/Law%20Firm:boris?secret=JBSWY3DPEHPK3PXP&issuer=Law%20Firm&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+
+    #[test]
+    fn it_should_hash_the_recovery_codes_regardless_of_the_format() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODES);
+        assert!(codes.iter().all(|code| code.len() == 11 && !is_code(code)));
+
+        assert_eq!(
+            hash_recovery_code("k3v9q-7mx2d"),
+            hash_recovery_code(" K3V9Q7MX2D ")
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use crate::domain::entities::UserID;
+use crate::repositories::{IRecoveryCodeRepository, ISecuritySettingsRepository, IUserRepository};
+use tokio::sync::Mutex;
+
+pub struct Request {
+    pub user_id: UserID,
+}
+
+pub struct Status {
+    pub enabled: bool,
+    /// The second factor is required for all the users, so it can't be disabled
+    pub required: bool,
+    pub recovery_codes_left: usize,
+}
+
+pub enum Error {
+    NotFound,
+    Unknown(String),
+}
+
+/// Whether the user logs in with a second factor
+pub async fn execute(
+    req: Request,
+    user_repo: Mutex<impl IUserRepository + Sync + Send>,
+    recovery_repo: Mutex<impl IRecoveryCodeRepository + Sync + Send>,
+    settings_repo: Mutex<impl ISecuritySettingsRepository + Sync + Send>,
+) -> Result<Status, Error> {
+    let user = user_repo
+        .lock()
+        .await
+        .get(&req.user_id)
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?
+        .ok_or(Error::NotFound)?;
+    let settings = settings_repo
+        .lock()
+        .await
+        .get()
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))?;
+
+    // the codes of a reset second factor are left until it's enrolled again
+    let recovery_codes_left = match user.totp_enabled {
+        true => recovery_repo
+            .lock()
+            .await
+            .count(&user.id)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?,
+        false => 0,
+    };
+
+    Ok(Status {
+        enabled: user.totp_enabled,
+        required: settings.require_totp,
+        recovery_codes_left,
+    })
+}
@@ -0,0 +1,105 @@
+use crate::domain::audit::{entry, Actor};
+use crate::domain::entities::{AuditAction, LoginAttempt, LoginOutcome};
+use crate::domain::users::authentication::Attempt;
+use crate::repositories::{IAuditRepository, ILoginAttemptRepository};
+use crate::uow::IUserUnitOfWork;
+use chrono::Utc;
+use tokio::sync::Mutex;
+
+pub struct Request {
+    pub username: String,
+    /// Where the admin unlocks the username
+    pub attempt: Attempt,
+    pub actor: Option<Actor>,
+}
+
+pub enum Error {
+    BadRequest,
+    Unknown(String),
+}
+
+/** Forgives the failures of the username, so it can log in again.
+*
+* The IPs which fail too many times are still locked out until the lockout is over.
+*/
+pub async fn execute<IUnitOfWork>(req: Request, uow: Mutex<IUnitOfWork>) -> Result<(), Error>
+where
+    IUnitOfWork: IUserUnitOfWork,
+{
+    if req.username.trim().is_empty() {
+        return Err(Error::BadRequest);
+    }
+
+    let audit = entry(
+        req.actor.as_ref(),
+        AuditAction::Unlock,
+        "username",
+        req.username.clone(),
+    );
+    let unlocked = LoginAttempt {
+        username: req.username,
+        ip: req.attempt.ip,
+        user_agent: req.attempt.user_agent,
+        outcome: LoginOutcome::Unlocked,
+        attempted_at: Utc::now(),
+    };
+
+    {
+        let mut lock = uow.lock().await;
+        lock.login_attempt_repository()
+            .save(unlocked)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
+        lock.audit_repository()
+            .save(&audit)
+            .await
+            .map_err(|e| Error::Unknown(e.to_string()))?;
+    }
+
+    uow.into_inner()
+        .commit()
+        .await
+        .map_err(|e| Error::Unknown(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::UserID;
+    use crate::repositories::{
+        InMemoryAuditRepository, InMemoryLoginAttemptRepository, InMemoryUserRepository,
+    };
+    use crate::uow::user::InMemory;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[tokio::test]
+    async fn it_should_unlock_the_username_and_audit_it() {
+        let attempt_repo = InMemoryLoginAttemptRepository::new();
+        let audit_repo = InMemoryAuditRepository::new();
+        let uow = InMemory::new(InMemoryUserRepository::new(), audit_repo.clone())
+            .with_login_attempt_repository(attempt_repo.clone());
+        let admin = UserID::from(uuid::Uuid::new_v4());
+
+        let req = Request {
+            username: "boris".to_string(),
+            attempt: Attempt {
+                ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+                user_agent: None,
+            },
+            actor: Some(Actor {
+                user_id: admin.clone(),
+                ip: None,
+            }),
+        };
+        assert!(execute(req, Mutex::new(uow)).await.is_ok());
+
+        let attempts = attempt_repo.list(Some("boris"), None, 10).await.unwrap();
+        assert_eq!(attempts[0].outcome, LoginOutcome::Unlocked);
+
+        let entries = audit_repo.entries().await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, AuditAction::Unlock);
+        assert_eq!(entries[0].user_id, Some(admin));
+        assert_eq!(entries[0].target_id, "boris");
+    }
+}
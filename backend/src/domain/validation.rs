@@ -0,0 +1,165 @@
+use crate::domain::entities::Language;
+use serde_json::{Map, Value};
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+/// A field which violates a rule of the validation
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    /// The path of the field in the request, e.g. `tags[0]`
+    pub path: String,
+    /// The code of the rule, e.g. `length`
+    pub rule: String,
+    /// The arguments of the rule, e.g. `min`
+    pub params: Map<String, Value>,
+}
+
+impl FieldError {
+    pub fn new(path: impl Into<String>, rule: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            rule: rule.into(),
+            params: Map::new(),
+        }
+    }
+
+    /// Flattens the nested errors of the validator, the fields are sorted by their paths
+    pub fn from_validation_errors(errors: &ValidationErrors) -> Vec<Self> {
+        let mut fields = vec![];
+        collect(errors, None, &mut fields);
+        fields.sort_by(|a, b| a.path.cmp(&b.path));
+
+        fields
+    }
+
+    /// The message which describes the violation in the language
+    pub fn message(&self, language: &Language) -> String {
+        let param = |key: &str| self.params.get(key).map(|value| value.to_string());
+
+        match (self.rule.as_str(), language) {
+            ("length", _) => match (param("min"), param("max"), language) {
+                (Some(min), None, Language::ZH) if min == "1" => "不能為空".to_string(),
+                (Some(min), None, Language::EN) if min == "1" => "can't be empty".to_string(),
+                (Some(min), None, Language::ZH) => format!("至少需要 {} 個字", min),
+                (Some(min), None, Language::EN) => format!("must have at least {} characters", min),
+                (None, Some(max), Language::ZH) => format!("最多只能有 {} 個字", max),
+                (None, Some(max), Language::EN) => format!("must have at most {} characters", max),
+                (Some(min), Some(max), Language::ZH) => format!("需要 {} 到 {} 個字", min, max),
+                (Some(min), Some(max), Language::EN) => {
+                    format!("must have {} to {} characters", min, max)
+                }
+                (None, None, Language::ZH) => "長度不正確".to_string(),
+                (None, None, Language::EN) => "has an invalid length".to_string(),
+            },
+            ("range", _) => match (param("min"), param("max"), language) {
+                (Some(min), Some(max), Language::ZH) => format!("需要介於 {} 到 {} 之間", min, max),
+                (Some(min), Some(max), Language::EN) => {
+                    format!("must be between {} and {}", min, max)
+                }
+                (Some(min), None, Language::ZH) => format!("不能小於 {}", min),
+                (Some(min), None, Language::EN) => format!("must be at least {}", min),
+                (None, Some(max), Language::ZH) => format!("不能大於 {}", max),
+                (None, Some(max), Language::EN) => format!("must be at most {}", max),
+                (None, None, Language::ZH) => "超出範圍".to_string(),
+                (None, None, Language::EN) => "is out of range".to_string(),
+            },
+            ("common_password", Language::ZH) => "太常見了，很容易被猜到".to_string(),
+            ("common_password", Language::EN) => "is too common to be safe".to_string(),
+            ("same_as_username", Language::ZH) => "不能和使用者名稱相同".to_string(),
+            ("same_as_username", Language::EN) => "can't be the same as the username".to_string(),
+            ("language", Language::ZH) => "只支援 zh 或 en".to_string(),
+            ("language", Language::EN) => "must be either zh or en".to_string(),
+            ("cycle", Language::ZH) => "不能指向自己或自己的下層".to_string(),
+            ("cycle", Language::EN) => "can't lead back to the resource itself".to_string(),
+            (_, Language::ZH) => "格式不正確".to_string(),
+            (_, Language::EN) => "is invalid".to_string(),
+        }
+    }
+}
+
+fn collect(errors: &ValidationErrors, prefix: Option<&str>, fields: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let path = match prefix {
+            Some(prefix) => format!("{}.{}", prefix, field),
+            None => field.to_string(),
+        };
+
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                fields.extend(errors.iter().map(|error| {
+                    FieldError {
+                        path: path.clone(),
+                        rule: error.code.to_string(),
+                        // the rejected value is left out, the client already has it
+                        params: error
+                            .params
+                            .iter()
+                            .filter(|(key, _)| *key != "value")
+                            .map(|(key, value)| (key.to_string(), value.clone()))
+                            .collect(),
+                    }
+                }))
+            }
+            ValidationErrorsKind::Struct(errors) => collect(errors, Some(&path), fields),
+            ValidationErrorsKind::List(items) => {
+                for (index, errors) in items {
+                    collect(errors, Some(&format!("{}[{}]", path, index)), fields);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{CaseResultData, MemberData};
+    use validator::Validate;
+
+    #[test]
+    fn it_should_list_every_failing_field() {
+        let errors = MemberData::new("".to_string(), "".to_string())
+            .validate()
+            .unwrap_err();
+
+        let fields = FieldError::from_validation_errors(&errors);
+
+        let paths = fields.iter().map(|f| f.path.as_str()).collect::<Vec<_>>();
+        assert_eq!(paths, vec!["description", "name"]);
+        assert!(fields.iter().all(|f| f.rule == "length"));
+        assert!(fields.iter().all(|f| !f.params.contains_key("value")));
+    }
+
+    #[test]
+    fn it_should_describe_the_rule_in_the_language() {
+        let errors = CaseResultData::new(
+            "divorce".to_string(),
+            "won".to_string(),
+            1800,
+            vec![],
+            vec![],
+            true,
+        )
+        .validate()
+        .unwrap_err();
+
+        let fields = FieldError::from_validation_errors(&errors);
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].path, "year");
+        assert_eq!(fields[0].rule, "range");
+        assert_eq!(
+            fields[0].message(&Language::EN),
+            "must be between 1900 and 2100"
+        );
+        assert_eq!(
+            fields[0].message(&Language::ZH),
+            "需要介於 1900 到 2100 之間"
+        );
+
+        let empty = FieldError {
+            params: Map::from_iter([("min".to_string(), Value::from(1))]),
+            ..FieldError::new("name", "length")
+        };
+        assert_eq!(empty.message(&Language::EN), "can't be empty");
+        assert_eq!(empty.message(&Language::ZH), "不能為空");
+    }
+}
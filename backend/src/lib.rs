@@ -3,8 +3,10 @@ pub use startup::run;
 pub use telemetry::{get_subscriber, init_subscriber, spawn_blocking_with_tracing};
 
 mod api;
+pub mod cache;
 mod configuration;
 pub mod domain;
+pub mod mailer;
 pub mod repositories;
 mod startup;
 mod telemetry;
@@ -0,0 +1,20 @@
+pub use outbox_mailer::OutboxMailer;
+pub use smtp_mailer::SmtpMailer;
+
+mod outbox_mailer;
+
+mod smtp_mailer;
+
+/// A plain text email to a single recipient, the sender is the one of the mailer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Sends the emails of the server, e.g. the links to reset the passwords
+#[async_trait::async_trait]
+pub trait IMailer: Send + Sync {
+    async fn send(&self, email: &Email) -> anyhow::Result<()>;
+}
@@ -0,0 +1,67 @@
+use crate::mailer::{Email, IMailer};
+use std::path::PathBuf;
+use ulid::Ulid;
+
+/** Writes the emails into a folder instead of sending them, for the local development and the
+* tests.
+*
+* Every email is a text file, named by a ULID so they are listed in the order they are sent.
+*/
+pub struct OutboxMailer {
+    folder: PathBuf,
+    from: String,
+}
+
+impl OutboxMailer {
+    pub fn new(folder: impl Into<PathBuf>, from: &str) -> Self {
+        Self {
+            folder: folder.into(),
+            from: from.to_string(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl IMailer for OutboxMailer {
+    async fn send(&self, email: &Email) -> anyhow::Result<()> {
+        let content = format!(
+            "From: {}\nTo: {}\nSubject: {}\n\n{}\n",
+            self.from, email.to, email.subject, email.body
+        );
+
+        tokio::fs::create_dir_all(&self.folder).await?;
+        let path = self.folder.join(format!("{}.txt", Ulid::new()));
+        tokio::fs::write(&path, content).await?;
+
+        tracing::info!("The email to {} is written to {}", email.to, path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_should_write_the_emails_into_the_folder() {
+        let folder = std::env::temp_dir().join(format!("outbox-{}", Ulid::new()));
+        let mailer = OutboxMailer::new(&folder, "no-reply@example.com");
+
+        let email = Email {
+            to: "boris@example.com".to_string(),
+            subject: "Hello".to_string(),
+            body: "The body".to_string(),
+        };
+        mailer.send(&email).await.unwrap();
+
+        let mut entries = std::fs::read_dir(&folder).unwrap();
+        let path = entries.next().unwrap().unwrap().path();
+        assert!(entries.next().is_none());
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "From: no-reply@example.com\nTo: boris@example.com\nSubject: Hello\n\nThe body\n"
+        );
+
+        std::fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -0,0 +1,45 @@
+use crate::mailer::{Email, IMailer};
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+/// Sends the emails through an SMTP server, the connection is upgraded with STARTTLS
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(
+        host: &str,
+        port: u16,
+        credentials: Option<(String, String)>,
+        from: &str,
+    ) -> anyhow::Result<Self> {
+        let mut transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?.port(port);
+        if let Some((username, password)) = credentials {
+            transport = transport.credentials(Credentials::new(username, password));
+        }
+
+        Ok(Self {
+            transport: transport.build(),
+            from: from.parse()?,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl IMailer for SmtpMailer {
+    async fn send(&self, email: &Email) -> anyhow::Result<()> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(email.to.parse()?)
+            .subject(email.subject.as_str())
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.body.clone())?;
+
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
@@ -2,6 +2,7 @@ use crate::repositories::Connection;
 use anyhow::anyhow;
 use sqlx::{Acquire, PgConnection};
 use std::net::IpAddr;
+#[cfg(test)]
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
@@ -15,6 +16,7 @@ pub trait IArticleViewsRepository {
     ) -> anyhow::Result<Uuid>;
 }
 
+#[cfg(test)]
 pub struct InMemoryArticleViewsRepository {
     error: bool,
     data: Mutex<Vec<(Uuid, String, IpAddr, String)>>,
@@ -27,8 +29,8 @@ impl Default for InMemoryArticleViewsRepository {
     }
 }
 
+#[cfg(test)]
 impl InMemoryArticleViewsRepository {
-    #[cfg(test)]
     pub fn new() -> Self {
         Self {
             error: false,
@@ -36,7 +38,6 @@ impl InMemoryArticleViewsRepository {
         }
     }
 
-    #[cfg(test)]
     pub fn with_error(self) -> Self {
         Self {
             error: true,
@@ -45,6 +46,7 @@ impl InMemoryArticleViewsRepository {
     }
 }
 
+#[cfg(test)]
 #[async_trait::async_trait]
 impl IArticleViewsRepository for InMemoryArticleViewsRepository {
     async fn save(
@@ -0,0 +1,210 @@
+use crate::domain::audit::AuditFilter;
+use crate::domain::entities::{AuditAction, AuditEntry, Language, UserID};
+use crate::repositories::Connection;
+use anyhow::anyhow;
+use sqlx::{Acquire, PgConnection, Postgres, QueryBuilder, Row};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+#[async_trait::async_trait]
+pub trait IAuditRepository {
+    async fn save(&self, entry: &AuditEntry) -> anyhow::Result<()>;
+
+    /// The latest entries which match the filter
+    async fn list(&self, filter: &AuditFilter, limit: usize) -> anyhow::Result<Vec<AuditEntry>>;
+}
+
+/// The clones share the entries
+#[derive(Clone)]
+pub struct InMemoryAuditRepository {
+    error: bool,
+    entries: Arc<Mutex<Vec<AuditEntry>>>,
+}
+
+impl Default for InMemoryAuditRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InMemoryAuditRepository {
+    pub fn new() -> Self {
+        Self {
+            error: false,
+            entries: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn with_error(self) -> Self {
+        Self {
+            error: true,
+            ..self
+        }
+    }
+
+    /// The saved entries, the oldest one first
+    pub async fn entries(&self) -> Vec<AuditEntry> {
+        self.entries.lock().await.clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl IAuditRepository for InMemoryAuditRepository {
+    async fn save(&self, entry: &AuditEntry) -> anyhow::Result<()> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        self.entries.lock().await.push(entry.clone());
+        Ok(())
+    }
+
+    async fn list(&self, filter: &AuditFilter, limit: usize) -> anyhow::Result<Vec<AuditEntry>> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        let entries = self.entries.lock().await;
+        Ok(entries
+            .iter()
+            .rev()
+            .filter(|entry| filter.matches(entry))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+}
+
+#[derive(Debug)]
+pub struct SqlxAuditRepository<'tx> {
+    conn: Connection<'tx>,
+}
+
+impl<'tx> SqlxAuditRepository<'tx> {
+    pub fn new(conn: Connection<'tx>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl IAuditRepository for SqlxAuditRepository<'_> {
+    async fn save(&self, entry: &AuditEntry) -> anyhow::Result<()> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                save(conn, entry).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                save(conn, entry).await
+            }
+        }
+    }
+
+    async fn list(&self, filter: &AuditFilter, limit: usize) -> anyhow::Result<Vec<AuditEntry>> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                list(conn, filter, limit).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                list(conn, filter, limit).await
+            }
+        }
+    }
+}
+
+async fn save(conn: &mut PgConnection, entry: &AuditEntry) -> anyhow::Result<()> {
+    sqlx::query(
+        "insert into audit_log (user_id, ip, action, target_type, target_id, language, before_snapshot, after_snapshot, created_at) values ($1, $2, $3, $4, $5, $6, $7, $8, $9);",
+    )
+    .bind(entry.user_id.as_ref().map(|id| *id.as_uuid()))
+    .bind(entry.ip)
+    .bind(entry.action.as_str())
+    .bind(&entry.target_type)
+    .bind(&entry.target_id)
+    .bind(entry.language.as_ref().map(|language| language.as_str()))
+    .bind(&entry.before)
+    .bind(&entry.after)
+    .bind(entry.created_at)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn list(
+    conn: &mut PgConnection,
+    filter: &AuditFilter,
+    limit: usize,
+) -> anyhow::Result<Vec<AuditEntry>> {
+    let mut query = QueryBuilder::<Postgres>::new(
+        r#"select user_id, ip, action, target_type, target_id, language, before_snapshot, after_snapshot, created_at
+        from audit_log
+        where true"#,
+    );
+    if let Some(user_id) = &filter.user_id {
+        query.push(" and user_id = ").push_bind(*user_id.as_uuid());
+    }
+    if let Some(action) = filter.action {
+        query.push(" and action = ").push_bind(action.as_str());
+    }
+    if let Some(target_type) = &filter.target_type {
+        query
+            .push(" and target_type = ")
+            .push_bind(target_type.as_str());
+    }
+    if let Some(target_id) = &filter.target_id {
+        query
+            .push(" and target_id = ")
+            .push_bind(target_id.as_str());
+    }
+    if let Some(language) = &filter.language {
+        query.push(" and language = ").push_bind(language.as_str());
+    }
+    if let Some(since) = filter.since {
+        query.push(" and created_at >= ").push_bind(since);
+    }
+    if let Some(until) = filter.until {
+        query.push(" and created_at < ").push_bind(until);
+    }
+    query
+        .push(" order by created_at desc limit ")
+        .push_bind(limit as i64);
+
+    let rows = query.build().fetch_all(conn).await?;
+
+    rows.into_iter()
+        .map(|row| {
+            let action = row.get::<String, usize>(2);
+            let language = row.get::<Option<String>, usize>(5);
+
+            Ok(AuditEntry {
+                user_id: row.get::<Option<uuid::Uuid>, usize>(0).map(UserID::from),
+                ip: row.get::<Option<std::net::IpAddr>, usize>(1),
+                action: AuditAction::try_from(action.as_str())
+                    .map_err(|_| anyhow!("invalid audit action: {}", action))?,
+                target_type: row.get::<String, usize>(3),
+                target_id: row.get::<String, usize>(4),
+                language: language
+                    .map(Language::try_from)
+                    .transpose()
+                    .map_err(|_| anyhow!("invalid language"))?,
+                before: row.get::<Option<serde_json::Value>, usize>(6),
+                after: row.get::<Option<serde_json::Value>, usize>(7),
+                created_at: row.get::<chrono::DateTime<chrono::Utc>, usize>(8),
+            })
+        })
+        .collect()
+}
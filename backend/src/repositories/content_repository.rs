@@ -3,6 +3,7 @@ use crate::repositories::Connection;
 use anyhow::anyhow;
 use sqlx::{Acquire, PgConnection, Row};
 use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::sync::Mutex;
 
 #[async_trait::async_trait]
@@ -14,20 +15,38 @@ pub trait IContentRepository {
         language: Language,
     ) -> anyhow::Result<ContentID>;
 
+    /// Updates the content if its version is the expected one, the version is increased
     async fn update(
         &self,
         id: &ContentID,
         data: ContentData,
         language: Language,
-    ) -> anyhow::Result<()>;
+        expected_version: Option<i32>,
+    ) -> anyhow::Result<UpdateOutcome>;
 
     async fn contains(&self, id: &ContentID, language: &Language) -> anyhow::Result<bool>;
+
+    /// Increases the version in every language, e.g. the avatar of the resource is replaced
+    async fn touch(&self, id: &ContentID) -> anyhow::Result<()>;
 }
 
-#[derive(Debug)]
+/// The result of an update which has a precondition on the version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateOutcome {
+    Updated {
+        version: i32,
+    },
+    /// The content has been updated by someone else, nothing is written
+    VersionMismatch {
+        current: i32,
+    },
+}
+
+/// The clones share the contents, so another repository can bump their versions
+#[derive(Debug, Clone)]
 pub struct InMemoryContentRepository {
     error: bool,
-    content: Mutex<HashMap<String, ContentData>>,
+    content: Arc<Mutex<HashMap<String, (ContentData, i32)>>>,
 }
 
 impl Default for InMemoryContentRepository {
@@ -40,7 +59,7 @@ impl InMemoryContentRepository {
     pub fn new() -> Self {
         Self {
             error: false,
-            content: Mutex::new(HashMap::new()),
+            content: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -63,7 +82,18 @@ impl InMemoryContentRepository {
         let lock = self.content.lock().await;
         let key = format!("{}_{}", id.as_str(), language.as_str());
 
-        Ok(lock.get(&key).cloned())
+        Ok(lock.get(&key).map(|(data, _)| data.clone()))
+    }
+
+    pub async fn version(&self, id: &ContentID, language: &Language) -> anyhow::Result<i32> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        let lock = self.content.lock().await;
+        let key = format!("{}_{}", id.as_str(), language.as_str());
+
+        Ok(lock.get(&key).map(|(_, version)| *version).unwrap_or(1))
     }
 
     pub async fn list(&self, language: &Language) -> anyhow::Result<Vec<(String, ContentData)>> {
@@ -75,7 +105,7 @@ impl InMemoryContentRepository {
         let values = lock
             .iter()
             .filter(|(key, _)| key.ends_with(language.as_str()))
-            .map(|(key, value)| {
+            .map(|(key, (value, _))| {
                 let id = key.split("_").next().unwrap().to_string();
                 (id, value.clone())
             })
@@ -104,7 +134,7 @@ impl IContentRepository for InMemoryContentRepository {
             return Err(anyhow!("{} already exists", id.as_str()));
         }
 
-        lock.insert(key, content);
+        lock.insert(key, (content, 1));
 
         Ok(id)
     }
@@ -114,7 +144,8 @@ impl IContentRepository for InMemoryContentRepository {
         id: &ContentID,
         data: ContentData,
         language: Language,
-    ) -> anyhow::Result<()> {
+        expected_version: Option<i32>,
+    ) -> anyhow::Result<UpdateOutcome> {
         if self.error {
             return Err(anyhow!("Internal Server Error"));
         }
@@ -122,13 +153,18 @@ impl IContentRepository for InMemoryContentRepository {
         let mut lock = self.content.lock().await;
 
         let key = format!("{}_{}", id.as_str(), language.as_str());
-        if !lock.contains_key(&key) {
+        let Some((content, version)) = lock.get_mut(&key) else {
             return Err(anyhow!("{} doesn't exists", id.as_str()));
+        };
+
+        if expected_version.is_some_and(|expected| expected != *version) {
+            return Ok(UpdateOutcome::VersionMismatch { current: *version });
         }
 
-        lock.entry(key).and_modify(|e| *e = data);
+        *content = data;
+        *version += 1;
 
-        Ok(())
+        Ok(UpdateOutcome::Updated { version: *version })
     }
 
     async fn contains(&self, id: &ContentID, language: &Language) -> anyhow::Result<bool> {
@@ -141,6 +177,25 @@ impl IContentRepository for InMemoryContentRepository {
         let key = format!("{}_{}", id.as_str(), language.as_str());
         Ok(lock.contains_key(key.as_str()))
     }
+
+    async fn touch(&self, id: &ContentID) -> anyhow::Result<()> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        let mut lock = self.content.lock().await;
+
+        let prefix = format!("{}_", id.as_str());
+        for (_, version) in lock
+            .iter_mut()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .map(|(_, value)| value)
+        {
+            *version += 1;
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -184,20 +239,21 @@ impl IContentRepository for SqlxContentRepository<'_> {
         id: &ContentID,
         data: ContentData,
         language: Language,
-    ) -> anyhow::Result<()> {
+        expected_version: Option<i32>,
+    ) -> anyhow::Result<UpdateOutcome> {
         match &self.conn {
             Connection::Pool(pool) => {
                 let mut conn = pool.acquire().await?;
                 let conn = conn.as_mut();
 
-                update(conn, id, data, language).await
+                update(conn, id, data, language, expected_version).await
             }
             Connection::Transaction(tx) => {
                 let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
                 let mut lock = conn_ptr.lock().await;
                 let conn = lock.acquire().await?;
 
-                update(conn, id, data, language).await
+                update(conn, id, data, language, expected_version).await
             }
         }
     }
@@ -219,6 +275,24 @@ impl IContentRepository for SqlxContentRepository<'_> {
             }
         }
     }
+
+    async fn touch(&self, id: &ContentID) -> anyhow::Result<()> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                touch(conn, id).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                touch(conn, id).await
+            }
+        }
+    }
 }
 
 async fn create(
@@ -244,16 +318,42 @@ async fn update(
     id: &ContentID,
     data: ContentData,
     language: Language,
-) -> anyhow::Result<()> {
-    sqlx::query(
-        "UPDATE \"content\" SET data = $1, updated_at = now() WHERE id = $2 AND language = $3;",
+    expected_version: Option<i32>,
+) -> anyhow::Result<UpdateOutcome> {
+    // the version is checked and increased in one statement, so the concurrent updates can't
+    // both pass the check
+    let version = sqlx::query_scalar::<_, i32>(
+        "UPDATE \"content\" SET data = $1, updated_at = now(), version = version + 1 WHERE id = $2 AND language = $3 AND ($4::int4 IS NULL OR version = $4) RETURNING version;",
     )
     .bind(data.as_json())
     .bind(id.as_str())
     .bind(language.as_str())
-    .execute(conn)
+    .bind(expected_version)
+    .fetch_optional(&mut *conn)
     .await?;
 
+    if let Some(version) = version {
+        return Ok(UpdateOutcome::Updated { version });
+    }
+
+    let current = sqlx::query_scalar::<_, i32>(
+        "SELECT version FROM \"content\" WHERE id = $1 AND language = $2;",
+    )
+    .bind(id.as_str())
+    .bind(language.as_str())
+    .fetch_optional(conn)
+    .await?
+    .ok_or(anyhow!("{} doesn't exists", id.as_str()))?;
+
+    Ok(UpdateOutcome::VersionMismatch { current })
+}
+
+async fn touch(conn: &mut PgConnection, id: &ContentID) -> anyhow::Result<()> {
+    sqlx::query("UPDATE \"content\" SET updated_at = now(), version = version + 1 WHERE id = $1;")
+        .bind(id.as_str())
+        .execute(conn)
+        .await?;
+
     Ok(())
 }
 
@@ -0,0 +1,341 @@
+use crate::domain::entities::{LoginAttempt, LoginOutcome};
+use crate::repositories::Connection;
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use sqlx::{Acquire, PgConnection, Row};
+use std::net::IpAddr;
+#[cfg(test)]
+use std::sync::Arc;
+#[cfg(test)]
+use tokio::sync::Mutex;
+
+/// The failed attempts which are counted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Failures {
+    pub count: usize,
+    /// When the last failure happened
+    pub last_at: Option<DateTime<Utc>>,
+}
+
+#[async_trait::async_trait]
+pub trait ILoginAttemptRepository {
+    async fn save(&self, attempt: LoginAttempt) -> anyhow::Result<()>;
+
+    /// The failures of the username since the time, the ones before its last success or unlock are forgiven
+    async fn failures_of_username(
+        &self,
+        username: &str,
+        since: DateTime<Utc>,
+    ) -> anyhow::Result<Failures>;
+
+    /// The failures from the IP since the time, whichever username is tried
+    async fn failures_of_ip(&self, ip: IpAddr, since: DateTime<Utc>) -> anyhow::Result<Failures>;
+
+    /// The latest attempts, they can be narrowed down to a username or an IP
+    async fn list(
+        &self,
+        username: Option<&str>,
+        ip: Option<IpAddr>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<LoginAttempt>>;
+}
+
+/// The clones share the attempts
+#[cfg(test)]
+#[derive(Clone)]
+pub struct InMemoryLoginAttemptRepository {
+    error: bool,
+    attempts: Arc<Mutex<Vec<LoginAttempt>>>,
+}
+
+#[cfg(test)]
+impl Default for InMemoryLoginAttemptRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+impl InMemoryLoginAttemptRepository {
+    pub fn new() -> Self {
+        Self {
+            error: false,
+            attempts: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn with_error(self) -> Self {
+        Self {
+            error: true,
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+fn count_failures<'a>(attempts: impl Iterator<Item = &'a LoginAttempt>) -> Failures {
+    attempts
+        .filter(|attempt| attempt.outcome == LoginOutcome::Failed)
+        .fold(Failures::default(), |failures, attempt| Failures {
+            count: failures.count + 1,
+            last_at: failures.last_at.max(Some(attempt.attempted_at)),
+        })
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl ILoginAttemptRepository for InMemoryLoginAttemptRepository {
+    async fn save(&self, attempt: LoginAttempt) -> anyhow::Result<()> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        self.attempts.lock().await.push(attempt);
+        Ok(())
+    }
+
+    async fn failures_of_username(
+        &self,
+        username: &str,
+        since: DateTime<Utc>,
+    ) -> anyhow::Result<Failures> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        let attempts = self.attempts.lock().await;
+        let forgiven_at = attempts
+            .iter()
+            .filter(|attempt| attempt.username == username)
+            .filter(|attempt| {
+                matches!(
+                    attempt.outcome,
+                    LoginOutcome::Succeeded | LoginOutcome::Unlocked
+                )
+            })
+            .map(|attempt| attempt.attempted_at)
+            .max();
+
+        Ok(count_failures(attempts.iter().filter(|attempt| {
+            attempt.username == username
+                && attempt.attempted_at > since
+                && forgiven_at.is_none_or(|forgiven_at| attempt.attempted_at > forgiven_at)
+        })))
+    }
+
+    async fn failures_of_ip(&self, ip: IpAddr, since: DateTime<Utc>) -> anyhow::Result<Failures> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        let attempts = self.attempts.lock().await;
+        Ok(count_failures(attempts.iter().filter(|attempt| {
+            attempt.ip == ip && attempt.attempted_at > since
+        })))
+    }
+
+    async fn list(
+        &self,
+        username: Option<&str>,
+        ip: Option<IpAddr>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<LoginAttempt>> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        let attempts = self.attempts.lock().await;
+        Ok(attempts
+            .iter()
+            .rev()
+            .filter(|attempt| username.is_none_or(|username| attempt.username == username))
+            .filter(|attempt| ip.is_none_or(|ip| attempt.ip == ip))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+}
+
+#[derive(Debug)]
+pub struct SqlxLoginAttemptRepository<'tx> {
+    conn: Connection<'tx>,
+}
+
+impl<'tx> SqlxLoginAttemptRepository<'tx> {
+    pub fn new(conn: Connection<'tx>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl ILoginAttemptRepository for SqlxLoginAttemptRepository<'_> {
+    async fn save(&self, attempt: LoginAttempt) -> anyhow::Result<()> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                save(conn, attempt).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                save(conn, attempt).await
+            }
+        }
+    }
+
+    async fn failures_of_username(
+        &self,
+        username: &str,
+        since: DateTime<Utc>,
+    ) -> anyhow::Result<Failures> {
+        let query = r#"select count(*), max(attempted_at)
+            from login_attempts
+            where username = $1
+              and outcome = 'failed'
+              and attempted_at > $2
+              and attempted_at > coalesce((select max(attempted_at)
+                                           from login_attempts
+                                           where username = $1
+                                             and outcome in ('succeeded', 'unlocked')),
+                                          '-infinity')"#;
+
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                failures(conn, query, username, since).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                failures(conn, query, username, since).await
+            }
+        }
+    }
+
+    async fn failures_of_ip(&self, ip: IpAddr, since: DateTime<Utc>) -> anyhow::Result<Failures> {
+        let query = r#"select count(*), max(attempted_at)
+            from login_attempts
+            where ip = $1
+              and outcome = 'failed'
+              and attempted_at > $2"#;
+
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                failures(conn, query, ip, since).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                failures(conn, query, ip, since).await
+            }
+        }
+    }
+
+    async fn list(
+        &self,
+        username: Option<&str>,
+        ip: Option<IpAddr>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<LoginAttempt>> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                list(conn, username, ip, limit).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                list(conn, username, ip, limit).await
+            }
+        }
+    }
+}
+
+async fn save(conn: &mut PgConnection, attempt: LoginAttempt) -> anyhow::Result<()> {
+    sqlx::query(
+        "insert into login_attempts (username, ip, user_agent, outcome, attempted_at) values ($1, $2, $3, $4, $5);",
+    )
+    .bind(attempt.username)
+    .bind(attempt.ip)
+    .bind(attempt.user_agent)
+    .bind(attempt.outcome.as_str())
+    .bind(attempt.attempted_at)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn failures<'q, T>(
+    conn: &mut PgConnection,
+    query: &'q str,
+    subject: T,
+    since: DateTime<Utc>,
+) -> anyhow::Result<Failures>
+where
+    T: 'q + Send + sqlx::Encode<'q, sqlx::Postgres> + sqlx::Type<sqlx::Postgres>,
+{
+    let row = sqlx::query(query)
+        .bind(subject)
+        .bind(since)
+        .fetch_one(conn)
+        .await?;
+
+    Ok(Failures {
+        count: row.get::<i64, usize>(0) as usize,
+        last_at: row.get::<Option<DateTime<Utc>>, usize>(1),
+    })
+}
+
+async fn list(
+    conn: &mut PgConnection,
+    username: Option<&str>,
+    ip: Option<IpAddr>,
+    limit: usize,
+) -> anyhow::Result<Vec<LoginAttempt>> {
+    let rows = sqlx::query(
+        r#"select username, ip, user_agent, outcome, attempted_at
+        from login_attempts
+        where ($1::text is null or username = $1)
+          and ($2::inet is null or ip = $2)
+        order by attempted_at desc
+        limit $3"#,
+    )
+    .bind(username)
+    .bind(ip)
+    .bind(limit as i64)
+    .fetch_all(conn)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            let outcome = row.get::<String, usize>(3);
+
+            Ok(LoginAttempt {
+                username: row.get::<String, usize>(0),
+                ip: row.get::<IpAddr, usize>(1),
+                user_agent: row.get::<Option<String>, usize>(2),
+                outcome: LoginOutcome::try_from(outcome.as_str())
+                    .map_err(|_| anyhow!("Unknown outcome {}", outcome))?,
+                attempted_at: row.get::<DateTime<Utc>, usize>(4),
+            })
+        })
+        .collect()
+}
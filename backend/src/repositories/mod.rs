@@ -5,6 +5,7 @@ pub use avatar_repository::SqlxAvatarRepository;
 pub use content_repository::IContentRepository;
 pub use content_repository::InMemoryContentRepository;
 pub use content_repository::SqlxContentRepository;
+pub use content_repository::UpdateOutcome;
 
 pub use resource_repository::IResourceRepository;
 pub use resource_repository::InMemoryResourceRepository;
@@ -20,6 +21,37 @@ pub use article_views_repository::IArticleViewsRepository;
 pub use article_views_repository::InMemoryArticleViewsRepository;
 pub use article_views_repository::SqlxArticleViewsRepository;
 
+pub use login_attempt_repository::Failures;
+pub use login_attempt_repository::ILoginAttemptRepository;
+#[cfg(test)]
+pub use login_attempt_repository::InMemoryLoginAttemptRepository;
+pub use login_attempt_repository::SqlxLoginAttemptRepository;
+
+pub use session_repository::ISessionRepository;
+#[cfg(test)]
+pub use session_repository::InMemorySessionRepository;
+pub use session_repository::RedisSessionRepository;
+pub use session_repository::Rotation;
+
+pub use audit_repository::IAuditRepository;
+pub use audit_repository::InMemoryAuditRepository;
+pub use audit_repository::SqlxAuditRepository;
+
+pub use password_reset_repository::IPasswordResetRepository;
+#[cfg(test)]
+pub use password_reset_repository::InMemoryPasswordResetRepository;
+pub use password_reset_repository::SqlxPasswordResetRepository;
+
+pub use recovery_code_repository::IRecoveryCodeRepository;
+#[cfg(test)]
+pub use recovery_code_repository::InMemoryRecoveryCodeRepository;
+pub use recovery_code_repository::SqlxRecoveryCodeRepository;
+
+pub use security_settings_repository::ISecuritySettingsRepository;
+#[cfg(test)]
+pub use security_settings_repository::InMemorySecuritySettingsRepository;
+pub use security_settings_repository::SqlxSecuritySettingsRepository;
+
 use sqlx::{Pool, Postgres, Transaction};
 use std::sync::Weak;
 use tokio::sync::Mutex;
@@ -39,3 +71,15 @@ mod resource_repository;
 mod user_repository;
 
 mod article_views_repository;
+
+mod login_attempt_repository;
+
+mod session_repository;
+
+mod audit_repository;
+
+mod password_reset_repository;
+
+mod recovery_code_repository;
+
+mod security_settings_repository;
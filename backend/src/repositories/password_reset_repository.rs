@@ -0,0 +1,247 @@
+use crate::domain::entities::{PasswordReset, UserID};
+use crate::repositories::Connection;
+use anyhow::anyhow;
+use sqlx::postgres::PgRow;
+use sqlx::{Acquire, PgConnection, Row};
+#[cfg(test)]
+use std::sync::Arc;
+#[cfg(test)]
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+#[async_trait::async_trait]
+pub trait IPasswordResetRepository {
+    async fn save(&self, reset: &PasswordReset) -> anyhow::Result<()>;
+
+    /// The reset of the token hash, even if it has expired
+    async fn get(&self, token_hash: &str) -> anyhow::Result<Option<PasswordReset>>;
+
+    /// Deletes the reset so it's used once, it returns false if it's been used already
+    async fn consume(&self, token_hash: &str) -> anyhow::Result<bool>;
+
+    /// Deletes the resets of the user, so only the latest token can be used
+    async fn delete_of_user(&self, user_id: &UserID) -> anyhow::Result<()>;
+}
+
+/// The clones share the resets
+#[cfg(test)]
+#[derive(Clone)]
+pub struct InMemoryPasswordResetRepository {
+    error: bool,
+    resets: Arc<Mutex<Vec<PasswordReset>>>,
+}
+
+#[cfg(test)]
+impl Default for InMemoryPasswordResetRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+impl InMemoryPasswordResetRepository {
+    pub fn new() -> Self {
+        Self {
+            error: false,
+            resets: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn with_error(self) -> Self {
+        Self {
+            error: true,
+            ..self
+        }
+    }
+
+    /// The saved resets, the oldest one first
+    pub async fn resets(&self) -> Vec<PasswordReset> {
+        self.resets.lock().await.clone()
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        match self.error {
+            true => Err(anyhow!("Internal Server Error")),
+            false => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl IPasswordResetRepository for InMemoryPasswordResetRepository {
+    async fn save(&self, reset: &PasswordReset) -> anyhow::Result<()> {
+        self.check()?;
+
+        self.resets.lock().await.push(reset.clone());
+        Ok(())
+    }
+
+    async fn get(&self, token_hash: &str) -> anyhow::Result<Option<PasswordReset>> {
+        self.check()?;
+
+        let lock = self.resets.lock().await;
+        Ok(lock
+            .iter()
+            .find(|reset| reset.token_hash == token_hash)
+            .cloned())
+    }
+
+    async fn consume(&self, token_hash: &str) -> anyhow::Result<bool> {
+        self.check()?;
+
+        let mut lock = self.resets.lock().await;
+        let count = lock.len();
+        lock.retain(|reset| reset.token_hash != token_hash);
+        Ok(lock.len() < count)
+    }
+
+    async fn delete_of_user(&self, user_id: &UserID) -> anyhow::Result<()> {
+        self.check()?;
+
+        self.resets
+            .lock()
+            .await
+            .retain(|reset| &reset.user_id != user_id);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct SqlxPasswordResetRepository<'tx> {
+    conn: Connection<'tx>,
+}
+
+impl<'tx> SqlxPasswordResetRepository<'tx> {
+    pub fn new(conn: Connection<'tx>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl IPasswordResetRepository for SqlxPasswordResetRepository<'_> {
+    async fn save(&self, reset: &PasswordReset) -> anyhow::Result<()> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                save(conn, reset).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                save(conn, reset).await
+            }
+        }
+    }
+
+    async fn get(&self, token_hash: &str) -> anyhow::Result<Option<PasswordReset>> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                get(conn, token_hash).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                get(conn, token_hash).await
+            }
+        }
+    }
+
+    async fn consume(&self, token_hash: &str) -> anyhow::Result<bool> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                consume(conn, token_hash).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                consume(conn, token_hash).await
+            }
+        }
+    }
+
+    async fn delete_of_user(&self, user_id: &UserID) -> anyhow::Result<()> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                delete_of_user(conn, user_id).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                delete_of_user(conn, user_id).await
+            }
+        }
+    }
+}
+
+async fn save(conn: &mut PgConnection, reset: &PasswordReset) -> anyhow::Result<()> {
+    sqlx::query(
+        "insert into password_resets (token_hash, user_id, expires_at, created_at) values ($1, $2, $3, $4);",
+    )
+    .bind(&reset.token_hash)
+    .bind(reset.user_id.as_uuid())
+    .bind(reset.expires_at)
+    .bind(reset.created_at)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+fn to_reset(row: PgRow) -> PasswordReset {
+    PasswordReset {
+        token_hash: row.get("token_hash"),
+        user_id: UserID::from(row.get::<Uuid, _>("user_id")),
+        expires_at: row.get("expires_at"),
+        created_at: row.get("created_at"),
+    }
+}
+
+async fn get(conn: &mut PgConnection, token_hash: &str) -> anyhow::Result<Option<PasswordReset>> {
+    let reset = sqlx::query(
+        "select token_hash, user_id, expires_at, created_at from password_resets where token_hash = $1",
+    )
+    .bind(token_hash)
+    .fetch_optional(conn)
+    .await?
+    .map(to_reset);
+
+    Ok(reset)
+}
+
+async fn consume(conn: &mut PgConnection, token_hash: &str) -> anyhow::Result<bool> {
+    let res = sqlx::query("delete from password_resets where token_hash = $1")
+        .bind(token_hash)
+        .execute(conn)
+        .await?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+async fn delete_of_user(conn: &mut PgConnection, user_id: &UserID) -> anyhow::Result<()> {
+    sqlx::query("delete from password_resets where user_id = $1")
+        .bind(user_id.as_uuid())
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
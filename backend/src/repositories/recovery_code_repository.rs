@@ -0,0 +1,207 @@
+use crate::domain::entities::UserID;
+use crate::repositories::Connection;
+use anyhow::anyhow;
+use sqlx::{Acquire, PgConnection};
+#[cfg(test)]
+use std::collections::HashMap;
+#[cfg(test)]
+use std::sync::Arc;
+#[cfg(test)]
+use tokio::sync::Mutex;
+
+/// The codes which log the users in when they lose their authenticator apps, only their hashes are kept
+#[async_trait::async_trait]
+pub trait IRecoveryCodeRepository {
+    /// Replaces the codes of the user, the old ones can't be used anymore
+    async fn replace(&self, user_id: &UserID, code_hashes: &[String]) -> anyhow::Result<()>;
+
+    /// Deletes the code so it's used once, it returns false if the user doesn't have it
+    async fn consume(&self, user_id: &UserID, code_hash: &str) -> anyhow::Result<bool>;
+
+    /// How many codes the user has left
+    async fn count(&self, user_id: &UserID) -> anyhow::Result<usize>;
+}
+
+/// The clones share the codes
+#[cfg(test)]
+#[derive(Clone)]
+pub struct InMemoryRecoveryCodeRepository {
+    error: bool,
+    codes: Arc<Mutex<HashMap<UserID, Vec<String>>>>,
+}
+
+#[cfg(test)]
+impl Default for InMemoryRecoveryCodeRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+impl InMemoryRecoveryCodeRepository {
+    pub fn new() -> Self {
+        Self {
+            error: false,
+            codes: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn with_error(self) -> Self {
+        Self {
+            error: true,
+            ..self
+        }
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        match self.error {
+            true => Err(anyhow!("Internal Server Error")),
+            false => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl IRecoveryCodeRepository for InMemoryRecoveryCodeRepository {
+    async fn replace(&self, user_id: &UserID, code_hashes: &[String]) -> anyhow::Result<()> {
+        self.check()?;
+
+        self.codes
+            .lock()
+            .await
+            .insert(user_id.clone(), code_hashes.to_vec());
+        Ok(())
+    }
+
+    async fn consume(&self, user_id: &UserID, code_hash: &str) -> anyhow::Result<bool> {
+        self.check()?;
+
+        let mut lock = self.codes.lock().await;
+        let codes = lock.entry(user_id.clone()).or_default();
+        let count = codes.len();
+        codes.retain(|hash| hash != code_hash);
+        Ok(codes.len() < count)
+    }
+
+    async fn count(&self, user_id: &UserID) -> anyhow::Result<usize> {
+        self.check()?;
+
+        Ok(self.codes.lock().await.get(user_id).map_or(0, Vec::len))
+    }
+}
+
+#[derive(Debug)]
+pub struct SqlxRecoveryCodeRepository<'tx> {
+    conn: Connection<'tx>,
+}
+
+impl<'tx> SqlxRecoveryCodeRepository<'tx> {
+    pub fn new(conn: Connection<'tx>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl IRecoveryCodeRepository for SqlxRecoveryCodeRepository<'_> {
+    async fn replace(&self, user_id: &UserID, code_hashes: &[String]) -> anyhow::Result<()> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                // the old codes are void only if the new ones are saved
+                let mut tx = pool.begin().await?;
+                replace(&mut tx, user_id, code_hashes).await?;
+                tx.commit().await?;
+
+                Ok(())
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                replace(conn, user_id, code_hashes).await
+            }
+        }
+    }
+
+    async fn consume(&self, user_id: &UserID, code_hash: &str) -> anyhow::Result<bool> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                consume(conn, user_id, code_hash).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                consume(conn, user_id, code_hash).await
+            }
+        }
+    }
+
+    async fn count(&self, user_id: &UserID) -> anyhow::Result<usize> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                count(conn, user_id).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                count(conn, user_id).await
+            }
+        }
+    }
+}
+
+async fn replace(
+    conn: &mut PgConnection,
+    user_id: &UserID,
+    code_hashes: &[String],
+) -> anyhow::Result<()> {
+    sqlx::query("delete from recovery_codes where user_id = $1")
+        .bind(user_id.as_uuid())
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        "insert into recovery_codes (user_id, code_hash) select $1, unnest($2::text[]) on conflict do nothing",
+    )
+    .bind(user_id.as_uuid())
+    .bind(code_hashes)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn consume(
+    conn: &mut PgConnection,
+    user_id: &UserID,
+    code_hash: &str,
+) -> anyhow::Result<bool> {
+    let res = sqlx::query("delete from recovery_codes where user_id = $1 and code_hash = $2")
+        .bind(user_id.as_uuid())
+        .bind(code_hash)
+        .execute(conn)
+        .await?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+async fn count(conn: &mut PgConnection, user_id: &UserID) -> anyhow::Result<usize> {
+    let count =
+        sqlx::query_scalar::<_, i64>("select count(*) from recovery_codes where user_id = $1")
+            .bind(user_id.as_uuid())
+            .fetch_one(conn)
+            .await?;
+
+    Ok(count as usize)
+}
@@ -1,5 +1,5 @@
-use crate::domain::entities::{ResourceID, ResourceType};
-use crate::repositories::Connection;
+use crate::domain::entities::{ContentID, ResourceID, ResourceType, UserID};
+use crate::repositories::{Connection, IContentRepository, InMemoryContentRepository};
 use anyhow::anyhow;
 use sqlx::{Acquire, PgConnection, Row};
 use tokio::sync::Mutex;
@@ -11,8 +11,12 @@ pub trait IResourceRepository {
         id: ResourceID,
         resource_type: ResourceType,
         seq: i32,
+        created_by: Option<UserID>,
     ) -> anyhow::Result<ResourceID>;
 
+    // get the user who created the resource, it's unknown for the resources created before it's recorded
+    async fn creator(&self, id: &ResourceID) -> anyhow::Result<Option<UserID>>;
+
     // check if the resource is already in the repository
     async fn contains(&self, id: &ResourceID, resource_type: &ResourceType)
         -> anyhow::Result<bool>;
@@ -20,14 +24,32 @@ pub trait IResourceRepository {
     // delete the resource from the repository
     async fn delete(&self, id: &ResourceID, resource_type: &ResourceType) -> anyhow::Result<()>;
 
-    // update the sequence of the resource in the repository
-    async fn update_seq(&self, id: &ResourceID, seq: i32) -> anyhow::Result<()>;
+    // update the sequence of the resource in the repository, the versions of its contents are bumped
+    // if the sequence changes, which is returned
+    async fn update_seq(&self, id: &ResourceID, seq: i32) -> anyhow::Result<bool>;
+
+    // get the sequence of the resource if it exists
+    async fn seq(&self, id: &ResourceID) -> anyhow::Result<Option<i32>>;
+
+    // get the sequence which puts a new resource at the end of the resource type's list
+    async fn next_seq(&self, resource_type: &ResourceType) -> anyhow::Result<i32>;
+
+    // move the resources of the type whose sequence is at least `from` by the offset, the versions of
+    // their contents are bumped and the moved resources are returned with their new sequences
+    async fn shift_seq(
+        &self,
+        resource_type: &ResourceType,
+        from: i32,
+        offset: i32,
+    ) -> anyhow::Result<Vec<(ResourceID, i32)>>;
 }
 
 #[derive(Debug)]
 pub struct InMemoryResourceRepository {
     error: bool,
-    resources: Mutex<Vec<(ResourceID, ResourceType)>>,
+    resources: Mutex<Vec<(ResourceID, ResourceType, i32)>>,
+    creators: Mutex<Vec<(ResourceID, UserID)>>,
+    content: Option<InMemoryContentRepository>,
 }
 
 impl Default for InMemoryResourceRepository {
@@ -41,6 +63,23 @@ impl InMemoryResourceRepository {
         Self {
             error: false,
             resources: Mutex::new(Vec::new()),
+            creators: Mutex::new(Vec::new()),
+            content: None,
+        }
+    }
+
+    /// The versions of the contents are bumped when the sequences change, as the database does
+    pub fn with_content(self, content: InMemoryContentRepository) -> Self {
+        Self {
+            content: Some(content),
+            ..self
+        }
+    }
+
+    async fn bump_versions(&self, id: &ResourceID) -> anyhow::Result<()> {
+        match &self.content {
+            Some(content) => content.touch(&ContentID::from(id.clone())).await,
+            None => Ok(()),
         }
     }
 
@@ -58,7 +97,8 @@ impl IResourceRepository for InMemoryResourceRepository {
         &self,
         id: ResourceID,
         resource_type: ResourceType,
-        _: i32,
+        seq: i32,
+        created_by: Option<UserID>,
     ) -> anyhow::Result<ResourceID> {
         if self.error {
             return Err(anyhow!("Internal Server Error"));
@@ -68,16 +108,32 @@ impl IResourceRepository for InMemoryResourceRepository {
 
         if lock
             .iter()
-            .any(|(res_id, kind)| res_id == &id && kind == &resource_type)
+            .any(|(res_id, kind, _)| res_id == &id && kind == &resource_type)
         {
             return Err(anyhow!("{} already exists", id));
         }
 
-        lock.push((id.clone(), resource_type));
+        lock.push((id.clone(), resource_type, seq));
+        if let Some(created_by) = created_by {
+            self.creators.lock().await.push((id.clone(), created_by));
+        }
 
         Ok(id)
     }
 
+    async fn creator(&self, id: &ResourceID) -> anyhow::Result<Option<UserID>> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        let lock = self.creators.lock().await;
+
+        Ok(lock
+            .iter()
+            .find(|(res_id, _)| res_id == id)
+            .map(|(_, created_by)| created_by.clone()))
+    }
+
     async fn contains(
         &self,
         id: &ResourceID,
@@ -91,7 +147,7 @@ impl IResourceRepository for InMemoryResourceRepository {
 
         Ok(lock
             .iter()
-            .any(|(res_id, kind)| res_id == id && kind == resource_type))
+            .any(|(res_id, kind, _)| res_id == id && kind == resource_type))
     }
 
     async fn delete(&self, id: &ResourceID, resource_type: &ResourceType) -> anyhow::Result<()> {
@@ -103,7 +159,7 @@ impl IResourceRepository for InMemoryResourceRepository {
 
         let removed = lock
             .iter()
-            .position(|(res_id, kind)| res_id == id && kind == resource_type);
+            .position(|(res_id, kind, _)| res_id == id && kind == resource_type);
         match removed {
             Some(index) => {
                 lock.remove(index);
@@ -113,14 +169,75 @@ impl IResourceRepository for InMemoryResourceRepository {
         }
     }
 
-    async fn update_seq(&self, _: &ResourceID, _: i32) -> anyhow::Result<()> {
+    async fn update_seq(&self, id: &ResourceID, seq: i32) -> anyhow::Result<bool> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        let mut lock = self.resources.lock().await;
+
+        match lock.iter_mut().find(|(res_id, _, _)| res_id == id) {
+            Some((_, _, current)) if *current != seq => {
+                *current = seq;
+                self.bump_versions(id).await?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn seq(&self, id: &ResourceID) -> anyhow::Result<Option<i32>> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        let lock = self.resources.lock().await;
+
+        Ok(lock
+            .iter()
+            .find(|(res_id, _, _)| res_id == id)
+            .map(|(_, _, seq)| *seq))
+    }
+
+    async fn next_seq(&self, resource_type: &ResourceType) -> anyhow::Result<i32> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        let lock = self.resources.lock().await;
+
+        Ok(lock
+            .iter()
+            .filter(|(_, kind, _)| kind == resource_type)
+            .map(|(_, _, seq)| seq + 1)
+            .max()
+            .unwrap_or(0))
+    }
+
+    async fn shift_seq(
+        &self,
+        resource_type: &ResourceType,
+        from: i32,
+        offset: i32,
+    ) -> anyhow::Result<Vec<(ResourceID, i32)>> {
         if self.error {
             return Err(anyhow!("Internal Server Error"));
         }
 
-        // TODO: handle seq
+        let mut lock = self.resources.lock().await;
+        let mut moved = vec![];
+
+        for (id, kind, seq) in lock.iter_mut() {
+            if kind == resource_type && *seq >= from {
+                *seq += offset;
+                moved.push((id.clone(), *seq));
+            }
+        }
+        for (id, _) in &moved {
+            self.bump_versions(id).await?;
+        }
 
-        Ok(())
+        Ok(moved)
     }
 }
 
@@ -142,20 +259,39 @@ impl IResourceRepository for SqlxResourceRepository<'_> {
         id: ResourceID,
         resource_type: ResourceType,
         seq: i32,
+        created_by: Option<UserID>,
     ) -> anyhow::Result<ResourceID> {
         match &self.conn {
             Connection::Pool(pool) => {
                 let mut conn = pool.acquire().await?;
                 let conn = conn.as_mut();
 
-                create(conn, id, resource_type, seq).await
+                create(conn, id, resource_type, seq, created_by).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                create(conn, id, resource_type, seq, created_by).await
+            }
+        }
+    }
+
+    async fn creator(&self, id: &ResourceID) -> anyhow::Result<Option<UserID>> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                get_creator(conn, id).await
             }
             Connection::Transaction(tx) => {
                 let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
                 let mut lock = conn_ptr.lock().await;
                 let conn = lock.acquire().await?;
 
-                create(conn, id, resource_type, seq).await
+                get_creator(conn, id).await
             }
         }
     }
@@ -200,7 +336,7 @@ impl IResourceRepository for SqlxResourceRepository<'_> {
         }
     }
 
-    async fn update_seq(&self, id: &ResourceID, seq: i32) -> anyhow::Result<()> {
+    async fn update_seq(&self, id: &ResourceID, seq: i32) -> anyhow::Result<bool> {
         match &self.conn {
             Connection::Pool(pool) => {
                 let mut conn = pool.acquire().await?;
@@ -217,6 +353,65 @@ impl IResourceRepository for SqlxResourceRepository<'_> {
             }
         }
     }
+
+    async fn seq(&self, id: &ResourceID) -> anyhow::Result<Option<i32>> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                get_seq(conn, id).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                get_seq(conn, id).await
+            }
+        }
+    }
+
+    async fn next_seq(&self, resource_type: &ResourceType) -> anyhow::Result<i32> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                next_seq(conn, resource_type).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                next_seq(conn, resource_type).await
+            }
+        }
+    }
+
+    async fn shift_seq(
+        &self,
+        resource_type: &ResourceType,
+        from: i32,
+        offset: i32,
+    ) -> anyhow::Result<Vec<(ResourceID, i32)>> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                shift_seq(conn, resource_type, from, offset).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                shift_seq(conn, resource_type, from, offset).await
+            }
+        }
+    }
 }
 
 async fn create(
@@ -224,26 +419,39 @@ async fn create(
     id: ResourceID,
     resource_type: ResourceType,
     seq: i32,
+    created_by: Option<UserID>,
 ) -> anyhow::Result<ResourceID> {
     sqlx::query(
-        "INSERT INTO \"resource\" (id, created_at, resource_type, seq) VALUES ($1, now(), $2, $3);",
+        "INSERT INTO \"resource\" (id, created_at, resource_type, seq, created_by) VALUES ($1, now(), $2, $3, $4);",
     )
     .bind(id.as_str())
     .bind(resource_type.as_str())
     .bind(seq)
+    .bind(created_by.as_ref().map(UserID::as_uuid))
     .execute(conn)
     .await?;
 
     Ok(id)
 }
 
+async fn get_creator(conn: &mut PgConnection, id: &ResourceID) -> anyhow::Result<Option<UserID>> {
+    let created_by = sqlx::query_scalar::<_, Option<uuid::Uuid>>(
+        "SELECT created_by FROM \"resource\" WHERE id = $1 and deleted_at is null;",
+    )
+    .bind(id.as_str())
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(created_by.flatten().map(UserID::from))
+}
+
 async fn contains(
     conn: &mut PgConnection,
     id: &ResourceID,
     resource_type: &ResourceType,
 ) -> anyhow::Result<bool> {
     let res =
-        sqlx::query("SELECT id FROM \"resource\" WHERE id = $1 and resource_type = $2 limit 1;")
+        sqlx::query("SELECT id FROM \"resource\" WHERE id = $1 and resource_type = $2 and deleted_at is null limit 1;")
             .bind(id.as_str())
             .bind(resource_type.as_str())
             .fetch_optional(conn)
@@ -270,12 +478,85 @@ async fn delete(
     Ok(())
 }
 
-async fn update_seq(conn: &mut PgConnection, id: &ResourceID, seq: i32) -> anyhow::Result<()> {
-    sqlx::query("UPDATE \"resource\" SET seq = $2 WHERE id = $1;")
-        .bind(id.as_str())
-        .bind(seq)
+async fn update_seq(conn: &mut PgConnection, id: &ResourceID, seq: i32) -> anyhow::Result<bool> {
+    // the contents are tagged with their versions, so they change along with the sequence
+    let moved = sqlx::query_scalar::<_, i64>(
+        r#"WITH moved AS (UPDATE "resource" SET seq = $2 WHERE id = $1 and seq <> $2 RETURNING id),
+             bumped AS (UPDATE "content" SET updated_at = now(), version = version + 1
+                        FROM moved WHERE "content".id = moved.id)
+        SELECT count(*) FROM moved;"#,
+    )
+    .bind(id.as_str())
+    .bind(seq)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(moved > 0)
+}
+
+async fn get_seq(conn: &mut PgConnection, id: &ResourceID) -> anyhow::Result<Option<i32>> {
+    let seq = sqlx::query_scalar::<_, i16>(
+        "SELECT seq FROM \"resource\" WHERE id = $1 and deleted_at is null;",
+    )
+    .bind(id.as_str())
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(seq.map(i32::from))
+}
+
+/// Serializes the changes of the sequences of a resource type until the transaction ends
+async fn lock_seq(conn: &mut PgConnection, resource_type: &ResourceType) -> anyhow::Result<()> {
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1));")
+        .bind(resource_type.as_str())
         .execute(conn)
         .await?;
 
     Ok(())
 }
+
+async fn next_seq(conn: &mut PgConnection, resource_type: &ResourceType) -> anyhow::Result<i32> {
+    lock_seq(&mut *conn, resource_type).await?;
+
+    let seq = sqlx::query_scalar::<_, i32>(
+        "SELECT coalesce(max(seq) + 1, 0)::int4 FROM \"resource\" WHERE resource_type = $1 and deleted_at is null;",
+    )
+    .bind(resource_type.as_str())
+    .fetch_one(conn)
+    .await?;
+
+    Ok(seq)
+}
+
+async fn shift_seq(
+    conn: &mut PgConnection,
+    resource_type: &ResourceType,
+    from: i32,
+    offset: i32,
+) -> anyhow::Result<Vec<(ResourceID, i32)>> {
+    lock_seq(&mut *conn, resource_type).await?;
+
+    // the contents are tagged with their versions, so they change along with the sequences
+    let moved = sqlx::query_as::<_, (String, i16)>(
+        r#"WITH moved AS (UPDATE "resource" SET seq = seq + $3
+                          WHERE resource_type = $1 and seq >= $2 and deleted_at is null
+                          RETURNING id, seq),
+             bumped AS (UPDATE "content" SET updated_at = now(), version = version + 1
+                        FROM moved WHERE "content".id = moved.id)
+        SELECT id, seq FROM moved;"#,
+    )
+    .bind(resource_type.as_str())
+    .bind(from)
+    .bind(offset)
+    .fetch_all(conn)
+    .await?;
+
+    moved
+        .into_iter()
+        .map(|(id, seq)| {
+            ResourceID::try_from(id)
+                .map(|id| (id, i32::from(seq)))
+                .map_err(|_| anyhow!("Invalid resource id"))
+        })
+        .collect()
+}
@@ -0,0 +1,141 @@
+use crate::domain::entities::SecuritySettings;
+use crate::repositories::Connection;
+use anyhow::anyhow;
+use sqlx::{Acquire, PgConnection};
+#[cfg(test)]
+use std::sync::Arc;
+#[cfg(test)]
+use tokio::sync::Mutex;
+
+#[async_trait::async_trait]
+pub trait ISecuritySettingsRepository {
+    async fn get(&self) -> anyhow::Result<SecuritySettings>;
+
+    async fn save(&self, settings: &SecuritySettings) -> anyhow::Result<()>;
+}
+
+/// The clones share the settings
+#[cfg(test)]
+#[derive(Clone)]
+pub struct InMemorySecuritySettingsRepository {
+    error: bool,
+    settings: Arc<Mutex<SecuritySettings>>,
+}
+
+#[cfg(test)]
+impl Default for InMemorySecuritySettingsRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+impl InMemorySecuritySettingsRepository {
+    pub fn new() -> Self {
+        Self {
+            error: false,
+            settings: Arc::new(Mutex::new(SecuritySettings::default())),
+        }
+    }
+
+    pub fn with_error(self) -> Self {
+        Self {
+            error: true,
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl ISecuritySettingsRepository for InMemorySecuritySettingsRepository {
+    async fn get(&self) -> anyhow::Result<SecuritySettings> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        Ok(self.settings.lock().await.clone())
+    }
+
+    async fn save(&self, settings: &SecuritySettings) -> anyhow::Result<()> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        *self.settings.lock().await = settings.clone();
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct SqlxSecuritySettingsRepository<'tx> {
+    conn: Connection<'tx>,
+}
+
+impl<'tx> SqlxSecuritySettingsRepository<'tx> {
+    pub fn new(conn: Connection<'tx>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl ISecuritySettingsRepository for SqlxSecuritySettingsRepository<'_> {
+    async fn get(&self) -> anyhow::Result<SecuritySettings> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                get(conn).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                get(conn).await
+            }
+        }
+    }
+
+    async fn save(&self, settings: &SecuritySettings) -> anyhow::Result<()> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                save(conn, settings).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                save(conn, settings).await
+            }
+        }
+    }
+}
+
+/// The defaults are used if the row is missing
+async fn get(conn: &mut PgConnection) -> anyhow::Result<SecuritySettings> {
+    let require_totp =
+        sqlx::query_scalar::<_, bool>("select require_totp from security_settings where id")
+            .fetch_optional(conn)
+            .await?;
+
+    Ok(SecuritySettings {
+        require_totp: require_totp.unwrap_or_default(),
+    })
+}
+
+async fn save(conn: &mut PgConnection, settings: &SecuritySettings) -> anyhow::Result<()> {
+    sqlx::query(
+        "insert into security_settings (id, require_totp) values (true, $1) on conflict (id) do update set require_totp = excluded.require_totp",
+    )
+    .bind(settings.require_totp)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
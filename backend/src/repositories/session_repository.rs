@@ -0,0 +1,402 @@
+use crate::domain::entities::{Session, SessionID, UserID};
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+#[cfg(test)]
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+#[cfg(test)]
+use tokio::sync::Mutex;
+
+/// How a refresh token is rotated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Rotated,
+    /// An older refresh token is given, the session is revoked since the token may be stolen
+    Reused,
+    /// The session has expired or it has been revoked
+    Missing,
+}
+
+/** Keeps the sessions along with the hashes of their current refresh tokens.
+*
+* The sessions are removed when they expire.
+*/
+#[async_trait::async_trait]
+pub trait ISessionRepository {
+    async fn save(&self, session: &Session, refresh_token_hash: &str) -> anyhow::Result<()>;
+
+    async fn get(&self, id: &SessionID) -> anyhow::Result<Option<Session>>;
+
+    /// Replaces the refresh token and the session atomically if the current refresh token is given
+    async fn rotate(
+        &self,
+        session: &Session,
+        refresh_token_hash: &str,
+        next_refresh_token_hash: &str,
+    ) -> anyhow::Result<Rotation>;
+
+    async fn revoke(&self, id: &SessionID) -> anyhow::Result<()>;
+
+    /// The sessions of the user which haven't expired
+    async fn list(&self, user_id: &UserID) -> anyhow::Result<Vec<Session>>;
+
+    /// Marks the session as used, it's ignored if the session is missing
+    async fn touch(&self, id: &SessionID, at: DateTime<Utc>) -> anyhow::Result<()>;
+}
+
+/// The clones share the sessions
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct InMemorySessionRepository {
+    error: bool,
+    sessions: Arc<Mutex<HashMap<SessionID, (Session, String)>>>,
+}
+
+#[cfg(test)]
+impl InMemorySessionRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_error(self) -> Self {
+        Self {
+            error: true,
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl ISessionRepository for InMemorySessionRepository {
+    async fn save(&self, session: &Session, refresh_token_hash: &str) -> anyhow::Result<()> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        self.sessions.lock().await.insert(
+            session.id.clone(),
+            (session.clone(), refresh_token_hash.to_string()),
+        );
+        Ok(())
+    }
+
+    async fn get(&self, id: &SessionID) -> anyhow::Result<Option<Session>> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        Ok(self
+            .sessions
+            .lock()
+            .await
+            .get(id)
+            .map(|(session, _)| session.clone())
+            .filter(|session| session.expires_at > Utc::now()))
+    }
+
+    async fn rotate(
+        &self,
+        session: &Session,
+        refresh_token_hash: &str,
+        next_refresh_token_hash: &str,
+    ) -> anyhow::Result<Rotation> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        let mut sessions = self.sessions.lock().await;
+        let current = sessions
+            .get(&session.id)
+            .filter(|(session, _)| session.expires_at > Utc::now())
+            .map(|(_, hash)| hash.as_str());
+
+        match current {
+            None => Ok(Rotation::Missing),
+            Some(current) if current != refresh_token_hash => {
+                sessions.remove(&session.id);
+                Ok(Rotation::Reused)
+            }
+            Some(_) => {
+                sessions.insert(
+                    session.id.clone(),
+                    (session.clone(), next_refresh_token_hash.to_string()),
+                );
+                Ok(Rotation::Rotated)
+            }
+        }
+    }
+
+    async fn revoke(&self, id: &SessionID) -> anyhow::Result<()> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        self.sessions.lock().await.remove(id);
+        Ok(())
+    }
+
+    async fn list(&self, user_id: &UserID) -> anyhow::Result<Vec<Session>> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        Ok(self
+            .sessions
+            .lock()
+            .await
+            .values()
+            .map(|(session, _)| session)
+            .filter(|session| &session.user_id == user_id && session.expires_at > Utc::now())
+            .cloned()
+            .collect())
+    }
+
+    async fn touch(&self, id: &SessionID, at: DateTime<Utc>) -> anyhow::Result<()> {
+        if self.error {
+            return Err(anyhow!("Internal Server Error"));
+        }
+
+        if let Some((session, _)) = self.sessions.lock().await.get_mut(id) {
+            session.last_seen_at = at;
+        }
+        Ok(())
+    }
+}
+
+/// Rotates the refresh token if the current one is given, or revokes the session otherwise.
+/// It returns 1 when it's rotated, -1 when it's revoked and 0 when the session is missing.
+const ROTATE: &str = r#"
+local current = redis.call('HGET', KEYS[1], 'refresh_token')
+if not current then
+    return 0
+end
+
+if current ~= ARGV[1] then
+    redis.call('DEL', KEYS[1])
+    redis.call('SREM', KEYS[2], ARGV[5])
+    return -1
+end
+
+redis.call('HSET', KEYS[1], 'refresh_token', ARGV[2], 'session', ARGV[3], 'last_seen', ARGV[6])
+redis.call('EXPIRE', KEYS[1], ARGV[4])
+redis.call('EXPIRE', KEYS[2], ARGV[4])
+return 1
+"#;
+
+/// Marks the session as used if it still exists, so a revoked session isn't brought back
+const TOUCH: &str = r#"
+if redis.call('EXISTS', KEYS[1]) == 1 then
+    redis.call('HSET', KEYS[1], 'last_seen', ARGV[1])
+end
+return 0
+"#;
+
+/// The session as it's stored in Redis, the time it's last seen is stored along with it
+/// since it's updated more often
+#[derive(Serialize, Deserialize)]
+struct StoredSession {
+    id: String,
+    user_id: String,
+    ip: IpAddr,
+    user_agent: Option<String>,
+    created_at: DateTime<Utc>,
+    refreshed_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+impl From<&Session> for StoredSession {
+    fn from(session: &Session) -> Self {
+        Self {
+            id: session.id.to_string(),
+            user_id: session.user_id.to_string(),
+            ip: session.ip,
+            user_agent: session.user_agent.clone(),
+            created_at: session.created_at,
+            refreshed_at: session.refreshed_at,
+            expires_at: session.expires_at,
+        }
+    }
+}
+
+impl TryFrom<StoredSession> for Session {
+    type Error = anyhow::Error;
+
+    fn try_from(stored: StoredSession) -> Result<Self, Self::Error> {
+        Ok(Session {
+            id: SessionID::try_from(stored.id).map_err(|_| anyhow!("Invalid session ID"))?,
+            user_id: UserID::try_from(stored.user_id).map_err(|_| anyhow!("Invalid user ID"))?,
+            ip: stored.ip,
+            user_agent: stored.user_agent,
+            created_at: stored.created_at,
+            refreshed_at: stored.refreshed_at,
+            last_seen_at: stored.refreshed_at,
+            expires_at: stored.expires_at,
+        })
+    }
+}
+
+/** Keeps the sessions in Redis, they expire along with their refresh tokens.
+*
+* A session is a hash of the session and its refresh token, the sessions of a user are indexed by a
+* set which expires along with the latest session.
+*/
+pub struct RedisSessionRepository {
+    client: Arc<redis::Client>,
+}
+
+impl RedisSessionRepository {
+    pub fn new(client: Arc<redis::Client>) -> Self {
+        Self { client }
+    }
+
+    fn session_key(id: &SessionID) -> String {
+        format!("session:{}", id)
+    }
+
+    fn user_sessions_key(user_id: &UserID) -> String {
+        format!("user_sessions:{}", user_id)
+    }
+
+    /// The seconds until the session expires, at least a second
+    fn ttl(session: &Session) -> i64 {
+        (session.expires_at - Utc::now()).num_seconds().max(1)
+    }
+}
+
+#[async_trait::async_trait]
+impl ISessionRepository for RedisSessionRepository {
+    async fn save(&self, session: &Session, refresh_token_hash: &str) -> anyhow::Result<()> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let key = Self::session_key(&session.id);
+        let user_sessions = Self::user_sessions_key(&session.user_id);
+        let ttl = Self::ttl(session);
+
+        // the sessions last as long, so the set expires along with the latest one
+        let () = redis::pipe()
+            .atomic()
+            .hset_multiple(
+                &key,
+                &[
+                    (
+                        "session",
+                        serde_json::to_string(&StoredSession::from(session))?,
+                    ),
+                    ("refresh_token", refresh_token_hash.to_string()),
+                    ("last_seen", session.last_seen_at.to_rfc3339()),
+                ],
+            )
+            .ignore()
+            .expire(&key, ttl)
+            .ignore()
+            .sadd(&user_sessions, session.id.to_string())
+            .ignore()
+            .expire(&user_sessions, ttl)
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get(&self, id: &SessionID) -> anyhow::Result<Option<Session>> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let (session, last_seen): (Option<String>, Option<String>) = conn
+            .hget(Self::session_key(id), &["session", "last_seen"])
+            .await?;
+        let Some(session) = session else {
+            return Ok(None);
+        };
+
+        let mut session: Session = serde_json::from_str::<StoredSession>(&session)?.try_into()?;
+        if let Some(last_seen) = last_seen {
+            session.last_seen_at = DateTime::parse_from_rfc3339(&last_seen)?.with_timezone(&Utc);
+        }
+
+        Ok(Some(session))
+    }
+
+    async fn rotate(
+        &self,
+        session: &Session,
+        refresh_token_hash: &str,
+        next_refresh_token_hash: &str,
+    ) -> anyhow::Result<Rotation> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+
+        let rotated: i64 = redis::Script::new(ROTATE)
+            .key(Self::session_key(&session.id))
+            .key(Self::user_sessions_key(&session.user_id))
+            .arg(refresh_token_hash)
+            .arg(next_refresh_token_hash)
+            .arg(serde_json::to_string(&StoredSession::from(session))?)
+            .arg(Self::ttl(session))
+            .arg(session.id.to_string())
+            .arg(session.last_seen_at.to_rfc3339())
+            .invoke_async(&mut conn)
+            .await?;
+
+        match rotated {
+            1 => Ok(Rotation::Rotated),
+            -1 => Ok(Rotation::Reused),
+            _ => Ok(Rotation::Missing),
+        }
+    }
+
+    async fn revoke(&self, id: &SessionID) -> anyhow::Result<()> {
+        let Some(session) = self.get(id).await? else {
+            return Ok(());
+        };
+
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let () = redis::pipe()
+            .atomic()
+            .del(Self::session_key(id))
+            .ignore()
+            .srem(Self::user_sessions_key(&session.user_id), id.to_string())
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn list(&self, user_id: &UserID) -> anyhow::Result<Vec<Session>> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let key = Self::user_sessions_key(user_id);
+        let ids: Vec<String> = conn.smembers(&key).await?;
+
+        let mut sessions = Vec::with_capacity(ids.len());
+        for id in ids {
+            let session = match SessionID::try_from(id.clone()) {
+                Ok(session_id) => self.get(&session_id).await?,
+                Err(_) => None,
+            };
+
+            // the set still holds the sessions which have expired
+            match session {
+                Some(session) => sessions.push(session),
+                None => {
+                    let () = conn.srem(&key, id).await?;
+                }
+            }
+        }
+
+        Ok(sessions)
+    }
+
+    async fn touch(&self, id: &SessionID, at: DateTime<Utc>) -> anyhow::Result<()> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+
+        let () = redis::Script::new(TOUCH)
+            .key(Self::session_key(id))
+            .arg(at.to_rfc3339())
+            .invoke_async(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+}
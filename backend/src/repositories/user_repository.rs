@@ -1,31 +1,86 @@
-use crate::domain::entities::UserID;
+use crate::domain::entities::{Role, User, UserID};
 use crate::repositories::Connection;
 use anyhow::anyhow;
 use secrecy::{ExposeSecret, SecretBox};
+use sqlx::postgres::PgRow;
 use sqlx::{Acquire, PgConnection, Row};
+#[cfg(test)]
 use std::collections::HashMap;
+#[cfg(test)]
+use std::sync::Arc;
+#[cfg(test)]
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
 #[async_trait::async_trait]
 pub trait IUserRepository {
+    /// The credentials of the username, the disabled users are treated as missing
     async fn get_credentials(
         &self,
         username: &str,
     ) -> anyhow::Result<Option<(UserID, SecretBox<String>)>>;
 
+    /// Changes the password, the user doesn't have to reset it anymore
     async fn change_password(&self, id: UserID, password: SecretBox<String>) -> anyhow::Result<()>;
 
+    /// Replaces the hash of the same password, e.g. with a stronger one, nothing else is changed
+    async fn rehash_password(&self, id: &UserID, password: SecretBox<String>)
+        -> anyhow::Result<()>;
+
     async fn create_user(
         &self,
         username: String,
         password: SecretBox<String>,
+        role: Role,
+        email: Option<String>,
     ) -> anyhow::Result<UserID>;
+
+    async fn get(&self, id: &UserID) -> anyhow::Result<Option<User>>;
+
+    async fn get_by_username(&self, username: &str) -> anyhow::Result<Option<User>>;
+
+    /// All the users, ordered by their usernames
+    async fn list(&self) -> anyhow::Result<Vec<User>>;
+
+    async fn set_disabled(&self, id: &UserID, disabled: bool) -> anyhow::Result<()>;
+
+    async fn set_must_reset_password(&self, id: &UserID, must: bool) -> anyhow::Result<()>;
+
+    async fn delete(&self, id: &UserID) -> anyhow::Result<()>;
+
+    /// The secret of the authenticator app, it's kept before the enrolment is confirmed too
+    async fn get_totp_secret(&self, id: &UserID) -> anyhow::Result<Option<SecretBox<String>>>;
+
+    /// Keeps the secret of a new enrolment, the second factor is off until it's confirmed. The
+    /// second factor is removed if the secret is missing
+    async fn set_totp_secret(
+        &self,
+        id: &UserID,
+        secret: Option<SecretBox<String>>,
+    ) -> anyhow::Result<()>;
+
+    async fn enable_totp(&self, id: &UserID) -> anyhow::Result<()>;
+
+    /// Marks the time step of a code as used, it returns false if the step or a later one has been
+    /// used, so the codes can't be replayed
+    async fn use_totp_step(&self, id: &UserID, step: i64) -> anyhow::Result<bool>;
+}
+
+/// A user along with the secrets
+#[cfg(test)]
+struct StoredUser {
+    user: User,
+    password: SecretBox<String>,
+    totp_secret: Option<SecretBox<String>>,
+    totp_last_step: Option<i64>,
 }
 
+/// The clones share the users
+#[cfg(test)]
+#[derive(Clone)]
 pub struct InMemoryUserRepository {
     error: bool,
-    credentials: Mutex<HashMap<UserID, (String, SecretBox<String>)>>,
+    users: Arc<Mutex<HashMap<UserID, StoredUser>>>,
 }
 
 #[cfg(test)]
@@ -35,16 +90,15 @@ impl Default for InMemoryUserRepository {
     }
 }
 
+#[cfg(test)]
 impl InMemoryUserRepository {
-    #[cfg(test)]
     pub fn new() -> Self {
         Self {
             error: false,
-            credentials: Mutex::new(HashMap::new()),
+            users: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    #[cfg(test)]
     pub fn with_error(self) -> Self {
         Self {
             error: true,
@@ -52,30 +106,74 @@ impl InMemoryUserRepository {
         }
     }
 
-    #[cfg(test)]
+    /// Adds an owner with the credentials
     pub async fn add_credentials(&self, id: UserID, username: String, password: SecretBox<String>) {
-        let mut lock = self.credentials.lock().await;
-        lock.insert(id, (username, password));
+        let user = User {
+            id: id.clone(),
+            username,
+            role: Role::Owner,
+            email: None,
+            disabled: false,
+            must_reset_password: false,
+            totp_enabled: false,
+            created_at: chrono::Utc::now(),
+        };
+        let stored = StoredUser {
+            user,
+            password,
+            totp_secret: None,
+            totp_last_step: None,
+        };
+        self.users.lock().await.insert(id, stored);
+    }
+
+    /// Changes the role of a user which has been added
+    pub async fn add_role(&self, id: UserID, role: Role) {
+        if let Some(stored) = self.users.lock().await.get_mut(&id) {
+            stored.user.role = role;
+        }
+    }
+
+    /// Changes the email of a user which has been added
+    pub async fn add_email(&self, id: UserID, email: Option<String>) {
+        if let Some(stored) = self.users.lock().await.get_mut(&id) {
+            stored.user.email = email;
+        }
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        match self.error {
+            true => Err(anyhow!("Internal Server Error")),
+            false => Ok(()),
+        }
+    }
+
+    async fn modify(&self, id: &UserID, f: impl FnOnce(&mut StoredUser)) -> anyhow::Result<()> {
+        self.check()?;
+
+        if let Some(stored) = self.users.lock().await.get_mut(id) {
+            f(stored);
+        }
+        Ok(())
     }
 }
 
+#[cfg(test)]
 #[async_trait::async_trait]
 impl IUserRepository for InMemoryUserRepository {
     async fn get_credentials(
         &self,
         username: &str,
     ) -> anyhow::Result<Option<(UserID, SecretBox<String>)>> {
-        if self.error {
-            return Err(anyhow!("Internal Server Error"));
-        }
+        self.check()?;
 
-        let lock = self.credentials.lock().await;
+        let lock = self.users.lock().await;
 
-        Ok(lock.iter().find_map(|(id, (name, password))| {
-            if name == username {
+        Ok(lock.values().find_map(|StoredUser { user, password, .. }| {
+            if user.username == username && !user.disabled {
                 let pwd = password.expose_secret().to_string();
                 let pwd = SecretBox::new(Box::new(pwd));
-                Some((id.clone(), pwd))
+                Some((user.id.clone(), pwd))
             } else {
                 None
             }
@@ -83,32 +181,134 @@ impl IUserRepository for InMemoryUserRepository {
     }
 
     async fn change_password(&self, id: UserID, password: SecretBox<String>) -> anyhow::Result<()> {
-        if self.error {
-            return Err(anyhow!("Internal Server Error"));
-        }
+        self.check()?;
 
-        let mut lock = self.credentials.lock().await;
+        let mut lock = self.users.lock().await;
         let entry = lock.entry(id);
-        entry.and_modify(|(_, pwd)| *pwd = password);
+        entry.and_modify(|stored| {
+            stored.user.must_reset_password = false;
+            stored.password = password;
+        });
 
         Ok(())
     }
 
+    async fn rehash_password(
+        &self,
+        id: &UserID,
+        password: SecretBox<String>,
+    ) -> anyhow::Result<()> {
+        self.modify(id, |stored| stored.password = password).await
+    }
+
     async fn create_user(
         &self,
         username: String,
         password: SecretBox<String>,
+        role: Role,
+        email: Option<String>,
     ) -> anyhow::Result<UserID> {
-        if self.error {
-            return Err(anyhow!("Internal Server Error"));
-        }
+        self.check()?;
 
-        let mut lock = self.credentials.lock().await;
-        let uuid = Uuid::new_v4();
-        let id = UserID::from(uuid);
-        lock.insert(id.clone(), (username, password));
+        let id = UserID::from(Uuid::new_v4());
+        self.add_credentials(id.clone(), username, password).await;
+        self.add_role(id.clone(), role).await;
+        self.add_email(id.clone(), email).await;
         Ok(id)
     }
+
+    async fn get(&self, id: &UserID) -> anyhow::Result<Option<User>> {
+        self.check()?;
+
+        Ok(self
+            .users
+            .lock()
+            .await
+            .get(id)
+            .map(|stored| stored.user.clone()))
+    }
+
+    async fn get_by_username(&self, username: &str) -> anyhow::Result<Option<User>> {
+        self.check()?;
+
+        let lock = self.users.lock().await;
+        Ok(lock
+            .values()
+            .find(|stored| stored.user.username == username)
+            .map(|stored| stored.user.clone()))
+    }
+
+    async fn list(&self) -> anyhow::Result<Vec<User>> {
+        self.check()?;
+
+        let mut users = self
+            .users
+            .lock()
+            .await
+            .values()
+            .map(|stored| stored.user.clone())
+            .collect::<Vec<_>>();
+        users.sort_by(|a, b| a.username.cmp(&b.username));
+        Ok(users)
+    }
+
+    async fn set_disabled(&self, id: &UserID, disabled: bool) -> anyhow::Result<()> {
+        self.modify(id, |stored| stored.user.disabled = disabled)
+            .await
+    }
+
+    async fn set_must_reset_password(&self, id: &UserID, must: bool) -> anyhow::Result<()> {
+        self.modify(id, |stored| stored.user.must_reset_password = must)
+            .await
+    }
+
+    async fn delete(&self, id: &UserID) -> anyhow::Result<()> {
+        self.check()?;
+
+        self.users.lock().await.remove(id);
+        Ok(())
+    }
+
+    async fn get_totp_secret(&self, id: &UserID) -> anyhow::Result<Option<SecretBox<String>>> {
+        self.check()?;
+
+        let lock = self.users.lock().await;
+        Ok(lock
+            .get(id)
+            .and_then(|stored| stored.totp_secret.as_ref())
+            .map(|secret| SecretBox::new(Box::new(secret.expose_secret().to_string()))))
+    }
+
+    async fn set_totp_secret(
+        &self,
+        id: &UserID,
+        secret: Option<SecretBox<String>>,
+    ) -> anyhow::Result<()> {
+        self.modify(id, |stored| {
+            stored.user.totp_enabled = false;
+            stored.totp_secret = secret;
+            stored.totp_last_step = None;
+        })
+        .await
+    }
+
+    async fn enable_totp(&self, id: &UserID) -> anyhow::Result<()> {
+        self.modify(id, |stored| stored.user.totp_enabled = true)
+            .await
+    }
+
+    async fn use_totp_step(&self, id: &UserID, step: i64) -> anyhow::Result<bool> {
+        self.check()?;
+
+        let mut lock = self.users.lock().await;
+        match lock.get_mut(id) {
+            Some(stored) if stored.totp_last_step.is_none_or(|last| last < step) => {
+                stored.totp_last_step = Some(step);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -163,17 +363,41 @@ impl IUserRepository for SqlxUserRepository<'_> {
         }
     }
 
+    async fn rehash_password(
+        &self,
+        id: &UserID,
+        password: SecretBox<String>,
+    ) -> anyhow::Result<()> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                Ok(rehash_password(conn, id, password).await?)
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                Ok(rehash_password(conn, id, password).await?)
+            }
+        }
+    }
+
     async fn create_user(
         &self,
         username: String,
         password: SecretBox<String>,
+        role: Role,
+        email: Option<String>,
     ) -> anyhow::Result<UserID> {
         match &self.conn {
             Connection::Pool(pool) => {
                 let mut conn = pool.acquire().await?;
                 let conn = conn.as_mut();
 
-                let id = create(conn, username, password).await?;
+                let id = create(conn, username, password, role, email).await?;
                 Ok(UserID::from(id))
             }
             Connection::Transaction(tx) => {
@@ -181,18 +405,206 @@ impl IUserRepository for SqlxUserRepository<'_> {
                 let mut lock = conn_ptr.lock().await;
                 let conn = lock.acquire().await?;
 
-                let id = create(conn, username, password).await?;
+                let id = create(conn, username, password, role, email).await?;
                 Ok(UserID::from(id))
             }
         }
     }
+
+    async fn get(&self, id: &UserID) -> anyhow::Result<Option<User>> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                get_user(conn, "id = $1::uuid", id.to_string()).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                get_user(conn, "id = $1::uuid", id.to_string()).await
+            }
+        }
+    }
+
+    async fn get_by_username(&self, username: &str) -> anyhow::Result<Option<User>> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                get_user(conn, "username = $1", username.to_string()).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                get_user(conn, "username = $1", username.to_string()).await
+            }
+        }
+    }
+
+    async fn list(&self) -> anyhow::Result<Vec<User>> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                list_users(conn).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                list_users(conn).await
+            }
+        }
+    }
+
+    async fn set_disabled(&self, id: &UserID, disabled: bool) -> anyhow::Result<()> {
+        let query = "update \"users\" set disabled = $1 where id = $2";
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                set_flag(conn, query, id, disabled).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                set_flag(conn, query, id, disabled).await
+            }
+        }
+    }
+
+    async fn set_must_reset_password(&self, id: &UserID, must: bool) -> anyhow::Result<()> {
+        let query = "update \"users\" set must_reset_password = $1 where id = $2";
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                set_flag(conn, query, id, must).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                set_flag(conn, query, id, must).await
+            }
+        }
+    }
+
+    async fn delete(&self, id: &UserID) -> anyhow::Result<()> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                delete_user(conn, id).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                delete_user(conn, id).await
+            }
+        }
+    }
+
+    async fn get_totp_secret(&self, id: &UserID) -> anyhow::Result<Option<SecretBox<String>>> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                get_totp_secret(conn, id).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                get_totp_secret(conn, id).await
+            }
+        }
+    }
+
+    async fn set_totp_secret(
+        &self,
+        id: &UserID,
+        secret: Option<SecretBox<String>>,
+    ) -> anyhow::Result<()> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                set_totp_secret(conn, id, secret).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                set_totp_secret(conn, id, secret).await
+            }
+        }
+    }
+
+    async fn enable_totp(&self, id: &UserID) -> anyhow::Result<()> {
+        let query =
+            "update \"users\" set totp_enabled = $1 where id = $2 and totp_secret is not null";
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                set_flag(conn, query, id, true).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                set_flag(conn, query, id, true).await
+            }
+        }
+    }
+
+    async fn use_totp_step(&self, id: &UserID, step: i64) -> anyhow::Result<bool> {
+        match &self.conn {
+            Connection::Pool(pool) => {
+                let mut conn = pool.acquire().await?;
+                let conn = conn.as_mut();
+
+                use_totp_step(conn, id, step).await
+            }
+            Connection::Transaction(tx) => {
+                let conn_ptr = tx.upgrade().ok_or(anyhow!("Internal Server Error"))?;
+                let mut lock = conn_ptr.lock().await;
+                let conn = lock.acquire().await?;
+
+                use_totp_step(conn, id, step).await
+            }
+        }
+    }
 }
 
 async fn get_credentials(
     conn: &mut PgConnection,
     username: &str,
 ) -> anyhow::Result<Option<(UserID, SecretBox<String>)>> {
-    let query = "select id, password_hash from \"users\" where username = $1";
+    let query = "select id, password_hash from \"users\" where username = $1 and not disabled";
 
     let res = sqlx::query(query)
         .bind(username)
@@ -217,7 +629,8 @@ async fn change_password(
     id: UserID,
     password: SecretBox<String>,
 ) -> anyhow::Result<()> {
-    let query = "UPDATE \"users\" SET password_hash = $1 WHERE id = $2";
+    let query =
+        "UPDATE \"users\" SET password_hash = $1, must_reset_password = false WHERE id = $2::uuid";
 
     sqlx::query(query)
         .bind(password.expose_secret().to_string().as_str())
@@ -228,20 +641,148 @@ async fn change_password(
     Ok(())
 }
 
+async fn rehash_password(
+    conn: &mut PgConnection,
+    id: &UserID,
+    password: SecretBox<String>,
+) -> anyhow::Result<()> {
+    sqlx::query("UPDATE \"users\" SET password_hash = $1 WHERE id = $2::uuid")
+        .bind(password.expose_secret().as_str())
+        .bind(id.to_string().as_str())
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
 async fn create(
     conn: &mut PgConnection,
     username: String,
     password: SecretBox<String>,
+    role: Role,
+    email: Option<String>,
 ) -> anyhow::Result<Uuid> {
     let uuid = Uuid::new_v4();
     let id = sqlx::query_scalar::<_, Uuid>(
-        "insert into \"users\" (id, username, password_hash) values ($1, $2, $3) returning id;",
+        "insert into \"users\" (id, username, password_hash, role, email) values ($1, $2, $3, $4, $5) returning id;",
     )
     .bind(uuid)
     .bind(username)
     .bind(password.expose_secret().to_string())
+    .bind(role.as_str())
+    .bind(email)
     .fetch_one(conn)
     .await?;
 
     Ok(id)
 }
+
+const USER_COLUMNS: &str =
+    "id, username, role, email, disabled, must_reset_password, totp_enabled, created_at";
+
+fn to_user(row: PgRow) -> anyhow::Result<User> {
+    let role = row.get::<String, _>("role");
+
+    Ok(User {
+        id: UserID::from(row.get::<Uuid, _>("id")),
+        username: row.get("username"),
+        role: Role::try_from(role.as_str()).map_err(|_| anyhow!("Unknown role {}", role))?,
+        email: row.get("email"),
+        disabled: row.get("disabled"),
+        must_reset_password: row.get("must_reset_password"),
+        totp_enabled: row.get("totp_enabled"),
+        created_at: row.get("created_at"),
+    })
+}
+
+async fn get_user(
+    conn: &mut PgConnection,
+    condition: &str,
+    value: String,
+) -> anyhow::Result<Option<User>> {
+    let query = format!("select {} from \"users\" where {}", USER_COLUMNS, condition);
+
+    sqlx::query(&query)
+        .bind(value)
+        .fetch_optional(conn)
+        .await?
+        .map(to_user)
+        .transpose()
+}
+
+async fn list_users(conn: &mut PgConnection) -> anyhow::Result<Vec<User>> {
+    let query = format!("select {} from \"users\" order by username", USER_COLUMNS);
+
+    sqlx::query(&query)
+        .fetch_all(conn)
+        .await?
+        .into_iter()
+        .map(to_user)
+        .collect()
+}
+
+async fn set_flag(
+    conn: &mut PgConnection,
+    query: &str,
+    id: &UserID,
+    value: bool,
+) -> anyhow::Result<()> {
+    sqlx::query(query)
+        .bind(value)
+        .bind(id.as_uuid())
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+async fn delete_user(conn: &mut PgConnection, id: &UserID) -> anyhow::Result<()> {
+    sqlx::query("delete from \"users\" where id = $1")
+        .bind(id.as_uuid())
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+async fn get_totp_secret(
+    conn: &mut PgConnection,
+    id: &UserID,
+) -> anyhow::Result<Option<SecretBox<String>>> {
+    let secret =
+        sqlx::query_scalar::<_, Option<String>>("select totp_secret from \"users\" where id = $1")
+            .bind(id.as_uuid())
+            .fetch_optional(conn)
+            .await?
+            .flatten();
+
+    Ok(secret.map(|secret| SecretBox::new(Box::new(secret))))
+}
+
+async fn set_totp_secret(
+    conn: &mut PgConnection,
+    id: &UserID,
+    secret: Option<SecretBox<String>>,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "update \"users\" set totp_secret = $1, totp_enabled = false, totp_last_step = null where id = $2",
+    )
+    .bind(secret.map(|secret| secret.expose_secret().to_string()))
+    .bind(id.as_uuid())
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn use_totp_step(conn: &mut PgConnection, id: &UserID, step: i64) -> anyhow::Result<bool> {
+    let res = sqlx::query(
+        "update \"users\" set totp_last_step = $1 where id = $2 and (totp_last_step is null or totp_last_step < $1)",
+    )
+    .bind(step)
+    .bind(id.as_uuid())
+    .execute(conn)
+    .await?;
+
+    Ok(res.rows_affected() > 0)
+}
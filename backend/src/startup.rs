@@ -1,27 +1,49 @@
 use crate::api::change_password::change_password;
-use crate::api::login::login;
+use crate::api::login::{login, login_totp};
 use crate::api::logout::logout;
+use crate::api::refresh::refresh;
+use crate::api::require;
 use crate::api::{
-    create_article, create_category, create_contact, create_home, create_member, create_service,
-    delete_article, delete_category, delete_member, delete_service, health_check, list_articles,
-    list_categories, list_contact, list_home, list_members, list_services, retrieve_article,
-    retrieve_category, retrieve_contact, retrieve_home, retrieve_member, retrieve_service,
-    update_article, update_category, update_contact, update_home, update_member, update_service,
-    upload_member_avatar, view_article,
+    conditional_get, confirm_totp, create_user, delete_user, deprecate, disable_totp, disable_user,
+    enable_user, enroll_totp, force_password_reset, forgot_password, get_security_settings,
+    health_check, list_audit_log, list_login_attempts, list_sessions, list_users, openapi,
+    rate_limit, regenerate_recovery_codes, request_id, reset_password, reset_totp,
+    retrieve_category_tree, retrieve_service_faq_page, revoke_other_sessions, revoke_session,
+    revoke_user_sessions, totp_status, unlock_account, update_security_settings,
+    upload_member_avatar, view_article, ApiRouter, ApiVersion, ClientIpHeader, IRateLimitStore,
+    InMemoryRateLimitStore, RateLimit, RateLimiter, RedisRateLimitStore, ResourceRoutes,
 };
-use crate::configuration::{DatabaseSettings, Settings};
+use crate::cache::{IResourceCache, InMemoryCache, NoCache, RedisCache};
+use crate::configuration::{
+    ApiSettings, CacheBackend, CacheSettings, DatabaseSettings, MailSettings, MailTransport,
+    RateLimitStore, Settings,
+};
+use crate::domain::registry::ResourceKind;
+use crate::domain::registry::{
+    ArticleKind, CaseResultKind, CategoryKind, ContactKind, FaqKind, HomeKind, MemberKind,
+    MemberV2Kind, ServiceKind, TestimonialKind,
+};
+use crate::domain::sessions::SessionPolicy;
+use crate::domain::users::authentication::LockoutPolicy;
+use crate::domain::users::forgot_password::PasswordResetPolicy;
+use crate::domain::users::password_hashing::PasswordHasher;
+use crate::domain::users::password_policy::PasswordPolicy;
+use crate::domain::users::permissions::{Action, Permission, Subject};
+use crate::domain::users::totp::TotpPolicy;
+use crate::mailer::{IMailer, OutboxMailer, SmtpMailer};
 use crate::utils::image::ImageUtil;
-use axum::http::HeaderValue;
-use axum::routing::{delete, get, post, put};
-use axum::{Extension, Router};
+use axum::http::{HeaderName, HeaderValue};
+use axum::Extension;
 use jsonwebtoken::{DecodingKey, EncodingKey};
 use secrecy::ExposeSecret;
 use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
 use std::net::SocketAddr;
+use std::num::NonZeroUsize;
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use tower_http::cors::CorsLayer;
+use utoipa_swagger_ui::SwaggerUi;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -29,11 +51,176 @@ pub struct AppState {
     pub upload_folder: Arc<String>,
     pub jwt_encoding_key: Arc<EncodingKey>,
     pub jwt_decoding_key: Arc<DecodingKey>,
+    pub cache: Arc<dyn IResourceCache>,
+    pub lockout: Arc<LockoutPolicy>,
+    pub sessions: Arc<SessionPolicy>,
+    pub passwords: Arc<PasswordPolicy>,
+    pub password_hashing: Arc<PasswordHasher>,
+    pub password_reset: Arc<PasswordResetPolicy>,
+    pub mailer: Arc<dyn IMailer>,
+    pub totp: Arc<TotpPolicy>,
+}
+
+/// The resource types which are the same in all versions
+fn register_common_resources(resources: ResourceRoutes) -> ResourceRoutes {
+    resources
+        .register::<ServiceKind>()
+        .register::<HomeKind>()
+        .register::<ContactKind>()
+        .register::<ArticleKind>()
+        .register::<CategoryKind>()
+        .register::<FaqKind>()
+        .register::<TestimonialKind>()
+        .register::<CaseResultKind>()
+}
+
+/// The versions of the API, the second version lists the full profiles of the members
+fn versions() -> Vec<ApiVersion> {
+    vec![
+        ApiVersion::new(
+            "v1",
+            register_common_resources(ResourceRoutes::new().register::<MemberKind>()),
+        ),
+        ApiVersion::new(
+            "v2",
+            register_common_resources(ResourceRoutes::new().register::<MemberV2Kind>()),
+        ),
+    ]
+}
+
+/// The routes of a version, the CRUD routes are derived from its resource types
+fn version_routes(resources: ResourceRoutes, settings: &ApiSettings) -> ApiRouter {
+    let (resource_routes, admin_resource_routes) = resources.into_routers();
+
+    // the roles are checked against the permissions of the routes
+    let require = |subject, action| {
+        axum::middleware::from_fn_with_state(Permission::new(subject, action), require)
+    };
+
+    let admin_member_routes = ApiRouter::new()
+        .post("/members/{id}/avatar", upload_member_avatar)
+        .layer(require(Subject::Resource(MemberKind::TYPE), Action::Update));
+
+    // the routes are limited by their groups, see the rate limits of the settings
+    let limit = |group| {
+        axum::middleware::from_fn_with_state(
+            RateLimit::new(group, &settings.rate_limits),
+            rate_limit,
+        )
+    };
+
+    let article_routes = ApiRouter::new()
+        .post("/articles/{id}/view", view_article)
+        .layer(limit("article_views"));
+
+    let faq_routes = ApiRouter::new().get("/services/{id}/faqs/json-ld", retrieve_service_faq_page);
+
+    let category_routes = ApiRouter::new().get("/categories/tree", retrieve_category_tree);
+
+    let login_routes = ApiRouter::new()
+        .post("/login", login)
+        .post("/login/totp", login_totp)
+        .post("/refresh", refresh)
+        .post("/password/forgot", forgot_password)
+        .post("/password/reset", reset_password)
+        .layer(limit("login"));
+
+    // the users manage their own accounts and sessions
+    let admin_account_routes = ApiRouter::new()
+        .post("/logout", logout)
+        .put("/password", change_password)
+        .get("/sessions", list_sessions)
+        .delete("/sessions/others", revoke_other_sessions)
+        .delete("/sessions/{id}", revoke_session)
+        .get("/totp", totp_status)
+        .post("/totp", enroll_totp)
+        .post("/totp/confirm", confirm_totp)
+        .post("/totp/disable", disable_totp)
+        .post("/totp/recovery-codes", regenerate_recovery_codes);
+
+    let admin_user_routes = ApiRouter::new()
+        .merge(
+            ApiRouter::new()
+                .get("/audit-log", list_audit_log)
+                .get("/login-attempts", list_login_attempts)
+                .get("/security", get_security_settings)
+                .layer(require(Subject::Users, Action::Read)),
+        )
+        .merge(
+            ApiRouter::new()
+                .delete("/lockouts/{username}", unlock_account)
+                .delete("/users/{id}/sessions", revoke_user_sessions)
+                .post("/users/{id}/disable", disable_user)
+                .post("/users/{id}/enable", enable_user)
+                .post("/users/{id}/password-reset", force_password_reset)
+                .post("/users/{id}/totp-reset", reset_totp)
+                .put("/security", update_security_settings)
+                .layer(require(Subject::Users, Action::Update)),
+        )
+        .merge(
+            ApiRouter::new()
+                .get("/users", list_users)
+                .layer(require(Subject::Users, Action::Read)),
+        )
+        .merge(
+            ApiRouter::new()
+                .post("/users", create_user)
+                .layer(require(Subject::Users, Action::Create)),
+        )
+        .merge(
+            ApiRouter::new()
+                .delete("/users/{id}", delete_user)
+                .layer(require(Subject::Users, Action::Delete)),
+        );
+
+    let admin_routes = ApiRouter::new()
+        .merge(admin_resource_routes)
+        .merge(admin_member_routes)
+        .merge(admin_account_routes)
+        .merge(admin_user_routes)
+        .layer(limit("admin"))
+        .merge(login_routes);
+
+    // the public reads can be revalidated with their ETags
+    let public_read_routes = ApiRouter::new()
+        .merge(resource_routes)
+        .merge(faq_routes)
+        .merge(category_routes)
+        .layer(limit("public"))
+        .layer(axum::middleware::from_fn_with_state(
+            Arc::new(settings.cache_control.clone()),
+            conditional_get,
+        ));
+
+    ApiRouter::new()
+        .merge(public_read_routes)
+        .merge(article_routes)
+        .nest("/admin", admin_routes)
+}
+
+/// Builds the routes along with the OpenAPI document which describes them
+pub(crate) fn api(settings: &ApiSettings) -> (ApiRouter, serde_json::Value) {
+    let versions = versions();
+    let spec = openapi::document(&versions, settings);
+
+    let mut api = ApiRouter::new().get("/health", health_check);
+    for version in versions {
+        let prefix = version.prefix();
+        let mut routes = version_routes(version.resources, settings);
+        if let Some(deprecation) = settings.deprecation(version.name) {
+            routes = deprecate(routes, deprecation);
+        }
+
+        api = api.nest(&prefix, routes);
+    }
+
+    (api, spec)
 }
 
 pub async fn run(config: Settings, listener: TcpListener) -> Result<(), std::io::Error> {
-    let redis_client =
-        redis::Client::open(config.redis_uri.as_str()).expect("Failed to connect the redis server");
+    let redis_client = Arc::new(
+        redis::Client::open(config.redis_uri.as_str()).expect("Failed to connect the redis server"),
+    );
 
     let jwt_encoding_key = Arc::new(EncodingKey::from_secret(
         config.application.jwt_secret.expose_secret().as_bytes(),
@@ -42,84 +229,50 @@ pub async fn run(config: Settings, listener: TcpListener) -> Result<(), std::io:
         config.application.jwt_secret.expose_secret().as_bytes(),
     ));
 
+    let rate_limiter = RateLimiter::new(
+        get_rate_limit_store(config.api.rate_limits.store, redis_client.clone()),
+        jwt_decoding_key.clone(),
+    );
+    let client_ip_header = ClientIpHeader(
+        config
+            .api
+            .client_ip_header
+            .as_ref()
+            .map(|name| HeaderName::try_from(name.as_str()).expect("Invalid client IP header")),
+    );
+
     let state = AppState {
         pool: get_database_connection(&config.database).await,
         upload_folder: Arc::new(config.application.upload_folder),
         jwt_decoding_key,
         jwt_encoding_key,
+        cache: get_cache(&config.cache, redis_client.clone()),
+        lockout: Arc::new(config.lockout.policy()),
+        sessions: Arc::new(config.sessions.policy()),
+        passwords: Arc::new(config.passwords.policy()),
+        password_hashing: Arc::new(config.password_hashing.hasher()),
+        password_reset: Arc::new(config.password_reset.policy()),
+        mailer: get_mailer(&config.mail),
+        totp: Arc::new(config.totp.policy()),
     };
     let image_util = ImageUtil {};
 
-    // Config the routes
-    let admin_member_routes = Router::new()
-        .route("/members", post(create_member).put(update_member))
-        .route("/members/{id}", delete(delete_member))
-        .route("/members/{id}/avatar", post(upload_member_avatar));
-    let member_routes = Router::new()
-        .route("/members/{id}", get(retrieve_member))
-        .route("/members", get(list_members));
-
-    let admin_service_routes = Router::new()
-        .route("/services", post(create_service).put(update_service))
-        .route("/services/{id}", delete(delete_service));
-    let service_routes = Router::new()
-        .route("/services/{id}", get(retrieve_service))
-        .route("/services", get(list_services));
-
-    let admin_home_routes = Router::new().route("/home", post(create_home).put(update_home));
-    let home_routes = Router::new()
-        .route("/home/{id}", get(retrieve_home))
-        .route("/home", get(list_home));
-
-    let admin_contact_routes =
-        Router::new().route("/contact", post(create_contact).put(update_contact));
-    let contact_routes = Router::new()
-        .route("/contact/{id}", get(retrieve_contact))
-        .route("/contact", get(list_contact));
-
-    let admin_article_routes = Router::new()
-        .route("/articles", post(create_article).put(update_article))
-        .route("/articles/{id}", delete(delete_article));
-    let article_routes = Router::new()
-        .route("/articles/{id}/view", post(view_article))
-        .route("/articles/{id}", get(retrieve_article))
-        .route("/articles", get(list_articles));
-
-    let admin_category_routes = Router::new()
-        .route("/categories", post(create_category).put(update_category))
-        .route("/categories/{id}", delete(delete_category));
-    let category_routes = Router::new()
-        .route("/categories", get(list_categories))
-        .route("/categories/{id}", get(retrieve_category));
-
-    let admin_user_routes = Router::new()
-        .route("/login", post(login))
-        .route("/logout", post(logout))
-        .route("/password", put(change_password));
-
-    let admin_routes = Router::new()
-        .merge(admin_member_routes)
-        .merge(admin_home_routes)
-        .merge(admin_service_routes)
-        .merge(admin_contact_routes)
-        .merge(admin_article_routes)
-        .merge(admin_category_routes)
-        .merge(admin_user_routes);
-
-    let routes = Router::new()
-        .merge(member_routes)
-        .merge(service_routes)
-        .merge(home_routes)
-        .merge(contact_routes)
-        .merge(category_routes)
-        .merge(article_routes);
+    let (routes, spec) = api(&config.api);
+    for (method, path) in routes.routes() {
+        tracing::debug!("Serving {} {}", method, path);
+    }
+    let explorer = SwaggerUi::new("/api/docs").external_url_unchecked("/api/openapi.json", spec);
 
-    let app = Router::new()
-        .route("/health", get(health_check))
-        .nest("/api/{version}/admin", admin_routes)
-        .nest("/api/{version}/", routes)
+    let app = routes
+        .into_router()
+        .merge(explorer)
+        .layer(axum::middleware::from_fn(request_id))
         .layer(Extension(Arc::new(image_util)))
-        .layer(Extension(Arc::new(redis_client)))
+        .layer(Extension(Arc::new(rate_limiter)))
+        .layer(Extension(client_ip_header))
+        .layer(Extension(redis_client))
+        // the `require` layers verify the tokens with it
+        .layer(Extension(state.clone()))
         .layer(CorsLayer::permissive())
         .layer(
             tower_http::set_header::response::SetResponseHeaderLayer::if_not_present(
@@ -141,3 +294,50 @@ pub async fn get_database_connection(config: &DatabaseSettings) -> PgPool {
         .acquire_timeout(std::time::Duration::from_secs(config.timeout))
         .connect_lazy_with(config.with_db())
 }
+
+pub fn get_cache(
+    config: &CacheSettings,
+    redis_client: Arc<redis::Client>,
+) -> Arc<dyn IResourceCache> {
+    let ttl = std::time::Duration::from_secs(config.ttl_seconds);
+
+    match config.backend {
+        CacheBackend::None => Arc::new(NoCache),
+        CacheBackend::Memory => Arc::new(InMemoryCache::new(
+            NonZeroUsize::new(config.capacity).unwrap_or(NonZeroUsize::MIN),
+            ttl,
+        )),
+        CacheBackend::Redis => Arc::new(RedisCache::new(redis_client, ttl)),
+    }
+}
+
+pub fn get_rate_limit_store(
+    store: RateLimitStore,
+    redis_client: Arc<redis::Client>,
+) -> Arc<dyn IRateLimitStore> {
+    match store {
+        RateLimitStore::Memory => Arc::new(InMemoryRateLimitStore::default()),
+        RateLimitStore::Redis => Arc::new(RedisRateLimitStore::new(redis_client)),
+    }
+}
+
+pub fn get_mailer(config: &MailSettings) -> Arc<dyn IMailer> {
+    match (config.transport, &config.smtp) {
+        (MailTransport::Outbox, _) => {
+            Arc::new(OutboxMailer::new(&config.outbox_folder, &config.from))
+        }
+        (MailTransport::Smtp, Some(smtp)) => {
+            let credentials = smtp.username.clone().zip(
+                smtp.password
+                    .as_ref()
+                    .map(|password| password.expose_secret().to_string()),
+            );
+
+            Arc::new(
+                SmtpMailer::new(&smtp.host, smtp.port, credentials, &config.from)
+                    .expect("Invalid SMTP settings"),
+            )
+        }
+        (MailTransport::Smtp, None) => panic!("The SMTP transport requires the smtp settings"),
+    }
+}
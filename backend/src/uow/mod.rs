@@ -4,4 +4,8 @@ pub use resource::InDatabase;
 #[cfg(test)]
 pub use resource::InMemory;
 
+pub use user::IUserUnitOfWork;
+
 pub mod resource;
+
+pub mod user;
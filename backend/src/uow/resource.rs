@@ -1,21 +1,18 @@
 use crate::domain::entities::{
-    ArticleData, ArticleEntity, ArticleEntityFromSQLx, CategoryData, CategoryEntity,
-    CategoryEntityFromSQLx, ContactData, ContactEntity, ContactEntityFromSQLx, ContentID, HomeData,
-    HomeEntity, HomeEntityFromSQLx, Language, MemberData, MemberEntity, MemberEntityFromSQLx,
-    Pagination, ResourceID, ResourceType, ServiceData, ServiceEntity, ServiceEntityFromSQLx,
-    SimpleArticleEntity, SimpleArticleEntityFromSQLx, SimpleMemberEntity,
-    SimpleMemberEntityFromSQLx,
+    ContentData, ContentID, Language, Pagination, ResourceID, ResourceRecord, ResourceType,
 };
 use crate::domain::member::entities::AvatarData;
+use crate::domain::registry::{Filter, Reference, ResourceKind};
 use crate::repositories::{
-    IAvatarRepository, InMemoryAvatarRepository, InMemoryContentRepository, SqlxResourceRepository,
+    IAuditRepository, IAvatarRepository, InMemoryAuditRepository, InMemoryAvatarRepository,
+    InMemoryContentRepository, SqlxAuditRepository, SqlxResourceRepository,
 };
 use crate::repositories::{IContentRepository, InMemoryResourceRepository};
 use crate::repositories::{IResourceRepository, SqlxAvatarRepository, SqlxContentRepository};
 use anyhow::anyhow;
 use serde::de::DeserializeOwned;
-use serde::Serialize;
-use sqlx::{PgPool, Postgres, Row, Transaction};
+use sqlx::{PgPool, Postgres, QueryBuilder, Row, Transaction};
+use std::collections::HashSet;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -24,6 +21,7 @@ use tokio::sync::Mutex;
 * - resource repository
 * - content repository
 * - avatar repository
+* - audit repository
 */
 #[async_trait::async_trait]
 pub trait IResourceUnitOfWork {
@@ -36,34 +34,63 @@ pub trait IResourceUnitOfWork {
     /** Avatar repository stores all avatars associated with the members. */
     fn avatar_repository(&mut self) -> &mut impl IAvatarRepository;
 
-    /** Get a resource by ID and language */
-    async fn get_resource<T>(
+    /** Audit repository records who changed which resource */
+    fn audit_repository(&mut self) -> &mut impl IAuditRepository;
+
+    /** Get a resource by ID and language in the transaction, the resource has to match all filters */
+    async fn get_resource<K: ResourceKind>(
         &self,
         id: &ResourceID,
         lang: &Language,
-        resource_type: &ResourceType,
-    ) -> anyhow::Result<Option<T>>
-    where
-        T: DeserializeOwned + Serialize;
+        filters: &[Filter],
+    ) -> anyhow::Result<Option<K::Entity>>;
 
-    async fn list_resources<T>(
+    /** List the resources of a resource type which match all filters */
+    async fn list_resources<K: ResourceKind>(
         &self,
         language: &Language,
-        filter_str: &Option<String>,
-        resource_type: &ResourceType,
+        filters: &[Filter],
         page: &Pagination,
-    ) -> anyhow::Result<Vec<T>>
-    where
-        T: DeserializeOwned + Serialize;
+    ) -> anyhow::Result<Vec<K::ListItem>>;
 
     /// Calculate how many resources
-    async fn count_resources(
+    async fn count_resources<K: ResourceKind>(
         &self,
         language: &Language,
-        filter_str: &Option<String>,
-        resource_type: &ResourceType,
+        filters: &[Filter],
     ) -> anyhow::Result<usize>;
 
+    /** List the resources along with how many resources refer to each of them in the language.
+     *
+     * The resources which are not translated fall back to the default language.
+     */
+    async fn list_resources_with_reference_counts<K: ResourceKind>(
+        &self,
+        language: &Language,
+        default_language: &Language,
+        reference: &Reference,
+    ) -> anyhow::Result<Vec<(K::Entity, usize)>>;
+
+    /** Count the resources which refer to the resource through the reference */
+    async fn count_references(
+        &self,
+        reference: &Reference,
+        id: &ResourceID,
+    ) -> anyhow::Result<usize>;
+
+    /** Point the references to the resource at the target, or clear them if there is no target.
+     *
+     * The resource is replaced by the target in the lists of IDs, or removed from them.
+     *
+     * Returns the IDs of the referring resources which are changed.
+     */
+    async fn replace_references(
+        &self,
+        reference: &Reference,
+        id: &ResourceID,
+        target: Option<&ResourceID>,
+    ) -> anyhow::Result<Vec<ResourceID>>;
+
     /** Commit the transaction */
     async fn commit(mut self) -> anyhow::Result<()>;
     /** Rollback the transaction */
@@ -76,6 +103,21 @@ pub struct InMemory {
     resource_repository: Option<InMemoryResourceRepository>,
     content_repository: Option<InMemoryContentRepository>,
     avatar_repository: Option<InMemoryAvatarRepository>,
+    audit_repository: Option<InMemoryAuditRepository>,
+}
+
+impl InMemory {
+    fn in_memory_content_repository(&mut self) -> &mut InMemoryContentRepository {
+        if self.content_repository.is_none() {
+            let content_repo = if self.error {
+                InMemoryContentRepository::new().with_error()
+            } else {
+                InMemoryContentRepository::new()
+            };
+            self.content_repository = Some(content_repo);
+        }
+        self.content_repository.as_mut().unwrap()
+    }
 }
 
 #[cfg(test)]
@@ -86,6 +128,15 @@ impl InMemory {
             resource_repository: None,
             content_repository: None,
             avatar_repository: None,
+            audit_repository: None,
+        }
+    }
+
+    /// The entries are shared with the given repository, so they can be checked
+    pub fn with_audit_repository(self, audit_repository: InMemoryAuditRepository) -> Self {
+        Self {
+            audit_repository: Some(audit_repository),
+            ..self
         }
     }
 
@@ -95,6 +146,7 @@ impl InMemory {
             resource_repository: self.resource_repository.map(|repo| repo.with_error()),
             content_repository: self.content_repository.map(|repo| repo.with_error()),
             avatar_repository: self.avatar_repository.map(|repo| repo.with_error()),
+            audit_repository: self.audit_repository.map(|repo| repo.with_error()),
         }
     }
 }
@@ -103,26 +155,20 @@ impl InMemory {
 impl IResourceUnitOfWork for InMemory {
     fn resource_repository(&mut self) -> &mut impl IResourceRepository {
         if self.resource_repository.is_none() {
+            // the contents are shared, so moving a resource bumps their versions
+            let content_repo = self.in_memory_content_repository().clone();
             let resource_repo = if self.error {
                 InMemoryResourceRepository::new().with_error()
             } else {
                 InMemoryResourceRepository::new()
             };
-            self.resource_repository = Some(resource_repo);
+            self.resource_repository = Some(resource_repo.with_content(content_repo));
         }
         self.resource_repository.as_mut().unwrap()
     }
 
     fn content_repository(&mut self) -> &mut impl IContentRepository {
-        if self.content_repository.is_none() {
-            let content_repo = if self.error {
-                InMemoryContentRepository::new().with_error()
-            } else {
-                InMemoryContentRepository::new()
-            };
-            self.content_repository = Some(content_repo);
-        }
-        self.content_repository.as_mut().unwrap()
+        self.in_memory_content_repository()
     }
 
     fn avatar_repository(&mut self) -> &mut impl IAvatarRepository {
@@ -137,182 +183,235 @@ impl IResourceUnitOfWork for InMemory {
         self.avatar_repository.as_mut().unwrap()
     }
 
-    async fn get_resource<T>(
+    fn audit_repository(&mut self) -> &mut impl IAuditRepository {
+        if self.audit_repository.is_none() {
+            let audit_repo = if self.error {
+                InMemoryAuditRepository::new().with_error()
+            } else {
+                InMemoryAuditRepository::new()
+            };
+            self.audit_repository = Some(audit_repo);
+        }
+        self.audit_repository.as_mut().unwrap()
+    }
+
+    async fn get_resource<K: ResourceKind>(
         &self,
         id: &ResourceID,
         lang: &Language,
-        resource_type: &ResourceType,
-    ) -> anyhow::Result<Option<T>>
-    where
-        T: DeserializeOwned + Serialize,
-    {
+        filters: &[Filter],
+    ) -> anyhow::Result<Option<K::Entity>> {
+        let exist = self
+            .resource_repository
+            .as_ref()
+            .unwrap()
+            .contains(id, &K::TYPE)
+            .await?;
+
         let content_id = ContentID::from(id.clone());
         let data = self
             .content_repository
             .as_ref()
             .unwrap()
             .get(&content_id, lang)
-            .await;
+            .await?;
 
         match data {
-            Ok(Some(data)) => {
-                let json = match resource_type {
-                    ResourceType::Member => {
-                        let avatar = self.avatar_repository.as_ref().unwrap().get(id).await?;
-                        let avatar = avatar.and_then(|json| {
-                            serde_json::value::from_value::<AvatarData>(json.get()).ok()
-                        });
-                        let json = serde_json::from_value::<MemberData>(data.clone().to_json())?;
-                        let member = MemberEntity::new(
-                            id.clone().to_string(),
-                            lang.as_str().to_string(),
-                            json,
-                            avatar,
-                            0,
-                        );
-                        serde_json::value::to_value(member)?
-                    }
-                    ResourceType::Service => {
-                        let json = serde_json::from_value::<ServiceData>(data.clone().to_json())?;
-                        let service = ServiceEntity::new(
-                            id.clone().to_string(),
-                            lang.as_str().to_string(),
-                            json,
-                            0,
-                        );
-
-                        serde_json::value::to_value(service)?
-                    }
-                    ResourceType::Home => {
-                        let json = serde_json::from_value::<HomeData>(data.clone().to_json())?;
-                        let home = HomeEntity::new(
-                            id.clone().to_string(),
-                            lang.as_str().to_string(),
-                            json,
-                        );
-
-                        serde_json::value::to_value(home)?
-                    }
-                    ResourceType::Contact => {
-                        let json = serde_json::from_value::<ContactData>(data.clone().to_json())?;
-                        let contact = ContactEntity::new(
-                            id.clone().to_string(),
-                            lang.as_str().to_string(),
-                            json.data,
-                        );
-
-                        serde_json::value::to_value(contact)?
-                    }
-                    ResourceType::Article => {
-                        let json = serde_json::from_value::<ArticleData>(data.clone().to_json())?;
-                        let article = ArticleEntity::new(
-                            id.clone().to_string(),
-                            lang.as_str().to_string(),
-                            json,
-                            0,
-                        );
-
-                        serde_json::value::to_value(article)?
-                    }
-                    ResourceType::Category => {
-                        let json = serde_json::from_value::<CategoryData>(data.clone().to_json())?;
-                        let category = CategoryEntity::new(
-                            id.clone().to_string(),
-                            lang.as_str().to_string(),
-                            json,
-                            0,
-                        );
-
-                        serde_json::value::to_value(category)?
-                    }
-                };
-
-                let res = from_resource::<T>(json)?;
-                Ok(Some(res))
+            Some(data) if exist && filters.iter().all(|f| f.matches(data.as_json())) => {
+                let record = self.to_record::<K::Data>(id, lang, data.to_json()).await?;
+                Ok(Some(K::to_entity(record)))
             }
-            Err(e) => Err(e),
-            Ok(None) => Ok(None),
+            _ => Ok(None),
         }
     }
 
-    async fn list_resources<T>(
+    async fn list_resources<K: ResourceKind>(
         &self,
         language: &Language,
-        _: &Option<String>,
-        resource_type: &ResourceType,
-        _: &Pagination,
-    ) -> anyhow::Result<Vec<T>>
-    where
-        T: DeserializeOwned + Serialize,
-    {
-        let contents = self
+        filters: &[Filter],
+        page: &Pagination,
+    ) -> anyhow::Result<Vec<K::ListItem>> {
+        let records = self.matched_records::<K>(language, filters).await?;
+
+        let records = match page {
+            Pagination::All => records,
+            Pagination::Single => records.into_iter().take(1).collect(),
+            Pagination::Page(page) => records
+                .into_iter()
+                .skip((page.page * page.size) as usize)
+                .take(page.size as usize)
+                .collect(),
+        };
+
+        Ok(records.into_iter().map(K::to_list_item).collect())
+    }
+
+    async fn count_resources<K: ResourceKind>(
+        &self,
+        language: &Language,
+        filters: &[Filter],
+    ) -> anyhow::Result<usize> {
+        let records = self.matched_records::<K>(language, filters).await?;
+
+        Ok(records.len())
+    }
+
+    async fn list_resources_with_reference_counts<K: ResourceKind>(
+        &self,
+        language: &Language,
+        default_language: &Language,
+        reference: &Reference,
+    ) -> anyhow::Result<Vec<(K::Entity, usize)>> {
+        let mut records = self.matched_records::<K>(language, &[]).await?;
+        for record in self.matched_records::<K>(default_language, &[]).await? {
+            if !records.iter().any(|r| r.id == record.id) {
+                records.push(record);
+            }
+        }
+
+        let mut resources = vec![];
+        for record in records {
+            let id = ResourceID::try_from(record.id.clone())
+                .map_err(|_| anyhow!("invalid resource id"))?;
+            let count = self
+                .referring_contents(reference, &id)
+                .await?
+                .iter()
+                .filter(|(_, lang, _)| lang == language)
+                .count();
+
+            resources.push((K::to_entity(record), count));
+        }
+
+        Ok(resources)
+    }
+
+    async fn count_references(
+        &self,
+        reference: &Reference,
+        id: &ResourceID,
+    ) -> anyhow::Result<usize> {
+        let referring = self.referring_contents(reference, id).await?;
+
+        Ok(referring
+            .iter()
+            .map(|(id, _, _)| id.as_str())
+            .collect::<HashSet<_>>()
+            .len())
+    }
+
+    async fn replace_references(
+        &self,
+        reference: &Reference,
+        id: &ResourceID,
+        target: Option<&ResourceID>,
+    ) -> anyhow::Result<Vec<ResourceID>> {
+        let mut changed = vec![];
+        for (referring, language, data) in self.referring_contents(reference, id).await? {
+            let value = reference.replaced(
+                data.as_json(),
+                id.as_str(),
+                target.map(|target| target.as_str()),
+            );
+            let data = data.with_field(reference.field, value);
+            self.content_repository
+                .as_ref()
+                .unwrap()
+                .update(&ContentID::from(referring.clone()), data, language, None)
+                .await?;
+            if !changed.contains(&referring) {
+                changed.push(referring);
+            }
+        }
+
+        Ok(changed)
+    }
+
+    async fn commit(mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn rollback(mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+impl InMemory {
+    async fn to_record<D: DeserializeOwned>(
+        &self,
+        id: &ResourceID,
+        lang: &Language,
+        data: serde_json::Value,
+    ) -> anyhow::Result<ResourceRecord<D>> {
+        // no avatar is uploaded if the repository isn't used yet
+        let avatar = match self.avatar_repository.as_ref() {
+            Some(avatar_repo) => avatar_repo
+                .get(id)
+                .await?
+                .and_then(|json| serde_json::value::from_value::<AvatarData>(json.get()).ok()),
+            None => None,
+        };
+        let seq = self
+            .resource_repository
+            .as_ref()
+            .unwrap()
+            .seq(id)
+            .await?
+            .unwrap_or_default();
+        let version = self
             .content_repository
             .as_ref()
             .unwrap()
-            .list(language)
+            .version(&ContentID::from(id.clone()), lang)
             .await?;
 
-        let mut res = vec![];
+        Ok(ResourceRecord {
+            id: id.to_string(),
+            language: lang.as_str().to_string(),
+            data: serde_json::value::from_value::<D>(data)?,
+            seq: seq as i16,
+            version,
+            created_at: chrono::Utc::now(),
+            avatar,
+        })
+    }
 
-        for content in contents {
-            let id = ResourceID::try_from(content.0.clone()).unwrap();
-            let exist = self
-                .resource_repository
-                .as_ref()
-                .unwrap()
-                .contains(&id, resource_type)
-                .await?;
+    /// The contents in all languages which refer to the resource through the reference
+    async fn referring_contents(
+        &self,
+        reference: &Reference,
+        id: &ResourceID,
+    ) -> anyhow::Result<Vec<(ResourceID, Language, ContentData)>> {
+        let (Some(resources), Some(contents)) =
+            (&self.resource_repository, &self.content_repository)
+        else {
+            return Ok(vec![]);
+        };
 
-            let entity = match resource_type {
-                ResourceType::Member => {
-                    if exist {
-                        let data =
-                            serde_json::value::from_value::<MemberData>(content.1.to_json())?;
-                        Some(serde_json::value::to_value(SimpleMemberEntity::new(
-                            content.0, data.name, None, 0,
-                        ))?)
-                    } else {
-                        None
-                    }
-                }
-                ResourceType::Service => {
-                    if exist {
-                        let data =
-                            serde_json::value::from_value::<ServiceData>(content.1.to_json())?;
-                        Some(serde_json::value::to_value(ServiceEntity::new(
-                            content.0,
-                            language.as_str().to_string(),
-                            data,
-                            0,
-                        ))?)
-                    } else {
-                        None
-                    }
+        let mut referring = vec![];
+        for language in Language::ALL {
+            for (content_id, data) in contents.list(&language).await? {
+                let content_id =
+                    ResourceID::try_from(content_id).map_err(|_| anyhow!("invalid resource id"))?;
+
+                if reference
+                    .referred_ids(data.as_json())
+                    .contains(&id.as_str())
+                    && resources.contains(&content_id, &reference.from).await?
+                {
+                    referring.push((content_id, language.clone(), data));
                 }
-                _ => unimplemented!(),
-            };
-
-            res.push(entity);
+            }
         }
 
-        let res = res
-            .into_iter()
-            .filter(|e| e.is_some())
-            .map(|e| {
-                let json = e.unwrap();
-                from_resource(json).unwrap()
-            })
-            .collect::<Vec<_>>();
-
-        Ok(res)
+        Ok(referring)
     }
 
-    async fn count_resources(
+    async fn matched_records<K: ResourceKind>(
         &self,
         language: &Language,
-        _: &Option<String>,
-        resource_type: &ResourceType,
-    ) -> anyhow::Result<usize> {
+        filters: &[Filter],
+    ) -> anyhow::Result<Vec<ResourceRecord<K::Data>>> {
         let contents = self
             .content_repository
             .as_ref()
@@ -320,40 +419,27 @@ impl IResourceUnitOfWork for InMemory {
             .list(language)
             .await?;
 
-        let mut count = 0;
-        for content in contents {
-            let id = ResourceID::try_from(content.0.clone()).unwrap();
+        let mut records = vec![];
+
+        for (id, data) in contents {
+            let id = ResourceID::try_from(id).map_err(|_| anyhow!("invalid resource id"))?;
             let exist = self
                 .resource_repository
                 .as_ref()
                 .unwrap()
-                .contains(&id, resource_type)
+                .contains(&id, &K::TYPE)
                 .await?;
 
-            if exist {
-                count += 1;
+            if exist && filters.iter().all(|f| f.matches(data.as_json())) {
+                records.push(self.to_record(&id, language, data.to_json()).await?);
             }
         }
+        records.sort_by_key(|record| record.seq);
 
-        Ok(count)
-    }
-
-    async fn commit(mut self) -> anyhow::Result<()> {
-        Ok(())
-    }
-
-    async fn rollback(mut self) -> anyhow::Result<()> {
-        Ok(())
+        Ok(records)
     }
 }
 
-fn from_resource<T>(resource: serde_json::value::Value) -> Result<T, serde_json::error::Error>
-where
-    T: DeserializeOwned + Serialize,
-{
-    serde_json::value::from_value::<T>(resource)
-}
-
 #[derive(Debug)]
 pub struct InDatabase<'tx> {
     pool: &'tx PgPool,
@@ -361,6 +447,7 @@ pub struct InDatabase<'tx> {
     resource_repository: Option<SqlxResourceRepository<'tx>>,
     content_repository: Option<SqlxContentRepository<'tx>>,
     avatar_repository: Option<SqlxAvatarRepository<'tx>>,
+    audit_repository: Option<SqlxAuditRepository<'tx>>,
 }
 
 impl<'tx> InDatabase<'tx> {
@@ -374,6 +461,7 @@ impl<'tx> InDatabase<'tx> {
             content_repository: None,
             avatar_repository: None,
             resource_repository: None,
+            audit_repository: None,
         })
     }
 }
@@ -417,282 +505,80 @@ impl IResourceUnitOfWork for InDatabase<'_> {
         self.avatar_repository.as_mut().unwrap()
     }
 
-    async fn get_resource<T>(
+    fn audit_repository(&mut self) -> &mut impl IAuditRepository {
+        if self.audit_repository.is_none() {
+            let audit_repo = SqlxAuditRepository::new(
+                crate::repositories::Connection::Transaction(Arc::downgrade(&self.tx)),
+            );
+            self.audit_repository = Some(audit_repo);
+        }
+        self.audit_repository.as_mut().unwrap()
+    }
+
+    async fn get_resource<K: ResourceKind>(
         &self,
         id: &ResourceID,
         lang: &Language,
-        resource_type: &ResourceType,
-    ) -> anyhow::Result<Option<T>>
-    where
-        T: DeserializeOwned + Serialize,
-    {
-        let query = r#"
-                select resource.id as id,
-                    content.data as data,
-                    content.language as language,
-                    resource.seq as seq
-                from resource,
-                     content
-                where resource.id = content.id
-                  and content.language = $2
-                  and resource.id = $1
-                  and resource.deleted_at is null;
-                        "#;
-
-        let res = match resource_type {
-            ResourceType::Member => {
-                let query = r#"
-                select resource.id as id,
-                    content.data as data,
-                    avatar.data as avatar,
-                    content.language as language,
-                    resource.seq as seq
-                from resource,
-                     content
-                         left join avatar on avatar.id = content.id
-                where resource.id = content.id
-                  and content.language = $2
-                  and resource.id = $1
-                  and resource.deleted_at is null;
-                        "#;
-
-                sqlx::query_as::<_, MemberEntityFromSQLx>(query)
-                    .bind(id.as_str())
-                    .bind(lang.as_str())
-                    .fetch_optional(self.pool)
-                    .await?
-                    .map(MemberEntity::from)
-                    .and_then(|e| serde_json::value::to_value(e).ok())
-            }
-            ResourceType::Service => sqlx::query_as::<_, ServiceEntityFromSQLx>(query)
-                .bind(id.as_str())
-                .bind(lang.as_str())
-                .fetch_optional(self.pool)
-                .await?
-                .map(ServiceEntity::from)
-                .and_then(|e| serde_json::value::to_value(e).ok()),
-            ResourceType::Home => sqlx::query_as::<_, HomeEntityFromSQLx>(query)
-                .bind(id.as_str())
-                .bind(lang.as_str())
-                .fetch_optional(self.pool)
-                .await?
-                .map(HomeEntity::from)
-                .and_then(|e| serde_json::value::to_value(e).ok()),
-            ResourceType::Contact => sqlx::query_as::<_, ContactEntityFromSQLx>(query)
-                .bind(id.as_str())
-                .bind(lang.as_str())
-                .fetch_optional(self.pool)
-                .await?
-                .map(ContactEntity::from)
-                .and_then(|e| serde_json::value::to_value(e).ok()),
-            ResourceType::Article => sqlx::query_as::<_, ArticleEntityFromSQLx>(query)
-                .bind(id.as_str())
-                .bind(lang.as_str())
-                .fetch_optional(self.pool)
-                .await?
-                .map(ArticleEntity::from)
-                .and_then(|e| serde_json::value::to_value(e).ok()),
-            ResourceType::Category => sqlx::query_as::<_, CategoryEntityFromSQLx>(query)
-                .bind(id.as_str())
-                .bind(lang.as_str())
-                .fetch_optional(self.pool)
-                .await?
-                .map(CategoryEntity::from)
-                .and_then(|e| serde_json::value::to_value(e).ok()),
-        };
+        filters: &[Filter],
+    ) -> anyhow::Result<Option<K::Entity>> {
+        let mut query = select_resources(RESOURCE_COLUMNS, &K::TYPE, lang, filters);
+        query.push(" and resource.id = ").push_bind(id.as_str());
+
+        // the changes of the transaction are seen, e.g. by the snapshots of the audit entries
+        let mut tx = self.tx.lock().await;
+        let row = query
+            .build_query_as::<ResourceRow>()
+            .fetch_optional(&mut **tx)
+            .await?;
 
-        match res {
+        match row {
+            Some(row) => Ok(Some(K::to_entity(row.into_record()?))),
             None => Ok(None),
-            Some(value) => {
-                let r = from_resource::<T>(value)?;
-                Ok(Some(r))
-            }
         }
     }
 
-    async fn list_resources<T>(
+    async fn list_resources<K: ResourceKind>(
         &self,
         language: &Language,
-        filter_str: &Option<String>,
-        resource_type: &ResourceType,
+        filters: &[Filter],
         page: &Pagination,
-    ) -> anyhow::Result<Vec<T>>
-    where
-        T: DeserializeOwned + Serialize,
-    {
-        let filter_str = filter_str.clone().unwrap_or_default();
-        let filter_str = filter_str.as_str();
-
-        let offset = match page {
-            Pagination::All => ";".to_string(),
-            Pagination::Single => "limit 1;".to_string(),
+    ) -> anyhow::Result<Vec<K::ListItem>> {
+        let mut query = select_resources(RESOURCE_COLUMNS, &K::TYPE, language, filters);
+        query.push(" order by resource.seq, resource.created_at desc");
+
+        match page {
+            Pagination::All => {}
+            Pagination::Single => {
+                query.push(" limit 1");
+            }
             Pagination::Page(page) => {
-                let offset = page.page * page.size;
-                format!("offset {} limit {};", offset, page.size)
+                query
+                    .push(" offset ")
+                    .push_bind((page.page * page.size) as i64)
+                    .push(" limit ")
+                    .push_bind(page.size as i64);
             }
-        };
+        }
 
-        let query = format!(
-            r#"select resource.id as id,
-                content.data as data,
-                content.language as language,
-                resource.seq as seq
-                from resource,
-                    content
-                where resource.id = content.id
-                and content.language = $1
-                and resource.resource_type = $2
-                and resource.deleted_at is null
-                {}
-                order by seq, resource.created_at desc
-                "#,
-            filter_str
-        );
-
-        let res = match resource_type {
-            ResourceType::Member => {
-                let query = format!(
-                    r"select resource.id as id,
-                content.data->>'name' as name,
-                avatar.data->>'small_image' as avatar,
-                resource.seq as seq
-                from resource,
-                     content
-                left join avatar on content.id = avatar.id
-                where resource.id = content.id
-                and content.language = $1
-                and resource.resource_type = $2
-                and resource.deleted_at is null
-                {}
-                order by seq, resource.created_at desc {}",
-                    filter_str, offset
-                );
-
-                sqlx::query_as::<_, SimpleMemberEntityFromSQLx>(query.as_str())
-                    .bind(language.as_str())
-                    .bind(resource_type.as_str())
-                    .fetch_all(self.pool)
-                    .await?
-                    .into_iter()
-                    .map(SimpleMemberEntity::from)
-                    .filter_map(|e| serde_json::value::to_value(e).ok())
-                    .collect::<Vec<_>>()
-            }
-            ResourceType::Service => {
-                let query = format!("{}{}", query, offset);
-
-                sqlx::query_as::<_, ServiceEntityFromSQLx>(query.as_str())
-                    .bind(language.as_str())
-                    .bind(resource_type.as_str())
-                    .fetch_all(self.pool)
-                    .await?
-                    .into_iter()
-                    .map(ServiceEntity::from)
-                    .filter_map(|e| serde_json::value::to_value(e).ok())
-                    .collect::<Vec<_>>()
-            }
-            ResourceType::Home => {
-                let query = format!("{}{}", query, offset);
-
-                sqlx::query_as::<_, HomeEntityFromSQLx>(query.as_str())
-                    .bind(language.as_str())
-                    .bind(resource_type.as_str())
-                    .fetch_all(self.pool)
-                    .await?
-                    .into_iter()
-                    .map(HomeEntity::from)
-                    .filter_map(|e| serde_json::value::to_value(e).ok())
-                    .collect::<Vec<_>>()
-            }
-            ResourceType::Article => {
-                let query = format!(
-                    r#"select resource.id as id,
-                content.data->>'title' as title,
-                content.created_at as created_at,
-                content.language as language,
-                resource.seq as seq
-                from resource,
-                    content
-                where resource.id = content.id
-                and content.language = $1
-                and resource.resource_type = $2
-                and resource.deleted_at is null
-                {}
-                order by seq, resource.created_at desc
-                {}
-                "#,
-                    filter_str, offset
-                );
-
-                sqlx::query_as::<_, SimpleArticleEntityFromSQLx>(query.as_str())
-                    .bind(language.as_str())
-                    .bind(resource_type.as_str())
-                    .fetch_all(self.pool)
-                    .await?
-                    .into_iter()
-                    .map(SimpleArticleEntity::from)
-                    .filter_map(|e| serde_json::value::to_value(e).ok())
-                    .collect::<Vec<_>>()
-            }
-            ResourceType::Contact => {
-                let query = format!("{}{}", query, offset);
-
-                sqlx::query_as::<_, ContactEntityFromSQLx>(query.as_str())
-                    .bind(language.as_str())
-                    .bind(resource_type.as_str())
-                    .fetch_all(self.pool)
-                    .await?
-                    .into_iter()
-                    .map(ContactEntity::from)
-                    .filter_map(|e| serde_json::value::to_value(e).ok())
-                    .collect::<Vec<_>>()
-            }
-            ResourceType::Category => {
-                let query = format!("{}{}", query, offset);
-
-                sqlx::query_as::<_, CategoryEntityFromSQLx>(query.as_str())
-                    .bind(language.as_str())
-                    .bind(resource_type.as_str())
-                    .fetch_all(self.pool)
-                    .await?
-                    .into_iter()
-                    .map(CategoryEntity::from)
-                    .filter_map(|e| serde_json::value::to_value(e).ok())
-                    .collect::<Vec<_>>()
-            }
-        };
+        let rows = query
+            .build_query_as::<ResourceRow>()
+            .fetch_all(self.pool)
+            .await?;
 
-        Ok(res
+        Ok(rows
             .into_iter()
-            .map(from_resource::<T>)
-            .filter_map(|e| e.ok())
+            .filter_map(|row| row.into_record().ok())
+            .map(K::to_list_item)
             .collect::<Vec<_>>())
     }
 
-    async fn count_resources(
+    async fn count_resources<K: ResourceKind>(
         &self,
         language: &Language,
-        filter_str: &Option<String>,
-        resource_type: &ResourceType,
+        filters: &[Filter],
     ) -> anyhow::Result<usize> {
-        let filter_str = filter_str.clone().unwrap_or_default();
-        let filter_str = filter_str.as_str();
-        let query = format!(
-            r#"select count(resource.id) as total
-                from resource,
-                    content
-                where resource.id = content.id
-                and content.language = $1
-                and resource.resource_type = $2
-                and resource.deleted_at is null
-                {}
-                "#,
-            filter_str
-        );
-
-        let count = sqlx::query(&query)
-            .bind(language.as_str())
-            .bind(resource_type.as_str())
+        let count = select_resources("count(resource.id) as total", &K::TYPE, language, filters)
+            .build()
             .fetch_one(self.pool)
             .await
             .map(|e| e.get::<i64, usize>(0))?;
@@ -700,6 +586,123 @@ impl IResourceUnitOfWork for InDatabase<'_> {
         Ok(count as usize)
     }
 
+    async fn list_resources_with_reference_counts<K: ResourceKind>(
+        &self,
+        language: &Language,
+        default_language: &Language,
+        reference: &Reference,
+    ) -> anyhow::Result<Vec<(K::Entity, usize)>> {
+        let rows = sqlx::query_as::<_, CountedResourceRow>(
+            r#"select resource.id                                          as id,
+                   coalesce(localized.language, fallback.language)     as language,
+                   coalesce(localized.data, fallback.data)             as data,
+                   resource.seq                                        as seq,
+                   coalesce(localized.created_at, fallback.created_at) as created_at,
+                   coalesce(localized.version, fallback.version)       as version,
+                   avatar.data                                         as avatar,
+                   count(referring.id)                                 as reference_count
+            from resource
+                     left join content localized on localized.id = resource.id and localized.language = $1
+                     left join content fallback on fallback.id = resource.id and fallback.language = $2
+                     left join avatar on avatar.id = resource.id
+                     left join content referring_content
+                               on referring_content.language = $1 and referring_content.data ->> $3 = resource.id
+                     left join resource referring
+                               on referring.id = referring_content.id
+                                   and referring.resource_type = $4
+                                   and referring.deleted_at is null
+            where resource.deleted_at is null
+              and resource.resource_type = $5
+              and coalesce(localized.id, fallback.id) is not null
+            group by resource.id, localized.id, localized.language, fallback.id, fallback.language, avatar.id
+            order by resource.seq, resource.created_at desc"#,
+        )
+        .bind(language.as_str())
+        .bind(default_language.as_str())
+        .bind(reference.field)
+        .bind(reference.from.as_str())
+        .bind(K::TYPE.as_str())
+        .fetch_all(self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let record = row.resource.into_record()?;
+                Ok((K::to_entity(record), row.reference_count as usize))
+            })
+            .collect()
+    }
+
+    async fn count_references(
+        &self,
+        reference: &Reference,
+        id: &ResourceID,
+    ) -> anyhow::Result<usize> {
+        let mut tx = self.tx.lock().await;
+
+        let count = sqlx::query(
+            r#"select count(distinct resource.id)
+            from resource
+                     join content on content.id = resource.id
+            where resource.deleted_at is null
+              and resource.resource_type = $1
+              and content.data -> $2 @> to_jsonb($3::text)"#,
+        )
+        .bind(reference.from.as_str())
+        .bind(reference.field)
+        .bind(id.as_str())
+        .fetch_one(&mut **tx)
+        .await
+        .map(|row| row.get::<i64, usize>(0))?;
+
+        Ok(count as usize)
+    }
+
+    async fn replace_references(
+        &self,
+        reference: &Reference,
+        id: &ResourceID,
+        target: Option<&ResourceID>,
+    ) -> anyhow::Result<Vec<ResourceID>> {
+        let mut tx = self.tx.lock().await;
+
+        let ids = sqlx::query_scalar::<_, String>(
+            r#"update content
+            set data       = jsonb_set(content.data, array [$2], case
+                when jsonb_typeof(content.data -> $2) <> 'array'
+                    then coalesce(to_jsonb($4::text), 'null'::jsonb)
+                when $4::text is null
+                    then (content.data -> $2) - $3::text
+                else ((content.data -> $2) - $3::text - $4::text) || jsonb_build_array($4::text)
+                end),
+                updated_at = now(),
+                version    = content.version + 1
+            from resource
+            where resource.id = content.id
+              and resource.deleted_at is null
+              and resource.resource_type = $1
+              and content.data -> $2 @> to_jsonb($3::text)
+            returning content.id"#,
+        )
+        .bind(reference.from.as_str())
+        .bind(reference.field)
+        .bind(id.as_str())
+        .bind(target.map(|target| target.as_str()))
+        .fetch_all(&mut **tx)
+        .await?;
+
+        // the content of every language is changed, the resources are returned once
+        let mut changed = vec![];
+        for id in ids {
+            let id = ResourceID::try_from(id).map_err(|_| anyhow!("invalid resource id"))?;
+            if !changed.contains(&id) {
+                changed.push(id);
+            }
+        }
+
+        Ok(changed)
+    }
+
     async fn commit(self) -> anyhow::Result<()> {
         match Arc::try_unwrap(self.tx) {
             Ok(lock) => {
@@ -720,3 +723,95 @@ impl IResourceUnitOfWork for InDatabase<'_> {
         }
     }
 }
+
+const RESOURCE_COLUMNS: &str = r#"resource.id as id,
+    content.language as language,
+    content.data as data,
+    resource.seq as seq,
+    content.created_at as created_at,
+    content.version as version,
+    avatar.data as avatar"#;
+
+/// Builds the query of the resources in a language, more conditions can be appended to it
+fn select_resources<'a>(
+    columns: &str,
+    resource_type: &ResourceType,
+    language: &'a Language,
+    filters: &'a [Filter],
+) -> QueryBuilder<'a, Postgres> {
+    let mut query = QueryBuilder::new(format!(
+        r#"select {}
+        from resource
+                 join content on content.id = resource.id
+                 left join avatar on avatar.id = resource.id
+        where resource.deleted_at is null"#,
+        columns
+    ));
+
+    query
+        .push(" and resource.resource_type = ")
+        .push_bind(resource_type.as_str())
+        .push(" and content.language = ")
+        .push_bind(language.as_str());
+
+    for filter in filters {
+        match filter {
+            Filter::Equals { field, value } => {
+                query
+                    .push(" and content.data->>")
+                    .push_bind(*field)
+                    .push(" = ")
+                    .push_bind(value.as_str());
+            }
+            Filter::Contains { field, value } => {
+                query
+                    .push(" and content.data->")
+                    .push_bind(*field)
+                    .push(" ? ")
+                    .push_bind(value.as_str());
+            }
+            Filter::IsTrue { field } => {
+                query
+                    .push(" and (content.data->>")
+                    .push_bind(*field)
+                    .push(")::boolean");
+            }
+        }
+    }
+
+    query
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ResourceRow {
+    id: String,
+    language: String,
+    data: serde_json::Value,
+    seq: i16,
+    created_at: chrono::DateTime<chrono::Utc>,
+    version: i32,
+    avatar: Option<serde_json::Value>,
+}
+
+impl ResourceRow {
+    fn into_record<D: DeserializeOwned>(self) -> anyhow::Result<ResourceRecord<D>> {
+        Ok(ResourceRecord {
+            id: self.id.trim().to_owned(),
+            language: self.language.trim().to_owned(),
+            data: serde_json::value::from_value::<D>(self.data)?,
+            seq: self.seq,
+            version: self.version,
+            created_at: self.created_at,
+            avatar: self
+                .avatar
+                .and_then(|json| serde_json::value::from_value::<AvatarData>(json).ok()),
+        })
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct CountedResourceRow {
+    #[sqlx(flatten)]
+    resource: ResourceRow,
+    reference_count: i64,
+}
@@ -0,0 +1,200 @@
+use crate::repositories::{
+    IAuditRepository, ILoginAttemptRepository, ISecuritySettingsRepository, IUserRepository,
+    SqlxAuditRepository, SqlxLoginAttemptRepository, SqlxSecuritySettingsRepository,
+    SqlxUserRepository,
+};
+#[cfg(test)]
+use crate::repositories::{
+    InMemoryAuditRepository, InMemoryLoginAttemptRepository, InMemorySecuritySettingsRepository,
+    InMemoryUserRepository,
+};
+use anyhow::anyhow;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/** Define a unit of work to change the users along with their audit entries.
+*
+* - user repository
+* - login attempt repository
+* - security settings repository
+* - audit repository
+*/
+#[async_trait::async_trait]
+pub trait IUserUnitOfWork {
+    /** User repository stores the admin users and their credentials */
+    fn user_repository(&mut self) -> &mut impl IUserRepository;
+
+    /** Login attempt repository records the logins and the unlocks */
+    fn login_attempt_repository(&mut self) -> &mut impl ILoginAttemptRepository;
+
+    /** Security settings repository stores the settings of all the users */
+    fn security_settings_repository(&mut self) -> &mut impl ISecuritySettingsRepository;
+
+    /** Audit repository records who did what */
+    fn audit_repository(&mut self) -> &mut impl IAuditRepository;
+
+    /** Commit the transaction */
+    async fn commit(mut self) -> anyhow::Result<()>;
+    /** Rollback the transaction */
+    async fn rollback(mut self) -> anyhow::Result<()>;
+}
+
+/// The repositories are shared with the given ones, so the changes can be checked
+#[cfg(test)]
+pub struct InMemory {
+    user_repository: InMemoryUserRepository,
+    login_attempt_repository: InMemoryLoginAttemptRepository,
+    security_settings_repository: InMemorySecuritySettingsRepository,
+    audit_repository: InMemoryAuditRepository,
+}
+
+#[cfg(test)]
+impl InMemory {
+    pub fn new(
+        user_repository: InMemoryUserRepository,
+        audit_repository: InMemoryAuditRepository,
+    ) -> Self {
+        Self {
+            user_repository,
+            login_attempt_repository: InMemoryLoginAttemptRepository::new(),
+            security_settings_repository: InMemorySecuritySettingsRepository::new(),
+            audit_repository,
+        }
+    }
+
+    pub fn with_login_attempt_repository(
+        self,
+        login_attempt_repository: InMemoryLoginAttemptRepository,
+    ) -> Self {
+        Self {
+            login_attempt_repository,
+            ..self
+        }
+    }
+
+    pub fn with_security_settings_repository(
+        self,
+        security_settings_repository: InMemorySecuritySettingsRepository,
+    ) -> Self {
+        Self {
+            security_settings_repository,
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl IUserUnitOfWork for InMemory {
+    fn user_repository(&mut self) -> &mut impl IUserRepository {
+        &mut self.user_repository
+    }
+
+    fn login_attempt_repository(&mut self) -> &mut impl ILoginAttemptRepository {
+        &mut self.login_attempt_repository
+    }
+
+    fn security_settings_repository(&mut self) -> &mut impl ISecuritySettingsRepository {
+        &mut self.security_settings_repository
+    }
+
+    fn audit_repository(&mut self) -> &mut impl IAuditRepository {
+        &mut self.audit_repository
+    }
+
+    async fn commit(mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn rollback(mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct InDatabase<'tx> {
+    tx: Arc<Mutex<Transaction<'tx, Postgres>>>,
+    user_repository: Option<SqlxUserRepository<'tx>>,
+    login_attempt_repository: Option<SqlxLoginAttemptRepository<'tx>>,
+    security_settings_repository: Option<SqlxSecuritySettingsRepository<'tx>>,
+    audit_repository: Option<SqlxAuditRepository<'tx>>,
+}
+
+impl<'tx> InDatabase<'tx> {
+    pub async fn new(pool: &'tx PgPool) -> anyhow::Result<Self> {
+        let tx = pool.begin().await?;
+        let tx = Arc::new(Mutex::new(tx));
+
+        Ok(Self {
+            tx,
+            user_repository: None,
+            login_attempt_repository: None,
+            security_settings_repository: None,
+            audit_repository: None,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl IUserUnitOfWork for InDatabase<'_> {
+    fn user_repository(&mut self) -> &mut impl IUserRepository {
+        if self.user_repository.is_none() {
+            let user_repo = SqlxUserRepository::new(crate::repositories::Connection::Transaction(
+                Arc::downgrade(&self.tx),
+            ));
+            self.user_repository = Some(user_repo);
+        }
+        self.user_repository.as_mut().unwrap()
+    }
+
+    fn login_attempt_repository(&mut self) -> &mut impl ILoginAttemptRepository {
+        if self.login_attempt_repository.is_none() {
+            let attempt_repo = SqlxLoginAttemptRepository::new(
+                crate::repositories::Connection::Transaction(Arc::downgrade(&self.tx)),
+            );
+            self.login_attempt_repository = Some(attempt_repo);
+        }
+        self.login_attempt_repository.as_mut().unwrap()
+    }
+
+    fn security_settings_repository(&mut self) -> &mut impl ISecuritySettingsRepository {
+        if self.security_settings_repository.is_none() {
+            let settings_repo = SqlxSecuritySettingsRepository::new(
+                crate::repositories::Connection::Transaction(Arc::downgrade(&self.tx)),
+            );
+            self.security_settings_repository = Some(settings_repo);
+        }
+        self.security_settings_repository.as_mut().unwrap()
+    }
+
+    fn audit_repository(&mut self) -> &mut impl IAuditRepository {
+        if self.audit_repository.is_none() {
+            let audit_repo = SqlxAuditRepository::new(
+                crate::repositories::Connection::Transaction(Arc::downgrade(&self.tx)),
+            );
+            self.audit_repository = Some(audit_repo);
+        }
+        self.audit_repository.as_mut().unwrap()
+    }
+
+    async fn commit(self) -> anyhow::Result<()> {
+        match Arc::try_unwrap(self.tx) {
+            Ok(lock) => {
+                lock.into_inner().commit().await?;
+                Ok(())
+            }
+            Err(_) => Err(anyhow!("can't commit transaction")),
+        }
+    }
+
+    async fn rollback(self) -> anyhow::Result<()> {
+        match Arc::try_unwrap(self.tx) {
+            Ok(lock) => {
+                lock.into_inner().rollback().await?;
+                Ok(())
+            }
+            Err(_) => Err(anyhow!("can't rollback transaction")),
+        }
+    }
+}
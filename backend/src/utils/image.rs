@@ -3,6 +3,7 @@ use image::{DynamicImage, ImageReader};
 use std::io::Cursor;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
+#[cfg(test)]
 use tokio::sync::Mutex;
 
 pub struct Size {
@@ -33,6 +34,7 @@ fn resize_image(data: &[u8], size: Size) -> anyhow::Result<DynamicImage> {
     Ok(img.resize_exact(size.width, size.height, FilterType::CatmullRom))
 }
 
+#[cfg(test)]
 #[derive(Debug)]
 pub struct FakeImageUtil {
     save_file_error: bool,
@@ -56,6 +58,7 @@ impl FakeImageUtil {
     }
 }
 
+#[cfg(test)]
 #[async_trait::async_trait]
 impl IImage for FakeImageUtil {
     async fn save_to_file(&self, file_path: &str, _: DynamicImage) -> anyhow::Result<()> {
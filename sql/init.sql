@@ -7,6 +7,7 @@ create table resource
     deleted_at    timestamptz,
     resource_type varchar(32) not null,
     seq           smallint    not null,
+    created_by    uuid,
     primary key (id)
 );
 
@@ -17,6 +18,7 @@ create table content
     created_at timestamptz not null,
     updated_at timestamptz,
     language   varchar(8)  not null,
+    version    integer     not null default 1,
     primary key (id, language)
 );
 
@@ -32,10 +34,80 @@ CREATE TABLE users
     id            uuid NOT NULL,
     username      TEXT NOT NULL UNIQUE,
     password_hash TEXT NOT NULL,
+    role          varchar(16) NOT NULL DEFAULT 'viewer',
+    email         TEXT,
+    disabled      boolean NOT NULL DEFAULT false,
+    must_reset_password boolean NOT NULL DEFAULT false,
+    totp_secret   TEXT,
+    totp_enabled  boolean NOT NULL DEFAULT false,
+    totp_last_step bigint,
+    created_at    timestamptz NOT NULL DEFAULT NOW(),
     PRIMARY KEY (id)
 );
 
+-- the hash has the outdated algorithm and parameters, so it's replaced on the first login
 insert into users
-    (id, username, password_hash)
+    (id, username, password_hash, role)
 values ('47ff8e18-7732-4e8c-a377-ec7491bd93d1', 'boris',
-        '$argon2d$v=19$m=15000,t=2,p=1$++wL1wKozweyizKVauMbFQ$XsZbPyHX3I+ndHRz8pWK+ltETNVBMbRzMoE5A2HOqqw');
+        '$argon2d$v=19$m=15000,t=2,p=1$++wL1wKozweyizKVauMbFQ$XsZbPyHX3I+ndHRz8pWK+ltETNVBMbRzMoE5A2HOqqw',
+        'owner');
+
+create table login_attempts
+(
+    id           uuid        not null default gen_random_uuid(),
+    username     text        not null,
+    ip           inet        not null,
+    user_agent   text,
+    outcome      varchar(16) not null,
+    attempted_at timestamptz not null default now(),
+    primary key (id)
+);
+
+create index login_attempts_username_idx on login_attempts (username, attempted_at desc);
+create index login_attempts_ip_idx on login_attempts (ip, attempted_at desc);
+
+create table audit_log
+(
+    id              uuid        not null default gen_random_uuid(),
+    user_id         uuid,
+    ip              inet,
+    action          varchar(32) not null,
+    target_type     varchar(32) not null,
+    target_id       text        not null,
+    language        varchar(8),
+    before_snapshot jsonb,
+    after_snapshot  jsonb,
+    created_at      timestamptz not null default now(),
+    primary key (id)
+);
+
+create index audit_log_created_at_idx on audit_log (created_at desc);
+create index audit_log_user_id_idx on audit_log (user_id, created_at desc);
+create index audit_log_target_idx on audit_log (target_type, target_id, created_at desc);
+
+create table password_resets
+(
+    token_hash text        not null,
+    user_id    uuid        not null references users (id) on delete cascade,
+    expires_at timestamptz not null,
+    created_at timestamptz not null default now(),
+    primary key (token_hash)
+);
+
+create index password_resets_user_id_idx on password_resets (user_id);
+
+create table recovery_codes
+(
+    user_id   uuid not null references users (id) on delete cascade,
+    code_hash text not null,
+    primary key (user_id, code_hash)
+);
+
+create table security_settings
+(
+    id           boolean not null default true check (id),
+    require_totp boolean not null default false,
+    primary key (id)
+);
+
+insert into security_settings default values;
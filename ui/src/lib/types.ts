@@ -128,6 +128,7 @@ export type CategoryData = {
     data: {
         icon?: string
         name: string
+        parent_id?: string
     }
     seq: number
 }
@@ -136,6 +137,7 @@ export type CategoryData = {
 export type CreateCategoryRequest = {
     icon?: string
     name: string
+    parent_id?: string
     language: Language
     seq: number
 }